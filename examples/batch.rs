@@ -6,9 +6,9 @@
 //! - 并发处理多个提示
 //! - 处理结果向量
 
-use nanoai::{batch_generate, LLMClient};
 use nanoai::config::Config;
 use nanoai::error::Result;
+use nanoai::{batch_generate, LLMClient};
 
 /// 主函数：演示批量文本生成
 ///
@@ -19,20 +19,20 @@ use nanoai::error::Result;
 async fn main() -> Result<()> {
     // 从环境变量加载配置
     let config = Config::from_env()?;
-    
+
     // 创建客户端
     let client = LLMClient::new(config);
-    
+
     // 定义多个提示
     let prompts = vec![
         "Rust 的所有权系统是什么？",
         "解释一下 Tokio 的异步运行时。",
         "Cargo 是做什么的？",
     ];
-    
+
     // 批量生成响应
-    let results = batch_generate(&client, &prompts.iter().map(|s| *s).collect::<Vec<_>>()).await;
-    
+    let results = batch_generate(&client, &prompts).await;
+
     // 处理结果
     for (i, result) in results.into_iter().enumerate() {
         match result {
@@ -40,6 +40,6 @@ async fn main() -> Result<()> {
             Err(e) => eprintln!("提示 {} 错误: {}", i + 1, e),
         }
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,348 @@
+//! 音频接口模块
+//!
+//! 提供语音转写 (`/audio/transcriptions`) 和语音合成 (`/audio/speech`) 能力。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::{
+    header::CONTENT_TYPE,
+    multipart::{Form, Part},
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// ================================================================================================
+// 语音转写
+// ================================================================================================
+
+/// 待转写的音频来源
+#[derive(Debug, Clone)]
+pub enum AudioInput {
+    /// 本地音频文件
+    File(PathBuf),
+    /// 内存中的音频数据
+    Bytes {
+        /// 音频内容
+        data: Vec<u8>,
+        /// 文件名，服务端据此推断音频格式（如 `speech.mp3`）
+        file_name: String,
+    },
+}
+
+impl AudioInput {
+    /// 从内存数据创建音频输入
+    pub fn from_bytes(data: impl Into<Vec<u8>>, file_name: impl Into<String>) -> Self {
+        AudioInput::Bytes {
+            data: data.into(),
+            file_name: file_name.into(),
+        }
+    }
+
+    /// 读取音频内容和文件名
+    async fn into_parts(self) -> Result<(Vec<u8>, String)> {
+        match self {
+            AudioInput::File(path) => {
+                let data = tokio::fs::read(&path).await?;
+                let file_name = path
+                    .file_name()
+                    .map_or_else(|| "audio".to_string(), |n| n.to_string_lossy().into_owned());
+                Ok((data, file_name))
+            }
+            AudioInput::Bytes { data, file_name } => Ok((data, file_name)),
+        }
+    }
+}
+
+impl From<PathBuf> for AudioInput {
+    fn from(path: PathBuf) -> Self {
+        AudioInput::File(path)
+    }
+}
+
+impl From<&Path> for AudioInput {
+    fn from(path: &Path) -> Self {
+        AudioInput::File(path.to_path_buf())
+    }
+}
+
+/// 语音转写选项
+#[derive(Debug, Clone)]
+pub struct TranscriptionOptions {
+    /// 转写模型
+    pub model: String,
+    /// 音频语言（ISO-639-1），为空时由模型自动识别
+    pub language: Option<String>,
+    /// 提示文本，用于引导专有名词或上下文
+    pub prompt: Option<String>,
+    /// 返回格式：`json`、`verbose_json`、`text`、`srt` 或 `vtt`
+    pub response_format: String,
+    /// 温度参数
+    pub temperature: Option<f32>,
+}
+
+impl Default for TranscriptionOptions {
+    fn default() -> Self {
+        Self {
+            model: "whisper-1".into(),
+            language: None,
+            prompt: None,
+            response_format: "verbose_json".into(),
+            temperature: None,
+        }
+    }
+}
+
+/// 转写片段
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct TranscriptionSegment {
+    /// 片段序号
+    #[serde(default)]
+    pub id: u32,
+    /// 开始时间（秒）
+    #[serde(default)]
+    pub start: f64,
+    /// 结束时间（秒）
+    #[serde(default)]
+    pub end: f64,
+    /// 片段文本
+    #[serde(default)]
+    pub text: String,
+}
+
+/// 语音转写结果
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct Transcription {
+    /// 完整文本
+    #[serde(default)]
+    pub text: String,
+    /// 识别出的语言
+    pub language: Option<String>,
+    /// 音频时长（秒）
+    pub duration: Option<f64>,
+    /// 分段信息，仅 `verbose_json` 格式返回
+    #[serde(default)]
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+impl LLMClient {
+    /// 将音频转写为文本
+    ///
+    /// 以 multipart 表单上传音频到 `/audio/transcriptions`。当 `response_format`
+    /// 为 `text`、`srt` 或 `vtt` 时，原始响应文本保存在 `Transcription::text` 中。
+    pub async fn transcribe(
+        &self,
+        audio: impl Into<AudioInput>,
+        options: &TranscriptionOptions,
+    ) -> Result<Transcription> {
+        let (data, file_name) = audio.into().into_parts().await?;
+        let file = Part::bytes(data).file_name(file_name);
+
+        let mut form = Form::new()
+            .part("file", file)
+            .text("model", options.model.clone())
+            .text("response_format", options.response_format.clone());
+        if let Some(language) = &options.language {
+            form = form.text("language", language.clone());
+        }
+        if let Some(prompt) = &options.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        if let Some(temperature) = options.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+
+        let endpoint = format!("{}/audio/transcriptions", self.config.api_base);
+        let mut headers = self.build_headers().await?;
+        // multipart 边界由 reqwest 生成
+        headers.remove(CONTENT_TYPE);
+        let request_builder = self.client.post(&endpoint).headers(headers).multipart(form);
+
+        let response = self.call_api_with_retry(request_builder).await?;
+        let body = response.text().await?;
+        match options.response_format.as_str() {
+            "json" | "verbose_json" => serde_json::from_str(&body).map_err(NanoError::from),
+            _ => Ok(Transcription {
+                text: body,
+                ..Transcription::default()
+            }),
+        }
+    }
+}
+
+// ================================================================================================
+// 语音合成
+// ================================================================================================
+
+/// 语音合成的音频格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// MP3
+    #[default]
+    Mp3,
+    /// Opus，适合低延迟的网络传输
+    Opus,
+    /// AAC
+    Aac,
+    /// FLAC 无损格式
+    Flac,
+    /// WAV
+    Wav,
+    /// 原始 PCM（24kHz，16 位，单声道）
+    Pcm,
+}
+
+impl LLMClient {
+    /// 将文本合成为语音
+    ///
+    /// 调用 `/audio/speech` 并以字节流的形式返回音频，可以边接收边播放或写入文件。
+    /// 使用的模型由 `Config::with_speech_model` 配置。返回的流在读完或被释放前
+    /// 占用一个并发配额。
+    pub async fn speak(
+        &self,
+        text: &str,
+        voice: &str,
+        format: AudioFormat,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let endpoint = format!("{}/audio/speech", self.config.api_base);
+        let headers = self.build_headers().await?;
+        let params = serde_json::json!({
+            "model": &self.config.speech_model,
+            "input": text,
+            "voice": voice,
+            "response_format": format,
+        });
+
+        let request_builder = self.client.post(&endpoint).headers(headers).json(&params);
+        let (response, permit) = self.call_api_for_stream(request_builder).await?;
+        Ok(response.bytes_stream().map(move |chunk| {
+            let _permit = &permit;
+            chunk.map_err(NanoError::from)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{MockResponse, MockServer};
+    use futures::TryStreamExt;
+
+    /// multipart 表单中名为 `name` 的字段值
+    fn form_field<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+        let start = body.find(&format!("name=\"{}\"", name))?;
+        let value = &body[start..];
+        let value = &value[value.find("\r\n\r\n")? + 4..];
+        Some(&value[..value.find("\r\n")?])
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_verbose_json() {
+        let server = MockServer::start(vec![MockResponse::json(serde_json::json!({
+            "text": "hello world",
+            "language": "english",
+            "duration": 1.5,
+            "segments": [
+                {"id": 0, "start": 0.0, "end": 0.7, "text": "hello"},
+                {"id": 1, "start": 0.7, "end": 1.5, "text": " world"}
+            ]
+        }))])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let options = TranscriptionOptions {
+            language: Some("en".into()),
+            ..TranscriptionOptions::default()
+        };
+
+        let transcription = client
+            .transcribe(
+                AudioInput::from_bytes(b"RIFF".to_vec(), "clip.wav"),
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(transcription.text, "hello world");
+        assert_eq!(transcription.duration, Some(1.5));
+        assert_eq!(transcription.segments.len(), 2);
+        assert_eq!(transcription.segments[1].start, 0.7);
+
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/audio/transcriptions");
+        assert!(request.headers["content-type"].starts_with("multipart/form-data; boundary="));
+        assert!(request
+            .body
+            .contains("name=\"file\"; filename=\"clip.wav\""));
+        assert_eq!(form_field(&request.body, "file"), Some("RIFF"));
+        assert_eq!(form_field(&request.body, "model"), Some("whisper-1"));
+        assert_eq!(
+            form_field(&request.body, "response_format"),
+            Some("verbose_json")
+        );
+        assert_eq!(form_field(&request.body, "language"), Some("en"));
+        assert_eq!(form_field(&request.body, "prompt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_text() {
+        let server = MockServer::start(vec![MockResponse::new(200, "hello world\n")]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let options = TranscriptionOptions {
+            response_format: "text".into(),
+            ..TranscriptionOptions::default()
+        };
+
+        let transcription = client
+            .transcribe(
+                AudioInput::from_bytes(b"RIFF".to_vec(), "clip.wav"),
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(transcription.text, "hello world\n");
+        assert!(transcription.segments.is_empty());
+
+        let request = &server.requests()[0];
+        assert_eq!(form_field(&request.body, "response_format"), Some("text"));
+        assert_eq!(form_field(&request.body, "language"), None);
+    }
+
+    #[tokio::test]
+    async fn test_speak() {
+        let server = MockServer::start(vec![
+            MockResponse::new(200, "ID3 audio").with_header("Content-Type", "audio/mpeg")
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_max_concurrent_requests(1),
+        );
+
+        let stream = client
+            .speak("Hello", "alloy", AudioFormat::Opus)
+            .await
+            .unwrap();
+        // 流未读完前一直占用配额
+        assert_eq!(client.semaphore.available_permits(), 0);
+        let audio: Vec<Bytes> = stream.try_collect().await.unwrap();
+        assert_eq!(audio.concat(), b"ID3 audio");
+        assert_eq!(client.semaphore.available_permits(), 1);
+
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/audio/speech");
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "model": "tts-1",
+                "input": "Hello",
+                "voice": "alloy",
+                "response_format": "opus",
+            })
+        );
+    }
+}
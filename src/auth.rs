@@ -0,0 +1,234 @@
+//! 认证模块
+//!
+//! 通过 [`AuthProvider`] 为每个请求提供认证标头，支持在进程运行期间过期、需要续期的凭据。
+//! 未配置时使用 `Config::with_api_key` 设置的静态密钥。
+use crate::error::{NanoError, Result};
+use futures::future::{self, BoxFuture};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// 令牌到期前提前续期的时间
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+// ================================================================================================
+// 认证接口
+// ================================================================================================
+
+/// 认证凭据提供者
+///
+/// 每次发送请求前调用，返回的标头会合并到请求中。需要续期的实现应自行缓存凭据。
+pub trait AuthProvider: Debug + Send + Sync {
+    /// 返回认证标头
+    fn credentials(&self) -> BoxFuture<'_, Result<HeaderMap>>;
+}
+
+/// 构造 `Authorization: Bearer` 标头
+pub(crate) fn bearer(token: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|e| NanoError::InvalidRequest(format!("Invalid API key: {}", e)))?,
+    );
+    Ok(headers)
+}
+
+// ================================================================================================
+// 内置实现
+// ================================================================================================
+
+/// 静态 API 密钥
+#[derive(Debug, Clone)]
+pub struct StaticKey(pub String);
+
+impl AuthProvider for StaticKey {
+    fn credentials(&self) -> BoxFuture<'_, Result<HeaderMap>> {
+        Box::pin(future::ready(bearer(&self.0)))
+    }
+}
+
+/// 每次请求时从环境变量读取 API 密钥，便于外部进程轮换密钥
+#[derive(Debug, Clone)]
+pub struct EnvKey {
+    var: String,
+}
+
+impl EnvKey {
+    /// 从环境变量 `var` 读取密钥
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+}
+
+impl AuthProvider for EnvKey {
+    fn credentials(&self) -> BoxFuture<'_, Result<HeaderMap>> {
+        let result = std::env::var(&self.var)
+            .map_err(|_| NanoError::Auth(format!("{} not found", self.var)))
+            .and_then(|key| bearer(&key));
+        Box::pin(future::ready(result))
+    }
+}
+
+/// 令牌端点响应体
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// OAuth 2.0 客户端凭据模式
+///
+/// 从令牌端点获取访问令牌并缓存，到期前一分钟自动续期。
+#[derive(Debug)]
+pub struct OAuthClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    http: Client,
+    token: Mutex<Option<(String, Option<Instant>)>>,
+}
+
+impl OAuthClientCredentials {
+    /// 使用令牌端点和客户端凭据创建
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            http: Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// 设置请求的权限范围
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// 返回缓存的令牌，过期或即将过期时重新获取
+    async fn token(&self) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if expires_at.is_none_or(|at| Instant::now() + REFRESH_MARGIN < at) {
+                return Ok(token.clone());
+            }
+        }
+
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        let response = self.http.post(&self.token_url).form(&form).send().await?;
+        if !response.status().is_success() {
+            return Err(NanoError::Auth(format!(
+                "Token request failed with status: {}",
+                response.status()
+            )));
+        }
+        let body = response.json::<TokenResponse>().await?;
+        let expires_at = body
+            .expires_in
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        *cached = Some((body.access_token.clone(), expires_at));
+        Ok(body.access_token)
+    }
+}
+
+impl AuthProvider for OAuthClientCredentials {
+    fn credentials(&self) -> BoxFuture<'_, Result<HeaderMap>> {
+        Box::pin(async move { bearer(&self.token().await?) })
+    }
+}
+
+/// Microsoft Entra ID（Azure AD）令牌，用于 Azure OpenAI
+///
+/// 使用客户端凭据模式获取 `https://cognitiveservices.azure.com/.default` 范围的令牌。
+#[derive(Debug)]
+pub struct AzureEntra(OAuthClientCredentials);
+
+impl AzureEntra {
+    /// 使用租户 ID 和应用注册的客户端凭据创建
+    pub fn new(
+        tenant_id: &str,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        let token_url = format!(
+            "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+            tenant_id
+        );
+        Self(
+            OAuthClientCredentials::new(token_url, client_id, client_secret)
+                .with_scope("https://cognitiveservices.azure.com/.default"),
+        )
+    }
+}
+
+impl AuthProvider for AzureEntra {
+    fn credentials(&self) -> BoxFuture<'_, Result<HeaderMap>> {
+        self.0.credentials()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_client_credentials_caches_token() {
+        let server = MockServer::start(vec![
+            MockResponse::json(serde_json::json!({"access_token": "t1", "expires_in": 3600})),
+            MockResponse::json(serde_json::json!({"access_token": "t2", "expires_in": 3600})),
+        ])
+        .await;
+        let provider =
+            OAuthClientCredentials::new(format!("{}/token", server.base), "id", "secret")
+                .with_scope("llm");
+
+        for _ in 0..2 {
+            let headers = provider.credentials().await.unwrap();
+            assert_eq!(headers[AUTHORIZATION], "Bearer t1");
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].body.contains("grant_type=client_credentials"));
+        assert!(requests[0].body.contains("scope=llm"));
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_renewed() {
+        let server = MockServer::start(vec![
+            MockResponse::json(serde_json::json!({"access_token": "t1", "expires_in": 30})),
+            MockResponse::json(serde_json::json!({"access_token": "t2", "expires_in": 3600})),
+        ])
+        .await;
+        let provider =
+            OAuthClientCredentials::new(format!("{}/token", server.base), "id", "secret");
+
+        assert_eq!(
+            provider.credentials().await.unwrap()[AUTHORIZATION],
+            "Bearer t1"
+        );
+        assert_eq!(
+            provider.credentials().await.unwrap()[AUTHORIZATION],
+            "Bearer t2"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! 压测模块
+//!
+//! 以固定 QPS 在指定时长内向一个或多个模型发送流式请求，统计延迟分位数、首 token 延迟
+//! (TTFT)、吞吐量和错误率，便于评估部署规模。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+    runtime,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+// ================================================================================================
+// 压测选项与报告
+// ================================================================================================
+
+/// 压测选项
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// 参与压测的模型，为空时使用客户端配置的模型
+    pub models: Vec<String>,
+    /// 提示集合，按顺序循环使用
+    pub prompts: Vec<String>,
+    /// 每秒发起的请求数
+    pub qps: f64,
+    /// 每个模型的压测时长
+    pub duration: Duration,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            models: Vec::new(),
+            prompts: vec!["Say hello.".into()],
+            qps: 1.0,
+            duration: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 延迟分位数（毫秒）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct Percentiles {
+    /// 中位数
+    pub p50: u64,
+    /// 90 分位
+    pub p90: u64,
+    /// 99 分位
+    pub p99: u64,
+    /// 最大值
+    pub max: u64,
+}
+
+impl Percentiles {
+    /// 计算一组样本的分位数（最近秩法），样本为空时全部为 0
+    pub fn from_samples(samples: &[u64]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let rank = |p: f64| {
+            if sorted.is_empty() {
+                return 0;
+            }
+            let index = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+            sorted[index - 1]
+        };
+        Self {
+            p50: rank(0.50),
+            p90: rank(0.90),
+            p99: rank(0.99),
+            max: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// 单个模型的压测结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BenchReport {
+    /// 模型名称
+    pub model: String,
+    /// 发起的请求数
+    pub requests: usize,
+    /// 失败的请求数
+    pub errors: usize,
+    /// 错误率（0.0-1.0）
+    pub error_rate: f64,
+    /// 成功请求的吞吐量（请求/秒）
+    pub throughput_rps: f64,
+    /// 成功请求的完整延迟
+    pub latency_ms: Percentiles,
+    /// 成功请求的首 token 延迟
+    pub ttft_ms: Percentiles,
+}
+
+/// 单个请求的测量结果
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    latency_ms: u64,
+    ttft_ms: Option<u64>,
+    ok: bool,
+}
+
+// ================================================================================================
+// 运行压测
+// ================================================================================================
+
+/// 按 `opts` 依次对每个模型进行压测
+///
+/// 各模型之间串行执行，避免相互争用并发配额；请求按 QPS 匀速发出，不等待前一个请求完成，
+/// 实际并发仍受客户端 `max_concurrent_requests` 约束。
+pub async fn run(client: &LLMClient, opts: &BenchOptions) -> Result<Vec<BenchReport>> {
+    if opts.prompts.is_empty() {
+        return Err(NanoError::InvalidRequest(
+            "Benchmark needs at least one prompt".into(),
+        ));
+    }
+    if !(opts.qps > 0.0 && opts.qps.is_finite()) {
+        return Err(NanoError::InvalidRequest(format!(
+            "Invalid QPS: {}",
+            opts.qps
+        )));
+    }
+    let models = if opts.models.is_empty() {
+        vec![client.config.model.clone()]
+    } else {
+        opts.models.clone()
+    };
+
+    let mut reports = Vec::with_capacity(models.len());
+    for model in &models {
+        reports.push(run_model(&client.with_model(model), opts).await);
+    }
+    Ok(reports)
+}
+
+async fn run_model(client: &LLMClient, opts: &BenchOptions) -> BenchReport {
+    let start = Instant::now();
+    let period = Duration::from_secs_f64(1.0 / opts.qps);
+    let mut next = start;
+    let mut tasks: Vec<JoinHandle<Sample>> = Vec::new();
+
+    for prompt in opts.prompts.iter().cycle() {
+        runtime::sleep_until(next).await;
+        next += period;
+        if start.elapsed() >= opts.duration {
+            break;
+        }
+        let client = client.clone();
+        let prompt = prompt.clone();
+        tasks.push(runtime::spawn(
+            async move { measure(&client, &prompt).await },
+        ));
+    }
+
+    let mut samples = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        samples.push(task.await.unwrap_or(Sample {
+            latency_ms: 0,
+            ttft_ms: None,
+            ok: false,
+        }));
+    }
+    summarize(&client.config.model, &samples, start.elapsed())
+}
+
+/// 发送一个流式请求并记录首 token 延迟和完整延迟
+async fn measure(client: &LLMClient, prompt: &str) -> Sample {
+    let start = Instant::now();
+    let mut ttft_ms = None;
+    let ok = match client.stream_generate(prompt).await {
+        Ok(stream) => {
+            let mut stream = Box::pin(stream);
+            let mut ok = true;
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(text) if !text.is_empty() => {
+                        ttft_ms.get_or_insert(start.elapsed().as_millis() as u64);
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        ok = false;
+                        break;
+                    }
+                }
+            }
+            ok
+        }
+        Err(_) => false,
+    };
+    Sample {
+        latency_ms: start.elapsed().as_millis() as u64,
+        ttft_ms,
+        ok,
+    }
+}
+
+fn summarize(model: &str, samples: &[Sample], elapsed: Duration) -> BenchReport {
+    let ok: Vec<&Sample> = samples.iter().filter(|s| s.ok).collect();
+    let latencies: Vec<u64> = ok.iter().map(|s| s.latency_ms).collect();
+    let ttfts: Vec<u64> = ok.iter().filter_map(|s| s.ttft_ms).collect();
+    let errors = samples.len() - ok.len();
+
+    BenchReport {
+        model: model.to_string(),
+        requests: samples.len(),
+        errors,
+        error_rate: errors as f64 / samples.len().max(1) as f64,
+        throughput_rps: ok.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        latency_ms: Percentiles::from_samples(&latencies),
+        ttft_ms: Percentiles::from_samples(&ttfts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let samples: Vec<u64> = (1..=100).rev().collect();
+        let p = Percentiles::from_samples(&samples);
+        assert_eq!(
+            p,
+            Percentiles {
+                p50: 50,
+                p90: 90,
+                p99: 99,
+                max: 100
+            }
+        );
+        assert_eq!(Percentiles::from_samples(&[]), Percentiles::default());
+        assert_eq!(Percentiles::from_samples(&[7]).p99, 7);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let sample = |latency_ms: u64, ok: bool| Sample {
+            latency_ms,
+            ttft_ms: ok.then_some(latency_ms / 2),
+            ok,
+        };
+        let samples = [
+            sample(100, true),
+            sample(300, true),
+            sample(0, false),
+            sample(200, true),
+        ];
+        let report = summarize("m", &samples, Duration::from_secs(2));
+        assert_eq!(report.requests, 4);
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.error_rate, 0.25);
+        assert_eq!(report.throughput_rps, 1.5);
+        assert_eq!(report.latency_ms.p50, 200);
+        assert_eq!(report.ttft_ms.max, 150);
+    }
+}
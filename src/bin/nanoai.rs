@@ -0,0 +1,87 @@
+//! # nanoai 命令行工具
+//!
+//! ```text
+//! nanoai chat [--tui] [--model <MODEL>]...
+//! ```
+//!
+//! - 配置从环境变量加载（见 `Config::from_env`），`--model` 可以重复指定，
+//!   第一个作为当前模型，全部作为终端界面中模型切换器的候选
+//! - 不带 `--tui` 时以逐行问答的方式运行，回复按 Markdown 渲染为终端样式
+
+use nanoai::client::LLMClient;
+use nanoai::config::Config;
+use nanoai::error::{NanoError, Result};
+use nanoai::render::MarkdownStream;
+use nanoai::session::ChatSession;
+use std::io::{self, BufRead, Write};
+
+const USAGE: &str = "usage: nanoai chat [--tui] [--model <MODEL>]...";
+
+/// `chat` 子命令的参数
+#[derive(Debug, Default)]
+struct ChatArgs {
+    tui: bool,
+    models: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ChatArgs> {
+    if args.next().as_deref() != Some("chat") {
+        return Err(NanoError::Config(USAGE.into()));
+    }
+    let mut parsed = ChatArgs::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| NanoError::Config(format!("missing value for {}\n{}", arg, USAGE)))
+        };
+        match arg.as_str() {
+            "--tui" => parsed.tui = true,
+            "--model" => parsed.models.push(value()?),
+            _ => {
+                return Err(NanoError::Config(format!(
+                    "unknown argument {}\n{}",
+                    arg, USAGE
+                )))
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    let mut config = Config::from_env()?;
+    if let Some(model) = args.models.first() {
+        config = config.with_model(model.clone());
+    }
+    let client = LLMClient::new(config);
+    if args.tui {
+        return nanoai::tui::run(client, args.models).await;
+    }
+
+    let mut session = ChatSession::new(client);
+    let mut renderer = MarkdownStream::new();
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut prompt = String::new();
+        if stdin.lock().read_line(&mut prompt)? == 0 {
+            return Ok(());
+        }
+        if prompt.trim().is_empty() {
+            continue;
+        }
+        let result = session
+            .send_streaming(prompt.trim(), |delta| {
+                print!("{}", renderer.push(delta));
+                let _ = io::stdout().flush();
+            })
+            .await;
+        println!("{}", renderer.finish());
+        if let Err(e) = result {
+            eprintln!("error: {}", e);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! 用量统计与预算控制模块
+use crate::error::{NanoError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 模型单价（美元 / 百万 token）
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Pricing {
+    /// 输入 token 单价
+    pub prompt_per_million: f64,
+    /// 输出 token 单价
+    pub completion_per_million: f64,
+}
+
+impl Pricing {
+    /// 计算给定用量的费用（美元）
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.prompt_per_million
+            + completion_tokens as f64 * self.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// 客户端用量预算
+///
+/// 累计用量达到任一上限后，后续请求返回 `NanoError::BudgetExceeded`。
+/// 费用上限需要配合 `pricing` 使用。
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Budget {
+    /// 累计 token 上限
+    pub max_tokens_total: Option<u64>,
+    /// 累计费用上限（美元）
+    pub max_cost_usd: Option<f64>,
+    /// 用于计算费用的模型单价
+    pub pricing: Option<Pricing>,
+}
+
+/// 用量快照
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct UsageSnapshot {
+    /// 请求次数
+    pub requests: u64,
+    /// 累计输入 token
+    pub prompt_tokens: u64,
+    /// 累计输出 token
+    pub completion_tokens: u64,
+    /// 累计费用（美元），未配置单价时为 0
+    pub cost_usd: f64,
+}
+
+impl UsageSnapshot {
+    /// 累计 token 总数
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// 线程安全的累计用量计数器
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    requests: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    /// 以百万分之一美元为单位累计费用，避免浮点原子操作
+    cost_micros: AtomicU64,
+}
+
+impl UsageTracker {
+    /// 记录一次请求的用量
+    pub fn record(&self, prompt_tokens: u64, completion_tokens: u64, pricing: Option<&Pricing>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.prompt_tokens
+            .fetch_add(prompt_tokens, Ordering::Relaxed);
+        self.completion_tokens
+            .fetch_add(completion_tokens, Ordering::Relaxed);
+        if let Some(pricing) = pricing {
+            let micros = (pricing.cost(prompt_tokens, completion_tokens) * 1_000_000.0).round();
+            self.cost_micros.fetch_add(micros as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// 获取当前累计用量
+    pub fn snapshot(&self) -> UsageSnapshot {
+        UsageSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            prompt_tokens: self.prompt_tokens.load(Ordering::Relaxed),
+            completion_tokens: self.completion_tokens.load(Ordering::Relaxed),
+            cost_usd: self.cost_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+
+    /// 检查累计用量是否已达到预算上限
+    pub(crate) fn check(&self, budget: Option<&Budget>) -> Result<()> {
+        let Some(budget) = budget else {
+            return Ok(());
+        };
+        let usage = self.snapshot();
+        if let Some(max) = budget.max_tokens_total {
+            if usage.total_tokens() >= max {
+                return Err(NanoError::BudgetExceeded(format!(
+                    "已使用 {} tokens，上限为 {}",
+                    usage.total_tokens(),
+                    max
+                )));
+            }
+        }
+        if let Some(max) = budget.max_cost_usd {
+            if usage.cost_usd >= max {
+                return Err(NanoError::BudgetExceeded(format!(
+                    "已花费 ${:.4}，上限为 ${:.4}",
+                    usage.cost_usd, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_accumulates_usage() {
+        let tracker = UsageTracker::default();
+        let pricing = Pricing {
+            prompt_per_million: 1.0,
+            completion_per_million: 2.0,
+        };
+        tracker.record(1_000, 500, Some(&pricing));
+        tracker.record(1_000, 500, None);
+
+        let usage = tracker.snapshot();
+        assert_eq!(usage.requests, 2);
+        assert_eq!(usage.total_tokens(), 3_000);
+        assert!((usage.cost_usd - 0.002).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_budget_check() {
+        let tracker = UsageTracker::default();
+        let budget = Budget {
+            max_tokens_total: Some(100),
+            ..Budget::default()
+        };
+        assert!(tracker.check(None).is_ok());
+        assert!(tracker.check(Some(&budget)).is_ok());
+
+        tracker.record(60, 40, None);
+        assert!(matches!(
+            tracker.check(Some(&budget)),
+            Err(NanoError::BudgetExceeded(_))
+        ));
+    }
+}
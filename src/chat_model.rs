@@ -0,0 +1,632 @@
+//! 通用模型接口模块
+//!
+//! [`ChatModel`] 抽象"给定消息生成回复"的能力。它是对象安全的，框架可以接受
+//! `Arc<dyn ChatModel>` 作为任意后端（真实客户端、模拟实现、带缓存或降级的包装等），
+//! 而不必依赖具体的 [`LLMClient`]。
+//!
+//! [`CachedClient`]、[`RetryClient`] 和 [`FallbackClient`] 包装任意 `ChatModel`，
+//! 可以显式地逐层组合缓存、重试和降级，而不只是通过 `Config` 的开关启用。
+use crate::{
+    client::{is_fallback_error, LLMClient},
+    error::{NanoError, Result},
+    options::RequestOptions,
+    runtime,
+    types::{Message, ResponseWithStats, Role},
+    utils::message,
+};
+use futures::{future::BoxFuture, stream, stream::BoxStream, FutureExt, StreamExt};
+use log::debug;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// ================================================================================================
+// 模型接口
+// ================================================================================================
+
+/// 可共享的模型对象，`Clone + Send + Sync`
+pub type SharedChatModel = Arc<dyn ChatModel>;
+
+/// 对话模型
+///
+/// 系统提示词作为 `messages` 中的 [`Role::System`] 消息传入。实现应是无状态的，
+/// 或在内部自行同步，因为同一个模型对象可能被多个任务同时调用。
+pub trait ChatModel: Debug + Send + Sync {
+    /// 模型名称
+    fn model_name(&self) -> &str;
+
+    /// 为消息列表生成完整响应
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>>;
+
+    /// 为消息列表生成文本增量流
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>>;
+
+    /// 为单个提示生成文本
+    fn complete<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let messages = [message(Role::User, prompt)];
+            let response = self.chat(&messages, &RequestOptions::default()).await?;
+            Ok(response.content)
+        })
+    }
+
+    /// 转换为可共享的模型对象
+    fn into_shared(self) -> SharedChatModel
+    where
+        Self: Sized + 'static,
+    {
+        Arc::new(self)
+    }
+}
+
+impl<T: ChatModel + ?Sized> ChatModel for Arc<T> {
+    fn model_name(&self) -> &str {
+        (**self).model_name()
+    }
+
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+        (**self).chat(messages, options)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+        (**self).chat_stream(messages, options)
+    }
+}
+
+impl<T: ChatModel + ?Sized> ChatModel for Box<T> {
+    fn model_name(&self) -> &str {
+        (**self).model_name()
+    }
+
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+        (**self).chat(messages, options)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+        (**self).chat_stream(messages, options)
+    }
+}
+
+// ================================================================================================
+// 客户端实现
+// ================================================================================================
+
+/// 分离系统消息：第一条系统消息作为系统提示词，未提供时使用配置中的系统提示词
+fn split_system(messages: &[Message]) -> (Option<&str>, &[Message]) {
+    match messages.split_first() {
+        Some((first, rest)) if first.role == Role::System => (Some(first.content.as_str()), rest),
+        _ => (None, messages),
+    }
+}
+
+impl ChatModel for LLMClient {
+    fn model_name(&self) -> &str {
+        &self.config.model
+    }
+
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+        let (system, messages) = split_system(messages);
+        self.generate_internal(system, messages, options).boxed()
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+        let (system, messages) = split_system(messages);
+        self.stream_internal(system, messages.to_vec(), options)
+            .boxed()
+    }
+}
+
+impl LLMClient {
+    /// 转换为可共享的模型对象
+    pub fn into_chat_model(self) -> SharedChatModel {
+        Arc::new(self)
+    }
+}
+
+// ================================================================================================
+// 包装模型
+// ================================================================================================
+
+/// 缓存完整响应的包装模型
+///
+/// 以消息列表和请求参数作为键，命中时直接返回缓存的响应。流式请求命中缓存时
+/// 以单个数据块返回缓存内容，未命中时直接转发且不写入缓存。
+#[derive(Debug)]
+pub struct CachedClient<M> {
+    inner: M,
+    ttl: Option<Duration>,
+    capacity: usize,
+    entries: Mutex<CacheEntries>,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    responses: HashMap<String, (Instant, ResponseWithStats)>,
+    /// 按写入顺序排列的键，超出容量时淘汰最早写入的条目
+    order: VecDeque<String>,
+}
+
+impl<M: ChatModel> CachedClient<M> {
+    /// 包装 `inner`，默认不过期、最多缓存 1000 条响应
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            ttl: None,
+            capacity: 1000,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// 设置缓存有效期
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// 设置最多缓存的响应数
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// 被包装的模型
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        *self.lock() = CacheEntries::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn key(&self, messages: &[Message], options: &RequestOptions) -> String {
+        serde_json::json!([self.inner.model_name(), messages, options]).to_string()
+    }
+
+    fn get(&self, key: &str) -> Option<ResponseWithStats> {
+        let mut entries = self.lock();
+        match entries.responses.get(key) {
+            Some((at, _)) if self.ttl.is_some_and(|ttl| at.elapsed() > ttl) => {
+                entries.responses.remove(key);
+                entries.order.retain(|k| k != key);
+                None
+            }
+            Some((_, response)) => Some(response.clone()),
+            None => None,
+        }
+    }
+
+    fn put(&self, key: String, response: &ResponseWithStats) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.lock();
+        if entries
+            .responses
+            .insert(key.clone(), (Instant::now(), response.clone()))
+            .is_none()
+        {
+            entries.order.push_back(key);
+        }
+        while entries.responses.len() > self.capacity {
+            match entries.order.pop_front() {
+                Some(oldest) => entries.responses.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+}
+
+impl<M: ChatModel> ChatModel for CachedClient<M> {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+        Box::pin(async move {
+            let key = self.key(messages, options);
+            if let Some(response) = self.get(&key) {
+                debug!("Cache hit for {}", self.inner.model_name());
+                return Ok(response);
+            }
+            let response = self.inner.chat(messages, options).await?;
+            self.put(key, &response);
+            Ok(response)
+        })
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+        Box::pin(async move {
+            match self.get(&self.key(messages, options)) {
+                Some(response) => Ok(stream::once(async move { Ok(response.content) }).boxed()),
+                None => self.inner.chat_stream(messages, options).await,
+            }
+        })
+    }
+}
+
+/// 对可重试的错误按指数退避重试的包装模型
+///
+/// 连接失败、超时、限流和 5xx 错误会被重试；限流错误给出等待时间时按该时间等待。
+/// 流式请求只重试建立流的阶段。
+#[derive(Debug)]
+pub struct RetryClient<M> {
+    inner: M,
+    max_retries: u32,
+    backoff: Duration,
+}
+
+impl<M: ChatModel> RetryClient<M> {
+    /// 包装 `inner`，默认最多重试 3 次、首次等待 500 毫秒
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// 设置最大重试次数
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 设置首次重试前的等待时间，之后每次翻倍
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    async fn retry<'a, T>(
+        &'a self,
+        mut call: impl FnMut() -> BoxFuture<'a, Result<T>>,
+    ) -> Result<T> {
+        let mut backoff = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    let delay = match &e {
+                        NanoError::RateLimit {
+                            retry_after: Some(retry_after),
+                            ..
+                        } => *retry_after,
+                        _ => backoff,
+                    };
+                    debug!("{} (attempt {}), retrying in {:?}", e, attempt, delay);
+                    runtime::sleep(delay).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// 可以重试的错误
+fn is_retryable(e: &NanoError) -> bool {
+    match e {
+        NanoError::Http(_) | NanoError::Connect(_) | NanoError::Timeout => true,
+        NanoError::RateLimit { .. } => true,
+        NanoError::Api { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
+
+impl<M: ChatModel> ChatModel for RetryClient<M> {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+        Box::pin(self.retry(move || self.inner.chat(messages, options)))
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+        Box::pin(self.retry(move || self.inner.chat_stream(messages, options)))
+    }
+}
+
+/// 依次尝试多个模型的包装模型
+///
+/// 前一个模型返回网络、超时、限流或 API 错误时尝试下一个，全部失败时返回最后一个错误。
+/// 流式请求只在建立流的阶段降级。
+#[derive(Debug, Clone)]
+pub struct FallbackClient {
+    models: Vec<SharedChatModel>,
+}
+
+impl FallbackClient {
+    /// 按优先顺序传入模型，至少需要一个，否则返回 `NanoError::InvalidRequest`
+    pub fn new(models: impl IntoIterator<Item = SharedChatModel>) -> Result<Self> {
+        let models: Vec<_> = models.into_iter().collect();
+        if models.is_empty() {
+            return Err(NanoError::InvalidRequest(
+                "FallbackClient requires at least one model".into(),
+            ));
+        }
+        Ok(Self { models })
+    }
+
+    async fn fallback<'a, T>(
+        &'a self,
+        call: impl Fn(&'a SharedChatModel) -> BoxFuture<'a, Result<T>>,
+    ) -> Result<T> {
+        let Some((last, rest)) = self.models.split_last() else {
+            return Err(NanoError::InvalidRequest(
+                "FallbackClient has no models".into(),
+            ));
+        };
+        for model in rest {
+            match call(model).await {
+                Err(e) if is_fallback_error(&e) => {
+                    debug!("{} failed: {}, falling back", model.model_name(), e);
+                }
+                result => return result,
+            }
+        }
+        call(last).await
+    }
+}
+
+impl ChatModel for FallbackClient {
+    fn model_name(&self) -> &str {
+        self.models[0].model_name()
+    }
+
+    fn chat<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+        Box::pin(self.fallback(move |model| model.chat(messages, options)))
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a RequestOptions,
+    ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+        Box::pin(self.fallback(move |model| model.chat_stream(messages, options)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, sse_response, MockResponse, MockServer};
+    use futures::StreamExt;
+
+    /// 接受任意后端的框架函数
+    async fn ask(model: SharedChatModel, prompt: &str) -> Result<String> {
+        model.complete(prompt).await
+    }
+
+    #[tokio::test]
+    async fn test_client_as_chat_model() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("hello")),
+            sse_response(&["str", "eam"]),
+        ])
+        .await;
+        let model = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_model("m"),
+        )
+        .into_chat_model();
+        assert_eq!(model.model_name(), "m");
+        assert_eq!(ask(model.clone(), "hi").await.unwrap(), "hello");
+
+        let messages = [message(Role::System, "be brief"), message(Role::User, "hi")];
+        let text: Vec<String> = model
+            .chat_stream(&messages, &RequestOptions::default())
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text.concat(), "stream");
+
+        let body: serde_json::Value = serde_json::from_str(&server.requests()[1].body).unwrap();
+        assert_eq!(body["messages"][0]["content"], "be brief");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+    }
+
+    /// 按顺序返回预设结果的模型
+    #[derive(Debug)]
+    struct Scripted {
+        name: &'static str,
+        results: Mutex<VecDeque<Result<String>>>,
+        calls: Mutex<u32>,
+    }
+
+    impl Scripted {
+        fn new(name: &'static str, results: Vec<Result<String>>) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                results: Mutex::new(results.into()),
+                calls: Mutex::new(0),
+            })
+        }
+
+        fn calls(&self) -> u32 {
+            *self.calls.lock().unwrap()
+        }
+
+        fn next(&self) -> Result<String> {
+            *self.calls.lock().unwrap() += 1;
+            self.results
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no more results")
+        }
+    }
+
+    impl ChatModel for Scripted {
+        fn model_name(&self) -> &str {
+            self.name
+        }
+
+        fn chat<'a>(
+            &'a self,
+            _: &'a [Message],
+            _: &'a RequestOptions,
+        ) -> BoxFuture<'a, Result<ResponseWithStats>> {
+            let result = self.next().map(|content| ResponseWithStats {
+                content,
+                stats: Default::default(),
+                citations: Vec::new(),
+            });
+            Box::pin(async move { result })
+        }
+
+        fn chat_stream<'a>(
+            &'a self,
+            _: &'a [Message],
+            _: &'a RequestOptions,
+        ) -> BoxFuture<'a, Result<BoxStream<'static, Result<String>>>> {
+            let result = self
+                .next()
+                .map(|text| stream::once(async move { Ok(text) }).boxed());
+            Box::pin(async move { result })
+        }
+    }
+
+    fn server_error() -> NanoError {
+        NanoError::Api {
+            status: 503,
+            code: None,
+            message: "unavailable".into(),
+            request_id: None,
+            raw_body: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_client() {
+        let inner = Scripted::new("m", vec![Ok("a".into()), Ok("b".into()), Ok("c".into())]);
+        let cached = CachedClient::new(inner.clone()).with_capacity(1);
+
+        assert_eq!(cached.complete("x").await.unwrap(), "a");
+        assert_eq!(cached.complete("x").await.unwrap(), "a");
+        assert_eq!(inner.calls(), 1);
+
+        // 容量为 1，写入新键后旧键被淘汰
+        assert_eq!(cached.complete("y").await.unwrap(), "b");
+        assert_eq!(cached.complete("x").await.unwrap(), "c");
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_client() {
+        let inner = Scripted::new(
+            "m",
+            vec![
+                Err(server_error()),
+                Err(NanoError::Timeout),
+                Ok("ok".into()),
+            ],
+        );
+        let retry = RetryClient::new(inner.clone()).with_backoff(Duration::from_millis(1));
+        assert_eq!(retry.complete("x").await.unwrap(), "ok");
+        assert_eq!(inner.calls(), 3);
+
+        let inner = Scripted::new("m", vec![Err(NanoError::InvalidRequest("bad".into()))]);
+        let retry = RetryClient::new(inner.clone()).with_backoff(Duration::from_millis(1));
+        assert!(matches!(
+            retry.complete("x").await,
+            Err(NanoError::InvalidRequest(_))
+        ));
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_client() {
+        let primary = Scripted::new("a", vec![Err(server_error()), Err(NanoError::Cancelled)]);
+        let backup = Scripted::new("b", vec![Ok("from b".into())]);
+        assert!(matches!(
+            FallbackClient::new([]),
+            Err(NanoError::InvalidRequest(_))
+        ));
+        let fallback = FallbackClient::new([
+            primary.clone() as SharedChatModel,
+            backup.clone().into_shared(),
+        ])
+        .unwrap();
+        assert_eq!(fallback.model_name(), "a");
+
+        let text: Vec<String> = fallback
+            .chat_stream(&[], &RequestOptions::default())
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text, ["from b"]);
+
+        // 非降级错误直接返回
+        assert!(matches!(
+            fallback.complete("x").await,
+            Err(NanoError::Cancelled)
+        ));
+        assert_eq!((primary.calls(), backup.calls()), (2, 1));
+    }
+}
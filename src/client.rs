@@ -1,147 +1,923 @@
 //! LLM 客户端核心模块
 use crate::{
+    auth,
+    budget::{UsageSnapshot, UsageTracker},
     config::Config,
-    error::{NanoError, Result},
-    stream::StreamWrapper,
-    types::{CompletionResponse, Message, RequestStats, ResponseWithStats, Role, StreamCompletionResponse},
-    utils::{message, prepare_messages},
+    context::{fit_messages, ContextPolicy},
+    error::{retry_after_headers, NanoError, Result},
+    glossary::{Glossary, GlossaryStream},
+    guardrail,
+    hooks::{FallbackEvent, Hooks, RateLimitEvent, RetryEvent},
+    interaction_log::InteractionLog,
+    jobs::JobQueue,
+    language::{self, Language},
+    latency::LatencyTracker,
+    options::RequestOptions,
+    pool::HostTracker,
+    priority::{Priority, PriorityPermit, PrioritySemaphore, QueueDepth},
+    progress::{Progress, ProgressSender},
+    recorder::FlightRecorder,
+    report::ReportTracker,
+    responses::ApiBackend,
+    runtime,
+    stream::{
+        parse_chunk_event, CompletionStream, PartialJson, StopFilter, StreamEvent, StreamHandle,
+        StreamWrapper,
+    },
+    template,
+    tenant::{Tenant, TenantRegistry},
+    tokenizer, trace, transform,
+    transport::Transport,
+    types::{
+        CompletionResponse, FinishReason, Message, RequestStats, ResponseWithStats, Role,
+        StreamCompletionResponse,
+    },
+    utils::{message, parse_json_lenient, parse_response, prepare_messages, validate_roles},
 };
-use futures::{Stream, StreamExt};
-use log::error;
+use async_stream::try_stream;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use log::{debug, error, warn};
 use reqwest::{
-    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
-    Client, RequestBuilder, Response,
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    Client, Method, Request, RequestBuilder, Response, StatusCode,
 };
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::Semaphore;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, OnceCell};
 
 // ================================================================================================
 // 核心客户端模块
 // ================================================================================================
 
+/// 幂等键标头
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+/// 组织 ID 标头
+const ORGANIZATION_HEADER: &str = "OpenAI-Organization";
+/// 项目 ID 标头
+const PROJECT_HEADER: &str = "OpenAI-Project";
+
+/// 一次逻辑请求的重试情况
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Attempts {
+    /// 实际发送的次数
+    pub(crate) count: u32,
+    /// 退避等待的总时长
+    pub(crate) backoff: Duration,
+}
+
+impl Attempts {
+    /// 将重试情况和实际服务的端点记入统计信息
+    pub(crate) fn record(&self, stats: &mut RequestStats, response: &Response) {
+        stats.attempts = self.count;
+        stats.backoff_ms = self.backoff.as_millis() as u64;
+        stats.endpoint = Some(response.url().to_string());
+    }
+}
+
+/// 一次请求占用的并发配额，释放时归还全局配额和租户配额
+pub(crate) struct RequestPermit {
+    _permit: PriorityPermit,
+    _tenant: Option<TenantPermit>,
+}
+
+/// 租户并发配额，释放时归还
+struct TenantPermit(Arc<Tenant>);
+
+impl Drop for TenantPermit {
+    fn drop(&mut self) {
+        self.0.semaphore.add_permits(1);
+    }
+}
+
+/// 可以切换到降级模型的错误：服务端或网络故障，而非请求本身的问题
+pub(crate) fn is_fallback_error(e: &NanoError) -> bool {
+    matches!(
+        e,
+        NanoError::Http(_)
+            | NanoError::Connect(_)
+            | NanoError::Api { .. }
+            | NanoError::Timeout
+            | NanoError::RateLimit { .. }
+    )
+}
+
+/// 可以重试的响应状态码
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
 /// LLM 客户端
 ///
 /// 提供与 OpenRouter API 交互的核心功能，支持同步和流式请求
 #[derive(Debug, Clone)]
 pub struct LLMClient {
-    client: Arc<Client>,
-    config: Arc<Config>,
-    semaphore: Arc<Semaphore>,
-    stream_handler: StreamWrapper,
+    pub(crate) client: Arc<Client>,
+    pub(crate) config: Arc<Config>,
+    pub(crate) semaphore: Arc<PrioritySemaphore>,
+    /// 当前句柄发出请求的优先级
+    pub(crate) priority: Priority,
+    pub(crate) stream_handler: StreamWrapper,
+    /// 从 `/models` 查询到的上下文窗口长度缓存
+    pub(crate) model_context: Arc<OnceCell<Option<u32>>>,
+    /// 累计用量
+    pub(crate) usage: Arc<UsageTracker>,
+    /// 当前句柄所属的租户
+    pub(crate) tenant: Option<Arc<Tenant>>,
+    /// 所有租户的注册表
+    pub(crate) tenants: TenantRegistry,
+    /// 后台任务队列
+    pub(crate) jobs: Arc<JobQueue>,
+    /// 已注册的事件回调
+    pub(crate) hooks: Arc<Hooks>,
+    /// 按主机统计的请求情况
+    pub(crate) hosts: Arc<HostTracker>,
+    /// 最近的请求记录，未启用时为空
+    pub(crate) recorder: Option<Arc<FlightRecorder>>,
+    /// 交互日志，未启用时为空
+    pub(crate) interaction_log: Option<Arc<InteractionLog>>,
+    /// 按模型统计的用量
+    pub(crate) report: Arc<ReportTracker>,
+    /// 按模型统计的延迟
+    pub(crate) latency: Arc<LatencyTracker>,
+    /// 当前句柄的进度事件发送端
+    pub(crate) progress: Option<ProgressSender>,
 }
 
 impl LLMClient {
     /// 创建一个新的 `LLMClient` 实例
-    pub fn new(config: Config) -> Self {
-        let client = Client::builder()
+    pub fn new(mut config: Config) -> Self {
+        config.resolve_unix_socket();
+        let mut builder = Client::builder()
             .pool_idle_timeout(config.pool_idle_timeout)
             .pool_max_idle_per_host(config.pool_max_idle_per_host)
             .tcp_keepalive(config.tcp_keepalive)
             .tcp_nodelay(config.tcp_nodelay)
             .timeout(config.timeout)
-            .build()
-            .unwrap_or_else(|e| {
-                error!("Failed to build reqwest client: {}", e);
-                Client::new()
-            });
+            .http2_keep_alive_interval(config.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(config.http2_keep_alive_while_idle)
+            .http2_adaptive_window(config.http2_adaptive_window);
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = config.http2_keep_alive_timeout {
+            builder = builder.http2_keep_alive_timeout(timeout);
+        }
+        if config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        let mut resolved: Vec<(&str, Vec<SocketAddr>)> = Vec::new();
+        for (host, ip) in &config.resolve {
+            let addr = SocketAddr::new(*ip, 0);
+            match resolved
+                .iter_mut()
+                .find(|(h, _)| h.eq_ignore_ascii_case(host))
+            {
+                Some((_, addrs)) => addrs.push(addr),
+                None => resolved.push((host, vec![addr])),
+            }
+        }
+        for (host, addrs) in resolved {
+            builder = builder.resolve_to_addrs(host, &addrs);
+        }
+        if let Some(path) = &config.unix_socket {
+            #[cfg(unix)]
+            {
+                builder = builder.unix_socket(path.as_path());
+            }
+            #[cfg(not(unix))]
+            error!(
+                "Unix domain sockets are not supported on this platform: {}",
+                path.display()
+            );
+        }
+        let client = builder.build().unwrap_or_else(|e| {
+            error!("Failed to build reqwest client: {}", e);
+            Client::new()
+        });
 
-        let semaphore = Semaphore::new(config.max_concurrent_requests.unwrap_or(64));
+        let semaphore = PrioritySemaphore::new(config.max_concurrent_requests.unwrap_or(64));
+        let recorder = (config.flight_recorder > 0)
+            .then(|| Arc::new(FlightRecorder::new(config.flight_recorder)));
+        let interaction_log = config.interaction_log.as_ref().map(|path| {
+            Arc::new(InteractionLog::new(
+                path,
+                config.interaction_log_max_bytes,
+                config.interaction_log_max_files,
+            ))
+        });
 
+        let stream_handler = StreamWrapper::new().with_heartbeats(config.stream_heartbeats);
         Self {
             client: Arc::new(client),
             config: Arc::new(config),
             semaphore: Arc::new(semaphore),
-            stream_handler: StreamWrapper::new(),
+            priority: Priority::default(),
+            stream_handler,
+            model_context: Arc::new(OnceCell::new()),
+            usage: Arc::new(UsageTracker::default()),
+            tenant: None,
+            tenants: TenantRegistry::default(),
+            jobs: Arc::new(JobQueue::default()),
+            hooks: Arc::new(Hooks::default()),
+            hosts: Arc::new(HostTracker::default()),
+            recorder,
+            interaction_log,
+            report: Arc::new(ReportTracker::default()),
+            latency: Arc::new(LatencyTracker::default()),
+            progress: None,
+        }
+    }
+
+    /// 获取客户端创建以来的累计用量
+    pub fn usage(&self) -> UsageSnapshot {
+        self.usage.snapshot()
+    }
+
+    /// 获取使用另一个模型的句柄
+    ///
+    /// 句柄与原客户端共享连接池、并发配额和用量统计，其余配置保持不变。
+    pub fn with_model(&self, model: &str) -> LLMClient {
+        let config = Config {
+            model: model.to_string(),
+            ..(*self.config).clone()
+        };
+        LLMClient {
+            config: Arc::new(config),
+            model_context: Arc::new(OnceCell::new()),
+            ..self.clone()
+        }
+    }
+
+    /// 获取以指定优先级发出请求的句柄
+    ///
+    /// 句柄与原客户端共享连接池、并发配额和用量统计。并发配额耗尽时，
+    /// 高优先级请求先于低优先级请求获得配额。
+    pub fn with_priority(&self, priority: Priority) -> LLMClient {
+        LLMClient {
+            priority,
+            ..self.clone()
+        }
+    }
+
+    /// 获取发送进度事件的句柄
+    ///
+    /// 句柄与原客户端共享连接池、并发配额和用量统计，通过它发出的请求会把
+    /// [`Progress`] 事件发送到 `progress`。每个请求使用单独的句柄即可区分各自的进度。
+    pub fn with_progress(&self, progress: ProgressSender) -> LLMClient {
+        LLMClient {
+            progress: Some(progress),
+            ..self.clone()
+        }
+    }
+
+    /// 发送进度事件，未设置发送端时忽略
+    fn report_progress(&self, event: impl FnOnce() -> Progress) {
+        if let Some(progress) = &self.progress {
+            progress.send(event());
+        }
+    }
+
+    /// 通知重试回调和进度接收端
+    fn notify_retry(&self, event: RetryEvent) {
+        self.hooks.retry(&event);
+        self.report_progress(|| Progress::Retried(event));
+    }
+
+    /// 通知降级回调和进度接收端
+    fn notify_fallback(&self, event: FallbackEvent) {
+        self.hooks.fallback(&event);
+        self.report_progress(|| Progress::Fallback(event));
+    }
+
+    /// 各优先级等待并发配额的请求数
+    pub fn queue_depth(&self) -> QueueDepth {
+        self.semaphore.queue_depth()
+    }
+
+    /// 记录一次请求的用量，租户句柄同时计入租户用量
+    pub(crate) fn record_usage(&self, prompt_tokens: u64, completion_tokens: u64) {
+        self.record_model_usage(&self.config.model, prompt_tokens, completion_tokens);
+    }
+
+    /// 记录一次请求的用量，并按实际使用的模型计入用量报告
+    pub(crate) fn record_model_usage(
+        &self,
+        model: &str,
+        prompt_tokens: u64,
+        completion_tokens: u64,
+    ) {
+        let pricing = self.config.budget.as_ref().and_then(|b| b.pricing.as_ref());
+        self.usage.record(prompt_tokens, completion_tokens, pricing);
+        self.report
+            .record(model, prompt_tokens, completion_tokens, pricing);
+        if let Some(tenant) = &self.tenant {
+            tenant
+                .usage
+                .record(prompt_tokens, completion_tokens, pricing);
         }
     }
 
     /// 构建 API 请求所需的 HTTP 标头
-    fn build_headers(&self) -> Result<HeaderMap> {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.config.api_key))
-                .map_err(|e| NanoError::InvalidRequest(format!("Invalid API key: {}", e)))?,
-        );
+    pub(crate) async fn build_headers(&self) -> Result<HeaderMap> {
+        let mut headers = match &self.config.auth_provider {
+            Some(provider) => provider.credentials().await?,
+            None => auth::bearer(&self.config.api_key)?,
+        };
+        let scopes = [
+            (ORGANIZATION_HEADER, &self.config.organization),
+            (PROJECT_HEADER, &self.config.project),
+        ];
+        for (name, value) in scopes {
+            if let Some(value) = value {
+                headers.insert(
+                    name,
+                    HeaderValue::from_str(value).map_err(|e| {
+                        NanoError::InvalidRequest(format!("Invalid {} header: {}", name, e))
+                    })?,
+                );
+            }
+        }
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         Ok(headers)
     }
 
+    /// 构建单次请求的 HTTP 标头，包含调用方指定的幂等键
+    pub(crate) async fn request_headers(&self, options: &RequestOptions) -> Result<HeaderMap> {
+        let mut headers = self.build_headers().await?;
+        if let Some(key) = &options.idempotency_key {
+            headers.insert(
+                IDEMPOTENCY_KEY,
+                HeaderValue::from_str(key).map_err(|e| {
+                    NanoError::InvalidRequest(format!("Invalid idempotency key: {}", e))
+                })?,
+            );
+        }
+        Ok(headers)
+    }
+
     /// 使用重试逻辑发送 HTTP 请求
-    async fn call_api_with_retry(&self, request_builder: RequestBuilder) -> Result<Response> {
-        // Note: backoff crate is not used here to simplify, add it back if needed.
-        let permit = self
-            .semaphore
-            .acquire()
+    ///
+    /// 连接失败、超时以及 429/5xx 响应会按指数退避重试，最多重试 `max_retries` 次。
+    /// 超时的请求可能已在服务端执行，只有 GET 请求或携带幂等键的请求才会在超时后重试。
+    /// 启用 `idempotency_keys` 时，未携带幂等键的请求会生成一个，所有重试复用同一个键；
+    /// 请求 ID 和链路追踪标头同样只生成一次。
+    /// 请求体无法复制（例如流式上传）时不重试。
+    pub(crate) async fn call_api_with_retry(
+        &self,
+        request_builder: RequestBuilder,
+    ) -> Result<Response> {
+        self.call_api_with_attempts(request_builder)
             .await
-            .map_err(|e| NanoError::Api(format!("Semaphore acquisition failed: {}", e)))?;
+            .map(|(response, _)| response)
+    }
 
-        let response_result = request_builder.send().await;
-        drop(permit);
+    /// 与 [`Self::call_api_with_retry`] 相同，同时返回尝试次数和退避等待时间
+    pub(crate) async fn call_api_with_attempts(
+        &self,
+        request_builder: RequestBuilder,
+    ) -> Result<(Response, Attempts)> {
+        self.call_api_with_permit(request_builder)
+            .await
+            .map(|(response, attempts, _)| (response, attempts))
+    }
 
-        let response = response_result?;
+    /// 与 [`Self::call_api_with_attempts`] 相同，同时返回本次请求占用的并发配额
+    ///
+    /// 配额在返回值释放时归还，流式读取响应体时应一直持有。
+    pub(crate) async fn call_api_with_permit(
+        &self,
+        request_builder: RequestBuilder,
+    ) -> Result<(Response, Attempts, RequestPermit)> {
+        let mut request = request_builder.build()?;
+        if !self.config.transport.supports(request.url().path()) {
+            return Err(NanoError::InvalidRequest(format!(
+                "{} is not supported over the {:?} transport",
+                request.url().path(),
+                self.config.transport
+            )));
+        }
+        if self.config.idempotency_keys && !request.headers().contains_key(IDEMPOTENCY_KEY) {
+            let key = format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..));
+            request.headers_mut().insert(
+                IDEMPOTENCY_KEY,
+                HeaderValue::from_str(&key).map_err(|e| {
+                    NanoError::InvalidRequest(format!("Invalid idempotency key: {}", e))
+                })?,
+            );
+        }
+        trace::inject(&self.config, request.headers_mut())?;
+        let repeatable =
+            request.method() == Method::GET || request.headers().contains_key(IDEMPOTENCY_KEY);
 
-        if response.status().is_success() {
-            Ok(response)
-        } else {
-            let error_msg = format!("Request failed with status: {}", response.status());
-            Err(NanoError::Api(error_msg))
+        let mut backoff = self.config.retry_backoff;
+        let mut attempt = 0;
+        let mut waited = Duration::ZERO;
+        loop {
+            let retry = if attempt < self.config.max_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let response_result = self.send_with_permit(request).await;
+
+            let retryable = match &response_result {
+                Ok((response, _)) => is_retryable_status(response.status()),
+                Err(e) => e.is_connect() || (e.is_timeout() && repeatable),
+            };
+            // 服务端通过 `retry-after` 等标头要求的等待时间，超过请求超时时间时不再重试
+            let server_delay = response_result
+                .as_ref()
+                .ok()
+                .and_then(|(response, _)| retry_after_headers(response.headers()));
+            let retryable =
+                retryable && server_delay.is_none_or(|delay| delay <= self.config.timeout);
+            match retry {
+                Some(next) if retryable => {
+                    attempt += 1;
+                    let delay = server_delay.unwrap_or(backoff);
+                    debug!(
+                        "Request failed, retrying in {:?} (attempt {})",
+                        delay, attempt
+                    );
+                    let status = response_result.as_ref().ok().map(|(r, _)| r.status());
+                    self.notify_retry(RetryEvent {
+                        attempt,
+                        delay,
+                        status: status.map(|s| s.as_u16()),
+                        error: response_result.as_ref().err().map(|e| e.to_string()),
+                    });
+                    // 退避等待期间不占用并发配额
+                    drop(response_result);
+                    if status == Some(StatusCode::TOO_MANY_REQUESTS) {
+                        self.hooks.rate_limit(&RateLimitEvent { attempt, delay });
+                    }
+                    runtime::sleep(delay).await;
+                    waited += delay;
+                    backoff *= 2;
+                    request = next;
+                }
+                _ => {
+                    let (response, permit) = response_result?;
+                    if response.status().is_success() {
+                        let attempts = Attempts {
+                            count: attempt + 1,
+                            backoff: waited,
+                        };
+                        return Ok((response, attempts, permit));
+                    }
+                    return Err(NanoError::from_response(response).await);
+                }
+            }
+        }
+    }
+
+    /// 占用并发配额后发送一次请求，配额随响应一同返回
+    async fn send_with_permit(
+        &self,
+        request: Request,
+    ) -> reqwest::Result<(Response, RequestPermit)> {
+        self.report_progress(|| Progress::Queued);
+        // 限速等待期间不占用任何配额
+        if let Some(rate) = self.tenant.as_ref().and_then(|t| t.rate.as_ref()) {
+            rate.acquire().await;
+        }
+        // 先占用租户配额再占用全局配额，单个租户无法占满全局并发槽位
+        let tenant = match &self.tenant {
+            Some(tenant) => tenant.semaphore.acquire().await.ok().map(|permit| {
+                // 由 `TenantPermit` 负责归还，等待全局配额时被取消也不会泄漏
+                permit.forget();
+                TenantPermit(tenant.clone())
+            }),
+            None => None,
+        };
+        let permit = RequestPermit {
+            _permit: self.semaphore.acquire(self.priority).await,
+            _tenant: tenant,
+        };
+        self.report_progress(|| Progress::Sent {
+            model: self.config.model.clone(),
+        });
+        let host = self.hosts.start(request.url());
+        let result = match self.config.transport {
+            Transport::Http => self.client.execute(request).await,
+            #[cfg(feature = "websocket")]
+            Transport::WebSocket => {
+                Ok(crate::transport::execute_websocket(request, self.config.timeout).await)
+            }
+            #[cfg(feature = "grpc")]
+            Transport::Grpc => {
+                Ok(crate::transport::grpc::execute(request, self.config.timeout).await)
+            }
+        };
+        match result {
+            Ok(response) => Ok((response, permit)),
+            Err(e) => {
+                host.fail();
+                Err(e)
+            }
         }
     }
 
     /// 调用 API 并返回带统计信息的完整响应
-    async fn call_api_with_stats(&self, params: &Value) -> Result<ResponseWithStats> {
+    ///
+    /// 响应没有内容时返回 `NanoError::NoContent`；启用 `retry_on_empty` 时
+    /// 按 `max_retries` 和 `retry_backoff` 重新请求，统计信息中的次数包含这些重试。
+    async fn call_api_with_stats(
+        &self,
+        params: &Value,
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
+        let mut backoff = self.config.retry_backoff;
+        let mut attempts = 0;
+        let mut waited = Duration::ZERO;
+        loop {
+            let result = self.call_api_once(params, options).await;
+            match result {
+                Err(NanoError::NoContent)
+                    if self.config.retry_on_empty && attempts < self.config.max_retries =>
+                {
+                    attempts += 1;
+                    debug!(
+                        "Empty response, retrying in {:?} (attempt {})",
+                        backoff, attempts
+                    );
+                    self.notify_retry(RetryEvent {
+                        attempt: attempts,
+                        delay: backoff,
+                        status: None,
+                        error: Some(NanoError::NoContent.to_string()),
+                    });
+                    runtime::sleep(backoff).await;
+                    waited += backoff;
+                    backoff *= 2;
+                }
+                Ok(mut response) => {
+                    response.stats.attempts += attempts;
+                    response.stats.backoff_ms += waited.as_millis() as u64;
+                    return Ok(response);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 发送一次 `/chat/completions` 请求（含传输层重试）
+    async fn call_api_once(
+        &self,
+        params: &Value,
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
         let endpoint = format!("{}/chat/completions", self.config.api_base);
-        let headers = self.build_headers()?;
+        let headers = self.request_headers(options).await?;
         let request_builder = self.client.post(&endpoint).headers(headers).json(params);
 
-        let response = self.call_api_with_retry(request_builder).await?;
-        let completion = response.json::<CompletionResponse>().await?;
-        let content = completion
-            .choices
-            .first()
-            .map_or(String::new(), |c| c.message.content.clone());
+        let started = Instant::now();
+        let (response, attempts) = match self.call_api_with_attempts(request_builder).await {
+            Ok(result) => result,
+            Err(e) => {
+                self.record_exchange(&endpoint, params, started, Err(&e));
+                return Err(e);
+            }
+        };
+        let mut stats = RequestStats::default();
+        attempts.record(&mut stats, &response);
+        let body = response.bytes().await?;
+        self.record_exchange(&endpoint, params, started, Ok(&body));
+        let completion = parse_response::<CompletionResponse>(
+            &body,
+            self.config.strict_parsing,
+            &["/choices", "/choices/*/message"],
+        )?;
+        let choice = completion.choices.first().ok_or(NanoError::NoContent)?;
+        stats.finish_reason = Some(choice.finish_reason.clone())
+            .filter(|r| !r.is_empty())
+            .map(FinishReason::from);
+        if stats.finish_reason == Some(FinishReason::ContentFilter)
+            || choice.message.refusal.is_some()
+        {
+            return Err(NanoError::ContentFiltered {
+                categories: choice.filtered_categories(),
+                refusal: choice.message.refusal.clone(),
+            });
+        }
+        // 工具调用的响应本身没有文本内容
+        if choice.message.content.is_empty() && stats.finish_reason != Some(FinishReason::ToolCalls)
+        {
+            return Err(NanoError::NoContent);
+        }
+        let content = choice.message.content.clone();
+        let citations = choice.message.citations();
 
         let u = completion.usage;
-        let mut stats = RequestStats {
-            prompt_tokens: Some(u.prompt_tokens),
-            completion_tokens: Some(u.completion_tokens),
-            total_tokens: Some(u.total_tokens),
-            ..RequestStats::default()
-        };
+        stats.prompt_tokens = Some(u.prompt_tokens);
+        stats.completion_tokens = Some(u.completion_tokens);
+        stats.total_tokens = Some(u.total_tokens);
+        stats.cached_tokens = u.prompt_tokens_details.map(|d| d.cached_tokens);
+        stats.prompt_audio_tokens = u.prompt_tokens_details.map(|d| d.audio_tokens);
+        stats.reasoning_tokens = u.completion_tokens_details.map(|d| d.reasoning_tokens);
+        stats.completion_audio_tokens = u.completion_tokens_details.map(|d| d.audio_tokens);
         stats.model = self.config.model.clone();
+        stats.served_model = Some(completion.model).filter(|m| !m.is_empty());
         stats.timestamp = Some(std::time::SystemTime::now());
 
-        Ok(ResponseWithStats { content, stats })
+        Ok(ResponseWithStats {
+            content,
+            stats,
+            citations,
+        })
     }
 
     /// 内部辅助函数，用于生成响应，处理上下文和统计信息
-    async fn generate_internal(
+    pub(crate) async fn generate_internal(
         &self,
         system_msg: Option<&str>,
         messages: &[Message],
+        options: &RequestOptions,
     ) -> Result<ResponseWithStats> {
         let start_time = Instant::now();
-        let system_message = system_msg.unwrap_or(&self.config.system_message);
-        let prepared_messages = prepare_messages(system_message, messages);
-
-        let params = serde_json::json!({
-            "model": &self.config.model,
-            "messages": prepared_messages,
-            "temperature": &self.config.temperature,
-            "top_p": &self.config.top_p,
-            "max_tokens": &self.config.max_tokens,
-            "stream": false,
+        let result = self.run_generation(system_msg, messages, options).await;
+        self.report_progress(|| match &result {
+            Ok(response) => Progress::Done {
+                elapsed: start_time.elapsed(),
+                completion_tokens: response.stats.completion_tokens.unwrap_or_default() as u64,
+            },
+            Err(e) => Progress::Failed {
+                error: e.to_string(),
+            },
         });
+        result
+    }
 
-        let mut response = self.call_api_with_stats(&params).await?;
+    async fn run_generation(
+        &self,
+        system_msg: Option<&str>,
+        messages: &[Message],
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
+        let start_time = Instant::now();
+        self.usage.check(self.config.budget.as_ref())?;
+        validate_roles(messages)?;
+        let default_system;
+        let system_message = match system_msg {
+            Some(system_message) => system_message,
+            None => {
+                default_system = template::system_message(&self.config)?;
+                &default_system
+            }
+        };
+        let transformed =
+            transform::apply_messages(&self.config.prompt_transforms, system_message, messages);
+        let (system_message, messages) = match &transformed {
+            Some((system, messages)) => (system.as_str(), messages.as_slice()),
+            None => (system_message, messages),
+        };
+        let (mut fitted, mut result) = self.fit_and_send(system_message, messages, options).await;
+        let mut current = self.config.model.clone();
+        for model in &self.config.fallback_models {
+            let reason = match &result {
+                Err(e) if is_fallback_error(e) => e.to_string(),
+                _ => break,
+            };
+            self.report.record_error(&current);
+            self.notify_fallback(FallbackEvent {
+                from: current,
+                to: model.clone(),
+                reason,
+            });
+            // 回退模型的上下文长度可能不同，按该模型重新裁剪
+            (fitted, result) = self
+                .with_model(model)
+                .fit_and_send(system_message, messages, options)
+                .await;
+            current = model.clone();
+        }
+        let messages = fitted.as_deref().unwrap_or(messages);
+        let mut response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                self.report.record_error(&current);
+                self.log_interaction(&current, system_message, messages, options, Err(&e));
+                return Err(e);
+            }
+        };
+        if let Some(language) = options.language {
+            response = self
+                .enforce_language(
+                    language,
+                    &current,
+                    system_message,
+                    messages,
+                    options,
+                    response,
+                )
+                .await;
+        }
+        if let Some(filter) = self.stop_filter(options) {
+            let (head, stopped) = filter.truncate(&response.content);
+            if stopped {
+                response.content.truncate(head.len());
+                response.stats.finish_reason = Some(FinishReason::Stop);
+            }
+        }
+        if !self.config.response_transforms.is_empty() {
+            response.content =
+                transform::apply(&self.config.response_transforms, &response.content);
+        }
+        if let Some(glossary) = &self.config.glossary {
+            response.content = glossary.apply(&response.content);
+        }
+        self.record_model_usage(
+            &current,
+            response.stats.prompt_tokens.unwrap_or_default() as u64,
+            response.stats.completion_tokens.unwrap_or_default() as u64,
+        );
         let duration = start_time.elapsed();
         response.stats.duration_ms = duration.as_millis() as u64;
+        response.stats.prompt_version = options.prompt_version.clone();
+        self.latency.record(&current, duration);
+        if let Err(e) = guardrail::enforce(&self.config.guardrails, &response.content).await {
+            self.log_interaction(&current, system_message, messages, options, Err(&e));
+            return Err(e);
+        }
+        self.log_interaction(&current, system_message, messages, options, Ok(&response));
+        self.emit_stats(&response.stats);
         Ok(response)
     }
 
+    /// 按当前模型的上下文长度调整消息后发送，同时返回调整后的消息
+    async fn fit_and_send(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+    ) -> (Option<Vec<Message>>, Result<ResponseWithStats>) {
+        match self.fit_context(system_message, messages).await {
+            Ok(fitted) => {
+                let messages = fitted.as_deref().unwrap_or(messages);
+                let result = self
+                    .send_generation(system_message, messages, options)
+                    .await;
+                (fitted, result)
+            }
+            Err(e) => (None, Err(e)),
+        }
+    }
+
+    /// 回复语言与 `language` 不符时，附加语言要求重新请求
+    ///
+    /// 最多重新请求 `language_retries` 次，被丢弃的回复同样计入用量。
+    /// 重新请求失败时保留上一次的回复。
+    async fn enforce_language(
+        &self,
+        language: Language,
+        model: &str,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+        mut response: ResponseWithStats,
+    ) -> ResponseWithStats {
+        let fallback;
+        let client = if model == self.config.model {
+            self
+        } else {
+            fallback = self.with_model(model);
+            &fallback
+        };
+        let system_message = language::instruction(system_message, language);
+        for attempt in 1..=self.config.language_retries {
+            let detected = match language::detect(&response.content) {
+                Some(detected) if detected != language => detected,
+                _ => break,
+            };
+            let error = format!("Expected a reply in {}, got {}", language, detected);
+            debug!("{}, retrying (attempt {})", error, attempt);
+            self.notify_retry(RetryEvent {
+                attempt,
+                delay: Duration::ZERO,
+                status: None,
+                error: Some(error),
+            });
+            match client
+                .send_generation(&system_message, messages, options)
+                .await
+            {
+                Ok(mut retried) => {
+                    self.record_model_usage(
+                        model,
+                        response.stats.prompt_tokens.unwrap_or_default() as u64,
+                        response.stats.completion_tokens.unwrap_or_default() as u64,
+                    );
+                    retried.stats.attempts += response.stats.attempts;
+                    response = retried;
+                }
+                Err(e) => {
+                    debug!("Language retry failed, keeping the previous reply: {}", e);
+                    break;
+                }
+            }
+        }
+        response
+    }
+
+    /// 启用客户端停止序列且请求设置了 `stop` 时，返回对应的过滤器
+    fn stop_filter(&self, options: &RequestOptions) -> Option<StopFilter> {
+        match &options.stop {
+            Some(stops) if self.config.client_side_stop && !stops.is_empty() => {
+                Some(StopFilter::new(stops))
+            }
+            _ => None,
+        }
+    }
+
+    /// 按配置的 API 后端发送一次生成请求
+    async fn send_generation(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
+        match self.config.api_backend {
+            ApiBackend::ChatCompletions => {
+                let prepared_messages = prepare_messages(system_message, messages);
+                let mut params = serde_json::json!({
+                    "model": &self.config.model,
+                    "messages": prepared_messages,
+                    "stream": false,
+                });
+                options.apply_chat(&self.config, &mut params);
+                self.call_api_with_stats(&params, options).await
+            }
+            ApiBackend::Responses => {
+                self.call_responses_with_stats(system_message, messages, options)
+                    .await
+            }
+        }
+    }
+
+    /// 按 `ContextPolicy` 检查消息列表是否超出上下文窗口
+    ///
+    /// 返回 `None` 表示无需调整，可直接使用原消息列表
+    async fn fit_context(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+    ) -> Result<Option<Vec<Message>>> {
+        if self.config.context_policy == ContextPolicy::Disabled {
+            return Ok(None);
+        }
+        let limit = match self.context_length().await {
+            Ok(Some(limit)) => limit,
+            Ok(None) => {
+                debug!(
+                    "Context length of {} is unknown, skipping check",
+                    self.config.model
+                );
+                return Ok(None);
+            }
+            // 查询模型列表失败不应阻断请求
+            Err(e) => {
+                warn!(
+                    "Failed to look up context length of {}, skipping check: {}",
+                    self.config.model, e
+                );
+                return Ok(None);
+            }
+        };
+        fit_messages(
+            &self.config.model,
+            system_message,
+            messages,
+            self.config.max_tokens,
+            limit as usize,
+            self.config.context_policy,
+        )
+        .map(Some)
+    }
+
+    /// 按当前模型计算文本的 token 数量
+    ///
+    /// 启用 `tokenizer` feature 时为精确计数，否则为估算值。
+    pub fn count_tokens(&self, text: &str) -> usize {
+        tokenizer::count_tokens(&self.config.model, text)
+    }
+
+    /// 按当前模型计算消息列表的 token 数量（包含系统消息和格式开销）
+    pub fn count_message_tokens(&self, messages: &[Message]) -> usize {
+        // 模板渲染失败时按未渲染的模板估算
+        let system_message = template::system_message(&self.config).unwrap_or_else(|_| {
+            self.config
+                .system_template
+                .as_deref()
+                .unwrap_or_default()
+                .into()
+        });
+        let prepared = prepare_messages(&system_message, messages);
+        tokenizer::count_message_tokens(&self.config.model, &prepared)
+    }
+
     /// 为给定的提示生成响应
     pub async fn generate(&self, prompt: &str) -> Result<String> {
         self.generate_with_stats(prompt)
@@ -151,8 +927,42 @@ impl LLMClient {
 
     /// 为给定的提示生成响应，包括性能统计信息
     pub async fn generate_with_stats(&self, prompt: &str) -> Result<ResponseWithStats> {
+        self.generate_with_options(prompt, &RequestOptions::default())
+            .await
+    }
+
+    /// 为给定的提示生成响应，并在本次请求中覆盖部分采样参数
+    pub async fn generate_with_options(
+        &self,
+        prompt: &str,
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
         let messages = vec![message(Role::User, prompt)];
-        self.generate_internal(None, &messages).await
+        self.generate_internal(None, &messages, options).await
+    }
+
+    /// 为给定的提示生成响应，并限制本次调用的总时长
+    ///
+    /// 截止时间独立于 `Config::timeout`，包含排队、重试和降级的全部时间。
+    /// 超时后中止请求并返回 `NanoError::Timeout`。
+    pub async fn generate_with_timeout(&self, prompt: &str, timeout: Duration) -> Result<String> {
+        runtime::timeout(timeout, self.generate(prompt))
+            .await
+            .unwrap_or(Err(NanoError::Timeout))
+    }
+
+    /// 以指定优先级为给定的提示生成响应
+    pub async fn generate_with_priority(&self, prompt: &str, priority: Priority) -> Result<String> {
+        self.with_priority(priority).generate(prompt).await
+    }
+
+    /// 为给定的提示生成响应，并将其解析为指定类型
+    ///
+    /// 模型输出的 JSON 经常带有代码块围栏或尾随逗号等小问题，
+    /// 直接解析失败时会先使用 [`crate::utils::repair_json`] 修复后再解析。
+    pub async fn generate_typed<T: DeserializeOwned>(&self, prompt: &str) -> Result<T> {
+        let content = self.generate(prompt).await?;
+        parse_json_lenient(&content)
     }
 
     /// 为给定的消息列表生成响应
@@ -167,7 +977,55 @@ impl LLMClient {
         &self,
         messages: &[Message],
     ) -> Result<ResponseWithStats> {
-        self.generate_internal(None, messages).await
+        self.generate_internal(None, messages, &RequestOptions::default())
+            .await
+    }
+
+    /// 以指定的系统消息和对话历史生成响应
+    ///
+    /// `system` 仅对本次请求生效，覆盖 `Config` 中的系统消息；`history` 为完整的对话历史，
+    /// 通常以用户消息结尾。
+    pub async fn generate_with_context(&self, system: &str, history: &[Message]) -> Result<String> {
+        self.generate_internal(Some(system), history, &RequestOptions::default())
+            .await
+            .map(|res| res.content)
+    }
+
+    /// 以助手消息的开头预填充输出，生成其后的续写
+    ///
+    /// 发送 `[用户提示, 助手预填充]`，返回包含预填充在内的完整文本。可用于约束输出的开头
+    /// （例如以 ```` ```json ```` 开始）。
+    pub async fn generate_with_prefill(&self, prompt: &str, prefill: &str) -> Result<String> {
+        self.continue_response(&[message(Role::User, prompt)], prefill)
+            .await
+    }
+
+    /// 从不完整的助手回复继续生成，例如续写被 `max_tokens` 截断的输出
+    ///
+    /// `partial` 作为末尾的助手消息追加到 `history` 之后发送，返回 `partial` 与续写拼接后的
+    /// 完整文本。服务端若在返回内容中重复了 `partial`，不会重复拼接。
+    pub async fn continue_response(&self, history: &[Message], partial: &str) -> Result<String> {
+        let mut messages = history.to_vec();
+        messages.push(message(Role::Assistant, partial));
+        let continuation = self
+            .generate_internal(None, &messages, &RequestOptions::default())
+            .await?
+            .content;
+        if continuation.starts_with(partial) {
+            Ok(continuation)
+        } else {
+            Ok(format!("{}{}", partial, continuation))
+        }
+    }
+
+    /// 以指定的系统消息和对话历史生成流式响应
+    pub async fn generate_stream_with_context(
+        &self,
+        system: &str,
+        history: &[Message],
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        self.stream_internal(Some(system), history.to_vec(), &RequestOptions::default())
+            .await
     }
 
     /// 为给定的提示生成流式响应
@@ -176,7 +1034,86 @@ impl LLMClient {
         prompt: &str,
     ) -> Result<impl Stream<Item = Result<String>>> {
         let messages = vec![message(Role::User, prompt)];
-        self.stream_internal(messages).await
+        self.stream_internal(None, messages, &RequestOptions::default())
+            .await
+    }
+
+    /// 为给定的提示生成流式响应，并在本次请求中覆盖部分采样参数
+    ///
+    /// 与 [`Self::generate_with_options`] 使用相同的参数规则：种子、停止序列、惩罚项等
+    /// 都会写入流式请求体。
+    pub async fn stream_generate_with_options(
+        &self,
+        prompt: &str,
+        options: &RequestOptions,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        let messages = vec![message(Role::User, prompt)];
+        self.stream_internal(None, messages, options).await
+    }
+
+    /// 为给定的提示生成流式响应，并限制整个流的总时长
+    ///
+    /// 截止时间覆盖建立连接和读取全部数据块。到期后流产生一个 `NanoError::Timeout`
+    /// 并结束，底层请求随之中止。
+    pub async fn stream_generate_with_timeout(
+        &self,
+        prompt: &str,
+        timeout: Duration,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        let deadline = Instant::now() + timeout;
+        let mut chunks = runtime::timeout_at(deadline, self.stream_generate(prompt))
+            .await
+            .unwrap_or(Err(NanoError::Timeout))?
+            .boxed();
+        Ok(async_stream::stream! {
+            loop {
+                match runtime::timeout_at(deadline, chunks.next()).await {
+                    Ok(Some(chunk)) => yield chunk,
+                    Ok(None) => break,
+                    Err(_) => {
+                        yield Err(NanoError::Timeout);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// 为给定的提示生成流式响应，同时返回 [`StreamHandle`]
+    ///
+    /// 流结束后可通过句柄读取响应 ID、模型和结束原因。
+    pub async fn stream_generate_with_handle(
+        &self,
+        prompt: &str,
+        options: &RequestOptions,
+    ) -> Result<(impl Stream<Item = Result<String>>, StreamHandle)> {
+        let messages = vec![message(Role::User, prompt)];
+        self.stream_internal_with_handle(None, messages, options)
+            .await
+    }
+
+    /// 为给定的提示生成原始数据块流
+    ///
+    /// 始终调用 `/chat/completions`，数据块不经过内容转换和客户端停止序列处理。
+    /// 流在读取期间占用一个并发配额，读完或提前释放时中止 HTTP 请求并归还配额。
+    pub async fn stream_chunks(
+        &self,
+        prompt: &str,
+        options: &RequestOptions,
+    ) -> Result<CompletionStream> {
+        self.usage.check(self.config.budget.as_ref())?;
+        let messages = vec![message(Role::User, prompt)];
+        let request_builder = self
+            .chat_stream_request(&template::system_message(&self.config)?, &messages, options)
+            .await?;
+        let (response, permit) = self.call_api_for_stream(request_builder).await?;
+        let strict = self.config.strict_parsing;
+        let stream = self
+            .stream_handler
+            .stream_with(response.bytes_stream(), move |event| {
+                parse_chunk_event(event, strict)
+            });
+        Ok(CompletionStream::with_permit(stream, permit))
     }
 
     /// 为给定的消息列表生成流式响应
@@ -184,41 +1121,1259 @@ impl LLMClient {
         &self,
         messages: Vec<Message>,
     ) -> Result<impl Stream<Item = Result<String>>> {
-        self.stream_internal(messages).await
+        self.stream_internal(None, messages, &RequestOptions::default())
+            .await
+    }
+
+    /// 为给定的提示生成流式响应，并将事件发送到通道
+    ///
+    /// 内部负责轮询流：每个文本片段发送一个 [`StreamEvent::Delta`]，正常结束时发送
+    /// [`StreamEvent::Done`]，请求或流出错时发送 [`StreamEvent::Error`]。启用
+    /// `stream_heartbeats` 时，服务端心跳发送为 [`StreamEvent::Heartbeat`]。接收端关闭后
+    /// 立即停止轮询并中止请求。适合 actor 模型或 UI 事件循环。
+    pub async fn stream_to_channel(&self, prompt: &str, tx: mpsc::Sender<StreamEvent>) {
+        let (mut chunks, handle) = match self
+            .stream_generate_with_handle(prompt, &RequestOptions::default())
+            .await
+        {
+            Ok((chunks, handle)) => (Box::pin(chunks), handle),
+            Err(e) => {
+                let _ = tx.send(StreamEvent::Error(e)).await;
+                return;
+            }
+        };
+        let mut heartbeats = handle.heartbeats();
+        loop {
+            let event = tokio::select! {
+                chunk = chunks.next() => match chunk {
+                    Some(Ok(text)) => StreamEvent::Delta(text),
+                    Some(Err(e)) => StreamEvent::Error(e),
+                    None => StreamEvent::Done,
+                },
+                Ok(()) = heartbeats.changed() => {
+                    StreamEvent::Heartbeat(heartbeats.borrow_and_update().clone().unwrap_or_default())
+                }
+                _ = tx.closed() => return,
+            };
+            let finished = matches!(event, StreamEvent::Error(_) | StreamEvent::Done);
+            if tx.send(event).await.is_err() || finished {
+                return;
+            }
+        }
+    }
+
+    /// 为给定的提示生成流式响应，通过回调接收文本片段和最终结果
+    ///
+    /// 内部负责轮询流：每个非空的文本片段调用一次 `on_chunk`，流结束后以完整内容和
+    /// 统计信息调用一次 `on_done`。请求或流出错时以该错误调用 `on_done`，
+    /// 此前已经交给 `on_chunk` 的片段不会撤回。流式响应的 token 数为客户端估算值。
+    pub async fn generate_streaming(
+        &self,
+        prompt: &str,
+        mut on_chunk: impl FnMut(&str),
+        on_done: impl FnOnce(Result<ResponseWithStats>),
+    ) {
+        on_done(self.collect_streaming(prompt, &mut on_chunk).await);
+    }
+
+    async fn collect_streaming(
+        &self,
+        prompt: &str,
+        on_chunk: &mut impl FnMut(&str),
+    ) -> Result<ResponseWithStats> {
+        let (chunks, handle) = self
+            .stream_generate_with_handle(prompt, &RequestOptions::default())
+            .await?;
+        let mut chunks = Box::pin(chunks);
+        let mut content = String::new();
+        while let Some(text) = chunks.next().await.transpose()? {
+            if !text.is_empty() {
+                on_chunk(&text);
+                content.push_str(&text);
+            }
+        }
+        Ok(ResponseWithStats {
+            content,
+            stats: handle.stats().unwrap_or_default(),
+            citations: Vec::new(),
+        })
+    }
+
+    /// 为给定的提示生成流式结构化响应
+    ///
+    /// 每收到新的文本片段都会尝试将已到达的部分解析为 `T`，解析结果发生变化时产出一个
+    /// 逐步填充的值。尚不完整、无法反序列化为 `T` 的中间状态会被跳过，因此 `T` 中可能
+    /// 迟到的字段应使用 `Option` 或 `#[serde(default)]`。流结束时若最终结果仍无法解析，
+    /// 则返回 `NanoError::Json`。
+    pub async fn stream_typed<T>(&self, prompt: &str) -> Result<impl Stream<Item = Result<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut chunks = self.stream_generate(prompt).await?;
+        Ok(try_stream! {
+            let mut partial = PartialJson::new();
+            let mut last: Option<Value> = None;
+            while let Some(chunk) = chunks.next().await {
+                partial.push(&chunk?);
+                let Some(value) = partial.value() else { continue };
+                if last.as_ref() == Some(&value) {
+                    continue;
+                }
+                if let Ok(typed) = serde_json::from_value::<T>(value.clone()) {
+                    last = Some(value);
+                    yield typed;
+                }
+            }
+            if last.is_none() || last != partial.value() {
+                yield parse_json_lenient::<T>(partial.buffer())?;
+            }
+        })
+    }
+
+    /// 为给定的提示生成流式响应，并逐个产出 JSON 数组中已完整生成的元素
+    ///
+    /// `pointer` 为数组在输出 JSON 中的位置（JSON Pointer 语法），空字符串表示顶层数组，
+    /// 例如 `"/items"`。每个元素只会产出一次。
+    pub async fn stream_json_items<T>(
+        &self,
+        prompt: &str,
+        pointer: &str,
+    ) -> Result<impl Stream<Item = Result<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut chunks = self.stream_generate(prompt).await?;
+        let pointer = pointer.to_string();
+        Ok(try_stream! {
+            let mut partial = PartialJson::new();
+            let mut emitted = 0;
+            while let Some(chunk) = chunks.next().await {
+                partial.push(&chunk?);
+                for item in partial.complete_items(&pointer, false).into_iter().skip(emitted) {
+                    emitted += 1;
+                    yield serde_json::from_value::<T>(item)?;
+                }
+            }
+            for item in partial.complete_items(&pointer, true).into_iter().skip(emitted) {
+                yield serde_json::from_value::<T>(item)?;
+            }
+        })
     }
 
     /// 内部辅助函数，用于处理流式响应
-    async fn stream_internal(
+    ///
+    /// 流式响应不返回用量信息，输入输出 token 按 [`tokenizer`] 计数，
+    /// 并在流正常结束时计入累计用量。
+    pub(crate) async fn stream_internal(
         &self,
+        system_msg: Option<&str>,
         messages: Vec<Message>,
-    ) -> Result<impl Stream<Item = Result<String>>> {
+        options: &RequestOptions,
+    ) -> Result<BoxStream<'static, Result<String>>> {
+        let (stream, _) = self
+            .stream_internal_with_handle(system_msg, messages, options)
+            .await?;
+        Ok(stream)
+    }
+
+    /// 与 [`Self::stream_internal`] 相同，同时返回记录响应元数据的 [`StreamHandle`]
+    pub(crate) async fn stream_internal_with_handle(
+        &self,
+        system_msg: Option<&str>,
+        messages: Vec<Message>,
+        options: &RequestOptions,
+    ) -> Result<(BoxStream<'static, Result<String>>, StreamHandle)> {
+        let result = self.open_stream(system_msg, messages, options).await;
+        if let Err(e) = &result {
+            self.report_progress(|| Progress::Failed {
+                error: e.to_string(),
+            });
+        }
+        result
+    }
+
+    async fn open_stream(
+        &self,
+        system_msg: Option<&str>,
+        messages: Vec<Message>,
+        options: &RequestOptions,
+    ) -> Result<(BoxStream<'static, Result<String>>, StreamHandle)> {
+        self.usage.check(self.config.budget.as_ref())?;
+        validate_roles(&messages)?;
+        let default_system;
+        let system_message = match system_msg {
+            Some(system_message) => system_message,
+            None => {
+                default_system = template::system_message(&self.config)?;
+                &default_system
+            }
+        };
+        let (system_message, messages) =
+            transform::apply_messages(&self.config.prompt_transforms, system_message, &messages)
+                .unwrap_or_else(|| (system_message.to_string(), messages));
+        let system_message = system_message.as_str();
+        let messages = self
+            .fit_context(system_message, &messages)
+            .await?
+            .unwrap_or(messages);
+        let prepared = prepare_messages(system_message, &messages);
+        let prompt_tokens = tokenizer::count_message_tokens(&self.config.model, &prepared) as u64;
+
+        let start_time = Instant::now();
+        let handle = StreamHandle::default();
+        let chunks = match self.config.api_backend {
+            ApiBackend::ChatCompletions => {
+                self.stream_chat(system_message, &messages, options, &handle)
+                    .await
+            }
+            ApiBackend::Responses => {
+                self.stream_responses(system_message, &messages, options, &handle)
+                    .await
+            }
+        };
+        let mut chunks = chunks.inspect_err(|_| self.report.record_error(&self.config.model))?;
+
+        let client = self.clone();
+        let stream_handle = handle.clone();
+        let mut stop_filter = self.stop_filter(options);
+        let transforms = self.config.response_transforms.clone();
+        let mut glossary = self.config.glossary.as_ref().map(Glossary::stream);
+        let prompt_version = options.prompt_version.clone();
+        // 请求已经发出，流在读完之前被释放时由守卫记录已生成的用量
+        let mut usage = StreamUsage::new(self.clone(), prompt_tokens);
+        let stream = async_stream::stream! {
+            let mut completion_tokens = 0;
+            let mut first_token = true;
+            let mut failed = false;
+            while let Some(mut chunk) = chunks.next().await {
+                if first_token && chunk.as_ref().is_ok_and(|text| !text.is_empty()) {
+                    first_token = false;
+                    let elapsed = start_time.elapsed();
+                    client.latency.record_ttft(&client.config.model, elapsed);
+                    client.report_progress(|| Progress::FirstToken { elapsed });
+                }
+                if let (Ok(text), Some(filter)) = (&mut chunk, stop_filter.as_mut()) {
+                    *text = filter.push(text);
+                }
+                if let Ok(text) = &mut chunk {
+                    completion_tokens += client.count_tokens(text) as u64;
+                    usage.completion_tokens = completion_tokens;
+                    if !transforms.is_empty() {
+                        *text = transform::apply(&transforms, text);
+                    }
+                    if let Some(glossary) = glossary.as_mut() {
+                        *text = glossary.push(text);
+                    }
+                    client.report_progress(|| Progress::Tokens { completion_tokens });
+                }
+                if let Err(e) = &chunk {
+                    failed = true;
+                    client.report_progress(|| Progress::Failed {
+                        error: e.to_string(),
+                    });
+                }
+                yield chunk;
+                if stop_filter.as_ref().is_some_and(StopFilter::stopped) {
+                    stream_handle.update(|meta| meta.finish_reason = Some(FinishReason::Stop));
+                    // 丢弃上游流即可关闭连接
+                    break;
+                }
+            }
+            if let Some(rest) = stop_filter.as_mut().map(StopFilter::finish).filter(|r| !r.is_empty()) {
+                completion_tokens += client.count_tokens(&rest) as u64;
+                usage.completion_tokens = completion_tokens;
+                let rest = transform::apply(&transforms, &rest);
+                match glossary.as_mut() {
+                    Some(glossary) => yield Ok(glossary.push(&rest)),
+                    None => yield Ok(rest),
+                }
+            }
+            if let Some(rest) = glossary.as_mut().map(GlossaryStream::finish).filter(|r| !r.is_empty()) {
+                yield Ok(rest);
+            }
+            usage.record();
+            let duration = start_time.elapsed();
+            client.latency.record(&client.config.model, duration);
+            let stats = RequestStats {
+                duration_ms: duration.as_millis() as u64,
+                prompt_tokens: Some(prompt_tokens as u32),
+                completion_tokens: Some(completion_tokens as u32),
+                total_tokens: Some((prompt_tokens + completion_tokens) as u32),
+                model: client.config.model.clone(),
+                timestamp: Some(std::time::SystemTime::now()),
+                served_model: stream_handle.model(),
+                finish_reason: stream_handle.finish_reason(),
+                prompt_version,
+                ..RequestStats::default()
+            };
+            if !failed {
+                client.report_progress(|| Progress::Done {
+                    elapsed: duration,
+                    completion_tokens,
+                });
+            }
+            client.emit_stats(&stats);
+            stream_handle.record_stats(stats);
+        };
+        Ok((stream.boxed(), handle))
+    }
+
+    /// 调用 `/chat/completions` 并返回文本增量流
+    async fn stream_chat(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+        handle: &StreamHandle,
+    ) -> Result<BoxStream<'static, Result<String>>> {
+        let request_builder = self
+            .chat_stream_request(system_message, messages, options)
+            .await?;
+        let (response, permit) = self.call_api_for_stream(request_builder).await?;
+
+        let handle = handle.clone();
+        let heartbeat = handle.clone();
+        let strict = self.config.strict_parsing;
+        let stream = self
+            .stream_handler
+            .stream_with(response.bytes_stream(), move |event| {
+                if let Some(comment) = &event.comment {
+                    heartbeat.record_heartbeat(comment.clone());
+                }
+                parse_chunk_event(event, strict)
+            });
+        Ok(stream
+            .map(move |res: Result<StreamCompletionResponse>| {
+                let _permit = &permit;
+                res.map(|chunk| {
+                    handle.record_chunk(&chunk);
+                    let content = chunk.choices.first().and_then(|c| c.delta.content.as_ref());
+                    content.cloned().unwrap_or_default()
+                })
+            })
+            .boxed())
+    }
+
+    /// 发送流式请求，返回的配额应随流一同持有，直到流结束或被释放
+    ///
+    /// 启用 `release_stream_permit_early` 时配额在收到响应头后立即归还，
+    /// `max_concurrent_requests` 将不再限制同时读取的流数量。
+    pub(crate) async fn call_api_for_stream(
+        &self,
+        request_builder: RequestBuilder,
+    ) -> Result<(Response, Option<RequestPermit>)> {
+        let (response, _, permit) = self.call_api_with_permit(request_builder).await?;
+        let permit = Some(permit).filter(|_| !self.config.release_stream_permit_early);
+        Ok((response, permit))
+    }
+
+    /// 构造 `/chat/completions` 流式请求
+    async fn chat_stream_request(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+    ) -> Result<RequestBuilder> {
         let endpoint = format!("{}/chat/completions", self.config.api_base);
-        let mut headers = self.build_headers()?;
+        let mut headers = self.request_headers(options).await?;
         headers.insert("Accept", HeaderValue::from_static("text/event-stream"));
 
-        let system_message = &self.config.system_message;
-        let prepared_messages = prepare_messages(system_message, &messages);
+        let prepared_messages = prepare_messages(system_message, messages);
 
-        let params = serde_json::json!({
+        let mut params = serde_json::json!({
             "model": &self.config.model,
             "messages": prepared_messages,
-            "temperature": &self.config.temperature,
-            "top_p": &self.config.top_p,
-            "max_tokens": &self.config.max_tokens,
             "stream": true,
         });
+        options.apply_chat(&self.config, &mut params);
 
-        let request_builder = self.client.post(&endpoint).headers(headers).json(&params);
-        let response = self.call_api_with_retry(request_builder).await?;
+        Ok(self.client.post(&endpoint).headers(headers).json(&params))
+    }
+}
 
-        let stream = self
-            .stream_handler
-            .stream(response.bytes_stream());
-        Ok(stream.map(|res: Result<StreamCompletionResponse>| {
-            res.map(|chunk| {
-                let content = chunk.choices.first().and_then(|c| c.delta.content.as_ref());
-                content.cloned().unwrap_or_default()
-            })
-        }).boxed())
+/// 流式请求的用量，流结束或被提前释放时记录一次
+struct StreamUsage {
+    client: LLMClient,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    recorded: bool,
+}
+
+impl StreamUsage {
+    fn new(client: LLMClient, prompt_tokens: u64) -> Self {
+        Self {
+            client,
+            prompt_tokens,
+            completion_tokens: 0,
+            recorded: false,
+        }
+    }
+
+    fn record(&mut self) {
+        if !std::mem::replace(&mut self.recorded, true) {
+            self.client
+                .record_usage(self.prompt_tokens, self.completion_tokens);
+        }
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        self.record();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{completion_body, sse_response, MockResponse, MockServer};
+    use crate::types::CacheControl;
+    use std::time::Duration;
+
+    fn test_config(base: &str) -> Config {
+        Config::default()
+            .with_api_base(base)
+            .with_retry_backoff(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_retry_reuses_idempotency_key() {
+        let server = MockServer::start(vec![
+            MockResponse::new(503, "busy"),
+            MockResponse::json(completion_body("ok")),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base).with_idempotency_keys(true));
+
+        let response = client.generate_with_stats("hi").await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(response.stats.attempts, 2);
+        assert!(response.stats.backoff_ms >= 1);
+        assert_eq!(response.stats.served_model.as_deref(), Some("mock"));
+        assert!(response
+            .stats
+            .endpoint
+            .unwrap()
+            .ends_with("/chat/completions"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/chat/completions");
+        assert!(requests[0].body.contains("\"hi\""));
+        let key = requests[0].headers.get("idempotency-key").unwrap();
+        assert_eq!(requests[1].headers.get("idempotency-key"), Some(key));
+    }
+
+    #[tokio::test]
+    async fn test_explicit_idempotency_key_and_no_retry_on_client_error() {
+        let server = MockServer::start(vec![MockResponse::new(400, "bad")]).await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let options = RequestOptions::new().with_idempotency_key("key-1");
+        assert!(client.generate_with_options("hi", &options).await.is_err());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].headers["idempotency-key"], "key-1");
+    }
+
+    #[tokio::test]
+    async fn test_strict_parsing() {
+        let body = serde_json::json!({"id": "x"});
+        let server = MockServer::start(vec![
+            MockResponse::json(body.clone()),
+            MockResponse::json(body),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+        assert!(matches!(
+            client.generate("hi").await,
+            Err(NanoError::NoContent)
+        ));
+        let client = LLMClient::new(test_config(&server.base).with_strict_parsing(true));
+        match client.generate("hi").await {
+            Err(NanoError::Json(message)) => assert!(message.contains("/choices"), "{}", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let body = serde_json::json!({"choices": [{"message": {"content": ["hi"]}}]});
+        let server = MockServer::start(vec![MockResponse::json(body)]).await;
+        let client = LLMClient::new(test_config(&server.base).with_strict_parsing(true));
+        match client.generate("hi").await {
+            Err(NanoError::Json(message)) => {
+                assert!(
+                    message.contains("/choices/0/message/content"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_id_header() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client =
+            LLMClient::new(test_config(&server.base).with_request_id_header("X-Request-Id"));
+        client.generate("hi").await.unwrap();
+        assert_eq!(server.requests()[0].headers["x-request-id"].len(), 32);
+    }
+
+    #[tokio::test]
+    async fn test_hooks_and_fallback() {
+        let server = MockServer::start(vec![
+            MockResponse::new(429, "slow down"),
+            MockResponse::new(500, "down"),
+            MockResponse::json(completion_body("from fallback")),
+        ])
+        .await;
+        let client = LLMClient::new(
+            test_config(&server.base)
+                .with_max_retries(1)
+                .with_fallback_models(vec!["backup".into()]),
+        );
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = events.clone();
+        client.on_retry(move |e| log.lock().unwrap().push(format!("retry {:?}", e.status)));
+        let log = events.clone();
+        client.on_rate_limit(move |e| {
+            log.lock()
+                .unwrap()
+                .push(format!("rate_limit {}", e.attempt))
+        });
+        let log = events.clone();
+        client.on_fallback(move |e| log.lock().unwrap().push(format!("fallback {}", e.to)));
+
+        let response = client.generate_with_stats("hi").await.unwrap();
+        assert_eq!(response.content, "from fallback");
+        assert_eq!(response.stats.model, "backup");
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["retry Some(429)", "rate_limit 1", "fallback backup"]
+        );
+        assert!(server.requests()[2].body.contains("\"backup\""));
+    }
+
+    #[tokio::test]
+    async fn test_stream_to_channel() {
+        let server = MockServer::start(vec![sse_response(&["Hel", "lo"])]).await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let (tx, mut rx) = mpsc::channel(8);
+        client.stream_to_channel("hi", tx).await;
+        let mut text = String::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Delta(delta) => text.push_str(&delta),
+                StreamEvent::Done => break,
+                StreamEvent::Heartbeat(_) => {}
+                StreamEvent::Error(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        assert_eq!(text, "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_records_usage() {
+        let server = MockServer::start(vec![
+            sse_response(&["one ", "two ", "three"]),
+            sse_response(&["four ", "five"]),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let stream = client.stream_generate("hi").await.unwrap();
+        let first: Vec<_> = stream.take(1).collect().await;
+        assert_eq!(first.len(), 1);
+        let usage = client.usage();
+        assert_eq!(usage.requests, 1);
+        assert!(usage.prompt_tokens > 0);
+        assert!(usage.completion_tokens > 0);
+
+        // 接收端关闭后同样记录用量
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        client.stream_to_channel("hi", tx).await;
+        assert_eq!(client.usage().requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_streaming_callbacks() {
+        let server = MockServer::start(vec![
+            sse_response(&["Hel", "lo"]),
+            MockResponse::new(500, "boom"),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base).with_max_retries(0));
+
+        let mut chunks = Vec::new();
+        let mut done = None;
+        client
+            .generate_streaming(
+                "hi",
+                |chunk| chunks.push(chunk.to_string()),
+                |r| done = Some(r),
+            )
+            .await;
+        assert_eq!(chunks, ["Hel", "lo"]);
+        let response = done.unwrap().unwrap();
+        assert_eq!(response.content, "Hello");
+        assert!(response.stats.completion_tokens.unwrap() > 0);
+        assert_eq!(response.stats.model, client.config.model);
+
+        let mut done = None;
+        client
+            .generate_streaming("hi", |_| panic!("no chunks expected"), |r| done = Some(r))
+            .await;
+        assert!(done.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_progress_events() {
+        use crate::progress::{Progress, ProgressSender};
+
+        let server = MockServer::start(vec![
+            MockResponse::new(503, "busy"),
+            MockResponse::json(completion_body("ok")),
+            sse_response(&["Hel", "lo"]),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+        let names = |rx: &mut tokio::sync::broadcast::Receiver<Progress>| {
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|event| match event {
+                    Progress::Queued => "queued",
+                    Progress::Sent { .. } => "sent",
+                    Progress::Retried(_) => "retried",
+                    Progress::Fallback(_) => "fallback",
+                    Progress::FirstToken { .. } => "first_token",
+                    Progress::Tokens { .. } => "tokens",
+                    Progress::Done { .. } => "done",
+                    Progress::Failed { .. } => "failed",
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (progress, mut rx) = ProgressSender::broadcast(32);
+        client.with_progress(progress).generate("hi").await.unwrap();
+        assert_eq!(
+            names(&mut rx),
+            ["queued", "sent", "retried", "queued", "sent", "done"]
+        );
+
+        let (progress, mut rx) = ProgressSender::broadcast(32);
+        let text: String = client
+            .with_progress(progress)
+            .stream_generate("hi")
+            .await
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text, "Hello");
+        assert_eq!(
+            names(&mut rx),
+            ["queued", "sent", "first_token", "tokens", "tokens", "done"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_heartbeats() {
+        let mut response = sse_response(&["Hi"]);
+        response.body = format!(": OPENROUTER PROCESSING\n\n{}", response.body);
+        let server = MockServer::start(vec![response.clone(), response]).await;
+
+        for enabled in [false, true] {
+            let client = LLMClient::new(test_config(&server.base).with_stream_heartbeats(enabled));
+            let (chunks, handle) = client
+                .stream_generate_with_handle("hi", &RequestOptions::default())
+                .await
+                .unwrap();
+            let heartbeats = handle.heartbeats();
+            let text: Vec<String> = chunks.map(|c| c.unwrap()).collect().await;
+            assert_eq!(text.concat(), "Hi");
+            let expected = enabled.then(|| "OPENROUTER PROCESSING".to_string());
+            assert_eq!(*heartbeats.borrow(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_handle_metadata() {
+        let mut response = sse_response(&["Hi"]);
+        let last = serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "mock",
+            "choices": [{"index": 0, "delta": {}, "finish_reason": "length"}]
+        });
+        response.body = response
+            .body
+            .replace("data: [DONE]", &format!("data: {}\n\ndata: [DONE]", last));
+        let server = MockServer::start(vec![response]).await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let (stream, handle) = client
+            .stream_generate_with_handle("hi", &RequestOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(handle.finish_reason(), None);
+        let text: String = stream.map(|c| c.unwrap()).collect().await;
+        assert_eq!(text, "Hi");
+        let metadata = handle.metadata();
+        assert_eq!(metadata.id.as_deref(), Some("chatcmpl-1"));
+        assert_eq!(metadata.model.as_deref(), Some("mock"));
+        assert_eq!(metadata.finish_reason, Some(FinishReason::Length));
+    }
+
+    #[tokio::test]
+    async fn test_completion_stream_releases_permit() {
+        let server = MockServer::start(vec![sse_response(&["a", "b"]), sse_response(&["c"])]).await;
+        let client = LLMClient::new(test_config(&server.base).with_max_concurrent_requests(1));
+        let options = RequestOptions::default();
+
+        let mut chunks = client.stream_chunks("hi", &options).await.unwrap();
+        let first = chunks.next().await.unwrap().unwrap();
+        assert_eq!(first.choices[0].delta.content.as_deref(), Some("a"));
+        assert_eq!(client.concurrency_stats().in_flight, 1);
+        drop(chunks);
+        assert_eq!(client.concurrency_stats().in_flight, 0);
+
+        let mut chunks = client.stream_chunks("hi", &options).await.unwrap();
+        while chunks.next().await.is_some() {}
+        assert_eq!(client.concurrency_stats().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_permit_until_finished() {
+        let server = MockServer::start(vec![
+            sse_response(&["a", "b"]),
+            sse_response(&["c"]),
+            sse_response(&["d"]),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base).with_max_concurrent_requests(1));
+
+        let mut stream = client.stream_generate("hi").await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "a");
+        assert_eq!(client.concurrency_stats().in_flight, 1);
+        while stream.next().await.is_some() {}
+        assert_eq!(client.concurrency_stats().in_flight, 0);
+
+        let stream = client.stream_generate("hi").await.unwrap();
+        assert_eq!(client.concurrency_stats().in_flight, 1);
+        drop(stream);
+        assert_eq!(client.concurrency_stats().in_flight, 0);
+
+        let client = LLMClient::new(
+            test_config(&server.base)
+                .with_max_concurrent_requests(1)
+                .with_release_stream_permit_early(true),
+        );
+        let _stream = client.stream_generate("hi").await.unwrap();
+        assert_eq!(client.concurrency_stats().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_mapping() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        // 接受连接但从不响应
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let client = LLMClient::new(
+            test_config(&base)
+                .with_max_retries(0)
+                .with_timeout(Duration::from_millis(50)),
+        );
+        assert!(matches!(
+            client.generate("hi").await,
+            Err(NanoError::Timeout)
+        ));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let client = LLMClient::new(test_config(&base).with_max_retries(0));
+        assert!(matches!(
+            client.generate("hi").await,
+            Err(NanoError::Connect(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_structured_api_error() {
+        let body = serde_json::json!({
+            "error": {"message": "Invalid model", "type": "invalid_request_error", "code": 400}
+        });
+        let server = MockServer::start(vec![
+            MockResponse::new(400, &body.to_string()).with_header("x-request-id", "req-1")
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        match client.generate("hi").await {
+            Err(NanoError::Api {
+                status,
+                code,
+                message,
+                request_id,
+                raw_body,
+            }) => {
+                assert_eq!(status, 400);
+                assert_eq!(code.as_deref(), Some("400"));
+                assert_eq!(message, "Invalid model");
+                assert_eq!(request_id.as_deref(), Some("req-1"));
+                assert_eq!(raw_body, body.to_string());
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_honors_retry_after() {
+        let server = MockServer::start(vec![
+            MockResponse::new(429, "slow down").with_header("retry-after-ms", "30"),
+            MockResponse::json(completion_body("ok")),
+            MockResponse::new(429, "come back tomorrow").with_header("retry-after", "86400"),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base).with_max_retries(1));
+        let delays = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = delays.clone();
+        client.on_rate_limit(move |e| log.lock().unwrap().push(e.delay));
+
+        let response = client.generate_with_stats("hi").await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(response.stats.backoff_ms, 30);
+        assert_eq!(*delays.lock().unwrap(), [Duration::from_millis(30)]);
+
+        // 等待时间超过请求超时时间时直接返回限流错误
+        match client.generate("hi").await {
+            Err(NanoError::RateLimit { retry_after, .. }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(86400)));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_error() {
+        let body = serde_json::json!({"error": {"message": "Slow down"}});
+        let server = MockServer::start(vec![MockResponse::new(429, &body.to_string())
+            .with_header("retry-after", "3")
+            .with_header("x-ratelimit-remaining-requests", "0")])
+        .await;
+        let client = LLMClient::new(test_config(&server.base).with_max_retries(0));
+
+        match client.generate("hi").await {
+            Err(NanoError::RateLimit {
+                message,
+                retry_after,
+                limit_type,
+            }) => {
+                assert_eq!(message, "Slow down");
+                assert_eq!(retry_after, Some(Duration::from_secs(3)));
+                assert_eq!(limit_type.as_deref(), Some("requests"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_filtered() {
+        let mut filtered = completion_body("");
+        filtered["choices"][0]["finish_reason"] = "content_filter".into();
+        filtered["choices"][0]["content_filter_results"] = serde_json::json!({
+            "hate": {"filtered": false, "severity": "safe"},
+            "violence": {"filtered": true, "severity": "high"}
+        });
+        let mut refused = completion_body("");
+        refused["choices"][0]["message"]["content"] = Value::Null;
+        refused["choices"][0]["message"]["refusal"] = "I can't help with that.".into();
+        let server = MockServer::start(vec![
+            MockResponse::json(filtered),
+            MockResponse::json(refused),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        match client.generate("hi").await {
+            Err(NanoError::ContentFiltered {
+                categories,
+                refusal,
+            }) => {
+                assert_eq!(categories, vec!["violence".to_string()]);
+                assert_eq!(refusal, None);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match client.generate("hi").await {
+            Err(NanoError::ContentFiltered {
+                categories,
+                refusal,
+            }) => {
+                assert!(categories.is_empty());
+                assert_eq!(refusal.as_deref(), Some("I can't help with that."));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_retried_only_with_idempotency_key() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count = accepted.clone();
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                sockets.push(socket);
+            }
+        });
+        let accepted = move || accepted.load(std::sync::atomic::Ordering::SeqCst);
+        let config = test_config(&base).with_timeout(Duration::from_millis(50));
+
+        // 默认配置下超时的 POST 可能已在服务端执行，不重试
+        let client = LLMClient::new(config.clone());
+        assert!(matches!(
+            client.generate("hi").await,
+            Err(NanoError::Timeout)
+        ));
+        assert_eq!(accepted(), 1);
+
+        let options = RequestOptions::new().with_idempotency_key("key-1");
+        let result = client.generate_with_options("hi", &options).await;
+        assert!(matches!(result, Err(NanoError::Timeout)));
+        assert_eq!(accepted(), 4);
+
+        let client = LLMClient::new(config.with_idempotency_keys(true));
+        assert!(matches!(
+            client.generate("hi").await,
+            Err(NanoError::Timeout)
+        ));
+        assert_eq!(accepted(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_role_rejected_before_sending() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(test_config(&server.base));
+        let messages = vec![message(Role::from("usr"), "hi")];
+        let options = RequestOptions::default();
+
+        let result = client.generate_internal(None, &messages, &options).await;
+        assert!(matches!(result, Err(NanoError::InvalidRequest(_))));
+        let result = client.stream_internal(None, messages, &options).await;
+        assert!(matches!(result, Err(NanoError::InvalidRequest(_))));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_per_call_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let client = LLMClient::new(test_config(&base).with_timeout(Duration::from_secs(30)));
+        let timeout = Duration::from_millis(50);
+
+        let started = Instant::now();
+        let result = client.generate_with_timeout("hi", timeout).await;
+        assert!(matches!(result, Err(NanoError::Timeout)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            client.stream_generate_with_timeout("hi", timeout).await,
+            Err(NanoError::Timeout)
+        ));
+        assert_eq!(client.concurrency_stats().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_sends_request_options() {
+        let server = MockServer::start(vec![sse_response(&["ok"])]).await;
+        let client = LLMClient::new(test_config(&server.base).with_random_seed(7));
+
+        let options = RequestOptions::new()
+            .with_stop(vec!["END".into()])
+            .with_frequency_penalty(0.5);
+        let chunks: Vec<String> = client
+            .stream_generate_with_options("hi", &options)
+            .await
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, ["ok"]);
+
+        let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["stop"], serde_json::json!(["END"]));
+        assert_eq!(body["frequency_penalty"], 0.5);
+        assert_eq!(body["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_context_overrides_system_message() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let history = vec![
+            message(Role::User, "Hi"),
+            message(Role::Assistant, "Hello!"),
+            message(Role::User, "Bye"),
+        ];
+        assert_eq!(
+            client
+                .generate_with_context("Be terse.", &history)
+                .await
+                .unwrap(),
+            "ok"
+        );
+
+        let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "Be terse.");
+        assert_eq!(messages[3]["content"], "Bye");
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_prefill() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("\n{\"a\": 1}\n```")),
+            MockResponse::json(completion_body("```json\n{}")),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let text = client
+            .generate_with_prefill("json please", "```json")
+            .await
+            .unwrap();
+        assert_eq!(text, "```json\n{\"a\": 1}\n```");
+        let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        let last = body["messages"].as_array().unwrap().last().unwrap().clone();
+        assert_eq!(
+            last,
+            serde_json::json!({"role": "assistant", "content": "```json"})
+        );
+
+        // 服务端回显了预填充内容
+        let text = client
+            .generate_with_prefill("json please", "```json")
+            .await
+            .unwrap();
+        assert_eq!(text, "```json\n{}");
+    }
+
+    #[tokio::test]
+    async fn test_client_side_stop() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("answer\nEND\nignored")),
+            sse_response(&["ans", "wer E", "ND more", " tail"]),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base).with_client_side_stop(true));
+        let options = RequestOptions::new().with_stop(vec!["END".into()]);
+
+        let response = client.generate_with_options("hi", &options).await.unwrap();
+        assert_eq!(response.content, "answer\n");
+        assert_eq!(response.stats.finish_reason, Some(FinishReason::Stop));
+
+        let text: String = client
+            .stream_generate_with_options("hi", &options)
+            .await
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text, "answer ");
+    }
+
+    #[tokio::test]
+    async fn test_content_transforms() {
+        use crate::transform::{FnTransform, RegexReplace};
+
+        let server =
+            MockServer::start(vec![MockResponse::json(completion_body("Mail  x@y.io"))]).await;
+        let client = LLMClient::new(
+            test_config(&server.base)
+                .with_prompt_transform(RegexReplace::emails())
+                .with_response_transform(FnTransform::new(|s| s.replace("  ", " "))),
+        );
+
+        let text = client.generate("write to me@example.com").await.unwrap();
+        assert_eq!(text, "Mail x@y.io");
+        let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        assert_eq!(body["messages"][1]["content"], "write to [EMAIL]");
+    }
+
+    #[tokio::test]
+    async fn test_language_retry() {
+        use crate::language::Language;
+
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("The capital of France is Paris.")),
+            MockResponse::json(completion_body("法国的首都是巴黎。")),
+            MockResponse::json(completion_body("The answer is in English again.")),
+            MockResponse::json(completion_body("Still the same answer in English.")),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+        let options = RequestOptions::new().with_language(Language::Chinese);
+
+        let response = client
+            .generate_with_options("法国的首都是哪里？", &options)
+            .await
+            .unwrap();
+        assert_eq!(response.content, "法国的首都是巴黎。");
+        assert_eq!(response.stats.attempts, 2);
+        let body: Value = serde_json::from_str(&server.requests()[1].body).unwrap();
+        let system = body["messages"][0]["content"].as_str().unwrap();
+        assert!(system.contains("respond only in Chinese"), "{}", system);
+
+        // 重试次数用完后返回最后一次的回复
+        let response = client
+            .generate_with_options("法国的首都是哪里？", &options)
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Still the same answer in English.");
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_glossary() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("Try open ai today")),
+            sse_response(&["Try op", "en a", "i today"]),
+        ])
+        .await;
+        let client = LLMClient::new(
+            test_config(&server.base).with_glossary(Glossary::new().with_term("open ai", "OpenAI")),
+        );
+
+        assert_eq!(client.generate("hi").await.unwrap(), "Try OpenAI today");
+        let text: String = client
+            .stream_generate("hi")
+            .await
+            .unwrap()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text, "Try OpenAI today");
+    }
+
+    #[tokio::test]
+    async fn test_empty_content_retry() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("")),
+            MockResponse::json(serde_json::json!({"choices": []})),
+            MockResponse::json(completion_body("ok")),
+        ])
+        .await;
+        let client = LLMClient::new(test_config(&server.base));
+        assert!(matches!(
+            client.generate("hi").await,
+            Err(NanoError::NoContent)
+        ));
+
+        let client = LLMClient::new(test_config(&server.base).with_retry_on_empty(true));
+        let response = client.generate_with_stats("hi").await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(response.stats.attempts, 2);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_transport() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("llm.sock");
+        let server =
+            MockServer::start_unix(&path, vec![MockResponse::json(completion_body("uds"))]).await;
+
+        let client = LLMClient::new(test_config(&server.base));
+        assert_eq!(client.generate("hi").await.unwrap(), "uds");
+        assert_eq!(server.requests()[0].path, "/chat/completions");
+        assert_eq!(client.config.api_base, "http://localhost");
+    }
+
+    #[tokio::test]
+    async fn test_with_resolve() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("pinned"))]).await;
+        let port = server.base.rsplit(':').next().unwrap();
+        let config = test_config(&format!("http://llm.internal:{}", port))
+            .with_resolve("llm.internal", "127.0.0.1".parse().unwrap());
+
+        let client = LLMClient::new(config);
+        assert_eq!(client.generate("hi").await.unwrap(), "pinned");
+        assert!(server.requests()[0].headers["host"].starts_with("llm.internal"));
+    }
+
+    #[tokio::test]
+    async fn test_auth_provider_replaces_api_key() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(
+            test_config(&server.base)
+                .with_api_key("static")
+                .with_auth_provider(crate::auth::StaticKey("rotated".into())),
+        );
+        client.generate("hi").await.unwrap();
+        assert_eq!(
+            server.requests()[0].headers["authorization"],
+            "Bearer rotated"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_organization_and_project_headers() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(
+            test_config(&server.base)
+                .with_organization("org-1")
+                .with_project("proj-1"),
+        );
+        client.generate("hi").await.unwrap();
+        let headers = &server.requests()[0].headers;
+        assert_eq!(headers["openai-organization"], "org-1");
+        assert_eq!(headers["openai-project"], "proj-1");
+    }
+
+    #[tokio::test]
+    async fn test_prompt_cache_hints() {
+        let mut body = completion_body("ok");
+        body["usage"]["prompt_tokens_details"] = serde_json::json!({"cached_tokens": 2});
+        let server = MockServer::start(vec![MockResponse::json(body)]).await;
+        let client = LLMClient::new(test_config(&server.base));
+
+        let history =
+            vec![message(Role::User, "shared").with_cache_control(CacheControl::ephemeral())];
+        let options = RequestOptions::new().with_prompt_cache_key("docs-v1");
+        let response = client
+            .generate_internal(None, &history, &options)
+            .await
+            .unwrap();
+        assert_eq!(response.stats.cached_tokens, Some(2));
+
+        let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        assert_eq!(body["prompt_cache_key"], "docs-v1");
+        assert_eq!(
+            body["messages"][1]["content"][0]["cache_control"]["type"],
+            "ephemeral"
+        );
     }
 }
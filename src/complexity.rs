@@ -0,0 +1,362 @@
+//! 提示词复杂度分类模块
+//!
+//! 按长度、是否包含代码、问题类型等启发式信号把提示词分为简单、中等和复杂三级，
+//! 结果可以交给 [`ModelRouter`](crate::router::ModelRouter)：简单的常见问题交给免费模型，
+//! 复杂的推理任务交给能力最强的模型。启发式无法确定时，可以选择再调用一次廉价模型判断。
+use crate::{client::LLMClient, error::Result, tokenizer};
+use serde::{Deserialize, Serialize};
+
+/// 提示词复杂度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Complexity {
+    /// 事实查询、闲聊等
+    Simple,
+    /// 一般的解释、改写和短代码
+    Moderate,
+    /// 多步推理、证明、长文档和复杂代码
+    Complex,
+}
+
+/// 问题类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuestionType {
+    /// 询问事实：是什么、谁、何时
+    Factual,
+    /// 询问做法：如何、怎样
+    Procedural,
+    /// 需要推理：为什么、比较、分析、证明
+    Reasoning,
+    /// 创作：写一篇、生成
+    Creative,
+    /// 无法判断
+    Other,
+}
+
+/// 分类依据的信号
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComplexitySignals {
+    /// 提示词 token 数
+    pub tokens: usize,
+    /// 是否包含代码
+    pub has_code: bool,
+    /// 问题类型
+    pub question: QuestionType,
+    /// 提示词中列出的步骤或子问题数
+    pub parts: usize,
+    /// 是否要求严格的推导，如证明或逐步推理
+    pub rigorous: bool,
+}
+
+/// 各问题类型的关键词，按优先级排列；ASCII 关键词只匹配完整的单词
+const KEYWORDS: &[(QuestionType, &[&str])] = &[
+    (
+        QuestionType::Reasoning,
+        &[
+            "why",
+            "prove",
+            "analyze",
+            "analyse",
+            "compare",
+            "trade-off",
+            "tradeoff",
+            "derive",
+            "evaluate",
+            "step by step",
+            "为什么",
+            "证明",
+            "分析",
+            "比较",
+            "推导",
+            "权衡",
+            "评估",
+        ],
+    ),
+    (
+        QuestionType::Creative,
+        &[
+            "write a", "write an", "compose", "draft", "story", "poem", "写一", "创作", "起草",
+        ],
+    ),
+    (
+        QuestionType::Procedural,
+        &[
+            "how do", "how to", "how can", "steps to", "如何", "怎么", "怎样", "步骤",
+        ],
+    ),
+    (
+        QuestionType::Factual,
+        &[
+            "what is",
+            "what's",
+            "who",
+            "when",
+            "where",
+            "which",
+            "define",
+            "是什么",
+            "什么是",
+            "谁",
+            "哪",
+            "多少",
+            "何时",
+        ],
+    ),
+];
+
+/// 要求严格推导的关键词
+const RIGOR: &[&str] = &[
+    "prove",
+    "proof",
+    "derive",
+    "step by step",
+    "证明",
+    "推导",
+    "逐步",
+];
+
+/// 启发式复杂度分类器
+///
+/// ```rust
+/// use nanoai::complexity::{Complexity, ComplexityClassifier};
+///
+/// let classifier = ComplexityClassifier::new();
+/// assert_eq!(classifier.classify("What is the capital of France?"), Complexity::Simple);
+/// assert_eq!(
+///     classifier.classify("Prove that there are infinitely many primes, step by step."),
+///     Complexity::Complex
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ComplexityClassifier {
+    long_prompt_tokens: usize,
+    very_long_prompt_tokens: usize,
+    model: Option<LLMClient>,
+}
+
+impl Default for ComplexityClassifier {
+    fn default() -> Self {
+        Self {
+            long_prompt_tokens: 300,
+            very_long_prompt_tokens: 2_000,
+            model: None,
+        }
+    }
+}
+
+impl ComplexityClassifier {
+    /// 创建使用默认阈值的分类器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置长提示词的阈值（token），默认分别为 300 和 2000
+    pub fn with_length_thresholds(mut self, long: usize, very_long: usize) -> Self {
+        self.long_prompt_tokens = long;
+        self.very_long_prompt_tokens = very_long;
+        self
+    }
+
+    /// 启发式结果为 [`Complexity::Moderate`] 时，调用 `client` 的模型再判断一次
+    pub fn with_model(mut self, client: LLMClient) -> Self {
+        self.model = Some(client);
+        self
+    }
+
+    /// 提取分类依据的信号
+    pub fn signals(&self, prompt: &str) -> ComplexitySignals {
+        let lower = prompt.to_lowercase();
+        let question = KEYWORDS
+            .iter()
+            .find(|(_, words)| words.iter().any(|w| contains_keyword(&lower, w)))
+            .map_or(QuestionType::Other, |(question, _)| *question);
+        let parts = prompt
+            .lines()
+            .filter(|line| is_list_item(line.trim_start()))
+            .count()
+            .max(prompt.matches(['?', '？']).count());
+        ComplexitySignals {
+            tokens: tokenizer::count_tokens("", prompt),
+            has_code: has_code(prompt),
+            question,
+            parts,
+            rigorous: RIGOR.iter().any(|w| contains_keyword(&lower, w)),
+        }
+    }
+
+    /// 按启发式信号分类
+    pub fn classify(&self, prompt: &str) -> Complexity {
+        let signals = self.signals(prompt);
+        let mut score = 0;
+        if signals.tokens >= self.very_long_prompt_tokens {
+            score += 2;
+        } else if signals.tokens >= self.long_prompt_tokens {
+            score += 1;
+        }
+        if signals.has_code {
+            score += 1;
+        }
+        if signals.parts >= 3 {
+            score += 1;
+        }
+        if signals.rigorous {
+            score += 1;
+        }
+        score += match signals.question {
+            QuestionType::Reasoning => 2,
+            QuestionType::Procedural | QuestionType::Creative => 1,
+            QuestionType::Factual | QuestionType::Other => 0,
+        };
+        match score {
+            0 => Complexity::Simple,
+            1 | 2 => Complexity::Moderate,
+            _ => Complexity::Complex,
+        }
+    }
+
+    /// 分类，启发式无法确定且配置了模型时调用模型判断
+    ///
+    /// 模型的回答无法识别时沿用启发式结果。
+    pub async fn classify_with_model(&self, prompt: &str) -> Result<Complexity> {
+        let heuristic = self.classify(prompt);
+        let Some(client) = self
+            .model
+            .as_ref()
+            .filter(|_| heuristic == Complexity::Moderate)
+        else {
+            return Ok(heuristic);
+        };
+        let question = format!(
+            "Classify how hard the following request is for a language model. \
+             Answer with exactly one word: simple, moderate or complex.\n\nRequest:\n{}",
+            prompt
+        );
+        let answer = client.generate(&question).await?.to_lowercase();
+        Ok([Complexity::Simple, Complexity::Complex]
+            .into_iter()
+            .find(|c| answer.contains(&format!("{:?}", c).to_lowercase()))
+            .unwrap_or(heuristic))
+    }
+}
+
+/// 文本中是否出现关键词
+///
+/// ASCII 关键词要求前后都不是字母或数字，避免 `story` 匹配 `history`；中文没有词间分隔，
+/// 按子串匹配。
+fn contains_keyword(text: &str, keyword: &str) -> bool {
+    if !keyword.is_ascii() {
+        return text.contains(keyword);
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(keyword).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + keyword.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+/// 是否为列表项：`- `、`* `、`1. `、`1)` 开头
+fn is_list_item(line: &str) -> bool {
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return true;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && matches!(line[digits..].chars().next(), Some('.' | ')'))
+}
+
+/// 是否包含代码块或看起来像代码的行
+fn has_code(prompt: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "fn ",
+        "def ",
+        "class ",
+        "function ",
+        "import ",
+        "#include",
+        "=>",
+        "->",
+        "};",
+        "();",
+    ];
+    prompt.contains("```")
+        || prompt
+            .lines()
+            .filter(|line| MARKERS.iter().any(|m| line.contains(m)))
+            .count()
+            >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[test]
+    fn test_classify() {
+        let classifier = ComplexityClassifier::new();
+        let cases = [
+            ("hi there", Complexity::Simple),
+            ("法国的首都是什么？", Complexity::Simple),
+            ("How do I reverse a list in Python?", Complexity::Moderate),
+            (
+                "Fix this:\n```rust\nfn main() {}\n```",
+                Complexity::Moderate,
+            ),
+            (
+                "为什么天空是蓝色的？请从物理角度分析。",
+                Complexity::Moderate,
+            ),
+            (
+                "Compare these two designs:\n1. a queue\n2. a stack\n3. a heap\n\
+                 Why would each be chosen?",
+                Complexity::Complex,
+            ),
+        ];
+        for (prompt, expected) in cases {
+            assert_eq!(classifier.classify(prompt), expected, "{}", prompt);
+        }
+
+        // 关键词按单词匹配，history 不算 story，derivative 不算 derive
+        let signals = classifier.signals("What is the history of Rome?");
+        assert_eq!(signals.question, QuestionType::Factual);
+        assert_eq!(
+            classifier.classify("What is the history of Rome?"),
+            Complexity::Simple
+        );
+        assert!(!classifier.signals("Explain the derivative of x^2").rigorous);
+        assert!(contains_keyword("trade-off: speed", "trade-off"));
+        assert!(contains_keyword("who?", "who"));
+        assert!(!contains_keyword("whole", "who"));
+
+        let signals = classifier.signals("Why?\n```\nfn x() {}\n```");
+        assert_eq!(signals.question, QuestionType::Reasoning);
+        assert!(signals.has_code);
+        let long = "word ".repeat(400);
+        assert_eq!(classifier.classify(&long), Complexity::Moderate);
+        let classifier = classifier.with_length_thresholds(10, 100);
+        assert_eq!(classifier.classify(&long), Complexity::Moderate);
+        assert_eq!(
+            classifier.classify(&format!("Why? {}", long)),
+            Complexity::Complex
+        );
+    }
+
+    #[tokio::test]
+    async fn test_classify_with_model() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("Complex."))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let classifier = ComplexityClassifier::new().with_model(client);
+
+        // 启发式已经确定时不调用模型
+        let simple = classifier.classify_with_model("hi").await.unwrap();
+        assert_eq!(simple, Complexity::Simple);
+        assert!(server.requests().is_empty());
+
+        let prompt = "How do I reverse a list in Python?";
+        let complexity = classifier.classify_with_model(prompt).await.unwrap();
+        assert_eq!(complexity, Complexity::Complex);
+        assert!(server.requests()[0].body.contains("reverse a list"));
+    }
+}
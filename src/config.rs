@@ -1,9 +1,25 @@
 //! 配置模块
+use crate::auth::AuthProvider;
+use crate::budget::Budget;
+use crate::context::ContextPolicy;
 use crate::error::{NanoError, Result};
+use crate::glossary::Glossary;
+use crate::guardrail::Guardrail;
+use crate::responses::{ApiBackend, ResponseTool};
+use crate::sink::StatsSink;
+use crate::store::JobStore;
+use crate::template::VariableProvider;
+use crate::transform::ContentTransform;
+use crate::transport::Transport;
 use dotenv::dotenv;
+use fastrand;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use fastrand;
 
 // ===============================================================================================
 // 配置模块
@@ -11,13 +27,23 @@ use fastrand;
 
 /// LLM 客户端配置
 ///
-/// 包含所有必要的配置参数，支持 Builder 模式和环境变量配置
-#[derive(Debug, Clone)]
+/// 包含所有必要的配置参数，支持 Builder 模式和环境变量配置。
+/// 可通过 serde 序列化（时长以毫秒表示），护栏、内容转换器和任务存储不参与序列化，
+/// API 密钥只在 [`Config::save_with_api_key`] 中写出。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     /// 模型名称
     pub(crate) model: String,
     /// 系统消息
     pub(crate) system_message: String,
+    /// 系统消息模板，设置后在每次请求时渲染并取代 `system_message`
+    pub(crate) system_template: Option<String>,
+    /// 系统消息模板的静态变量
+    pub(crate) template_vars: HashMap<String, String>,
+    /// 系统消息模板的运行时变量提供者，按添加顺序查找
+    #[serde(skip)]
+    pub(crate) variable_providers: Vec<Arc<dyn VariableProvider>>,
     /// 温度参数 (0.0-2.0)
     pub(crate) temperature: f32,
     /// Top-p 参数 (0.0-1.0)
@@ -25,23 +51,134 @@ pub struct Config {
     /// 最大生成 token 数
     pub(crate) max_tokens: u32,
     /// 请求超时时间
+    #[serde(with = "duration_ms")]
     pub(crate) timeout: Duration,
     /// API 基础 URL
     pub(crate) api_base: String,
     /// API 密钥
+    #[serde(skip_serializing)]
     pub(crate) api_key: String,
+    /// 动态认证凭据，设置后取代 `api_key`
+    #[serde(skip)]
+    pub(crate) auth_provider: Option<Arc<dyn AuthProvider>>,
+    /// 组织 ID，通过 `OpenAI-Organization` 标头发送
+    pub(crate) organization: Option<String>,
+    /// 项目 ID，通过 `OpenAI-Project` 标头发送
+    pub(crate) project: Option<String>,
     /// 随机种子
     pub(crate) random_seed: Option<u64>,
     /// 最大并发请求数
     pub(crate) max_concurrent_requests: Option<usize>,
+    /// 每个租户的最大并发请求数，为空时与全局上限相同
+    pub(crate) tenant_max_concurrent_requests: Option<usize>,
+    /// 每个租户每分钟最多发出的请求数（含重试），请求按固定间隔放行，为空时不限速
+    pub(crate) tenant_rate_limit: Option<u32>,
+    /// 流式请求在收到响应头后立即归还并发配额，而不是持有到流结束
+    pub(crate) release_stream_permit_early: bool,
+    /// 将流中的 SSE 注释行作为心跳通知，见 `StreamHandle::heartbeats`
+    pub(crate) stream_heartbeats: bool,
+    /// 严格解析响应：结构不符时返回带 JSON 指针的错误，而不是按默认值容忍
+    pub(crate) strict_parsing: bool,
     /// 连接池空闲超时时间
+    #[serde(with = "duration_ms")]
     pub(crate) pool_idle_timeout: Duration,
     /// 每个主机的最大空闲连接数
     pub(crate) pool_max_idle_per_host: usize,
     /// TCP Keepalive
+    #[serde(with = "duration_ms")]
     pub(crate) tcp_keepalive: Duration,
     /// TCP Nodelay
     pub(crate) tcp_nodelay: bool,
+    /// 通过 Unix 域套接字连接服务端，设置后忽略 TCP 相关选项
+    pub(crate) unix_socket: Option<PathBuf>,
+    /// 静态解析条目，覆盖对应主机名的 DNS 查询结果
+    pub(crate) resolve: Vec<(String, IpAddr)>,
+    /// 建立连接的超时时间
+    #[serde(with = "option_duration_ms")]
+    pub(crate) connect_timeout: Option<Duration>,
+    /// 不经协商直接使用 HTTP/2，适用于确定支持 h2c 或 HTTP/2 的网关
+    pub(crate) http2_prior_knowledge: bool,
+    /// HTTP/2 PING 保活间隔
+    #[serde(with = "option_duration_ms")]
+    pub(crate) http2_keep_alive_interval: Option<Duration>,
+    /// HTTP/2 PING 保活的响应超时时间
+    #[serde(with = "option_duration_ms")]
+    pub(crate) http2_keep_alive_timeout: Option<Duration>,
+    /// 连接空闲时是否继续发送 HTTP/2 PING 保活
+    pub(crate) http2_keep_alive_while_idle: bool,
+    /// 是否启用 HTTP/2 自适应流控窗口
+    pub(crate) http2_adaptive_window: bool,
+    /// 模型上下文窗口长度，为空时从 `/models` 查询
+    pub(crate) context_window: Option<u32>,
+    /// 上下文窗口超限时的处理策略
+    pub(crate) context_policy: ContextPolicy,
+    /// 累计用量预算
+    pub(crate) budget: Option<Budget>,
+    /// 生成请求使用的 API 后端
+    pub(crate) api_backend: ApiBackend,
+    /// 请求使用的传输方式
+    pub(crate) transport: Transport,
+    /// Responses API 内置工具
+    pub(crate) response_tools: Vec<ResponseTool>,
+    /// 语音合成模型
+    pub(crate) speech_model: String,
+    /// 向量模型
+    pub(crate) embedding_model: String,
+    /// 重排序模型
+    pub(crate) rerank_model: String,
+    /// 输出护栏，按添加顺序执行
+    #[serde(skip)]
+    pub(crate) guardrails: Vec<Arc<dyn Guardrail>>,
+    /// 发送前对提示词执行的转换，按添加顺序执行
+    #[serde(skip)]
+    pub(crate) prompt_transforms: Vec<Arc<dyn ContentTransform>>,
+    /// 返回前对输出执行的转换，按添加顺序执行
+    #[serde(skip)]
+    pub(crate) response_transforms: Vec<Arc<dyn ContentTransform>>,
+    /// 在输出转换之后执行的术语表
+    #[serde(skip)]
+    pub(crate) glossary: Option<Glossary>,
+    /// 每次请求完成后接收统计信息的接收器
+    #[serde(skip)]
+    pub(crate) stats_sinks: Vec<Arc<dyn StatsSink>>,
+    /// 请求失败后的最大重试次数
+    pub(crate) max_retries: u32,
+    /// 首次重试前的等待时间，之后每次翻倍
+    #[serde(with = "duration_ms")]
+    pub(crate) retry_backoff: Duration,
+    /// 响应内容为空时是否按重试策略重新请求
+    pub(crate) retry_on_empty: bool,
+    /// 回复语言与 `RequestOptions::with_language` 不符时的最大重新请求次数
+    pub(crate) language_retries: u32,
+    /// 请求失败（重试耗尽）后依次尝试的降级模型
+    pub(crate) fallback_models: Vec<String>,
+    /// 是否为每个请求自动生成幂等键，默认关闭
+    ///
+    /// 开启后超时的请求也会重试，仅适用于按 `Idempotency-Key` 去重的服务端；
+    /// 忽略该标头的服务端会重复生成并重复计费。
+    pub(crate) idempotency_keys: bool,
+    /// 是否在客户端按 `RequestOptions::with_stop` 截断输出，用于忽略 `stop` 参数的服务端
+    pub(crate) client_side_stop: bool,
+    /// 携带自动生成的请求 ID 的标头名称，例如 `X-Request-Id`
+    pub(crate) request_id_header: Option<String>,
+    /// 飞行记录器保留的最近请求数，为 0 时不记录
+    pub(crate) flight_recorder: usize,
+    /// 交互日志文件，每次生成请求追加一行 JSON
+    pub(crate) interaction_log: Option<PathBuf>,
+    /// 交互日志文件的轮转大小（字节）
+    pub(crate) interaction_log_max_bytes: u64,
+    /// 保留的已轮转交互日志文件数
+    pub(crate) interaction_log_max_files: usize,
+    /// 是否从当前 `tracing` span 注入 `traceparent`/`tracestate` 标头
+    #[cfg(feature = "otel")]
+    pub(crate) trace_propagation: bool,
+    /// 后台任务的最大并行执行数
+    pub(crate) job_workers: usize,
+    /// 后台任务队列容量，队列已满时拒绝提交
+    pub(crate) job_queue_capacity: usize,
+    /// 后台任务存储
+    #[serde(skip)]
+    pub(crate) job_store: Option<Arc<dyn JobStore>>,
 }
 
 impl Default for Config {
@@ -52,26 +189,142 @@ impl Default for Config {
         Self {
             model: "deepseek-chat".into(),
             system_message: "You are a helpful AI assistant.".into(),
+            system_template: None,
+            template_vars: HashMap::new(),
+            variable_providers: Vec::new(),
             temperature: 0.7,
             top_p: 1.0,
             max_tokens: 4096,
             timeout: Duration::from_secs(60),
             api_base: "https://openrouter.ai/api/v1".into(),
             api_key: String::new(),
+            auth_provider: None,
+            organization: None,
+            project: None,
             random_seed: None,
             max_concurrent_requests: Some(64),
+            tenant_max_concurrent_requests: None,
+            tenant_rate_limit: None,
+            release_stream_permit_early: false,
+            stream_heartbeats: false,
+            strict_parsing: false,
             pool_idle_timeout: Duration::from_secs(90),
             pool_max_idle_per_host: 16,
             tcp_keepalive: Duration::from_secs(60),
             tcp_nodelay: true,
+            unix_socket: None,
+            resolve: Vec::new(),
+            connect_timeout: None,
+            http2_prior_knowledge: false,
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: None,
+            http2_keep_alive_while_idle: false,
+            http2_adaptive_window: false,
+            context_window: None,
+            context_policy: ContextPolicy::default(),
+            budget: None,
+            api_backend: ApiBackend::default(),
+            transport: Transport::default(),
+            response_tools: Vec::new(),
+            speech_model: "tts-1".into(),
+            embedding_model: "text-embedding-3-small".into(),
+            rerank_model: "rerank-v3.5".into(),
+            guardrails: Vec::new(),
+            prompt_transforms: Vec::new(),
+            response_transforms: Vec::new(),
+            glossary: None,
+            stats_sinks: Vec::new(),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            retry_on_empty: false,
+            language_retries: 1,
+            fallback_models: Vec::new(),
+            idempotency_keys: false,
+            client_side_stop: false,
+            request_id_header: None,
+            flight_recorder: 0,
+            interaction_log: None,
+            interaction_log_max_bytes: 64 * 1024 * 1024,
+            interaction_log_max_files: 5,
+            #[cfg(feature = "otel")]
+            trace_propagation: false,
+            job_workers: 4,
+            job_queue_capacity: 256,
+            job_store: None,
         }
     }
 }
 
+/// 以毫秒整数序列化 `Duration`
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_millis() as u64)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// 以毫秒整数序列化 `Option<Duration>`
+mod option_duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&(value.as_millis() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<u64>::deserialize(deserializer).map(|ms| ms.map(Duration::from_millis))
+    }
+}
+
+/// `Config::deterministic` 使用的固定随机种子
+pub const DETERMINISTIC_SEED: u64 = 42;
+
 /// 生成 Config Builder 方法的宏
 ///
-/// 自动生成 `with_field_name` 形式的 builder 方法
+/// 自动生成 `with_field_name` 形式的 builder 方法，`String` 字段接受 `impl Into<String>`
 macro_rules! config_builder {
+    ($field:ident, String) => {
+        paste::paste! {
+            #[doc = "设置 `"]
+            #[doc = stringify!($field)]
+            #[doc = "`"]
+            pub fn [<with_ $field>](mut self, $field: impl Into<String>) -> Self {
+                self.$field = $field.into();
+                self
+            }
+        }
+    };
+    ($field:ident, String, option) => {
+        paste::paste! {
+            #[doc = "设置 `"]
+            #[doc = stringify!($field)]
+            #[doc = "`"]
+            pub fn [<with_ $field>](mut self, $field: impl Into<String>) -> Self {
+                self.$field = Some($field.into());
+                self
+            }
+        }
+    };
     ($field:ident, $type:ty) => {
         paste::paste! {
             #[doc = "设置 `"]
@@ -97,13 +350,27 @@ macro_rules! config_builder {
 }
 
 impl Config {
-    pub fn model(&self) -> &str { &self.model }
-    pub fn temperature(&self) -> f32 { self.temperature }
-    pub fn top_p(&self) -> f32 { self.top_p }
-    pub fn max_tokens(&self) -> u32 { self.max_tokens }
-    pub fn timeout(&self) -> Duration { self.timeout }
-    pub fn api_base(&self) -> &str { &self.api_base }
-    pub fn api_key(&self) -> &str { &self.api_key }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+    pub fn top_p(&self) -> f32 {
+        self.top_p
+    }
+    pub fn max_tokens(&self) -> u32 {
+        self.max_tokens
+    }
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
 
     /// 从环境变量和 `.env` 文件加载配置
     ///
@@ -111,10 +378,11 @@ impl Config {
     pub fn from_env() -> Result<Self> {
         dotenv().ok();
         let api_key = env::var("OPENROUTER_API_KEY")
-            .map_err(|_| NanoError::Config("YOPENROUTER_API_KEY not found".into()))?;
+            .map_err(|_| NanoError::Config("OPENROUTER_API_KEY not found".into()))?;
 
         let model = env::var("OPENROUTER_MODEL").unwrap_or_else(|_| "deepseek-chat".to_string());
-        let api_base = env::var("API_BASE").unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
+        let api_base =
+            env::var("API_BASE").unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
 
         let config = Config {
             api_key,
@@ -126,20 +394,252 @@ impl Config {
         Ok(config)
     }
 
+    /// 从 JSON 文件加载配置
+    ///
+    /// 文件中缺失的字段使用默认值；文件未包含 API 密钥时，从 `OPENROUTER_API_KEY` 环境变量读取
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let mut config: Config = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if config.api_key.is_empty() {
+            dotenv().ok();
+            config.api_key = env::var("OPENROUTER_API_KEY").unwrap_or_default();
+        }
+        Ok(config)
+    }
+
+    /// 将配置保存为 JSON 文件，不包含 API 密钥
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(path.as_ref(), false)
+    }
+
+    /// 将配置连同 API 密钥保存为 JSON 文件
+    pub fn save_with_api_key(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(path.as_ref(), true)
+    }
+
+    /// 将 `unix://` 形式的 `api_base` 拆分为套接字路径和 HTTP 基础 URL
+    pub(crate) fn resolve_unix_socket(&mut self) {
+        if let Some(path) = self.api_base.strip_prefix("unix://") {
+            self.unix_socket = Some(PathBuf::from(path));
+            self.api_base = "http://localhost".into();
+        }
+    }
+
+    fn write(&self, path: &Path, include_api_key: bool) -> Result<()> {
+        let mut value = serde_json::to_value(self)?;
+        if include_api_key {
+            value["api_key"] = serde_json::Value::from(self.api_key.as_str());
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&value)?)?;
+        Ok(())
+    }
+
+    /// 可复现输出的配置，适用于测试用例和评估
+    ///
+    /// 温度为 0、`top_p` 为 1，并使用固定种子 [`DETERMINISTIC_SEED`]，不会自动生成随机种子。
+    /// 通过 `/chat/completions` 发送的每个请求都会携带种子（`RequestOptions::with_seed`
+    /// 可按请求覆盖）。Responses API 不支持种子，且服务端不保证相同种子一定产生相同输出。
+    pub fn deterministic() -> Self {
+        Config {
+            temperature: 0.0,
+            top_p: 1.0,
+            random_seed: Some(DETERMINISTIC_SEED),
+            ..Default::default()
+        }
+    }
+
+    /// 创意写作预设：较高的温度和较长的输出
+    pub fn creative() -> Self {
+        Config {
+            temperature: 1.0,
+            top_p: 0.95,
+            max_tokens: 4096,
+            ..Default::default()
+        }
+    }
+
+    /// 精确回答预设：较低的温度，适合问答、抽取和代码生成
+    pub fn precise() -> Self {
+        Config {
+            temperature: 0.2,
+            top_p: 0.9,
+            max_tokens: 2048,
+            ..Default::default()
+        }
+    }
+
+    /// 低成本预设：使用小模型并限制输出长度
+    pub fn economical() -> Self {
+        Config {
+            model: "openai/gpt-4o-mini".into(),
+            temperature: 0.5,
+            top_p: 1.0,
+            max_tokens: 512,
+            ..Default::default()
+        }
+    }
+
     // 使用宏生成 builder 方法
     config_builder!(api_base, String);
     config_builder!(model, String);
     config_builder!(api_key, String);
+    config_builder!(organization, String, option);
+    config_builder!(project, String, option);
     config_builder!(temperature, f32);
     config_builder!(top_p, f32);
     config_builder!(max_tokens, u32);
     config_builder!(timeout, Duration);
     config_builder!(random_seed, u64, option);
     config_builder!(max_concurrent_requests, usize, option);
+    config_builder!(tenant_max_concurrent_requests, usize, option);
+    config_builder!(tenant_rate_limit, u32, option);
+    config_builder!(release_stream_permit_early, bool);
+    config_builder!(stream_heartbeats, bool);
+    config_builder!(strict_parsing, bool);
     config_builder!(pool_idle_timeout, Duration);
     config_builder!(pool_max_idle_per_host, usize);
     config_builder!(tcp_keepalive, Duration);
     config_builder!(tcp_nodelay, bool);
+    config_builder!(connect_timeout, Duration, option);
+
+    /// 将主机名固定解析到指定地址，不再查询 DNS
+    ///
+    /// 适用于隔离网络或内外网解析不同的环境。端口仍取自 `api_base`，
+    /// 同一主机名多次调用时会添加多个候选地址。
+    pub fn with_resolve(mut self, host: impl Into<String>, ip: IpAddr) -> Self {
+        self.resolve.push((host.into(), ip));
+        self
+    }
+
+    /// 通过 Unix 域套接字连接本地服务（如 llama.cpp、vLLM）
+    ///
+    /// 请求 URL 仍由 `api_base` 决定，主机名只用于 `Host` 标头，例如
+    /// `with_api_base("http://localhost/v1")`。也可以直接将 `api_base` 设为
+    /// `unix:///path/to/server.sock`，此时请求路径不带前缀。仅支持 Unix 平台。
+    pub fn with_unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+    config_builder!(http2_prior_knowledge, bool);
+    config_builder!(http2_keep_alive_interval, Duration, option);
+    config_builder!(http2_keep_alive_timeout, Duration, option);
+    config_builder!(http2_keep_alive_while_idle, bool);
+    config_builder!(http2_adaptive_window, bool);
+    config_builder!(speech_model, String);
+    config_builder!(embedding_model, String);
+    config_builder!(rerank_model, String);
+    config_builder!(api_backend, ApiBackend);
+    config_builder!(transport, Transport);
+    config_builder!(context_window, u32, option);
+    config_builder!(context_policy, ContextPolicy);
+    config_builder!(budget, Budget, option);
+    config_builder!(response_tools, Vec<ResponseTool>);
+    config_builder!(max_retries, u32);
+    config_builder!(retry_backoff, Duration);
+    config_builder!(retry_on_empty, bool);
+    config_builder!(language_retries, u32);
+    config_builder!(fallback_models, Vec<String>);
+    config_builder!(idempotency_keys, bool);
+    config_builder!(client_side_stop, bool);
+    config_builder!(request_id_header, String, option);
+    config_builder!(flight_recorder, usize);
+    config_builder!(interaction_log_max_bytes, u64);
+    config_builder!(interaction_log_max_files, usize);
+
+    /// 将每次生成请求的模型、参数、消息、输出和统计信息以 JSON Lines 追加到 `path`
+    ///
+    /// 文件超过 `interaction_log_max_bytes` 时轮转为 `path.1`、`path.2`……，
+    /// 最多保留 `interaction_log_max_files` 个旧文件。
+    pub fn with_interaction_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.interaction_log = Some(path.into());
+        self
+    }
+    #[cfg(feature = "otel")]
+    config_builder!(trace_propagation, bool);
+    config_builder!(job_workers, usize);
+    config_builder!(job_queue_capacity, usize);
+
+    /// 设置认证凭据提供者
+    ///
+    /// 每次请求前调用以获取认证标头，适用于会过期的令牌（OAuth、Azure Entra 等）。
+    /// 设置后不再使用 `api_key`
+    pub fn with_auth_provider(mut self, provider: impl AuthProvider + 'static) -> Self {
+        self.auth_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// 添加一个输出护栏
+    ///
+    /// 配置护栏后，`generate` 系列方法会在返回前依次检查输出，
+    /// 不通过时返回 `NanoError::GuardrailViolation`
+    pub fn with_guardrail(mut self, guardrail: impl Guardrail + 'static) -> Self {
+        self.guardrails.push(Arc::new(guardrail));
+        self
+    }
+
+    /// 添加一个提示词转换器
+    ///
+    /// 系统消息和所有消息内容在发送前依次经过转换，例如去除邮箱等敏感信息
+    pub fn with_prompt_transform(mut self, transform: impl ContentTransform + 'static) -> Self {
+        self.prompt_transforms.push(Arc::new(transform));
+        self
+    }
+
+    /// 添加一个输出转换器
+    ///
+    /// 输出在护栏检查之前依次经过转换。流式响应按增量逐个转换，
+    /// 跨越多个增量的内容不会被匹配
+    pub fn with_response_transform(mut self, transform: impl ContentTransform + 'static) -> Self {
+        self.response_transforms.push(Arc::new(transform));
+        self
+    }
+
+    /// 设置系统消息模板，如 `"You are {persona}. Today is {date}."`
+    ///
+    /// 模板在每次请求时渲染并取代固定的系统消息，变量来自 [`Self::with_template_var`]、
+    /// [`Self::with_variable_provider`] 和 [`crate::template::builtin_variable`] 中的内置变量。
+    /// 模板写错或缺少变量时请求返回 [`NanoError::InvalidRequest`]。
+    pub fn with_system_template(mut self, template: impl Into<String>) -> Self {
+        self.system_template = Some(template.into());
+        self
+    }
+
+    /// 设置系统消息模板的静态变量，优先于变量提供者和内置变量
+    pub fn with_template_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.template_vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// 添加系统消息模板的变量提供者，每次请求时查询
+    pub fn with_variable_provider(mut self, provider: impl VariableProvider + 'static) -> Self {
+        self.variable_providers.push(Arc::new(provider));
+        self
+    }
+
+    /// 设置术语表
+    ///
+    /// 输出在输出转换之后、护栏检查之前按术语表改写。与输出转换不同，
+    /// 流式响应中跨越多个增量的术语同样会被替换
+    pub fn with_glossary(mut self, glossary: Glossary) -> Self {
+        self.glossary = Some(glossary);
+        self
+    }
+
+    /// 添加一个统计信息接收器
+    ///
+    /// 每次请求成功完成后在后台调用，不阻塞请求返回
+    pub fn with_stats_sink(mut self, sink: impl StatsSink + 'static) -> Self {
+        self.stats_sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// 设置后台任务存储
+    ///
+    /// 设置后，后台任务的输入、状态和输出会在每次状态变化时写入存储，
+    /// 重启后可通过 `LLMClient::resume_jobs` 恢复未完成的任务
+    pub fn with_job_store(mut self, store: impl JobStore + 'static) -> Self {
+        self.job_store = Some(Arc::new(store));
+        self
+    }
 
     /// 自动生成随机种子
     ///
@@ -178,8 +678,8 @@ mod tests {
     #[test]
     fn test_config_builder_methods() {
         let config = Config::default()
-            .with_model("test_model".to_string())
-            .with_api_key("test_key".to_string())
+            .with_model("test_model")
+            .with_api_key("test_key")
             .with_temperature(0.9)
             .with_random_seed(12345);
 
@@ -189,6 +689,63 @@ mod tests {
         assert_eq!(config.random_seed, Some(12345));
     }
 
+    /// Tests that the deterministic preset pins sampling and sends its seed.
+    #[test]
+    fn test_deterministic() {
+        let config = Config::deterministic().with_model("m");
+        assert_eq!(config.temperature, 0.0);
+        assert_eq!(config.top_p, 1.0);
+        assert_eq!(config.random_seed, Some(DETERMINISTIC_SEED));
+
+        let mut params = serde_json::json!({});
+        crate::options::RequestOptions::new().apply_chat(&config, &mut params);
+        assert_eq!(params["seed"], DETERMINISTIC_SEED);
+        assert_eq!(params["temperature"], 0.0);
+    }
+
+    /// Tests that presets can be customized with builder methods.
+    #[test]
+    fn test_presets() {
+        assert!(Config::creative().temperature > Config::default().temperature);
+        assert!(Config::precise().temperature < Config::default().temperature);
+        let config = Config::economical().with_max_tokens(256);
+        assert_eq!(config.model, "openai/gpt-4o-mini");
+        assert_eq!(config.max_tokens, 256);
+    }
+
+    /// Tests saving a configuration and loading it back.
+    #[test]
+    fn test_save_and_from_file() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let config = Config::precise()
+            .with_api_key("secret")
+            .with_retry_backoff(Duration::from_millis(250))
+            .with_context_policy(ContextPolicy::TruncateOldest)
+            .with_http2_keep_alive_interval(Duration::from_secs(30))
+            .with_guardrail(crate::guardrail::MaxLength(10));
+
+        config.save(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("secret"));
+        assert!(saved.contains("\"retry_backoff\": 250"));
+        assert!(saved.contains("truncate_oldest"));
+
+        config.save_with_api_key(&path).unwrap();
+        let loaded = Config::from_file(&path).unwrap();
+        assert_eq!(loaded.api_key, "secret");
+        assert_eq!(loaded.temperature, config.temperature);
+        assert_eq!(loaded.retry_backoff, Duration::from_millis(250));
+        assert_eq!(loaded.context_policy, ContextPolicy::TruncateOldest);
+        assert_eq!(
+            loaded.http2_keep_alive_interval,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(loaded.http2_keep_alive_timeout, None);
+        assert!(loaded.guardrails.is_empty());
+    }
+
     /// Tests that `with_random_seed_auto` sets a random seed.
     #[test]
     fn test_with_random_seed_auto() {
@@ -203,8 +760,8 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join(".env");
         let mut file = File::create(&file_path).unwrap();
-        writeln!(file, "DEEPSEEK_API_KEY=dotenv_key").unwrap();
-        writeln!(file, "MODEL=dotenv_model").unwrap();
+        writeln!(file, "OPENROUTER_API_KEY=dotenv_key").unwrap();
+        writeln!(file, "OPENROUTER_MODEL=dotenv_model").unwrap();
 
         // Temporarily change the current directory to the temp dir
         let original_dir = env::current_dir().unwrap();
@@ -214,8 +771,10 @@ mod tests {
         assert_eq!(config.api_key, "dotenv_key");
         assert_eq!(config.model, "dotenv_model");
 
-        // Restore the original directory
+        // Restore the original directory and drop the variables loaded from .env
         env::set_current_dir(original_dir).unwrap();
+        env::remove_var("OPENROUTER_API_KEY");
+        env::remove_var("OPENROUTER_MODEL");
     }
 
     /// Tests loading configuration from environment variables.
@@ -228,16 +787,16 @@ mod tests {
         let original_dir = env::current_dir().unwrap();
         env::set_current_dir(dir.path()).unwrap();
 
-        env::set_var("DEEPSEEK_API_KEY", "env_var_key");
-        env::set_var("MODEL", "env_var_model");
+        env::set_var("OPENROUTER_API_KEY", "env_var_key");
+        env::set_var("OPENROUTER_MODEL", "env_var_model");
 
         let config = Config::from_env().unwrap();
         assert_eq!(config.api_key, "env_var_key");
         assert_eq!(config.model, "env_var_model");
 
         // Cleanup
-        env::remove_var("DEEPSEEK_API_KEY");
-        env::remove_var("MODEL");
+        env::remove_var("OPENROUTER_API_KEY");
+        env::remove_var("OPENROUTER_MODEL");
         env::set_current_dir(original_dir).unwrap();
     }
 
@@ -248,9 +807,9 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join(".env");
         let mut file = File::create(&file_path).unwrap();
-        writeln!(file, "DEEPSEEK_API_KEY=dotenv_key").unwrap();
+        writeln!(file, "OPENROUTER_API_KEY=dotenv_key").unwrap();
 
-        env::set_var("DEEPSEEK_API_KEY", "env_var_key");
+        env::set_var("OPENROUTER_API_KEY", "env_var_key");
 
         let original_dir = env::current_dir().unwrap();
         env::set_current_dir(dir.path()).unwrap();
@@ -259,7 +818,7 @@ mod tests {
         assert_eq!(config.api_key, "env_var_key");
 
         env::set_current_dir(original_dir).unwrap();
-        env::remove_var("DEEPSEEK_API_KEY");
+        env::remove_var("OPENROUTER_API_KEY");
     }
 
     /// Tests that an error is returned if the API key is not found.
@@ -267,7 +826,7 @@ mod tests {
     fn test_from_env_missing_api_key() {
         let _lock = ENV_LOCK.lock().unwrap();
         // Ensure no relevant env vars are set
-        env::remove_var("DEEPSEEK_API_KEY");
+        env::remove_var("OPENROUTER_API_KEY");
 
         // Run in a directory without a .env file
         let dir = tempdir().unwrap();
@@ -284,9 +843,9 @@ mod tests {
     #[test]
     fn test_from_env_uses_defaults_for_model() {
         let _lock = ENV_LOCK.lock().unwrap();
-        env::set_var("DEEPSEEK_API_KEY", "some_key");
+        env::set_var("OPENROUTER_API_KEY", "some_key");
         // Ensure no model is set in env or .env
-        env::remove_var("MODEL");
+        env::remove_var("OPENROUTER_MODEL");
 
         let dir = tempdir().unwrap();
         let original_dir = env::current_dir().unwrap();
@@ -296,6 +855,6 @@ mod tests {
         assert_eq!(config.model, Config::default().model);
 
         env::set_current_dir(original_dir).unwrap();
-        env::remove_var("DEEPSEEK_API_KEY");
+        env::remove_var("OPENROUTER_API_KEY");
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,116 @@
+//! 上下文窗口适配模块
+use crate::{
+    error::{NanoError, Result},
+    tokenizer::count_message_tokens,
+    types::Message,
+    utils::prepare_messages,
+};
+use serde::{Deserialize, Serialize};
+
+/// 请求超出模型上下文窗口时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPolicy {
+    /// 不做检查，直接发送
+    #[default]
+    Disabled,
+    /// 超出时返回 `NanoError::ContextOverflow`
+    Error,
+    /// 超出时从最早的历史消息开始丢弃，始终保留最后一条消息
+    TruncateOldest,
+}
+
+/// 按策略调整消息列表，使 `提示 token + max_tokens` 不超过 `limit`
+pub(crate) fn fit_messages(
+    model: &str,
+    system_message: &str,
+    messages: &[Message],
+    max_tokens: u32,
+    limit: usize,
+    policy: ContextPolicy,
+) -> Result<Vec<Message>> {
+    let required = |msgs: &[Message]| {
+        count_message_tokens(model, &prepare_messages(system_message, msgs)) + max_tokens as usize
+    };
+
+    let mut start = 0;
+    let mut total = required(messages);
+    if policy == ContextPolicy::TruncateOldest {
+        while total > limit && start + 1 < messages.len() {
+            start += 1;
+            total = required(&messages[start..]);
+        }
+    }
+
+    if total > limit {
+        return Err(NanoError::ContextOverflow {
+            required: total,
+            limit,
+        });
+    }
+    Ok(messages[start..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Role;
+    use crate::utils::message;
+
+    fn history() -> Vec<Message> {
+        (0..10)
+            .map(|i| {
+                message(
+                    Role::User,
+                    &format!("message number {} with some padding text", i),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_messages_within_limit() {
+        let messages = history();
+        let fitted =
+            fit_messages("gpt-4o", "", &messages, 10, 10_000, ContextPolicy::Error).unwrap();
+        assert_eq!(fitted.len(), messages.len());
+    }
+
+    #[test]
+    fn test_fit_messages_error_policy() {
+        let result = fit_messages("gpt-4o", "", &history(), 10, 50, ContextPolicy::Error);
+        assert!(matches!(
+            result,
+            Err(NanoError::ContextOverflow { limit: 50, .. })
+        ));
+    }
+
+    #[test]
+    fn test_fit_messages_truncates_oldest() {
+        let messages = history();
+        let fitted = fit_messages(
+            "gpt-4o",
+            "sys",
+            &messages,
+            10,
+            60,
+            ContextPolicy::TruncateOldest,
+        )
+        .unwrap();
+        assert!(!fitted.is_empty() && fitted.len() < messages.len());
+        assert_eq!(
+            fitted.last().unwrap().content,
+            messages.last().unwrap().content
+        );
+
+        let result = fit_messages(
+            "gpt-4o",
+            "sys",
+            &messages,
+            100,
+            60,
+            ContextPolicy::TruncateOldest,
+        );
+        assert!(matches!(result, Err(NanoError::ContextOverflow { .. })));
+    }
+}
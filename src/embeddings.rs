@@ -0,0 +1,99 @@
+//! 文本向量模块
+use crate::{
+    client::LLMClient,
+    error::Result,
+    utils::{diff_responses, ResponseDiff},
+};
+use serde::{Deserialize, Serialize};
+
+/// 单条向量结果
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct Embedding {
+    /// 对应输入的下标
+    #[serde(default)]
+    pub index: usize,
+    /// 向量
+    #[serde(default)]
+    pub embedding: Vec<f32>,
+}
+
+/// `/embeddings` 响应体
+#[derive(Debug, Deserialize, Default)]
+struct EmbeddingList {
+    #[serde(default)]
+    data: Vec<Embedding>,
+}
+
+impl LLMClient {
+    /// 计算一组文本的向量，返回顺序与输入一致
+    ///
+    /// 使用的模型由 `Config::with_embedding_model` 配置。
+    pub async fn embed(&self, inputs: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let endpoint = format!("{}/embeddings", self.config.api_base);
+        let headers = self.build_headers().await?;
+        let params = serde_json::json!({
+            "model": &self.config.embedding_model,
+            "input": inputs,
+        });
+        let request_builder = self.client.post(&endpoint).headers(headers).json(&params);
+        let response = self.call_api_with_retry(request_builder).await?;
+
+        let mut data = response.json::<EmbeddingList>().await?.data;
+        data.sort_by_key(|e| e.index);
+        Ok(data.into_iter().map(|e| e.embedding).collect())
+    }
+
+    /// 逐词比较两段回复，并用向量的余弦相似度衡量语义上的接近程度
+    ///
+    /// 词级差异见 [`diff_responses`]，语义相似度写入 `ResponseDiff::semantic_similarity`。
+    pub async fn diff_responses(&self, a: &str, b: &str) -> Result<ResponseDiff> {
+        let mut diff = diff_responses(a, b);
+        if let [x, y] = self.embed(&[a, b]).await?.as_slice() {
+            diff.semantic_similarity = Some(cosine_similarity(x, y));
+        }
+        Ok(diff)
+    }
+}
+
+/// 计算两个向量的余弦相似度，任一向量为零向量时返回 0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_diff_responses_with_embeddings() {
+        use crate::config::Config;
+        use crate::test_util::{MockResponse, MockServer};
+
+        let server = MockServer::start(vec![MockResponse::json(serde_json::json!({
+            "data": [
+                {"index": 1, "embedding": [0.0, 1.0]},
+                {"index": 0, "embedding": [1.0, 1.0]},
+            ]
+        }))])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let diff = client.diff_responses("yes", "sure").await.unwrap();
+        assert_eq!(diff.similarity, 0.0);
+        let semantic = diff.semantic_similarity.unwrap();
+        assert!((semantic - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+    }
+}
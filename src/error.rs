@@ -1,7 +1,13 @@
 //! 错误处理模块
 
+use reqwest::{header::HeaderMap, Response, StatusCode};
+use serde_json::Value;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+/// 服务端返回请求 ID 的标头，按顺序查找
+const REQUEST_ID_HEADERS: [&str; 2] = ["x-request-id", "request-id"];
+
 /// NanoAI 库的统一错误类型
 ///
 /// 提供了完整的错误分类，便于上层应用进行精确的错误处理
@@ -9,15 +15,30 @@ use thiserror::Error;
 pub enum NanoError {
     /// HTTP 请求相关错误
     #[error("HTTP请求失败: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(reqwest::Error),
+
+    /// 无法建立连接，例如 DNS 解析失败或连接被拒绝
+    #[error("连接失败: {0}")]
+    Connect(reqwest::Error),
 
     /// JSON 序列化/反序列化错误
     #[error("JSON处理错误: {0}")]
     Json(String),
 
-    /// API 服务端错误
-    #[error("API错误: {0}")]
-    Api(String),
+    /// API 服务端返回的非成功响应
+    #[error("API错误 ({status}): {message}")]
+    Api {
+        /// HTTP 状态码
+        status: u16,
+        /// 服务端返回的错误码
+        code: Option<String>,
+        /// 错误信息
+        message: String,
+        /// 服务端返回的请求 ID
+        request_id: Option<String>,
+        /// 原始响应体
+        raw_body: String,
+    },
 
     /// 请求超时错误
     #[error("请求超时")]
@@ -32,8 +53,15 @@ pub enum NanoError {
     StreamError(String),
 
     /// API 请求频率限制
-    #[error("请求频率超限: {0}")]
-    RateLimit(String),
+    #[error("请求频率超限: {message}")]
+    RateLimit {
+        /// 错误信息
+        message: String,
+        /// 服务端建议的等待时间
+        retry_after: Option<Duration>,
+        /// 触发的限额类型，例如 `requests` 或 `tokens`
+        limit_type: Option<String>,
+    },
 
     /// 身份验证失败
     #[error("身份验证失败: {0}")]
@@ -62,11 +90,237 @@ pub enum NanoError {
     /// IO 错误
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// 请求超出模型上下文窗口
+    #[error("上下文超限: 需要 {required} tokens，上限为 {limit}")]
+    ContextOverflow {
+        /// 提示与最大生成 token 数之和
+        required: usize,
+        /// 模型上下文窗口长度
+        limit: usize,
+    },
+
+    /// 输出被服务端内容过滤拦截，或模型拒绝回答
+    #[error("内容被过滤: {categories:?}")]
+    ContentFiltered {
+        /// 被拦截的类别，服务端未提供时为空
+        categories: Vec<String>,
+        /// 模型给出的拒绝说明
+        refusal: Option<String>,
+    },
+
+    /// 外部存储错误
+    #[error("存储错误: {0}")]
+    Storage(String),
+
+    /// 累计用量超出预算
+    #[error("超出预算: {0}")]
+    BudgetExceeded(String),
+
+    /// 后台任务队列已满
+    #[error("任务队列已满: 容量为 {0}")]
+    QueueFull(usize),
+
+    /// 请求已被取消
+    #[error("请求已取消")]
+    Cancelled,
+
+    /// 输出未通过护栏检查
+    #[error("输出未通过护栏检查 [{guardrail}]: {reason}")]
+    GuardrailViolation {
+        /// 护栏名称
+        guardrail: String,
+        /// 拒绝原因
+        reason: String,
+    },
 }
 
 /// NanoAI 库的 Result 类型别名
 pub type Result<T> = std::result::Result<T, NanoError>;
 
+impl NanoError {
+    /// 从非成功响应构造 [`NanoError::Api`]，429 响应构造 [`NanoError::RateLimit`]
+    ///
+    /// 响应体为 OpenAI 风格的 `{"error": {"message", "code"}}` 时提取错误信息和错误码，
+    /// 否则以原始响应体作为错误信息。
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let request_id = REQUEST_ID_HEADERS
+            .iter()
+            .find_map(|name| response.headers().get(*name))
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let raw_body = response.text().await.unwrap_or_default();
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let (message, _) = error_fields(status.as_u16(), &raw_body);
+            let limit_type = exhausted_limit(&headers, &message);
+            return NanoError::RateLimit {
+                retry_after: retry_after(&headers, limit_type.as_deref()),
+                limit_type,
+                message,
+            };
+        }
+
+        Self::from_error_body(status.as_u16(), raw_body, request_id)
+    }
+
+    /// 从错误响应体构造 [`NanoError::Api`]
+    ///
+    /// 流中途的 `error` 事件没有单独的状态码，使用流响应本身的状态码。
+    pub(crate) fn from_error_body(
+        status: u16,
+        raw_body: String,
+        request_id: Option<String>,
+    ) -> Self {
+        let (message, code) = error_fields(status, &raw_body);
+        NanoError::Api {
+            status,
+            code,
+            message,
+            request_id,
+            raw_body,
+        }
+    }
+}
+
+/// 从错误响应体提取错误信息和错误码
+fn error_fields(status: u16, raw_body: &str) -> (String, Option<String>) {
+    let error = serde_json::from_str::<Value>(raw_body)
+        .ok()
+        .and_then(|mut body| body.get_mut("error").map(Value::take));
+    let field = |name: &str| match error.as_ref().and_then(|e| e.get(name)) {
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+    let message = field("message")
+        .or_else(|| Some(raw_body.trim().to_string()).filter(|s| !s.is_empty()))
+        .unwrap_or_else(|| {
+            let reason = StatusCode::from_u16(status)
+                .ok()
+                .and_then(|status| status.canonical_reason());
+            match reason {
+                Some(reason) => format!("Request failed with status: {} {}", status, reason),
+                None => format!("Request failed with status: {}", status),
+            }
+        });
+    (message, field("code").or_else(|| field("type")))
+}
+
+/// 读取字符串标头
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// 判断触发的限额类型：优先看剩余额度为 0 的标头，其次看错误信息
+fn exhausted_limit(headers: &HeaderMap, message: &str) -> Option<String> {
+    ["requests", "tokens"]
+        .into_iter()
+        .find(|kind| header(headers, &format!("x-ratelimit-remaining-{}", kind)) == Some("0"))
+        .or_else(|| {
+            let message = message.to_lowercase();
+            ["requests", "tokens"]
+                .into_iter()
+                .find(|kind| message.contains(&format!("{} per", kind)))
+        })
+        .map(str::to_string)
+}
+
+/// 服务端建议的等待时间的上限
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(24 * 3600);
+
+/// 秒数转换为等待时间，结果不超过一天
+///
+/// 无穷大或超出 `Duration` 范围的值同样取上限，只有 NaN 返回 `None`。
+fn wait_secs(secs: f64) -> Option<Duration> {
+    if secs.is_nan() {
+        return None;
+    }
+    let wait = Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(MAX_RETRY_AFTER);
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+/// HTTP 日期形式的 `retry-after`（RFC 9110 §10.2.3），已过去的时间视为无需等待
+fn wait_until(date: &str) -> Option<Duration> {
+    let at = httpdate::parse_http_date(date.trim()).ok()?;
+    let wait = at.duration_since(SystemTime::now()).unwrap_or_default();
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+/// 响应标头中服务端建议的重试等待时间
+pub(crate) fn retry_after_headers(headers: &HeaderMap) -> Option<Duration> {
+    retry_after(headers, exhausted_limit(headers, "").as_deref())
+}
+
+/// 服务端建议的等待时间
+///
+/// 依次读取 `retry-after-ms`、`retry-after`（秒数或 HTTP 日期）以及对应限额的
+/// `x-ratelimit-reset-*` 标头（如 `6m0s`、`20ms`）。无法解析的值被忽略。
+fn retry_after(headers: &HeaderMap, limit_type: Option<&str>) -> Option<Duration> {
+    let number = |name: &str| header(headers, name).and_then(|v| v.trim().parse::<f64>().ok());
+    if let Some(wait) = number("retry-after-ms").and_then(|ms| wait_secs(ms / 1000.0)) {
+        return Some(wait);
+    }
+    let retry_after = number("retry-after")
+        .and_then(wait_secs)
+        .or_else(|| header(headers, "retry-after").and_then(wait_until));
+    if let Some(wait) = retry_after {
+        return Some(wait);
+    }
+    let kinds = match limit_type {
+        Some(kind) => vec![kind],
+        None => vec!["requests", "tokens"],
+    };
+    kinds
+        .into_iter()
+        .find_map(|kind| header(headers, &format!("x-ratelimit-reset-{}", kind)))
+        .and_then(parse_reset)
+}
+
+/// 解析 `1h2m3.5s`、`20ms` 形式的时长
+fn parse_reset(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "ms" => 0.001,
+            "s" | "" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        total += number * scale;
+        rest = &rest[unit_len..];
+    }
+    wait_secs(total)
+}
+
+impl From<reqwest::Error> for NanoError {
+    /// 超时映射为 [`NanoError::Timeout`]，连接失败映射为 [`NanoError::Connect`]
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            NanoError::Timeout
+        } else if e.is_connect() {
+            NanoError::Connect(e)
+        } else {
+            NanoError::Http(e)
+        }
+    }
+}
+
 impl From<serde_json::Error> for NanoError {
     fn from(e: serde_json::Error) -> Self {
         NanoError::Json(e.to_string())
@@ -78,3 +332,86 @@ impl From<std::string::FromUtf8Error> for NanoError {
         NanoError::Utf8(e.utf8_error())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_rate_limit_headers() {
+        assert_eq!(parse_reset("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_reset("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_reset("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_reset("soon"), None);
+        assert_eq!(parse_reset("99999999999999999999h"), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_reset("400h"), Some(MAX_RETRY_AFTER));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ratelimit-remaining-tokens",
+            HeaderValue::from_static("0"),
+        );
+        headers.insert("x-ratelimit-reset-tokens", HeaderValue::from_static("2s"));
+        headers.insert("x-ratelimit-reset-requests", HeaderValue::from_static("1m"));
+        let limit = exhausted_limit(&headers, "");
+        assert_eq!(limit.as_deref(), Some("tokens"));
+        assert_eq!(
+            retry_after(&headers, limit.as_deref()),
+            Some(Duration::from_secs(2))
+        );
+
+        headers.insert("retry-after", HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers, None), Some(Duration::from_secs(7)));
+
+        // 超出范围的值取上限，不会 panic
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("1e400"));
+        assert_eq!(retry_after(&headers, None), Some(MAX_RETRY_AFTER));
+        headers.insert("retry-after", HeaderValue::from_static("1e12"));
+        assert_eq!(retry_after_headers(&headers), Some(MAX_RETRY_AFTER));
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after-ms", HeaderValue::from_static("1e30"));
+        assert_eq!(retry_after(&headers, None), Some(MAX_RETRY_AFTER));
+        headers.insert("retry-after-ms", HeaderValue::from_static("NaN"));
+        assert_eq!(retry_after(&headers, None), None);
+        let huge = HeaderValue::from_static("99999999999999999999h");
+        headers.insert("x-ratelimit-reset-tokens", huge);
+        assert_eq!(retry_after(&headers, Some("tokens")), Some(MAX_RETRY_AFTER));
+
+        // HTTP 日期形式
+        let mut headers = HeaderMap::new();
+        let at = SystemTime::now() + Duration::from_secs(120);
+        let date = HeaderValue::from_str(&httpdate::fmt_http_date(at)).unwrap();
+        headers.insert("retry-after", date);
+        let wait = retry_after(&headers, None).unwrap();
+        assert!(wait > Duration::from_secs(100) && wait <= Duration::from_secs(120));
+        let past = HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT");
+        headers.insert("retry-after", past);
+        assert_eq!(retry_after(&headers, None), Some(Duration::ZERO));
+        headers.insert("retry-after", HeaderValue::from_static("later"));
+        assert_eq!(retry_after(&headers, None), None);
+        assert_eq!(
+            exhausted_limit(
+                &HeaderMap::new(),
+                "Rate limit reached on requests per min (RPM)"
+            )
+            .as_deref(),
+            Some("requests")
+        );
+    }
+
+    #[test]
+    fn test_error_fields_fallback_message() {
+        let (message, code) = error_fields(503, "");
+        assert_eq!(
+            message,
+            "Request failed with status: 503 Service Unavailable"
+        );
+        assert_eq!(code, None);
+        let (message, _) = error_fields(599, " ");
+        assert_eq!(message, "Request failed with status: 599");
+        let (message, code) = error_fields(400, r#"{"error":{"message":"bad","code":42}}"#);
+        assert_eq!((message.as_str(), code.as_deref()), ("bad", Some("42")));
+    }
+}
@@ -0,0 +1,311 @@
+//! 评测模块
+//!
+//! 从 JSONL 数据集读取提示和期望输出，在一个或多个模型上运行并计算指标，
+//! 生成可写入文件的评测报告。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// ================================================================================================
+// 数据集与选项
+// ================================================================================================
+
+/// 评测用例，对应数据集中的一行
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EvalCase {
+    /// 用例 ID，缺省时使用行号
+    #[serde(default)]
+    pub id: Option<String>,
+    /// 提示
+    pub prompt: String,
+    /// 期望输出
+    #[serde(default)]
+    pub expected: Option<String>,
+}
+
+/// 评测指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// 去除首尾空白后与期望输出完全一致
+    ExactMatch,
+    /// 输出包含期望输出（不区分大小写）
+    Contains,
+    /// 由评审模型判断输出是否正确，需要设置 `EvalOptions::judge`
+    Judge,
+}
+
+impl Metric {
+    fn name(self) -> &'static str {
+        match self {
+            Metric::ExactMatch => "exact_match",
+            Metric::Contains => "contains",
+            Metric::Judge => "judge",
+        }
+    }
+}
+
+/// 评测选项
+#[derive(Debug, Clone)]
+pub struct EvalOptions {
+    /// 参与评测的模型，为空时使用客户端配置的模型
+    pub models: Vec<String>,
+    /// 计算的指标
+    pub metrics: Vec<Metric>,
+    /// 评审模型客户端，用于 `Metric::Judge`
+    pub judge: Option<LLMClient>,
+    /// 报告输出路径（JSON），为空时不写文件
+    pub report_path: Option<PathBuf>,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        Self {
+            models: Vec::new(),
+            metrics: vec![Metric::ExactMatch, Metric::Contains],
+            judge: None,
+            report_path: None,
+        }
+    }
+}
+
+// ================================================================================================
+// 评测报告
+// ================================================================================================
+
+/// 单个用例在单个模型上的结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EvalResult {
+    /// 用例 ID
+    pub id: String,
+    /// 模型名称
+    pub model: String,
+    /// 提示
+    pub prompt: String,
+    /// 期望输出
+    pub expected: Option<String>,
+    /// 模型输出
+    pub output: Option<String>,
+    /// 请求失败时的错误信息
+    pub error: Option<String>,
+    /// 各指标得分（0.0-1.0）
+    pub scores: BTreeMap<String, f64>,
+    /// 请求耗时（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 单个模型的汇总结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ModelSummary {
+    /// 模型名称
+    pub model: String,
+    /// 用例数
+    pub cases: usize,
+    /// 请求失败数
+    pub errors: usize,
+    /// 各指标的平均得分，请求失败的用例计为 0
+    pub mean_scores: BTreeMap<String, f64>,
+    /// 平均耗时（毫秒）
+    pub mean_duration_ms: f64,
+}
+
+/// 评测报告
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EvalReport {
+    /// 按模型汇总的结果
+    pub summary: Vec<ModelSummary>,
+    /// 每个用例的详细结果
+    pub results: Vec<EvalResult>,
+}
+
+// ================================================================================================
+// 运行评测
+// ================================================================================================
+
+/// 读取 JSONL 数据集，每行为一个 [`EvalCase`]，空行会被忽略
+pub fn load_dataset(path: &Path) -> Result<Vec<EvalCase>> {
+    let content = std::fs::read_to_string(path)?;
+    parse_dataset(&content)
+}
+
+fn parse_dataset(content: &str) -> Result<Vec<EvalCase>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut case: EvalCase = serde_json::from_str(line)
+                .map_err(|e| NanoError::Json(format!("Dataset line {}: {}", i + 1, e)))?;
+            case.id.get_or_insert_with(|| (i + 1).to_string());
+            Ok(case)
+        })
+        .collect()
+}
+
+/// 在数据集上运行评测
+///
+/// 每个模型上的所有用例并发执行（受客户端并发上限约束），结果按模型、用例顺序排列。
+pub async fn run_dataset(
+    client: &LLMClient,
+    path: impl AsRef<Path>,
+    opts: &EvalOptions,
+) -> Result<EvalReport> {
+    let cases = load_dataset(path.as_ref())?;
+    let models = if opts.models.is_empty() {
+        vec![client.config.model.clone()]
+    } else {
+        opts.models.clone()
+    };
+
+    let mut report = EvalReport::default();
+    for model in &models {
+        let model_client = client.with_model(model);
+        let results = join_all(cases.iter().map(|case| run_case(&model_client, case, opts))).await;
+        report.summary.push(summarize(model, &results, opts));
+        report.results.extend(results);
+    }
+
+    if let Some(path) = &opts.report_path {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(report)
+}
+
+async fn run_case(client: &LLMClient, case: &EvalCase, opts: &EvalOptions) -> EvalResult {
+    let start = Instant::now();
+    let response = client.generate(&case.prompt).await;
+    let mut result = EvalResult {
+        id: case.id.clone().unwrap_or_default(),
+        model: client.config.model.clone(),
+        prompt: case.prompt.clone(),
+        expected: case.expected.clone(),
+        duration_ms: start.elapsed().as_millis() as u64,
+        ..EvalResult::default()
+    };
+
+    let output = match response {
+        Ok(output) => output,
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+
+    if let Some(expected) = &case.expected {
+        for metric in &opts.metrics {
+            let score = match metric {
+                Metric::ExactMatch => Some(exact_match(&output, expected)),
+                Metric::Contains => Some(contains(&output, expected)),
+                Metric::Judge => match &opts.judge {
+                    Some(judge) => judge_score(judge, &case.prompt, expected, &output).await,
+                    None => None,
+                },
+            };
+            if let Some(score) = score {
+                result.scores.insert(metric.name().to_string(), score);
+            }
+        }
+    }
+    result.output = Some(output);
+    result
+}
+
+fn exact_match(output: &str, expected: &str) -> f64 {
+    f64::from(u8::from(output.trim() == expected.trim()))
+}
+
+fn contains(output: &str, expected: &str) -> f64 {
+    let output = output.to_lowercase();
+    f64::from(u8::from(output.contains(&expected.trim().to_lowercase())))
+}
+
+const JUDGE_PROMPT: &str = "You are grading an answer against a reference. Reply with exactly \
+CORRECT if the answer is consistent with the reference, otherwise INCORRECT.";
+
+/// 由评审模型打分，评审请求失败时不计分
+async fn judge_score(judge: &LLMClient, prompt: &str, expected: &str, output: &str) -> Option<f64> {
+    let request = format!(
+        "{}\n\nQuestion:\n{}\n\nReference:\n{}\n\nAnswer:\n{}",
+        JUDGE_PROMPT, prompt, expected, output
+    );
+    let verdict = judge.generate(&request).await.ok()?;
+    let verdict = verdict.trim().to_uppercase();
+    Some(f64::from(u8::from(verdict.starts_with("CORRECT"))))
+}
+
+fn summarize(model: &str, results: &[EvalResult], opts: &EvalOptions) -> ModelSummary {
+    let cases = results.len();
+    let errors = results.iter().filter(|r| r.error.is_some()).count();
+    let mean_scores = opts
+        .metrics
+        .iter()
+        .map(|metric| {
+            let total: f64 = results
+                .iter()
+                .filter_map(|r| r.scores.get(metric.name()))
+                .sum();
+            (metric.name().to_string(), total / cases.max(1) as f64)
+        })
+        .collect();
+    let total_duration: u64 = results.iter().map(|r| r.duration_ms).sum();
+
+    ModelSummary {
+        model: model.to_string(),
+        cases,
+        errors,
+        mean_scores,
+        mean_duration_ms: total_duration as f64 / cases.max(1) as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dataset() {
+        let content = r#"{"prompt": "1+1?", "expected": "2"}
+
+{"id": "capital", "prompt": "Capital of France?", "expected": "Paris"}"#;
+        let cases = parse_dataset(content).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].id.as_deref(), Some("1"));
+        assert_eq!(cases[1].id.as_deref(), Some("capital"));
+
+        assert!(parse_dataset("{not json}").is_err());
+    }
+
+    #[test]
+    fn test_metrics() {
+        assert_eq!(exact_match(" Paris\n", "Paris"), 1.0);
+        assert_eq!(exact_match("Paris.", "Paris"), 0.0);
+        assert_eq!(contains("The capital is PARIS.", "paris"), 1.0);
+        assert_eq!(contains("Lyon", "Paris"), 0.0);
+    }
+
+    #[test]
+    fn test_summarize_counts_errors_as_zero() {
+        let opts = EvalOptions::default();
+        let results = vec![
+            EvalResult {
+                scores: BTreeMap::from([("exact_match".to_string(), 1.0)]),
+                duration_ms: 10,
+                ..EvalResult::default()
+            },
+            EvalResult {
+                error: Some("timeout".into()),
+                duration_ms: 30,
+                ..EvalResult::default()
+            },
+        ];
+        let summary = summarize("m", &results, &opts);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.mean_scores["exact_match"], 0.5);
+        assert_eq!(summary.mean_duration_ms, 20.0);
+    }
+}
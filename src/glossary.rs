@@ -0,0 +1,286 @@
+//! 术语表模块
+//!
+//! 按用户提供的术语表改写模型输出，例如统一产品名称的写法、屏蔽禁用词。
+//! 通过 `Config::with_glossary` 配置后对 `generate` 系列方法和流式输出同样生效：
+//! 流式输出会暂存可能被截断的术语，因此跨越多个增量的术语也能被替换。
+use crate::transform::ContentTransform;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+
+/// 术语表
+///
+/// ```rust
+/// use nanoai::glossary::Glossary;
+///
+/// let glossary = Glossary::new()
+///     .with_term("nano ai", "NanoAI")
+///     .with_banned("frobnicate");
+/// assert_eq!(
+///     glossary.apply("Nano AI can frobnicate."),
+///     "NanoAI can ***."
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Glossary {
+    terms: HashMap<String, String>,
+    banned: Vec<String>,
+    mask: String,
+    case_sensitive: bool,
+    whole_words: bool,
+    pattern: Option<Regex>,
+    /// 流式替换时需要暂存的字符数
+    holdback: usize,
+}
+
+impl Default for Glossary {
+    fn default() -> Self {
+        Self {
+            terms: HashMap::new(),
+            banned: Vec::new(),
+            mask: "***".into(),
+            case_sensitive: false,
+            whole_words: true,
+            pattern: None,
+            holdback: 0,
+        }
+    }
+}
+
+impl Glossary {
+    /// 创建空的术语表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 将 `term` 替换为 `replacement`
+    pub fn with_term(self, term: impl Into<String>, replacement: impl Into<String>) -> Self {
+        self.with_terms([(term, replacement)])
+    }
+
+    /// 批量添加术语
+    pub fn with_terms<T, R>(mut self, terms: impl IntoIterator<Item = (T, R)>) -> Self
+    where
+        T: Into<String>,
+        R: Into<String>,
+    {
+        for (term, replacement) in terms {
+            self.terms.insert(term.into(), replacement.into());
+        }
+        self.compile()
+    }
+
+    /// 将禁用词替换为掩码，见 [`Self::with_mask`]
+    pub fn with_banned(mut self, word: impl Into<String>) -> Self {
+        self.banned.push(word.into());
+        self.compile()
+    }
+
+    /// 设置禁用词的掩码，默认为 `***`
+    pub fn with_mask(mut self, mask: impl Into<String>) -> Self {
+        self.mask = mask.into();
+        self
+    }
+
+    /// 是否区分大小写，默认不区分
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self.compile()
+    }
+
+    /// 是否只匹配完整的单词，默认开启。中文等不以空格分词的文本应关闭
+    pub fn with_whole_words(mut self, whole_words: bool) -> Self {
+        self.whole_words = whole_words;
+        self.compile()
+    }
+
+    /// 返回改写后的文本
+    pub fn apply(&self, text: &str) -> String {
+        let Some(pattern) = &self.pattern else {
+            return text.to_string();
+        };
+        pattern
+            .replace_all(text, |caps: &regex::Captures| {
+                self.replacement(&caps[0]).to_string()
+            })
+            .into_owned()
+    }
+
+    /// 创建流式改写器
+    pub fn stream(&self) -> GlossaryStream {
+        GlossaryStream {
+            glossary: self.clone(),
+            buffer: String::new(),
+            start: 0,
+        }
+    }
+
+    fn replacement(&self, matched: &str) -> &str {
+        let key = self.key(matched);
+        self.terms
+            .iter()
+            .find(|(term, _)| self.key(term) == key)
+            .map(|(_, replacement)| replacement.as_str())
+            .unwrap_or(&self.mask)
+    }
+
+    fn key(&self, text: &str) -> String {
+        if self.case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        }
+    }
+
+    /// 根据术语重新生成匹配用的正则表达式，较长的术语优先匹配
+    fn compile(mut self) -> Self {
+        let mut words: Vec<&str> = self
+            .terms
+            .keys()
+            .chain(&self.banned)
+            .map(String::as_str)
+            .filter(|word| !word.is_empty())
+            .collect();
+        words.sort_by_key(|word| std::cmp::Reverse(word.chars().count()));
+        self.holdback = words.first().map_or(0, |word| word.chars().count() + 1);
+        if words.is_empty() {
+            self.pattern = None;
+            return self;
+        }
+        let alternatives: Vec<String> = words
+            .iter()
+            .map(|word| {
+                let boundary = |c: Option<char>| {
+                    if self.whole_words && c.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                        r"\b"
+                    } else {
+                        ""
+                    }
+                };
+                let (first, last) = (word.chars().next(), word.chars().last());
+                format!(
+                    "{}{}{}",
+                    boundary(first),
+                    regex::escape(word),
+                    boundary(last)
+                )
+            })
+            .collect();
+        let pattern = RegexBuilder::new(&alternatives.join("|"))
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .expect("escaped terms form a valid pattern");
+        self.pattern = Some(pattern);
+        self
+    }
+}
+
+impl ContentTransform for Glossary {
+    fn transform(&self, text: &str) -> String {
+        self.apply(text)
+    }
+}
+
+/// 流式改写器
+///
+/// 输出末尾可能是某个术语的前半部分，因此会暂存最长术语长度的内容，
+/// 直到能够确定是否匹配。流结束时调用 [`Self::finish`] 取出剩余内容。
+#[derive(Debug, Clone)]
+pub struct GlossaryStream {
+    glossary: Glossary,
+    /// 上次输出的最后一个字符和尚未输出的内容，前者用于判断单词边界
+    buffer: String,
+    /// 尚未输出的内容在 `buffer` 中的起始位置
+    start: usize,
+}
+
+impl GlossaryStream {
+    /// 追加一个增量，返回可以确定的改写结果
+    pub fn push(&mut self, delta: &str) -> String {
+        self.buffer.push_str(delta);
+        let pending = self.buffer[self.start..].chars().count();
+        if pending <= self.glossary.holdback {
+            return String::new();
+        }
+        let cut = self.buffer[self.start..]
+            .char_indices()
+            .nth(pending - self.glossary.holdback)
+            .map_or(self.buffer.len(), |(i, _)| self.start + i);
+        self.emit(cut)
+    }
+
+    /// 流结束时调用，返回剩余内容的改写结果
+    pub fn finish(&mut self) -> String {
+        let out = self.emit(self.buffer.len());
+        self.buffer.clear();
+        self.start = 0;
+        out
+    }
+
+    /// 输出 `cut` 之前的内容，跨越 `cut` 的匹配会一并输出
+    fn emit(&mut self, mut cut: usize) -> String {
+        let mut out = String::new();
+        let mut pos = self.start;
+        if let Some(pattern) = &self.glossary.pattern {
+            while let Some(m) = pattern.find_at(&self.buffer, pos) {
+                if m.start() >= cut {
+                    break;
+                }
+                out.push_str(&self.buffer[pos..m.start()]);
+                out.push_str(self.glossary.replacement(m.as_str()));
+                pos = m.end();
+                cut = cut.max(pos);
+            }
+        }
+        out.push_str(&self.buffer[pos..cut]);
+        let keep = self.buffer[..cut]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i);
+        self.buffer.drain(..keep);
+        self.start = cut - keep;
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glossary() -> Glossary {
+        Glossary::new()
+            .with_terms([("open ai", "OpenAI"), ("gpt", "GPT"), ("C++", "C++20")])
+            .with_banned("darn")
+    }
+
+    #[test]
+    fn test_apply() {
+        let g = glossary();
+        assert_eq!(
+            g.apply("Open AI built gpt in c++, darn. gptx darned"),
+            "OpenAI built GPT in C++20, ***. gptx darned"
+        );
+        let g = g.with_case_sensitive(true);
+        assert_eq!(g.apply("GPT gpt"), "GPT GPT");
+        let g = Glossary::new()
+            .with_term("大模型", "LLM")
+            .with_whole_words(false);
+        assert_eq!(g.apply("这个大模型很好"), "这个LLM很好");
+        assert_eq!(Glossary::new().apply("unchanged"), "unchanged");
+    }
+
+    #[test]
+    fn test_stream_matches_apply() {
+        let g = glossary();
+        let text = "Open AI shipped gpt and agpt; darn, open ai! 中文 gpt";
+        for size in 1..8 {
+            let chars: Vec<char> = text.chars().collect();
+            let mut stream = g.stream();
+            let mut out = String::new();
+            for chunk in chars.chunks(size) {
+                out.push_str(&stream.push(&chunk.iter().collect::<String>()));
+            }
+            out.push_str(&stream.finish());
+            assert_eq!(out, g.apply(text), "chunk size {}", size);
+        }
+    }
+}
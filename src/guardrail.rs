@@ -0,0 +1,196 @@
+//! 输出护栏模块
+//!
+//! 在生成结果返回给调用方之前对其进行校验，校验失败时返回 `NanoError::GuardrailViolation`。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+};
+use futures::future::{self, BoxFuture};
+use regex::Regex;
+use std::fmt::Debug;
+
+// ================================================================================================
+// 护栏接口
+// ================================================================================================
+
+/// 输出护栏
+///
+/// 对模型的最终输出进行检查。同步检查可以直接返回 `Box::pin(future::ready(..))`，
+/// 需要网络调用的检查（例如 [`LlmModeration`]）则返回真正的异步 future。
+pub trait Guardrail: Debug + Send + Sync {
+    /// 护栏名称，用于错误信息
+    fn name(&self) -> &str;
+
+    /// 检查输出内容，不通过时返回拒绝原因
+    fn check<'a>(&'a self, output: &'a str) -> BoxFuture<'a, std::result::Result<(), String>>;
+}
+
+/// 依次执行所有护栏，遇到第一个不通过的护栏时返回错误
+pub(crate) async fn enforce(
+    guardrails: &[std::sync::Arc<dyn Guardrail>],
+    output: &str,
+) -> Result<()> {
+    for guardrail in guardrails {
+        if let Err(reason) = guardrail.check(output).await {
+            return Err(NanoError::GuardrailViolation {
+                guardrail: guardrail.name().to_string(),
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+// ================================================================================================
+// 内置护栏
+// ================================================================================================
+
+/// 限制输出的最大字符数
+#[derive(Debug, Clone, Copy)]
+pub struct MaxLength(pub usize);
+
+impl Guardrail for MaxLength {
+    fn name(&self) -> &str {
+        "max_length"
+    }
+
+    fn check<'a>(&'a self, output: &'a str) -> BoxFuture<'a, std::result::Result<(), String>> {
+        let len = output.chars().count();
+        let result = if len > self.0 {
+            Err(format!("输出长度 {} 超过上限 {}", len, self.0))
+        } else {
+            Ok(())
+        };
+        Box::pin(future::ready(result))
+    }
+}
+
+/// 基于正则表达式的允许/拒绝规则
+#[derive(Debug, Clone)]
+pub struct RegexGuardrail {
+    pattern: Regex,
+    deny: bool,
+}
+
+impl RegexGuardrail {
+    /// 输出必须匹配 `pattern`
+    pub fn allow(pattern: &str) -> Result<Self> {
+        Self::build(pattern, false)
+    }
+
+    /// 输出不得匹配 `pattern`
+    pub fn deny(pattern: &str) -> Result<Self> {
+        Self::build(pattern, true)
+    }
+
+    fn build(pattern: &str, deny: bool) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| NanoError::Config(format!("Invalid guardrail pattern: {}", e)))?;
+        Ok(Self { pattern, deny })
+    }
+}
+
+impl Guardrail for RegexGuardrail {
+    fn name(&self) -> &str {
+        if self.deny {
+            "regex_deny"
+        } else {
+            "regex_allow"
+        }
+    }
+
+    fn check<'a>(&'a self, output: &'a str) -> BoxFuture<'a, std::result::Result<(), String>> {
+        let result = match (self.deny, self.pattern.find(output)) {
+            (true, Some(m)) => Err(format!("输出包含被禁止的内容: {}", m.as_str())),
+            (false, None) => Err(format!("输出不匹配规则: {}", self.pattern)),
+            _ => Ok(()),
+        };
+        Box::pin(future::ready(result))
+    }
+}
+
+const MODERATION_PROMPT: &str = "You are a content moderator. Decide whether the following text \
+violates content safety policies (hate, harassment, violence, sexual content, self-harm, illegal \
+activity). Answer with exactly `SAFE`, or `UNSAFE: <short reason>`.\n\nText:\n";
+
+/// 使用另一个 LLM 进行内容审核
+///
+/// 审核模型只需回答 `SAFE` 或 `UNSAFE: <原因>`。建议使用单独的、未配置护栏的客户端，
+/// 以免审核请求本身再次触发护栏检查。
+#[derive(Debug, Clone)]
+pub struct LlmModeration {
+    client: LLMClient,
+}
+
+impl LlmModeration {
+    /// 使用给定的客户端创建审核护栏
+    pub fn new(client: LLMClient) -> Self {
+        Self { client }
+    }
+}
+
+impl Guardrail for LlmModeration {
+    fn name(&self) -> &str {
+        "llm_moderation"
+    }
+
+    fn check<'a>(&'a self, output: &'a str) -> BoxFuture<'a, std::result::Result<(), String>> {
+        Box::pin(async move {
+            let prompt = format!("{}{}", MODERATION_PROMPT, output);
+            let verdict = self
+                .client
+                .generate(&prompt)
+                .await
+                .map_err(|e| format!("审核请求失败: {}", e))?;
+            let verdict = verdict.trim();
+            if verdict.to_uppercase().starts_with("SAFE") {
+                Ok(())
+            } else {
+                let reason = verdict
+                    .split_once(':')
+                    .map_or(verdict, |(_, reason)| reason.trim());
+                Err(reason.to_string())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_max_length() {
+        assert!(MaxLength(5).check("你好").await.is_ok());
+        assert!(MaxLength(1).check("你好").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_regex_allow_and_deny() {
+        let allow = RegexGuardrail::allow(r"^\{.*\}$").unwrap();
+        assert!(allow.check("{}").await.is_ok());
+        assert!(allow.check("plain text").await.is_err());
+
+        let deny = RegexGuardrail::deny(r"(?i)password").unwrap();
+        assert!(deny.check("hello").await.is_ok());
+        assert!(deny.check("my PASSWORD is").await.is_err());
+
+        assert!(RegexGuardrail::deny("(").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_enforce_reports_first_violation() {
+        let guardrails: Vec<Arc<dyn Guardrail>> = vec![
+            Arc::new(MaxLength(100)),
+            Arc::new(RegexGuardrail::deny("secret").unwrap()),
+        ];
+        assert!(enforce(&guardrails, "fine").await.is_ok());
+        match enforce(&guardrails, "a secret").await {
+            Err(NanoError::GuardrailViolation { guardrail, .. }) => {
+                assert_eq!(guardrail, "regex_deny")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
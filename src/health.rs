@@ -0,0 +1,105 @@
+//! 健康检查模块
+use crate::{client::LLMClient, types::Role, utils::message};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// 健康检查结果
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct HealthStatus {
+    /// 服务可用且认证成功
+    pub healthy: bool,
+    /// API 密钥是否通过认证；服务不可达时为 `false`
+    pub authenticated: bool,
+    /// 请求耗时（毫秒）
+    pub latency_ms: u64,
+    /// HTTP 状态码，网络错误时为空
+    pub status_code: Option<u16>,
+    /// 失败原因
+    pub error: Option<String>,
+}
+
+impl LLMClient {
+    /// 检查服务可用性和 API 密钥是否有效，适合作为部署服务的就绪探针
+    ///
+    /// 以当前模型发起一个 `max_tokens` 为 1 的补全请求，因此同时验证了模型是否可用。
+    /// 探针不占用并发配额、不重试、不计入累计用量，也不会返回错误：
+    /// 所有失败都体现在返回的 [`HealthStatus`] 中。
+    pub async fn health_check(&self) -> HealthStatus {
+        let start = Instant::now();
+        let headers = match self.build_headers().await {
+            Ok(headers) => headers,
+            Err(e) => {
+                return HealthStatus {
+                    error: Some(e.to_string()),
+                    ..HealthStatus::default()
+                }
+            }
+        };
+        let endpoint = format!("{}/chat/completions", self.config.api_base);
+        let params = serde_json::json!({
+            "model": &self.config.model,
+            "messages": [message(Role::User, "ping")],
+            "max_tokens": 1,
+            "stream": false,
+        });
+
+        let response = self
+            .client
+            .post(&endpoint)
+            .headers(headers)
+            .json(&params)
+            .send()
+            .await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+        match response {
+            Ok(response) => status_from_code(response.status(), latency_ms),
+            Err(e) => HealthStatus {
+                latency_ms,
+                error: Some(e.to_string()),
+                ..HealthStatus::default()
+            },
+        }
+    }
+}
+
+fn status_from_code(status: StatusCode, latency_ms: u64) -> HealthStatus {
+    let authenticated = !matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN);
+    let healthy = status.is_success();
+    HealthStatus {
+        healthy,
+        authenticated,
+        latency_ms,
+        status_code: Some(status.as_u16()),
+        error: (!healthy).then(|| format!("Request failed with status: {}", status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_status_from_code() {
+        let ok = status_from_code(StatusCode::OK, 12);
+        assert!(ok.healthy && ok.authenticated);
+        assert!(ok.error.is_none());
+
+        let unauthorized = status_from_code(StatusCode::UNAUTHORIZED, 5);
+        assert!(!unauthorized.healthy && !unauthorized.authenticated);
+        assert_eq!(unauthorized.status_code, Some(401));
+
+        let overloaded = status_from_code(StatusCode::SERVICE_UNAVAILABLE, 5);
+        assert!(!overloaded.healthy && overloaded.authenticated);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_service() {
+        let client = LLMClient::new(Config::default().with_api_base("http://127.0.0.1:1"));
+        let status = client.health_check().await;
+        assert!(!status.healthy);
+        assert!(status.status_code.is_none());
+        assert!(status.error.is_some());
+    }
+}
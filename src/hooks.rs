@@ -0,0 +1,106 @@
+//! 事件回调模块
+//!
+//! 在 [`LLMClient`] 上注册轻量级回调，在发生重试、限流等待和模型降级时得到通知，
+//! 便于记录日志或告警。回调在请求所在的任务中同步执行，应避免阻塞。
+use crate::client::LLMClient;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 请求即将重试
+#[derive(Debug, Clone)]
+pub struct RetryEvent {
+    /// 即将进行的重试序号，从 1 开始
+    pub attempt: u32,
+    /// 重试前的等待时间
+    pub delay: Duration,
+    /// 触发重试的响应状态码，网络错误时为空
+    pub status: Option<u16>,
+    /// 触发重试的网络错误
+    pub error: Option<String>,
+}
+
+/// 服务端返回 429，请求进入限流等待
+#[derive(Debug, Clone)]
+pub struct RateLimitEvent {
+    /// 即将进行的重试序号，从 1 开始
+    pub attempt: u32,
+    /// 等待时间
+    pub delay: Duration,
+}
+
+/// 请求失败后切换到降级模型
+#[derive(Debug, Clone)]
+pub struct FallbackEvent {
+    /// 失败的模型
+    pub from: String,
+    /// 切换到的模型
+    pub to: String,
+    /// 失败原因
+    pub reason: String,
+}
+
+type Hook<E> = Arc<dyn Fn(&E) + Send + Sync>;
+
+/// 已注册的回调，由同一客户端的所有句柄共享
+#[derive(Default)]
+pub(crate) struct Hooks {
+    retry: RwLock<Vec<Hook<RetryEvent>>>,
+    rate_limit: RwLock<Vec<Hook<RateLimitEvent>>>,
+    fallback: RwLock<Vec<Hook<FallbackEvent>>>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("retry", &read(&self.retry).len())
+            .field("rate_limit", &read(&self.rate_limit).len())
+            .field("fallback", &read(&self.fallback).len())
+            .finish()
+    }
+}
+
+fn read<T>(lock: &RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn push<E>(hooks: &RwLock<Vec<Hook<E>>>, hook: Hook<E>) {
+    hooks.write().unwrap_or_else(|e| e.into_inner()).push(hook);
+}
+
+fn emit<E>(hooks: &RwLock<Vec<Hook<E>>>, event: &E) {
+    for hook in read(hooks).iter() {
+        hook(event);
+    }
+}
+
+impl Hooks {
+    pub(crate) fn retry(&self, event: &RetryEvent) {
+        emit(&self.retry, event);
+    }
+
+    pub(crate) fn rate_limit(&self, event: &RateLimitEvent) {
+        emit(&self.rate_limit, event);
+    }
+
+    pub(crate) fn fallback(&self, event: &FallbackEvent) {
+        emit(&self.fallback, event);
+    }
+}
+
+impl LLMClient {
+    /// 注册重试回调
+    pub fn on_retry(&self, hook: impl Fn(&RetryEvent) + Send + Sync + 'static) {
+        push(&self.hooks.retry, Arc::new(hook));
+    }
+
+    /// 注册限流等待回调，在收到 429 响应并准备重试时触发
+    pub fn on_rate_limit(&self, hook: impl Fn(&RateLimitEvent) + Send + Sync + 'static) {
+        push(&self.hooks.rate_limit, Arc::new(hook));
+    }
+
+    /// 注册模型降级回调，在切换到 `Config::with_fallback_models` 中的模型时触发
+    pub fn on_fallback(&self, hook: impl Fn(&FallbackEvent) + Send + Sync + 'static) {
+        push(&self.hooks.fallback, Arc::new(hook));
+    }
+}
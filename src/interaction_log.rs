@@ -0,0 +1,211 @@
+//! 交互日志模块
+//!
+//! 通过 `Config::with_interaction_log` 启用后，每次生成请求（模型、参数、消息、输出和
+//! 统计信息）作为一行 JSON 追加到日志文件，可直接用作微调或分析的数据集。
+//! 文件超过大小上限时按 `path.1`、`path.2`…… 轮转。写入失败只记录警告，不影响请求。
+use crate::{
+    client::LLMClient,
+    error::NanoError,
+    options::RequestOptions,
+    types::{Message, RequestStats, ResponseWithStats},
+    utils::prepare_messages,
+};
+use log::warn;
+use serde::Serialize;
+use serde_json::Value;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 日志中的一行
+#[derive(Debug, Serialize)]
+struct Interaction<'a> {
+    /// Unix 时间戳（毫秒）
+    timestamp_ms: u64,
+    model: &'a str,
+    params: Value,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<&'a RequestStats>,
+}
+
+/// 按大小轮转的 JSON Lines 日志文件
+#[derive(Debug)]
+pub(crate) struct InteractionLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    /// 打开的文件及其当前大小
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl InteractionLog {
+    pub(crate) fn new(path: &Path, max_bytes: u64, max_files: usize) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// 第 `n` 个轮转文件的路径
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    /// 将 `path` 轮转为 `path.1`，已有的轮转文件依次后移，超出数量的删除
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let _ = fs::remove_file(self.rotated(self.max_files));
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(from, self.rotated(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    /// 追加一行，写入前超过大小上限时先轮转
+    fn append(&self, line: &[u8]) -> std::io::Result<()> {
+        let mut guard = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, size)) = guard.as_ref() {
+            if *size > 0 && *size + line.len() as u64 > self.max_bytes {
+                *guard = None;
+                self.rotate()?;
+            }
+        }
+        if guard.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            let size = file.metadata()?.len();
+            *guard = Some((file, size));
+        }
+        let (file, size) = guard.as_mut().expect("log file is open");
+        file.write_all(line)?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl LLMClient {
+    /// 将一次生成请求写入交互日志
+    pub(crate) fn log_interaction(
+        &self,
+        model: &str,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+        outcome: std::result::Result<&ResponseWithStats, &NanoError>,
+    ) {
+        let Some(log) = &self.interaction_log else {
+            return;
+        };
+        let mut params = Value::Object(Default::default());
+        options.apply_chat(&self.config, &mut params);
+        let (output, error, stats) = match outcome {
+            Ok(response) => (Some(response.content.as_str()), None, Some(&response.stats)),
+            Err(e) => (None, Some(e.to_string()), None),
+        };
+        let entry = Interaction {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            model,
+            params,
+            messages: prepare_messages(system_message, messages),
+            output,
+            error,
+            stats,
+        };
+        let result = serde_json::to_vec(&entry)
+            .map_err(std::io::Error::other)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                log.append(&line)
+            });
+        if let Err(e) = result {
+            warn!(
+                "Failed to write interaction log {}: {}",
+                log.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_appends_and_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("interactions.jsonl");
+        let server = MockServer::start(vec![MockResponse::json(completion_body("hello"))]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_interaction_log(&path)
+                .with_interaction_log_max_bytes(1)
+                .with_interaction_log_max_files(1),
+        );
+
+        for _ in 0..3 {
+            client.generate("hi").await.unwrap();
+        }
+        let line = fs::read_to_string(&path).unwrap();
+        let entry: Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(entry["output"], "hello");
+        assert_eq!(entry["model"], Config::default().model());
+        assert_eq!(
+            entry["messages"].as_array().unwrap().last().unwrap()["content"],
+            "hi"
+        );
+        assert_eq!(entry["stats"]["total_tokens"], 5);
+        assert!(entry["params"]["temperature"].is_number());
+
+        // 每行都超过上限：当前文件和一个轮转文件各一行，更早的被删除
+        assert_eq!(line.lines().count(), 1);
+        assert!(dir.path().join("interactions.jsonl.1").exists());
+        assert!(!dir.path().join("interactions.jsonl.2").exists());
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_rejection_logged_as_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("interactions.jsonl");
+        let server = MockServer::start(vec![MockResponse::json(completion_body("too long"))]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_interaction_log(&path)
+                .with_guardrail(crate::guardrail::MaxLength(3)),
+        );
+
+        let err = client.generate("hi").await.unwrap_err();
+        assert!(matches!(err, NanoError::GuardrailViolation { .. }));
+        let line = fs::read_to_string(&path).unwrap();
+        assert_eq!(line.lines().count(), 1);
+        let entry: Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert!(entry["output"].is_null());
+        assert!(entry["error"].as_str().unwrap().contains("max_length"));
+    }
+}
@@ -0,0 +1,417 @@
+//! 后台任务模块
+//!
+//! [`LLMClient::submit`] 将生成请求放入有界队列后立即返回 [`JobHandle`]，由内部任务池按
+//! `Config::with_job_workers` 设置的并行度执行，使接收请求与执行请求解耦。
+//!
+//! 配置了 [`JobStore`] 时，任务的每次状态变化都会写入存储，
+//! [`LLMClient::resume_jobs`] 可在重启后重新执行未完成的任务。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+    options::RequestOptions,
+    runtime,
+    store::{JobRecord, JobStore},
+    types::ResponseWithStats,
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    watch, Notify, Semaphore,
+};
+
+// ================================================================================================
+// 任务状态
+// ================================================================================================
+
+/// 后台任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// 排队等待执行
+    Queued,
+    /// 正在执行
+    Running,
+    /// 执行成功
+    Completed,
+    /// 执行失败
+    Failed,
+    /// 已取消
+    Cancelled,
+}
+
+impl JobStatus {
+    /// 任务是否已经结束
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug)]
+struct JobState {
+    status: watch::Sender<JobStatus>,
+    result: Mutex<Option<Result<ResponseWithStats>>>,
+    /// 用于持久化的结果摘要：成功时为输出内容，失败时为错误信息
+    outcome: OnceLock<std::result::Result<String, String>>,
+    cancel: Notify,
+}
+
+impl JobState {
+    /// 仅当当前状态为 `from` 时切换到 `to`，返回是否切换成功
+    fn transition(&self, from: JobStatus, to: JobStatus) -> bool {
+        self.status.send_if_modified(|status| {
+            let matched = *status == from;
+            if matched {
+                *status = to;
+            }
+            matched
+        })
+    }
+}
+
+/// 后台任务句柄
+#[derive(Debug)]
+pub struct JobHandle {
+    id: String,
+    state: Arc<JobState>,
+}
+
+impl JobHandle {
+    /// 任务 ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 当前状态
+    pub fn status(&self) -> JobStatus {
+        *self.state.status.borrow()
+    }
+
+    /// 取消任务
+    ///
+    /// 排队中的任务不会再执行，执行中的任务会中止正在进行的请求。已结束的任务不受影响。
+    pub fn cancel(&self) {
+        if self
+            .state
+            .transition(JobStatus::Queued, JobStatus::Cancelled)
+        {
+            return;
+        }
+        if self
+            .state
+            .transition(JobStatus::Running, JobStatus::Cancelled)
+        {
+            self.state.cancel.notify_one();
+        }
+    }
+
+    /// 等待任务结束并返回结果，任务被取消时返回 `NanoError::Cancelled`
+    pub async fn await_result(self) -> Result<ResponseWithStats> {
+        let mut status = self.state.status.subscribe();
+        // 发送端由 `self.state` 持有，等待不会因通道关闭而失败
+        let _ = status.wait_for(|s| s.is_finished()).await;
+        let result = self
+            .state
+            .result
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        result.unwrap_or(Err(NanoError::Cancelled))
+    }
+}
+
+// ================================================================================================
+// 任务队列
+// ================================================================================================
+
+struct Job {
+    client: LLMClient,
+    prompt: String,
+    options: RequestOptions,
+    state: Arc<JobState>,
+}
+
+impl Job {
+    async fn run(&self) {
+        if !self.state.transition(JobStatus::Queued, JobStatus::Running) {
+            return;
+        }
+        tokio::select! {
+            result = self.client.generate_with_options(&self.prompt, &self.options) => {
+                let (status, outcome) = match &result {
+                    Ok(response) => (JobStatus::Completed, Ok(response.content.clone())),
+                    Err(e) => (JobStatus::Failed, Err(e.to_string())),
+                };
+                let _ = self.state.outcome.set(outcome);
+                *self.state.result.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+                // 与 `cancel` 竞争时以先切换状态者为准，已取消的任务保持取消状态
+                self.state.transition(JobStatus::Running, status);
+            }
+            _ = self.state.cancel.notified() => {}
+        }
+    }
+}
+
+impl Drop for Job {
+    /// 任务在执行前被丢弃（例如运行时关闭）时标记为取消，避免等待方永久阻塞
+    fn drop(&mut self) {
+        self.state.status.send_if_modified(|status| {
+            let unfinished = !status.is_finished();
+            if unfinished {
+                *status = JobStatus::Cancelled;
+            }
+            unfinished
+        });
+    }
+}
+
+/// 客户端共享的后台任务队列，首次提交任务时启动调度任务
+#[derive(Debug, Default)]
+pub(crate) struct JobQueue {
+    sender: OnceLock<mpsc::Sender<Job>>,
+}
+
+impl JobQueue {
+    fn sender(&self, workers: usize, capacity: usize) -> &mpsc::Sender<Job> {
+        self.sender.get_or_init(|| {
+            let (tx, rx) = mpsc::channel(capacity.max(1));
+            runtime::spawn(dispatch(rx, workers.max(1)));
+            tx
+        })
+    }
+}
+
+/// 先占用执行槽位再从队列取出任务，保证排队中的任务数不超过队列容量
+async fn dispatch(mut rx: mpsc::Receiver<Job>, workers: usize) {
+    let semaphore = Arc::new(Semaphore::new(workers));
+    loop {
+        let Ok(permit) = semaphore.clone().acquire_owned().await else {
+            break;
+        };
+        let Some(job) = rx.recv().await else {
+            break;
+        };
+        runtime::spawn(async move {
+            job.run().await;
+            drop(permit);
+        });
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// 跟随任务状态变化写入存储，直到任务结束
+///
+/// 状态通过 `watch` 通道观察，连续的变化可能被合并，但最终状态一定会被写入。
+async fn persist(store: Arc<dyn JobStore>, mut record: JobRecord, state: Arc<JobState>) {
+    let mut status = state.status.subscribe();
+    loop {
+        record.status = *status.borrow_and_update();
+        record.updated_at = unix_now();
+        match state.outcome.get() {
+            Some(Ok(output)) if record.status == JobStatus::Completed => {
+                record.output = Some(output.clone());
+            }
+            Some(Err(error)) if record.status == JobStatus::Failed => {
+                record.error = Some(error.clone());
+            }
+            _ => {}
+        }
+        if let Err(e) = store.save(&record).await {
+            warn!("Failed to persist job {}: {}", record.id, e);
+        }
+        if record.status.is_finished() || status.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+impl LLMClient {
+    /// 提交一个后台生成任务
+    ///
+    /// 任务进入有界队列后立即返回句柄，队列已满时返回 `NanoError::QueueFull`。
+    /// 必须在 tokio 运行时中调用。
+    pub fn submit(&self, prompt: &str) -> Result<JobHandle> {
+        self.submit_with_options(prompt, RequestOptions::default())
+    }
+
+    /// 提交一个带参数覆盖的后台生成任务
+    pub fn submit_with_options(&self, prompt: &str, options: RequestOptions) -> Result<JobHandle> {
+        let id = format!("job-{:016x}", fastrand::u64(..));
+        self.enqueue(id, prompt.to_string(), options, unix_now())
+    }
+
+    /// 重新提交存储中排队或执行中的任务，通常在服务重启后调用
+    ///
+    /// 任务保留原来的 ID，并使用记录中的模型执行。未配置任务存储时返回空列表。
+    pub async fn resume_jobs(&self) -> Result<Vec<JobHandle>> {
+        let Some(store) = &self.config.job_store else {
+            return Ok(Vec::new());
+        };
+        let mut records = store.list(Some(JobStatus::Queued)).await?;
+        records.extend(store.list(Some(JobStatus::Running)).await?);
+        records.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+        records
+            .into_iter()
+            .map(|record| {
+                let client = if record.model == self.config.model {
+                    self.clone()
+                } else {
+                    self.with_model(&record.model)
+                };
+                client.enqueue(record.id, record.prompt, record.options, record.created_at)
+            })
+            .collect()
+    }
+
+    fn enqueue(
+        &self,
+        id: String,
+        prompt: String,
+        options: RequestOptions,
+        created_at: u64,
+    ) -> Result<JobHandle> {
+        let (status, _) = watch::channel(JobStatus::Queued);
+        let state = Arc::new(JobState {
+            status,
+            result: Mutex::new(None),
+            outcome: OnceLock::new(),
+            cancel: Notify::new(),
+        });
+        let record = self.config.job_store.as_ref().map(|store| {
+            let record = JobRecord {
+                id: id.clone(),
+                model: self.config.model.clone(),
+                prompt: prompt.clone(),
+                options: options.clone(),
+                status: JobStatus::Queued,
+                output: None,
+                error: None,
+                created_at,
+                updated_at: created_at,
+            };
+            (store.clone(), record)
+        });
+        let job = Job {
+            client: self.clone(),
+            prompt,
+            options,
+            state: state.clone(),
+        };
+
+        let capacity = self.config.job_queue_capacity;
+        self.jobs
+            .sender(self.config.job_workers, capacity)
+            .try_send(job)
+            .map_err(|e| match e {
+                TrySendError::Full(_) => NanoError::QueueFull(capacity),
+                TrySendError::Closed(_) => NanoError::Cancelled,
+            })?;
+
+        if let Some((store, record)) = record {
+            runtime::spawn(persist(store, record, state.clone()));
+        }
+        Ok(JobHandle { id, state })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::time::Duration;
+
+    /// 接受连接但从不响应的服务端，使请求一直处于执行中
+    async fn hanging_server() -> (tokio::net::TcpListener, String) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        (listener, base)
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_and_running_jobs() {
+        let (_listener, base) = hanging_server().await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(base)
+                .with_job_workers(1)
+                .with_job_queue_capacity(1),
+        );
+
+        let running = client.submit("a").unwrap();
+        // 任务由后台调度，轮询直到开始执行
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while running.status() != JobStatus::Running {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("job did not start running");
+
+        let queued = client.submit("b").unwrap();
+        assert_eq!(queued.status(), JobStatus::Queued);
+        assert!(matches!(client.submit("c"), Err(NanoError::QueueFull(1))));
+
+        queued.cancel();
+        assert!(matches!(
+            queued.await_result().await,
+            Err(NanoError::Cancelled)
+        ));
+
+        running.cancel();
+        assert_eq!(running.status(), JobStatus::Cancelled);
+        assert!(matches!(
+            running.await_result().await,
+            Err(NanoError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_reports_error() {
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base("http://127.0.0.1:1")
+                .with_max_retries(0),
+        );
+        let job = client.submit("a").unwrap();
+        assert!(job.await_result().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_job_store_records_status() {
+        let store = Arc::new(crate::store::InMemoryJobStore::new());
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base("http://127.0.0.1:1")
+                .with_max_retries(0)
+                .with_job_store(store.clone()),
+        );
+        let job = client.submit("hello").unwrap();
+        let id = job.id().to_string();
+        assert!(job.await_result().await.is_err());
+
+        // 状态在后台写入存储，轮询直到出现最终状态
+        let record = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match store.load(&id).await.unwrap() {
+                    Some(record) if record.status == JobStatus::Failed => return record,
+                    _ => tokio::time::sleep(Duration::from_millis(5)).await,
+                }
+            }
+        })
+        .await
+        .expect("final job status was not persisted");
+        assert_eq!(record.prompt, "hello");
+        assert!(record.error.is_some());
+    }
+}
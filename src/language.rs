@@ -0,0 +1,276 @@
+//! 语言检测模块
+//!
+//! 按文字系统和常见虚词粗略判断文本的语言，不依赖外部模型或词典。
+//! 请求通过 `RequestOptions::with_language` 指定输出语言后，客户端会检测回复的语言，
+//! 不符时附加明确的语言要求重新请求，次数由 `Config::with_language_retries` 控制。
+use crate::error::NanoError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 可以检测的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Chinese,
+    Japanese,
+    Korean,
+    English,
+    French,
+    German,
+    Spanish,
+    Russian,
+    Arabic,
+}
+
+impl Language {
+    /// 全部语言
+    pub const ALL: [Language; 9] = [
+        Language::Chinese,
+        Language::Japanese,
+        Language::Korean,
+        Language::English,
+        Language::French,
+        Language::German,
+        Language::Spanish,
+        Language::Russian,
+        Language::Arabic,
+    ];
+
+    /// 英文名称，用于发给模型的语言要求
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Chinese => "Chinese",
+            Language::Japanese => "Japanese",
+            Language::Korean => "Korean",
+            Language::English => "English",
+            Language::French => "French",
+            Language::German => "German",
+            Language::Spanish => "Spanish",
+            Language::Russian => "Russian",
+            Language::Arabic => "Arabic",
+        }
+    }
+
+    /// ISO 639-1 代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::Chinese => "zh",
+            Language::Japanese => "ja",
+            Language::Korean => "ko",
+            Language::English => "en",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Spanish => "es",
+            Language::Russian => "ru",
+            Language::Arabic => "ar",
+        }
+    }
+
+    /// 使用拉丁字母的语言的常见虚词
+    fn stopwords(&self) -> &'static [&'static str] {
+        match self {
+            Language::English => &[
+                "the", "and", "is", "are", "of", "to", "in", "that", "it", "you", "with", "for",
+                "this", "was", "be",
+            ],
+            Language::French => &[
+                "le", "la", "les", "et", "est", "des", "une", "un", "du", "de", "que", "pour",
+                "dans", "pas", "vous", "avec", "ce",
+            ],
+            Language::German => &[
+                "der", "die", "das", "und", "ist", "nicht", "ein", "eine", "zu", "mit", "den",
+                "ich", "sie", "auf", "für",
+            ],
+            Language::Spanish => &[
+                "el", "la", "los", "las", "y", "es", "de", "que", "en", "un", "una", "por", "con",
+                "para", "no", "del",
+            ],
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = NanoError;
+
+    /// 接受英文名称或 ISO 639-1 代码，不区分大小写
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Language::ALL
+            .into_iter()
+            .find(|l| l.name().eq_ignore_ascii_case(s) || l.code().eq_ignore_ascii_case(s))
+            .ok_or_else(|| NanoError::InvalidRequest(format!("Unknown language '{}'", s)))
+    }
+}
+
+/// 各文字系统的字符数
+#[derive(Debug, Default)]
+struct ScriptCounts {
+    han: usize,
+    kana: usize,
+    hangul: usize,
+    cyrillic: usize,
+    arabic: usize,
+    latin: usize,
+}
+
+/// 检测文本的主要语言，文本过短或无法判断时返回 `None`
+///
+/// 代码块和行内代码不参与检测，因此夹带代码的中文回复仍会被识别为中文。
+/// 汉字、假名和谚文按每个字符相当于三个字母计算。
+///
+/// ```rust
+/// use nanoai::language::{detect, Language};
+///
+/// assert_eq!(detect("我们使用 Rust 的 tokio 库来处理异步任务"), Some(Language::Chinese));
+/// assert_eq!(detect("The quick brown fox jumps over the lazy dog"), Some(Language::English));
+/// assert_eq!(detect("ok"), None);
+/// ```
+pub fn detect(text: &str) -> Option<Language> {
+    let prose = strip_code(text);
+    let mut counts = ScriptCounts::default();
+    for c in prose.chars() {
+        match c {
+            '\u{3040}'..='\u{30ff}' => counts.kana += 1,
+            '\u{4e00}'..='\u{9fff}' | '\u{3400}'..='\u{4dbf}' => counts.han += 1,
+            '\u{ac00}'..='\u{d7af}' | '\u{1100}'..='\u{11ff}' => counts.hangul += 1,
+            '\u{0400}'..='\u{04ff}' => counts.cyrillic += 1,
+            '\u{0600}'..='\u{06ff}' => counts.arabic += 1,
+            c if c.is_alphabetic() && (c.is_ascii() || ('\u{00c0}'..='\u{024f}').contains(&c)) => {
+                counts.latin += 1
+            }
+            _ => {}
+        }
+    }
+    let scores = [
+        ((counts.han + counts.kana) * 3, None),
+        (counts.hangul * 3, Some(Language::Korean)),
+        (counts.cyrillic, Some(Language::Russian)),
+        (counts.arabic, Some(Language::Arabic)),
+        (counts.latin, Some(Language::English)),
+    ];
+    let (score, language) = scores.into_iter().max_by_key(|(score, _)| *score)?;
+    if score < 6 {
+        return None;
+    }
+    match language {
+        None if counts.kana * 10 >= counts.han + counts.kana => Some(Language::Japanese),
+        None => Some(Language::Chinese),
+        Some(Language::English) => detect_latin(&prose),
+        language => language,
+    }
+}
+
+/// 按虚词出现的次数区分使用拉丁字母的语言，没有任何虚词时返回 `None`
+fn detect_latin(text: &str) -> Option<Language> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    [
+        Language::English,
+        Language::French,
+        Language::German,
+        Language::Spanish,
+    ]
+    .into_iter()
+    .map(|language| {
+        let hits = words
+            .iter()
+            .filter(|w| language.stopwords().contains(&w.as_str()))
+            .count();
+        (hits, language)
+    })
+    .filter(|(hits, _)| *hits > 0)
+    // 得分相同时取靠前的语言
+    .max_by_key(|(hits, language)| (*hits, std::cmp::Reverse(*language as u8)))
+    .map(|(_, language)| language)
+}
+
+/// 去掉 Markdown 代码块和行内代码
+fn strip_code(text: &str) -> String {
+    let mut prose = String::new();
+    for (i, block) in text.split("```").enumerate() {
+        if i % 2 == 0 {
+            for (j, span) in block.split('`').enumerate() {
+                if j % 2 == 0 {
+                    prose.push_str(span);
+                    prose.push(' ');
+                }
+            }
+        }
+    }
+    prose
+}
+
+/// 在系统消息后附加语言要求
+pub(crate) fn instruction(system_message: &str, language: Language) -> String {
+    let instruction = format!(
+        "You must respond only in {}, regardless of the language of the conversation.",
+        language.name()
+    );
+    if system_message.is_empty() {
+        instruction
+    } else {
+        format!("{}\n\n{}", system_message, instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let cases = [
+            (
+                "今日はいい天気ですね。散歩に行きましょう。",
+                Some(Language::Japanese),
+            ),
+            (
+                "这个函数返回一个 `Result<String>`，出错时需要处理。",
+                Some(Language::Chinese),
+            ),
+            ("안녕하세요, 만나서 반갑습니다", Some(Language::Korean)),
+            ("Привет, как дела?", Some(Language::Russian)),
+            (
+                "C'est une question pour les experts de la maison",
+                Some(Language::French),
+            ),
+            (
+                "Das ist nicht die Antwort, die ich wollte",
+                Some(Language::German),
+            ),
+            (
+                "El gato está en la casa con los niños",
+                Some(Language::Spanish),
+            ),
+            ("好", None),
+            ("", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(detect(text), expected, "{}", text);
+        }
+        let text = concat!(
+            "中文回答：\n",
+            "```rust\nfn main() {\n    println!(\"the answer is in the code\");\n}\n```"
+        );
+        assert_eq!(detect(text), Some(Language::Chinese));
+    }
+
+    #[test]
+    fn test_parse_and_instruction() {
+        assert_eq!("zh".parse::<Language>().unwrap(), Language::Chinese);
+        assert_eq!("german".parse::<Language>().unwrap(), Language::German);
+        assert!("klingon".parse::<Language>().is_err());
+        assert!(instruction("Be brief.", Language::French).starts_with("Be brief.\n\n"));
+        assert!(instruction("", Language::French).contains("only in French"));
+    }
+}
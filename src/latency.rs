@@ -0,0 +1,183 @@
+//! 延迟统计模块
+//!
+//! 客户端按模型记录成功请求的延迟直方图，流式请求额外记录首 token 延迟 (TTFT)，
+//! 通过 [`LLMClient::latency`] 读取分位数，无需外部指标系统即可发现服务端变慢。
+use crate::client::LLMClient;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// 桶边界的增长系数，分位数的相对误差不超过 25%
+const BUCKET_GROWTH: f64 = 1.25;
+/// 桶数量，最后一个桶的上界约为 27 分钟
+const BUCKETS: usize = 64;
+
+/// 按指数分桶的延迟直方图（毫秒精度）
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    counts: [u64; BUCKETS],
+    count: u64,
+    max_ms: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BUCKETS],
+            count: 0,
+            max_ms: 0,
+        }
+    }
+}
+
+/// 第 `index` 个桶的上界（毫秒）
+fn bucket_bound(index: usize) -> u64 {
+    BUCKET_GROWTH.powi(index as i32).ceil() as u64
+}
+
+impl LatencyHistogram {
+    /// 记录一个样本
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let index = (0..BUCKETS)
+            .find(|&i| ms <= bucket_bound(i))
+            .unwrap_or(BUCKETS - 1);
+        self.counts[index] += 1;
+        self.count += 1;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    /// 样本数
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 最大延迟
+    pub fn max(&self) -> Duration {
+        Duration::from_millis(self.max_ms)
+    }
+
+    /// 第 `p`（0.0-1.0）分位的延迟，返回样本所在桶的上界，没有样本时为 0
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((p.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_millis(bucket_bound(index).min(self.max_ms));
+            }
+        }
+        self.max()
+    }
+
+    /// 中位数
+    pub fn p50(&self) -> Duration {
+        self.percentile(0.50)
+    }
+
+    /// 95 分位
+    pub fn p95(&self) -> Duration {
+        self.percentile(0.95)
+    }
+
+    /// 99 分位
+    pub fn p99(&self) -> Duration {
+        self.percentile(0.99)
+    }
+}
+
+/// 单个模型的延迟统计
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ModelLatency {
+    /// 完整请求的延迟
+    pub latency: LatencyHistogram,
+    /// 流式请求的首 token 延迟
+    pub ttft: LatencyHistogram,
+}
+
+/// 按模型记录延迟，由同一客户端的所有句柄共享
+#[derive(Debug, Default)]
+pub(crate) struct LatencyTracker {
+    models: Mutex<BTreeMap<String, ModelLatency>>,
+}
+
+impl LatencyTracker {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, ModelLatency>> {
+        self.models.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录一次完整请求的延迟
+    pub(crate) fn record(&self, model: &str, latency: Duration) {
+        self.lock()
+            .entry(model.to_string())
+            .or_default()
+            .latency
+            .record(latency);
+    }
+
+    /// 记录一次流式请求的首 token 延迟
+    pub(crate) fn record_ttft(&self, model: &str, ttft: Duration) {
+        self.lock()
+            .entry(model.to_string())
+            .or_default()
+            .ttft
+            .record(ttft);
+    }
+}
+
+impl LLMClient {
+    /// 指定模型的延迟统计，没有成功请求时返回 `None`
+    pub fn latency(&self, model: &str) -> Option<ModelLatency> {
+        self.latency.lock().get(model).cloned()
+    }
+
+    /// 所有模型的延迟统计
+    pub fn latency_stats(&self) -> BTreeMap<String, ModelLatency> {
+        self.latency.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{sse_response, MockServer};
+    use futures::StreamExt;
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.p50(), Duration::ZERO);
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.max(), Duration::from_millis(100));
+        for (p, expected) in [(0.50, 50), (0.95, 95), (0.99, 99)] {
+            let actual = histogram.percentile(p).as_millis() as f64;
+            assert!(actual >= expected as f64 && actual <= expected as f64 * BUCKET_GROWTH);
+        }
+        assert_eq!(histogram.percentile(1.0), Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_client_records_latency() {
+        let server = MockServer::start(vec![sse_response(&["a", "b"])]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_model("m"),
+        );
+        assert!(client.latency("m").is_none());
+
+        let text: Vec<_> = client.stream_generate("hi").await.unwrap().collect().await;
+        assert_eq!(text.len(), 2);
+        let stats = client.latency("m").unwrap();
+        assert_eq!((stats.latency.count(), stats.ttft.count()), (1, 1));
+        assert!(stats.ttft.p50() <= stats.latency.p50());
+        assert_eq!(client.latency_stats().len(), 1);
+    }
+}
@@ -21,7 +21,7 @@
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<()> {
-//!     // 从环境变量加载配置 (需要设置 YOPENROUTER_API_KEY)
+//!     // 从环境变量加载配置 (需要设置 OPENROUTER_API_KEY)
 //!     let config = Config::from_env()?;
 //!     let client = LLMClient::new(config);
 //!     
@@ -34,17 +34,72 @@
 //! ```
 
 // 模块定义
+pub mod audio;
+pub mod auth;
+pub mod bench;
+pub mod budget;
+pub mod chat_model;
 pub mod client;
+pub mod complexity;
 pub mod config;
+pub mod context;
+pub mod embeddings;
 pub mod error;
+pub mod eval;
+pub mod glossary;
+pub mod guardrail;
+pub mod health;
+pub mod hooks;
+pub mod interaction_log;
+pub mod jobs;
+pub mod language;
+pub mod latency;
+pub mod memory;
+pub mod models;
+pub mod options;
+pub mod pool;
+pub mod priority;
+pub mod progress;
+pub mod prompts;
+pub mod rag;
+pub mod recorder;
+pub mod registry;
+pub mod render;
+pub mod report;
+pub mod rerank;
+pub mod responses;
+pub mod router;
+mod runtime;
+pub mod sampling;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
+pub mod sink;
+pub mod sse;
+pub mod store;
 pub mod stream;
+pub mod template;
+pub mod tenant;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub mod tokenizer;
+mod trace;
+pub mod transform;
+pub mod transport;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod types;
 pub mod utils;
+pub mod webhook;
 
+pub use chat_model::{ChatModel, SharedChatModel};
 pub use client::LLMClient;
 use error::Result;
 use futures::future::join_all;
-use types::ResponseWithStats;
+use options::RequestOptions;
+use std::hash::{Hash, Hasher};
+use types::{CacheControl, PrefixBatch, ResponseWithStats, Role};
+use utils::message;
 
 // ================================================================================================
 //  并发工具函数
@@ -94,7 +149,10 @@ use types::ResponseWithStats;
 /// }
 /// ```
 pub async fn batch_generate(client: &LLMClient, prompts: &[&str]) -> Vec<Result<String>> {
-    let futures = prompts.iter().map(|p| client.generate(p)).collect::<Vec<_>>();
+    let futures = prompts
+        .iter()
+        .map(|p| client.generate(p))
+        .collect::<Vec<_>>();
     join_all(futures).await
 }
 
@@ -120,3 +178,79 @@ pub async fn batch_generate_with_stats(
         .collect::<Vec<_>>();
     join_all(futures).await
 }
+
+/// 共享前缀的批量生成
+///
+/// 每个请求都以完全相同的 `shared_context` 消息开头（带提示缓存断点和相同的
+/// `prompt_cache_key`），后接各自的后缀。先单独发送第一个请求写入服务端缓存，
+/// 其余请求再并发发送，以尽量命中提示缓存。
+///
+/// # 返回
+///
+/// 各后缀的结果以及汇总的输入 token 和缓存命中 token 数。
+pub async fn generate_many_with_prefix(
+    client: &LLMClient,
+    shared_context: &str,
+    suffixes: &[&str],
+) -> PrefixBatch {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    shared_context.hash(&mut hasher);
+    let cache_key = format!("prefix-{:016x}", hasher.finish());
+    let options = RequestOptions::new().with_prompt_cache_key(cache_key);
+    let prefix = message(Role::User, shared_context).with_cache_control(CacheControl::ephemeral());
+
+    let generate = |suffix: &str| {
+        let messages = vec![prefix.clone(), message(Role::User, suffix)];
+        let options = &options;
+        async move { client.generate_internal(None, &messages, options).await }
+    };
+    let mut results = Vec::with_capacity(suffixes.len());
+    if let Some((first, rest)) = suffixes.split_first() {
+        results.push(generate(first).await);
+        results.extend(join_all(rest.iter().map(|s| generate(s))).await);
+    }
+
+    let succeeded = results.iter().filter_map(|r| r.as_ref().ok());
+    let (prompt_tokens, cached_tokens) = succeeded.fold((0, 0), |(prompt, cached), r| {
+        (
+            prompt + r.stats.prompt_tokens.unwrap_or_default() as u64,
+            cached + r.stats.cached_tokens.unwrap_or_default() as u64,
+        )
+    });
+    PrefixBatch {
+        results,
+        prompt_tokens,
+        cached_tokens,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_generate_many_with_prefix() {
+        let mut cached = completion_body("ok");
+        cached["usage"]["prompt_tokens_details"] = serde_json::json!({"cached_tokens": 2});
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("first")),
+            MockResponse::json(cached),
+        ])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+
+        let batch = generate_many_with_prefix(&client, "shared docs", &["q1", "q2", "q3"]).await;
+        assert_eq!(batch.results[0].as_ref().unwrap().content, "first");
+        assert_eq!((batch.prompt_tokens, batch.cached_tokens), (9, 4));
+
+        let bodies: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .map(|r| serde_json::from_str(&r.body).unwrap())
+            .collect();
+        assert_eq!(bodies[0]["messages"][1], bodies[2]["messages"][1]);
+        assert_eq!(bodies[0]["prompt_cache_key"], bodies[1]["prompt_cache_key"]);
+    }
+}
@@ -0,0 +1,327 @@
+//! 会话记忆模块
+//!
+//! [`ChatSession`](crate::session::ChatSession) 配置记忆后，每次发送时由记忆决定
+//! 哪些历史消息进入上下文，而不是总是发送完整历史。
+use crate::{
+    client::LLMClient,
+    embeddings::cosine_similarity,
+    error::Result,
+    types::{Message, Role},
+    utils::message,
+};
+use futures::future::BoxFuture;
+use log::warn;
+use std::fmt::Debug;
+use tokio::sync::Mutex;
+
+// ================================================================================================
+// 记忆接口
+// ================================================================================================
+
+/// 会话记忆
+pub trait Memory: Debug + Send + Sync {
+    /// 保存新一轮对话产生的消息
+    fn save<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<()>>;
+
+    /// 根据当前用户输入构建上下文消息（不含系统消息和当前输入）
+    fn context<'a>(&'a self, input: &'a str) -> BoxFuture<'a, Result<Vec<Message>>>;
+
+    /// 清空记忆
+    fn clear(&self) -> BoxFuture<'_, ()>;
+}
+
+// ================================================================================================
+// 缓冲记忆
+// ================================================================================================
+
+/// 保留最近若干条消息的缓冲记忆
+#[derive(Debug, Default)]
+pub struct BufferMemory {
+    max_messages: Option<usize>,
+    messages: Mutex<Vec<Message>>,
+}
+
+impl BufferMemory {
+    /// 创建不限长度的缓冲记忆
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建只保留最近 `max_messages` 条消息的缓冲记忆
+    pub fn with_max_messages(max_messages: usize) -> Self {
+        Self {
+            max_messages: Some(max_messages),
+            ..Self::default()
+        }
+    }
+}
+
+impl Memory for BufferMemory {
+    fn save<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut buffer = self.messages.lock().await;
+            buffer.extend_from_slice(messages);
+            if let Some(max) = self.max_messages {
+                let overflow = buffer.len().saturating_sub(max);
+                buffer.drain(..overflow);
+            }
+            Ok(())
+        })
+    }
+
+    fn context<'a>(&'a self, _input: &'a str) -> BoxFuture<'a, Result<Vec<Message>>> {
+        Box::pin(async move { Ok(self.messages.lock().await.clone()) })
+    }
+
+    fn clear(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move { self.messages.lock().await.clear() })
+    }
+}
+
+// ================================================================================================
+// 摘要记忆
+// ================================================================================================
+
+const SUMMARY_PROMPT: &str = "Progressively summarize the conversation below, merging it with the \
+existing summary. Keep names, facts, decisions and open questions. Reply with the new summary \
+only.\n\n";
+
+/// 摘要消息的前缀
+pub(crate) const SUMMARY_HEADER: &str = "Summary of the earlier conversation:\n";
+
+/// 将 `messages` 与已有摘要合并为新的摘要
+pub(crate) async fn summarize(
+    client: &LLMClient,
+    summary: &str,
+    messages: &[Message],
+) -> Result<String> {
+    let transcript = messages
+        .iter()
+        .map(|m| format!("{}: {}", m.role.as_str(), m.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "{}Existing summary:\n{}\n\nConversation:\n{}",
+        SUMMARY_PROMPT, summary, transcript
+    );
+    client.generate(&prompt).await
+}
+
+/// 以系统消息的形式放在上下文开头的摘要
+pub(crate) fn summary_message(summary: &str) -> Message {
+    message(Role::System, &format!("{}{}", SUMMARY_HEADER, summary))
+}
+
+#[derive(Debug, Default)]
+struct SummaryState {
+    summary: String,
+    recent: Vec<Message>,
+}
+
+/// 摘要记忆
+///
+/// 保留最近 `keep_recent` 条消息原文，更早的消息由 LLM 压缩为一段摘要，
+/// 以系统消息的形式放在上下文开头。生成摘要失败时保留原文，下次保存时再压缩。
+#[derive(Debug)]
+pub struct SummaryMemory {
+    client: LLMClient,
+    keep_recent: usize,
+    state: Mutex<SummaryState>,
+}
+
+impl SummaryMemory {
+    /// 使用给定的客户端生成摘要，保留最近 `keep_recent` 条消息原文
+    pub fn new(client: LLMClient, keep_recent: usize) -> Self {
+        Self {
+            client,
+            keep_recent,
+            state: Mutex::new(SummaryState::default()),
+        }
+    }
+
+    /// 当前摘要
+    pub async fn summary(&self) -> String {
+        self.state.lock().await.summary.clone()
+    }
+}
+
+impl Memory for SummaryMemory {
+    fn save<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().await;
+            state.recent.extend_from_slice(messages);
+            let overflow = state.recent.len().saturating_sub(self.keep_recent);
+            if overflow == 0 {
+                return Ok(());
+            }
+
+            let older = &state.recent[..overflow];
+            match summarize(&self.client, &state.summary, older).await {
+                Ok(summary) => {
+                    state.summary = summary;
+                    state.recent.drain(..overflow);
+                }
+                Err(e) => warn!(
+                    "Failed to summarize {} messages, keeping them: {}",
+                    overflow, e
+                ),
+            }
+            Ok(())
+        })
+    }
+
+    fn context<'a>(&'a self, _input: &'a str) -> BoxFuture<'a, Result<Vec<Message>>> {
+        Box::pin(async move {
+            let state = self.state.lock().await;
+            let summary = (!state.summary.is_empty()).then(|| summary_message(&state.summary));
+            Ok(summary
+                .into_iter()
+                .chain(state.recent.iter().cloned())
+                .collect())
+        })
+    }
+
+    fn clear(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let mut state = self.state.lock().await;
+            *state = SummaryState::default();
+        })
+    }
+}
+
+// ================================================================================================
+// 向量记忆
+// ================================================================================================
+
+/// 向量记忆
+///
+/// 保存每条消息的向量，构建上下文时按与当前输入的相似度召回最相关的 `top_k` 条消息，
+/// 并按原始顺序排列。
+#[derive(Debug)]
+pub struct VectorMemory {
+    client: LLMClient,
+    top_k: usize,
+    entries: Mutex<Vec<(Vec<f32>, Message)>>,
+}
+
+impl VectorMemory {
+    /// 使用给定的客户端计算向量，每次召回 `top_k` 条消息
+    pub fn new(client: LLMClient, top_k: usize) -> Self {
+        Self {
+            client,
+            top_k,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Memory for VectorMemory {
+    fn save<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if messages.is_empty() {
+                return Ok(());
+            }
+            let inputs: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+            let vectors = self.client.embed(&inputs).await?;
+            self.entries
+                .lock()
+                .await
+                .extend(vectors.into_iter().zip(messages.iter().cloned()));
+            Ok(())
+        })
+    }
+
+    fn context<'a>(&'a self, input: &'a str) -> BoxFuture<'a, Result<Vec<Message>>> {
+        Box::pin(async move {
+            let entries = self.entries.lock().await;
+            if entries.is_empty() {
+                return Ok(Vec::new());
+            }
+            let query = self.client.embed(&[input]).await?.pop().unwrap_or_default();
+            Ok(recall(&entries, &query, self.top_k))
+        })
+    }
+
+    fn clear(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move { self.entries.lock().await.clear() })
+    }
+}
+
+/// 按相似度选出 `top_k` 条消息，保持原始顺序
+fn recall(entries: &[(Vec<f32>, Message)], query: &[f32], top_k: usize) -> Vec<Message> {
+    let mut scored: Vec<(usize, f32)> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, (vector, _))| (i, cosine_similarity(vector, query)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(top_k);
+    scored.sort_by_key(|(i, _)| *i);
+    scored
+        .into_iter()
+        .map(|(i, _)| entries[i].1.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_buffer_memory_keeps_recent() {
+        let memory = BufferMemory::with_max_messages(2);
+        memory
+            .save(&[message(Role::User, "a"), message(Role::Assistant, "b")])
+            .await
+            .unwrap();
+        memory.save(&[message(Role::User, "c")]).await.unwrap();
+
+        let context = memory.context("").await.unwrap();
+        let contents: Vec<_> = context.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["b", "c"]);
+
+        memory.clear().await;
+        assert!(memory.context("").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_summary_memory_keeps_messages_on_error() {
+        use crate::config::Config;
+        use crate::test_util::{completion_body, MockResponse, MockServer};
+
+        let server = MockServer::start(vec![
+            MockResponse::new(400, "bad request"),
+            MockResponse::json(completion_body("S")),
+        ])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let memory = SummaryMemory::new(client, 1);
+        memory
+            .save(&[message(Role::User, "a"), message(Role::Assistant, "b")])
+            .await
+            .unwrap();
+        let context = memory.context("").await.unwrap();
+        let contents: Vec<_> = context.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["a", "b"]);
+
+        memory.save(&[message(Role::User, "c")]).await.unwrap();
+        assert_eq!(memory.summary().await, "S");
+        let context = memory.context("").await.unwrap();
+        let contents: Vec<_> = context.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec![&summary_message("S").content, "c"]);
+        // 对话记录使用 API 中的角色名称
+        assert!(server.requests()[1].body.contains("user: a\\nassistant: b"));
+    }
+
+    #[test]
+    fn test_recall_keeps_original_order() {
+        let entries = vec![
+            (vec![1.0, 0.0], message(Role::User, "x")),
+            (vec![0.0, 1.0], message(Role::User, "y")),
+            (vec![0.7, 0.7], message(Role::User, "xy")),
+        ];
+        let recalled = recall(&entries, &[1.0, 0.1], 2);
+        let contents: Vec<_> = recalled.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["x", "xy"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! 模型列表模块
+use crate::{client::LLMClient, error::Result};
+use serde::{Deserialize, Serialize};
+
+/// 模型信息
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ModelInfo {
+    /// 模型 ID
+    #[serde(default)]
+    pub id: String,
+    /// 模型展示名称
+    #[serde(default)]
+    pub name: String,
+    /// 上下文窗口长度（token）
+    pub context_length: Option<u32>,
+    /// 创建时间
+    #[serde(default)]
+    pub created: u64,
+    /// 所属组织
+    pub owned_by: Option<String>,
+}
+
+/// `/models` 响应体
+#[derive(Debug, Deserialize, Default)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelInfo>,
+}
+
+impl LLMClient {
+    /// 列出服务端提供的模型
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let endpoint = format!("{}/models", self.config.api_base);
+        let headers = self.build_headers().await?;
+        let request_builder = self.client.get(&endpoint).headers(headers);
+        let response = self.call_api_with_retry(request_builder).await?;
+        Ok(response.json::<ModelList>().await?.data)
+    }
+
+    /// 当前模型的上下文窗口长度
+    ///
+    /// 优先使用 `Config::with_context_window` 配置的值，否则从 `/models` 查询并缓存。
+    /// 服务端未提供该信息时返回 `None`；查询失败时返回错误且不缓存，下次调用会重新查询。
+    pub async fn context_length(&self) -> Result<Option<u32>> {
+        if let Some(window) = self.config.context_window {
+            return Ok(Some(window));
+        }
+        self.model_context
+            .get_or_try_init(|| async {
+                let models = self.list_models().await?;
+                Ok(models
+                    .into_iter()
+                    .find(|m| m.id == self.config.model)
+                    .and_then(|m| m.context_length))
+            })
+            .await
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::context::ContextPolicy;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+    use crate::types::Role;
+    use crate::utils::message;
+
+    fn model_list() -> MockResponse {
+        MockResponse::json(serde_json::json!({
+            "object": "list",
+            "data": [
+                {"id": "deepseek-chat", "context_length": 65536, "owned_by": "deepseek"},
+                {"id": "deepseek-reasoner", "created": 1700000000}
+            ]
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_list_models() {
+        let server = MockServer::start(vec![model_list()]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].context_length, Some(65536));
+        assert_eq!(models[0].owned_by.as_deref(), Some("deepseek"));
+        assert_eq!(models[1].context_length, None);
+        assert_eq!(models[1].created, 1700000000);
+        assert_eq!(server.requests()[0].path, "/models");
+    }
+
+    #[tokio::test]
+    async fn test_context_length_is_cached() {
+        let server = MockServer::start(vec![model_list(), model_list()]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_model("deepseek-chat"),
+        );
+
+        assert_eq!(client.context_length().await.unwrap(), Some(65536));
+        assert_eq!(client.context_length().await.unwrap(), Some(65536));
+        assert_eq!(server.requests().len(), 1);
+
+        // 切换模型后重新查询
+        let other = client.with_model("deepseek-reasoner");
+        assert_eq!(other.context_length().await.unwrap(), None);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_models_error_skips_context_check() {
+        let server = MockServer::start(vec![
+            MockResponse::new(404, "not found"),
+            MockResponse::json(completion_body("ok")),
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_context_policy(ContextPolicy::Error),
+        );
+
+        assert_eq!(client.generate("hi").await.unwrap(), "ok");
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, ["/models", "/chat/completions"]);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_model_refits_context() {
+        let models = MockResponse::json(serde_json::json!({
+            "object": "list",
+            "data": [
+                {"id": "large", "context_length": 65536},
+                {"id": "small", "context_length": 200}
+            ]
+        }));
+        let server = MockServer::start(vec![
+            models.clone(),
+            MockResponse::new(503, "overloaded"),
+            models,
+            MockResponse::json(completion_body("ok")),
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_model("large")
+                .with_fallback_models(vec!["small".to_string()])
+                .with_max_retries(0)
+                .with_max_tokens(10)
+                .with_context_policy(ContextPolicy::TruncateOldest),
+        );
+        let old = "lorem ipsum dolor sit amet ".repeat(40);
+        let messages = [
+            message(Role::User, &old),
+            message(Role::Assistant, &old),
+            message(Role::User, "hi"),
+        ];
+
+        let response = client
+            .generate_internal(None, &messages, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(response.content, "ok");
+        let requests = server.requests();
+        let paths: Vec<_> = requests.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/models",
+                "/chat/completions",
+                "/models",
+                "/chat/completions"
+            ]
+        );
+        let sent = |i: usize| {
+            let body: serde_json::Value = serde_json::from_str(&requests[i].body).unwrap();
+            body["messages"].as_array().unwrap().len()
+        };
+        assert_eq!(sent(1), 4);
+        assert_eq!(sent(3), 2);
+    }
+}
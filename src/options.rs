@@ -0,0 +1,360 @@
+//! 单次请求选项模块
+//!
+//! [`RequestOptions`] 用于在单次请求中覆盖 `Config` 中的采样参数，
+//! 未设置的字段沿用客户端配置。
+use crate::config::Config;
+use crate::language::Language;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 工具选择策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// 由模型决定是否调用工具
+    Auto,
+    /// 不调用任何工具
+    None,
+    /// 必须调用至少一个工具
+    Required,
+    /// 必须调用指定名称的函数
+    Function(String),
+}
+
+impl ToolChoice {
+    /// `/chat/completions` 请求体中的表示
+    pub(crate) fn chat_value(&self) -> Value {
+        match self {
+            ToolChoice::Function(name) => {
+                serde_json::json!({"type": "function", "function": {"name": name}})
+            }
+            _ => self.mode_value(),
+        }
+    }
+
+    /// `/responses` 请求体中的表示
+    pub(crate) fn responses_value(&self) -> Value {
+        match self {
+            ToolChoice::Function(name) => serde_json::json!({"type": "function", "name": name}),
+            _ => self.mode_value(),
+        }
+    }
+
+    fn mode_value(&self) -> Value {
+        match self {
+            ToolChoice::Auto => Value::from("auto"),
+            ToolChoice::None => Value::from("none"),
+            ToolChoice::Required | ToolChoice::Function(_) => Value::from("required"),
+        }
+    }
+}
+
+/// OpenRouter 联网搜索插件选项
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebSearchOptions {
+    /// 最多返回的搜索结果数
+    pub max_results: Option<u32>,
+    /// 插入搜索结果前的提示语
+    pub search_prompt: Option<String>,
+    /// 搜索引擎，例如 `native`、`exa`
+    pub engine: Option<String>,
+    /// 改用模型名加 `:online` 后缀的方式启用搜索，而不是发送插件配置
+    pub online_suffix: bool,
+}
+
+impl WebSearchOptions {
+    /// 使用服务端默认设置
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置最多返回的搜索结果数
+    pub fn with_max_results(mut self, max_results: u32) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// 设置插入搜索结果前的提示语
+    pub fn with_search_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.search_prompt = Some(prompt.into());
+        self
+    }
+
+    /// 设置搜索引擎
+    pub fn with_engine(mut self, engine: impl Into<String>) -> Self {
+        self.engine = Some(engine.into());
+        self
+    }
+
+    /// 改用 `:online` 模型后缀启用搜索
+    pub fn with_online_suffix(mut self) -> Self {
+        self.online_suffix = true;
+        self
+    }
+
+    /// 将搜索配置写入请求体，`params["model"]` 需已设置
+    pub(crate) fn apply(&self, params: &mut Value) {
+        if self.online_suffix {
+            if let Some(model) = params["model"].as_str() {
+                if !model.ends_with(":online") {
+                    params["model"] = Value::from(format!("{}:online", model));
+                }
+            }
+            return;
+        }
+        let mut plugin = serde_json::json!({"id": "web"});
+        if let Some(max_results) = self.max_results {
+            plugin["max_results"] = Value::from(max_results);
+        }
+        if let Some(prompt) = &self.search_prompt {
+            plugin["search_prompt"] = Value::from(prompt.as_str());
+        }
+        if let Some(engine) = &self.engine {
+            plugin["engine"] = Value::from(engine.as_str());
+        }
+        params["plugins"] = Value::from(vec![plugin]);
+    }
+}
+
+/// 单次请求的参数覆盖
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestOptions {
+    pub(crate) seed: Option<u64>,
+    pub(crate) temperature: Option<f32>,
+    pub(crate) top_p: Option<f32>,
+    pub(crate) max_tokens: Option<u32>,
+    pub(crate) stop: Option<Vec<String>>,
+    pub(crate) presence_penalty: Option<f32>,
+    pub(crate) frequency_penalty: Option<f32>,
+    pub(crate) idempotency_key: Option<String>,
+    pub(crate) tool_choice: Option<ToolChoice>,
+    pub(crate) parallel_tool_calls: Option<bool>,
+    pub(crate) web_search: Option<WebSearchOptions>,
+    pub(crate) prompt_cache_key: Option<String>,
+    pub(crate) user: Option<String>,
+    pub(crate) prompt_version: Option<String>,
+    pub(crate) language: Option<Language>,
+}
+
+impl RequestOptions {
+    /// 创建不覆盖任何参数的选项
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置随机种子，覆盖 `Config` 中的 `random_seed`
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// 设置温度参数
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// 设置 top_p 参数
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// 设置最大生成 token 数
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// 设置停止序列
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// 设置 presence penalty
+    pub fn with_presence_penalty(mut self, penalty: f32) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    /// 设置 frequency penalty
+    pub fn with_frequency_penalty(mut self, penalty: f32) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    /// 设置幂等键，以 `Idempotency-Key` 标头发送，内部重试复用同一个键
+    ///
+    /// 未设置时，若 `Config` 启用了 `idempotency_keys` 则自动生成。
+    pub fn with_idempotency_key(mut self, key: &str) -> Self {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+
+    /// 设置工具选择策略，仅在请求携带工具时生效
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+
+    /// 设置是否允许模型在一轮中并行调用多个工具
+    ///
+    /// 工具处理函数不能并发执行时设为 `false`，模型每轮最多调用一个工具
+    pub fn with_parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel);
+        self
+    }
+
+    /// 启用 OpenRouter 联网搜索，搜索来源可通过 `ResponseWithStats::citations` 获取
+    pub fn with_web_search(mut self, options: WebSearchOptions) -> Self {
+        self.web_search = Some(options);
+        self
+    }
+
+    /// 设置提示缓存键（OpenAI `prompt_cache_key`）
+    ///
+    /// 共享长前缀的请求使用相同的键，可以提高服务端自动提示缓存的命中率
+    pub fn with_prompt_cache_key(mut self, key: impl Into<String>) -> Self {
+        self.prompt_cache_key = Some(key.into());
+        self
+    }
+
+    /// 设置终端用户标识（`user` 字段）
+    ///
+    /// 多租户产品中用于服务端的滥用监控和归因，应使用不含个人信息的稳定 ID
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// 标记本次请求使用的提示词版本（如 `summarize@v2`），记录到 `RequestStats::prompt_version`
+    ///
+    /// 只用于统计，不会发送给服务端。
+    pub fn with_prompt_version(mut self, version: impl Into<String>) -> Self {
+        self.prompt_version = Some(version.into());
+        self
+    }
+
+    /// 要求输出使用指定的语言
+    ///
+    /// 检测到回复是其他语言时，附加明确的语言要求重新请求，最多
+    /// `Config::with_language_retries` 次。只对非流式请求生效，无法判断语言的回复视为符合要求。
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// 本次请求实际使用的随机种子
+    pub(crate) fn effective_seed(&self, config: &Config) -> Option<u64> {
+        self.seed.or(config.random_seed)
+    }
+
+    /// 将采样参数写入 `/chat/completions` 请求体
+    pub(crate) fn apply_chat(&self, config: &Config, params: &mut Value) {
+        params["temperature"] = Value::from(self.temperature.unwrap_or(config.temperature));
+        params["top_p"] = Value::from(self.top_p.unwrap_or(config.top_p));
+        params["max_tokens"] = Value::from(self.max_tokens.unwrap_or(config.max_tokens));
+        if let Some(seed) = self.effective_seed(config) {
+            params["seed"] = Value::from(seed);
+        }
+        if let Some(stop) = &self.stop {
+            params["stop"] = Value::from(stop.clone());
+        }
+        if let Some(penalty) = self.presence_penalty {
+            params["presence_penalty"] = Value::from(penalty);
+        }
+        if let Some(penalty) = self.frequency_penalty {
+            params["frequency_penalty"] = Value::from(penalty);
+        }
+        if let Some(choice) = &self.tool_choice {
+            params["tool_choice"] = choice.chat_value();
+        }
+        if let Some(parallel) = self.parallel_tool_calls {
+            params["parallel_tool_calls"] = Value::from(parallel);
+        }
+        if let Some(search) = &self.web_search {
+            search.apply(params);
+        }
+        if let Some(key) = &self.prompt_cache_key {
+            params["prompt_cache_key"] = Value::from(key.as_str());
+        }
+        if let Some(user) = &self.user {
+            params["user"] = Value::from(user.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_chat_overrides_config() {
+        let config = Config::default().with_temperature(0.7).with_random_seed(1);
+        let options = RequestOptions::new()
+            .with_temperature(0.0)
+            .with_seed(42)
+            .with_stop(vec!["\n".into()])
+            .with_user("user-1");
+
+        let mut params = serde_json::json!({"model": "m"});
+        options.apply_chat(&config, &mut params);
+        assert_eq!(params["temperature"], 0.0);
+        assert_eq!(params["user"], "user-1");
+        assert_eq!(params["seed"], 42);
+        assert_eq!(params["stop"], serde_json::json!(["\n"]));
+        assert!(params.get("presence_penalty").is_none());
+
+        let mut params = serde_json::json!({});
+        RequestOptions::default().apply_chat(&config, &mut params);
+        assert_eq!(params["seed"], 1);
+        assert_eq!(params["max_tokens"], config.max_tokens);
+        assert!(params.get("user").is_none());
+    }
+
+    #[test]
+    fn test_tool_choice_values() {
+        let choice = ToolChoice::Function("lookup".into());
+        assert_eq!(
+            choice.chat_value(),
+            serde_json::json!({"type": "function", "function": {"name": "lookup"}})
+        );
+        assert_eq!(
+            choice.responses_value(),
+            serde_json::json!({"type": "function", "name": "lookup"})
+        );
+        assert_eq!(ToolChoice::Required.chat_value(), "required");
+
+        let mut params = serde_json::json!({});
+        RequestOptions::new()
+            .with_tool_choice(ToolChoice::None)
+            .with_parallel_tool_calls(false)
+            .apply_chat(&Config::default(), &mut params);
+        assert_eq!(params["tool_choice"], "none");
+        assert_eq!(params["parallel_tool_calls"], false);
+    }
+
+    #[test]
+    fn test_web_search() {
+        let config = Config::default();
+        let mut params = serde_json::json!({"model": "openai/gpt-4o"});
+        RequestOptions::new()
+            .with_web_search(WebSearchOptions::new().with_max_results(3))
+            .apply_chat(&config, &mut params);
+        assert_eq!(
+            params["plugins"],
+            serde_json::json!([{"id": "web", "max_results": 3}])
+        );
+        assert_eq!(params["model"], "openai/gpt-4o");
+
+        let options =
+            RequestOptions::new().with_web_search(WebSearchOptions::new().with_online_suffix());
+        for _ in 0..2 {
+            options.apply_chat(&config, &mut params);
+        }
+        assert_eq!(params["model"], "openai/gpt-4o:online");
+    }
+}
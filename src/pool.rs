@@ -0,0 +1,146 @@
+//! 连接统计模块
+//!
+//! 提供并发配额和按主机统计的请求情况，并允许在运行时调整并发上限，
+//! 便于根据实际饱和程度调优。reqwest 不公开连接池内部状态，
+//! 按主机统计以请求为单位，从发送到收到响应头为止计为执行中。
+use crate::client::LLMClient;
+use crate::priority::ConcurrencyStats;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// 单个主机的请求统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HostStats {
+    /// 已发送的请求数
+    pub requests: u64,
+    /// 网络层失败的请求数
+    pub errors: u64,
+    /// 正在等待响应头的请求数
+    pub in_flight: usize,
+    /// 等待响应头的请求数的历史峰值
+    pub peak_in_flight: usize,
+}
+
+/// 连接池配置和按主机统计的请求情况
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PoolStats {
+    /// 每个主机的最大空闲连接数
+    pub max_idle_per_host: usize,
+    /// 空闲连接的超时时间
+    pub idle_timeout: Duration,
+    /// 按主机（`host:port`）统计的请求情况
+    pub hosts: BTreeMap<String, HostStats>,
+}
+
+/// 按主机统计请求，由同一客户端的所有句柄共享
+#[derive(Debug, Default)]
+pub(crate) struct HostTracker {
+    hosts: Mutex<BTreeMap<String, HostStats>>,
+}
+
+impl HostTracker {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, HostStats>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录一次请求开始，返回的守卫在请求结束时更新统计
+    pub(crate) fn start(self: &Arc<Self>, url: &reqwest::Url) -> HostGuard {
+        let host = match (url.host_str(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => String::new(),
+        };
+        let mut hosts = self.lock();
+        let stats = hosts.entry(host.clone()).or_default();
+        stats.requests += 1;
+        stats.in_flight += 1;
+        stats.peak_in_flight = stats.peak_in_flight.max(stats.in_flight);
+        HostGuard {
+            tracker: self.clone(),
+            host,
+            failed: false,
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, HostStats> {
+        self.lock().clone()
+    }
+}
+
+/// 执行中的请求，释放时从主机统计中移除
+pub(crate) struct HostGuard {
+    tracker: Arc<HostTracker>,
+    host: String,
+    failed: bool,
+}
+
+impl HostGuard {
+    /// 标记请求在网络层失败
+    pub(crate) fn fail(mut self) {
+        self.failed = true;
+    }
+}
+
+impl Drop for HostGuard {
+    fn drop(&mut self) {
+        if let Some(stats) = self.tracker.lock().get_mut(&self.host) {
+            stats.in_flight -= 1;
+            if self.failed {
+                stats.errors += 1;
+            }
+        }
+    }
+}
+
+impl LLMClient {
+    /// 并发配额的使用情况
+    pub fn concurrency_stats(&self) -> ConcurrencyStats {
+        self.semaphore.stats()
+    }
+
+    /// 连接池配置和按主机统计的请求情况
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            max_idle_per_host: self.config.pool_max_idle_per_host,
+            idle_timeout: self.config.pool_idle_timeout,
+            hosts: self.hosts.snapshot(),
+        }
+    }
+
+    /// 在运行时调整最大并发请求数
+    ///
+    /// 对共享同一并发配额的所有句柄生效。调小时不会中断执行中的请求。
+    pub fn set_max_concurrent_requests(&self, max: usize) {
+        self.semaphore.set_capacity(max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_pool_stats() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_max_concurrent_requests(4),
+        );
+        client.generate("hi").await.unwrap();
+        client.with_model("other").generate("hi").await.unwrap();
+
+        let stats = client.pool_stats();
+        let host = stats.hosts.values().next().unwrap();
+        assert_eq!((host.requests, host.errors, host.in_flight), (2, 0, 0));
+        assert_eq!(host.peak_in_flight, 1);
+
+        client.set_max_concurrent_requests(8);
+        let concurrency = client.concurrency_stats();
+        assert_eq!((concurrency.capacity, concurrency.in_flight), (8, 0));
+        assert_eq!(concurrency.peak_in_flight, 1);
+    }
+}
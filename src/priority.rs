@@ -0,0 +1,304 @@
+//! 请求优先级模块
+//!
+//! 全局并发配额由 [`PrioritySemaphore`] 管理：配额耗尽时，等待中的请求按优先级
+//! （同一优先级内按到达顺序）依次获得配额。
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::oneshot;
+
+/// 请求优先级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// 低优先级，适合后台批处理
+    Low,
+    /// 普通优先级
+    #[default]
+    Normal,
+    /// 高优先级，适合交互式请求
+    High,
+}
+
+impl Priority {
+    /// 等待队列下标，高优先级排在前面
+    fn queue_index(self) -> usize {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+}
+
+/// 各优先级等待队列的长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueueDepth {
+    /// 等待中的高优先级请求数
+    pub high: usize,
+    /// 等待中的普通优先级请求数
+    pub normal: usize,
+    /// 等待中的低优先级请求数
+    pub low: usize,
+}
+
+impl QueueDepth {
+    /// 等待中的请求总数
+    pub fn total(&self) -> usize {
+        self.high + self.normal + self.low
+    }
+}
+
+/// 并发配额的运行时统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConcurrencyStats {
+    /// 配额上限
+    pub capacity: usize,
+    /// 正在执行的请求数
+    pub in_flight: usize,
+    /// 执行中请求数的历史峰值
+    pub peak_in_flight: usize,
+    /// 各优先级等待队列的长度
+    pub queued: QueueDepth,
+}
+
+#[derive(Debug)]
+struct State {
+    capacity: usize,
+    in_flight: usize,
+    peak_in_flight: usize,
+    waiters: [VecDeque<oneshot::Sender<()>>; 3],
+}
+
+impl State {
+    fn take(&mut self) {
+        self.in_flight += 1;
+        self.peak_in_flight = self.peak_in_flight.max(self.in_flight);
+    }
+
+    /// 在配额允许时依次唤醒最高优先级的等待者
+    fn dispatch(&mut self) {
+        for index in 0..self.waiters.len() {
+            while self.in_flight < self.capacity {
+                let Some(tx) = self.waiters[index].pop_front() else {
+                    break;
+                };
+                // 等待者已放弃时发送失败，继续尝试下一个
+                if tx.send(()).is_ok() {
+                    self.take();
+                }
+            }
+        }
+    }
+}
+
+/// 按优先级分配配额的信号量
+#[derive(Debug)]
+pub struct PrioritySemaphore {
+    state: Mutex<State>,
+}
+
+impl PrioritySemaphore {
+    /// 创建拥有 `permits` 个配额的信号量
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                capacity: permits,
+                in_flight: 0,
+                peak_in_flight: 0,
+                waiters: Default::default(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 以指定优先级获取一个配额，配额在返回的 [`PriorityPermit`] 释放时归还
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> PriorityPermit {
+        let rx = {
+            let mut state = self.lock();
+            if state.in_flight < state.capacity && state.waiters.iter().all(VecDeque::is_empty) {
+                state.take();
+                return PriorityPermit {
+                    semaphore: self.clone(),
+                };
+            }
+            let (tx, rx) = oneshot::channel();
+            state.waiters[priority.queue_index()].push_back(tx);
+            rx
+        };
+
+        let mut waiter = Waiter {
+            rx,
+            semaphore: self.clone(),
+        };
+        // 发送端只会在移交配额时使用，不会在未发送的情况下被丢弃
+        let _ = (&mut waiter.rx).await;
+        waiter.granted();
+        PriorityPermit {
+            semaphore: self.clone(),
+        }
+    }
+
+    /// 当前可用的配额数
+    pub fn available_permits(&self) -> usize {
+        let state = self.lock();
+        state.capacity.saturating_sub(state.in_flight)
+    }
+
+    /// 调整配额上限
+    ///
+    /// 调大时立即唤醒等待者；调小时不会中断执行中的请求，
+    /// 新请求需等执行中的请求数降到新上限以下才能获得配额。
+    pub fn set_capacity(&self, permits: usize) {
+        let mut state = self.lock();
+        state.capacity = permits;
+        state.dispatch();
+    }
+
+    /// 当前配额使用情况
+    pub fn stats(&self) -> ConcurrencyStats {
+        let (capacity, in_flight, peak_in_flight) = {
+            let state = self.lock();
+            (state.capacity, state.in_flight, state.peak_in_flight)
+        };
+        ConcurrencyStats {
+            capacity,
+            in_flight,
+            peak_in_flight,
+            queued: self.queue_depth(),
+        }
+    }
+
+    /// 各优先级等待队列的长度
+    pub fn queue_depth(&self) -> QueueDepth {
+        let state = self.lock();
+        // 已放弃等待的请求仍留在队列中，直到下一次归还配额时被清理
+        let pending = |queue: &VecDeque<oneshot::Sender<()>>| {
+            queue.iter().filter(|tx| !tx.is_closed()).count()
+        };
+        QueueDepth {
+            high: pending(&state.waiters[0]),
+            normal: pending(&state.waiters[1]),
+            low: pending(&state.waiters[2]),
+        }
+    }
+
+    /// 归还一个配额：优先移交给最高优先级的等待者
+    fn release(&self) {
+        let mut state = self.lock();
+        state.in_flight -= 1;
+        state.dispatch();
+    }
+}
+
+/// 等待中的配额请求，被取消时若配额已移交则归还
+struct Waiter {
+    rx: oneshot::Receiver<()>,
+    semaphore: Arc<PrioritySemaphore>,
+}
+
+impl Waiter {
+    fn granted(mut self) {
+        // 配额已转交给 `PriorityPermit`，不再由 `Drop` 归还
+        self.rx = oneshot::channel().1;
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        self.rx.close();
+        if self.rx.try_recv().is_ok() {
+            self.semaphore.release();
+        }
+    }
+}
+
+/// 已获取的配额，释放时自动归还
+#[derive(Debug)]
+pub struct PriorityPermit {
+    semaphore: Arc<PrioritySemaphore>,
+}
+
+impl Drop for PriorityPermit {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_higher_priority_acquires_first() {
+        let semaphore = Arc::new(PrioritySemaphore::new(1));
+        let permit = semaphore.acquire(Priority::Normal).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            let semaphore = semaphore.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire(priority).await;
+                order.lock().unwrap().push(priority);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(semaphore.queue_depth().total(), 3);
+        drop(permit);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec![Priority::High, Priority::Normal, Priority::Low]
+        );
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_does_not_leak_permit() {
+        let semaphore = Arc::new(PrioritySemaphore::new(1));
+        let permit = semaphore.acquire(Priority::Normal).await;
+
+        let waiting =
+            tokio::time::timeout(Duration::from_millis(10), semaphore.acquire(Priority::High))
+                .await;
+        assert!(waiting.is_err());
+
+        drop(permit);
+        assert_eq!(semaphore.available_permits(), 1);
+        assert_eq!(semaphore.queue_depth().total(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_capacity_and_stats() {
+        let semaphore = Arc::new(PrioritySemaphore::new(1));
+        let first = semaphore.acquire(Priority::Normal).await;
+        let waiting = {
+            let semaphore = semaphore.clone();
+            tokio::spawn(async move { semaphore.acquire(Priority::Low).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(semaphore.stats().queued.low, 1);
+
+        semaphore.set_capacity(2);
+        let second = waiting.await.unwrap();
+        let stats = semaphore.stats();
+        assert_eq!(
+            (stats.capacity, stats.in_flight, stats.peak_in_flight),
+            (2, 2, 2)
+        );
+
+        semaphore.set_capacity(1);
+        drop(first);
+        assert_eq!(semaphore.available_permits(), 0);
+        drop(second);
+        assert_eq!(semaphore.available_permits(), 1);
+        assert_eq!(semaphore.stats().peak_in_flight, 2);
+    }
+}
@@ -0,0 +1,99 @@
+//! 请求进度模块
+//!
+//! 通过 [`LLMClient::with_progress`] 获取的句柄在请求的各个阶段发送 [`Progress`] 事件：
+//! 排队等待并发配额、发出请求、重试、降级、收到首个 token、token 计数更新以及完成或失败。
+//! 界面可以据此显示细粒度的状态，而不必等到请求结束才知道发生了重试或降级。
+//!
+//! [`LLMClient::with_progress`]: crate::client::LLMClient::with_progress
+use crate::hooks::{FallbackEvent, RetryEvent};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+
+/// 请求进度事件
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// 等待并发配额
+    Queued,
+    /// 已获得配额并向 `model` 发出请求
+    Sent { model: String },
+    /// 请求即将重试，包括网络错误、空响应和语言不符等原因
+    Retried(RetryEvent),
+    /// 切换到降级模型
+    Fallback(FallbackEvent),
+    /// 流式响应收到首个 token
+    FirstToken { elapsed: Duration },
+    /// 流式响应已生成的 token 数（客户端估算）
+    Tokens { completion_tokens: u64 },
+    /// 请求完成
+    Done {
+        elapsed: Duration,
+        completion_tokens: u64,
+    },
+    /// 请求失败
+    Failed { error: String },
+}
+
+impl Progress {
+    /// 是否为终止事件（完成或失败）
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Progress::Done { .. } | Progress::Failed { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Channel {
+    Watch(Arc<watch::Sender<Option<Progress>>>),
+    Broadcast(broadcast::Sender<Progress>),
+}
+
+/// 进度事件的发送端
+///
+/// `watch` 通道只保留最新的事件，适合显示当前状态；`broadcast` 通道保留每个事件，
+/// 适合记录完整的时间线，接收端落后超过容量时会丢失最早的事件。
+///
+/// ```rust,no_run
+/// use nanoai::client::LLMClient;
+/// use nanoai::config::Config;
+/// use nanoai::progress::ProgressSender;
+///
+/// # async fn run() -> nanoai::error::Result<()> {
+/// let client = LLMClient::new(Config::from_env()?);
+/// let (progress, mut rx) = ProgressSender::watch();
+/// tokio::spawn(async move {
+///     while rx.changed().await.is_ok() {
+///         println!("{:?}", *rx.borrow_and_update());
+///     }
+/// });
+/// client.with_progress(progress).generate("Hello").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgressSender(Channel);
+
+impl ProgressSender {
+    /// 创建只保留最新事件的通道，接收端初始值为 `None`
+    pub fn watch() -> (Self, watch::Receiver<Option<Progress>>) {
+        let (tx, rx) = watch::channel(None);
+        (Self(Channel::Watch(Arc::new(tx))), rx)
+    }
+
+    /// 创建保留全部事件的通道，最多缓存 `capacity` 个未读事件
+    pub fn broadcast(capacity: usize) -> (Self, broadcast::Receiver<Progress>) {
+        let (tx, rx) = broadcast::channel(capacity);
+        (Self(Channel::Broadcast(tx)), rx)
+    }
+
+    /// 发送事件，没有接收端时忽略
+    pub(crate) fn send(&self, event: Progress) {
+        match &self.0 {
+            Channel::Watch(tx) => {
+                tx.send_replace(Some(event));
+            }
+            Channel::Broadcast(tx) => {
+                let _ = tx.send(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! 提示词注册表模块
+//!
+//! 按名称和版本管理提示词模板，可以从目录或编译期嵌入的资源加载，并支持在多个版本间做
+//! A/B 分配。渲染结果携带 `name@version` 标记，通过 [`RenderedPrompt::options`] 发送的请求
+//! 会把它记录在 `RequestStats::prompt_version` 中，便于比较各版本的效果。
+use crate::{
+    error::{NanoError, Result},
+    options::RequestOptions,
+    template::PromptTemplate,
+};
+use log::debug;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// 目录中的提示词文件可以带的扩展名，加载时从版本号中去掉
+const PROMPT_EXTENSIONS: &[&str] = &["txt", "md", "prompt", "tmpl"];
+
+/// 一个版本的提示词
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    name: String,
+    version: String,
+    template: PromptTemplate,
+}
+
+impl Prompt {
+    /// 提示词名称
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 版本号
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// `name@version` 形式的标识
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    /// 提示词模板
+    pub fn template(&self) -> &PromptTemplate {
+        &self.template
+    }
+
+    /// 用给定的变量渲染提示词
+    pub fn render(&self, vars: &[(&str, &str)]) -> Result<RenderedPrompt> {
+        Ok(RenderedPrompt {
+            text: self.template.render(vars)?,
+            id: self.id(),
+        })
+    }
+}
+
+/// 渲染后的提示词
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedPrompt {
+    /// 提示词文本
+    pub text: String,
+    /// 生成该文本的提示词版本，`name@version`
+    pub id: String,
+}
+
+impl RenderedPrompt {
+    /// 标记了提示词版本的请求选项
+    pub fn options(&self) -> RequestOptions {
+        RequestOptions::new().with_prompt_version(self.id.as_str())
+    }
+}
+
+/// 提示词注册表
+///
+/// 目录或嵌入资源中的每个提示词以 `name@version` 命名（目录中的文件可以带 `.txt`、`.md`、
+/// `.prompt` 或 `.tmpl` 扩展名，如 `summarize@v2.txt`，其他后缀视为版本号的一部分）。未指定默认版本时，[`Self::get`] 返回最新的版本：
+/// 版本号按其中的数字逐段比较，因此 `v10` 比 `v9` 新，`1.10.0` 比 `1.9.2` 新。
+///
+/// ```rust
+/// use nanoai::prompts::PromptRegistry;
+///
+/// let mut registry = PromptRegistry::from_embedded(&[
+///     ("greet@v1", "Say hi to {name}."),
+///     ("greet@v2", "Greet {name} warmly."),
+/// ])
+/// .unwrap();
+/// registry.set_split("greet", &[("v1", 50), ("v2", 50)]).unwrap();
+///
+/// let prompt = registry.assign("greet", "user-42").unwrap();
+/// let rendered = prompt.render(&[("name", "Ada")]).unwrap();
+/// assert!(rendered.id.starts_with("greet@v"));
+/// // client.generate_with_options(&rendered.text, &rendered.options())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    /// 每个名称的全部版本，按版本从旧到新排列
+    prompts: BTreeMap<String, Vec<Prompt>>,
+    defaults: HashMap<String, String>,
+    splits: HashMap<String, Vec<(String, u32)>>,
+}
+
+impl PromptRegistry {
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加载目录中以 `name@version` 命名的文件，其他文件被忽略
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut registry = Self::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            let Some((name, version)) = file_name.and_then(|name| name.split_once('@')) else {
+                debug!(
+                    "Skipping {} without a name@version file name",
+                    path.display()
+                );
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            // 版本号本身可以带点（如 `1.10.0`），只去掉已知的扩展名
+            let version = match version.rsplit_once('.') {
+                Some((version, ext)) if PROMPT_EXTENSIONS.contains(&ext) => version,
+                _ => version,
+            };
+            registry.register(name, version, &std::fs::read_to_string(&path)?)?;
+        }
+        Ok(registry)
+    }
+
+    /// 加载嵌入的资源，每项为 `("name@version", 模板)`，通常配合 `include_str!` 使用
+    pub fn from_embedded(assets: &[(&str, &str)]) -> Result<Self> {
+        let mut registry = Self::new();
+        for (key, source) in assets {
+            let (name, version) = key.split_once('@').ok_or_else(|| {
+                NanoError::InvalidRequest(format!("Prompt key '{}' is not name@version", key))
+            })?;
+            registry.register(name, version, source)?;
+        }
+        Ok(registry)
+    }
+
+    /// 注册一个版本，同名同版本的提示词会被替换
+    pub fn register(&mut self, name: &str, version: &str, source: &str) -> Result<()> {
+        let prompt = Prompt {
+            name: name.to_string(),
+            version: version.to_string(),
+            template: PromptTemplate::parse(source)?,
+        };
+        let versions = self.prompts.entry(name.to_string()).or_default();
+        versions.retain(|existing| existing.version != version);
+        versions.push(prompt);
+        versions.sort_by(|a, b| compare_versions(&a.version, &b.version));
+        Ok(())
+    }
+
+    /// 已注册的提示词名称
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prompts.keys().map(String::as_str)
+    }
+
+    /// 提示词的全部版本，从旧到新排列
+    pub fn versions(&self, name: &str) -> Vec<&str> {
+        self.prompts
+            .get(name)
+            .map(|versions| versions.iter().map(|p| p.version.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 获取提示词的指定版本
+    pub fn version(&self, name: &str, version: &str) -> Result<&Prompt> {
+        self.all(name)?
+            .iter()
+            .find(|prompt| prompt.version == version)
+            .ok_or_else(|| {
+                NanoError::InvalidRequest(format!("Unknown prompt version '{}@{}'", name, version))
+            })
+    }
+
+    /// 获取提示词的默认版本，未设置时为最新版本
+    pub fn get(&self, name: &str) -> Result<&Prompt> {
+        match self.defaults.get(name) {
+            Some(version) => self.version(name, version),
+            None => self.all(name)?.last().ok_or_else(|| unknown(name)),
+        }
+    }
+
+    /// 设置提示词的默认版本
+    pub fn set_default(&mut self, name: &str, version: &str) -> Result<()> {
+        self.version(name, version)?;
+        self.defaults.insert(name.to_string(), version.to_string());
+        Ok(())
+    }
+
+    /// 设置 A/B 分配的版本及其权重，见 [`Self::assign`]
+    pub fn set_split(&mut self, name: &str, weights: &[(&str, u32)]) -> Result<()> {
+        for (version, _) in weights {
+            self.version(name, version)?;
+        }
+        if weights.iter().all(|(_, weight)| *weight == 0) {
+            return Err(NanoError::InvalidRequest(format!(
+                "Split for prompt '{}' has no positive weight",
+                name
+            )));
+        }
+        let weights = weights
+            .iter()
+            .map(|(version, weight)| (version.to_string(), *weight))
+            .collect();
+        self.splits.insert(name.to_string(), weights);
+        Ok(())
+    }
+
+    /// 按 `key`（如用户或会话 ID）分配一个版本
+    ///
+    /// 同一个 `key` 总是分到同一个版本，不同的 `key` 按权重分布。
+    /// 未设置分配时返回 [`Self::get`] 的结果。
+    pub fn assign(&self, name: &str, key: &str) -> Result<&Prompt> {
+        let Some(weights) = self.splits.get(name) else {
+            return self.get(name);
+        };
+        let total: u64 = weights.iter().map(|(_, weight)| *weight as u64).sum();
+        let mut bucket = fnv1a(&format!("{}\0{}", name, key)) % total;
+        for (version, weight) in weights {
+            if bucket < *weight as u64 {
+                return self.version(name, version);
+            }
+            bucket -= *weight as u64;
+        }
+        unreachable!("bucket is below the total weight")
+    }
+
+    fn all(&self, name: &str) -> Result<&[Prompt]> {
+        self.prompts
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| unknown(name))
+    }
+}
+
+fn unknown(name: &str) -> NanoError {
+    NanoError::InvalidRequest(format!("Unknown prompt '{}'", name))
+}
+
+/// 按版本号中的数字逐段比较，数字相同时按字符串比较
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let numbers = |v: &str| -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse().ok())
+            .collect()
+    };
+    numbers(a).cmp(&numbers(b)).then_with(|| a.cmp(b))
+}
+
+/// 64 位 FNV-1a 哈希，在不同进程和版本间保持稳定
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::LLMClient;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[test]
+    fn test_versions_and_defaults() {
+        let mut registry = PromptRegistry::from_embedded(&[
+            ("qa@v9", "nine {q}"),
+            ("qa@v10", "ten {q}"),
+            ("qa@v2", "two {q}"),
+        ])
+        .unwrap();
+        assert_eq!(registry.versions("qa"), ["v2", "v9", "v10"]);
+        assert_eq!(registry.get("qa").unwrap().version(), "v10");
+
+        registry.set_default("qa", "v9").unwrap();
+        let rendered = registry.get("qa").unwrap().render(&[("q", "x")]).unwrap();
+        assert_eq!(rendered.text, "nine x");
+        assert_eq!(rendered.id, "qa@v9");
+
+        assert!(registry.get("missing").is_err());
+        assert!(registry.set_default("qa", "v1").is_err());
+        assert!(PromptRegistry::from_embedded(&[("no-version", "")]).is_err());
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("summarize@v1.txt"), "Summarize: {text}").unwrap();
+        std::fs::write(dir.path().join("summarize@v2.md"), "TL;DR {text}").unwrap();
+        std::fs::write(dir.path().join("README.md"), "ignored").unwrap();
+
+        let registry = PromptRegistry::from_dir(dir.path()).unwrap();
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["summarize"]);
+        assert_eq!(registry.get("summarize").unwrap().id(), "summarize@v2");
+    }
+
+    #[test]
+    fn test_from_dir_dotted_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("greet@1.9.2.txt"), "Hi {name}").unwrap();
+        std::fs::write(dir.path().join("greet@1.10.0"), "Hello {name}").unwrap();
+
+        let registry = PromptRegistry::from_dir(dir.path()).unwrap();
+        assert_eq!(registry.get("greet").unwrap().id(), "greet@1.10.0");
+        assert_eq!(registry.versions("greet"), ["1.9.2", "1.10.0"]);
+    }
+
+    #[test]
+    fn test_ab_assignment() {
+        let mut registry = PromptRegistry::from_embedded(&[("p@a", "A"), ("p@b", "B")]).unwrap();
+        assert!(registry.set_split("p", &[("a", 0), ("b", 0)]).is_err());
+        registry.set_split("p", &[("a", 1), ("b", 3)]).unwrap();
+
+        let first = registry.assign("p", "user-1").unwrap().id();
+        assert_eq!(registry.assign("p", "user-1").unwrap().id(), first);
+        let b = (0..1000)
+            .filter(|i| registry.assign("p", &i.to_string()).unwrap().version() == "b")
+            .count();
+        assert!((650..850).contains(&b), "{}", b);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_version_in_stats() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let registry = PromptRegistry::from_embedded(&[("greet@v3", "Hi {name}")]).unwrap();
+
+        let rendered = registry
+            .get("greet")
+            .unwrap()
+            .render(&[("name", "Ada")])
+            .unwrap();
+        let response = client
+            .generate_with_options(&rendered.text, &rendered.options())
+            .await
+            .unwrap();
+        assert_eq!(response.stats.prompt_version.as_deref(), Some("greet@v3"));
+        assert!(!server.requests()[0].body.contains("greet@v3"));
+    }
+}
@@ -0,0 +1,436 @@
+//! 检索增强生成 (RAG) 模块
+//!
+//! 将文本切分、向量计算、向量检索和提示词模板组合为一条流水线：
+//! [`RagPipeline::index`] 切分并索引文档，[`RagPipeline::ask`] 召回最相关的片段、
+//! 填入上下文后生成回答，并标出回答引用了哪些片段。
+use crate::{
+    client::LLMClient,
+    embeddings::cosine_similarity,
+    error::Result,
+    options::RequestOptions,
+    template::PromptTemplate,
+    types::{RequestStats, Role},
+    utils::message,
+};
+use serde::{Deserialize, Serialize};
+
+/// 每次向量请求包含的片段数
+const EMBED_BATCH: usize = 64;
+
+/// 默认的问答模板，`{context}` 为编号的片段，`{question}` 为问题
+const DEFAULT_TEMPLATE: &str = "Answer the question using only the numbered sources below. \
+Cite the sources you use with their numbers in square brackets, e.g. [1]. \
+If the sources do not contain the answer, say so.\n\n\
+Sources:\n{context}\n\nQuestion: {question}";
+
+// ================================================================================================
+// 文本切分
+// ================================================================================================
+
+/// 按字符数切分文本
+///
+/// 优先在段落和句子边界处切分，单个句子超过 `chunk_size` 时才在句中截断。
+/// 相邻片段之间保留 `overlap` 个字符的重叠，避免答案恰好跨越切分点时丢失上下文。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSplitter {
+    chunk_size: usize,
+    overlap: usize,
+}
+
+impl Default for TextSplitter {
+    fn default() -> Self {
+        Self::new(1000, 100)
+    }
+}
+
+impl TextSplitter {
+    /// 创建切分器，`overlap` 会被限制在 `chunk_size` 的一半以内
+    pub fn new(chunk_size: usize, overlap: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            chunk_size,
+            overlap: overlap.min(chunk_size / 2),
+        }
+    }
+
+    /// 切分文本，返回去除首尾空白后的非空片段
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut len = 0;
+        for sentence in sentences(text) {
+            let chars: Vec<char> = sentence.chars().collect();
+            for piece in chars.chunks(self.chunk_size) {
+                if len + piece.len() > self.chunk_size && !current.is_empty() {
+                    push_chunk(&mut chunks, &current.concat());
+                    // 保留末尾总长不超过 overlap 的完整句子作为重叠
+                    let mut keep = 0;
+                    len = 0;
+                    for kept in current.iter().rev() {
+                        let kept_len = kept.chars().count();
+                        if len + kept_len > self.overlap {
+                            break;
+                        }
+                        len += kept_len;
+                        keep += 1;
+                    }
+                    current.drain(..current.len() - keep);
+                    if len + piece.len() > self.chunk_size {
+                        current.clear();
+                        len = 0;
+                    }
+                }
+                len += piece.len();
+                current.push(piece.iter().collect());
+            }
+        }
+        push_chunk(&mut chunks, &current.concat());
+        chunks
+    }
+}
+
+/// 在段落和句末标点之后切开，保留分隔符
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut start = 0;
+    let mut ends: Vec<usize> = text
+        .char_indices()
+        .filter(|&(_, c)| matches!(c, '\n' | '.' | '!' | '?' | '。' | '！' | '？' | '；'))
+        .map(|(i, c)| i + c.len_utf8())
+        .collect();
+    ends.push(text.len());
+    ends.into_iter().filter_map(move |end| {
+        let sentence = &text[start..end];
+        start = end;
+        (!sentence.is_empty()).then_some(sentence)
+    })
+}
+
+fn push_chunk(chunks: &mut Vec<String>, chunk: &str) {
+    let chunk = chunk.trim();
+    if !chunk.is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+// ================================================================================================
+// 向量索引
+// ================================================================================================
+
+/// 待索引的文档
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Document {
+    /// 文档标识，例如文件路径或 URL
+    pub id: String,
+    /// 文档正文
+    pub text: String,
+}
+
+impl Document {
+    /// 创建文档
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// 文档切分出的片段
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Chunk {
+    /// 所属文档的标识
+    pub document_id: String,
+    /// 在所属文档中的序号
+    pub position: usize,
+    /// 片段文本
+    pub text: String,
+}
+
+/// 内存中的向量索引，按余弦相似度暴力检索
+#[derive(Debug, Clone, Default)]
+pub struct VectorIndex {
+    entries: Vec<(Vec<f32>, Chunk)>,
+}
+
+impl VectorIndex {
+    /// 创建空索引
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个片段及其向量
+    pub fn add(&mut self, vector: Vec<f32>, chunk: Chunk) {
+        self.entries.push((vector, chunk));
+    }
+
+    /// 已索引的片段数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 索引是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 返回与 `query` 最相似的 `top_k` 个片段及其相似度，按相似度从高到低排列
+    pub fn search(&self, query: &[f32], top_k: usize) -> Vec<(f32, &Chunk)> {
+        let mut scored: Vec<(f32, &Chunk)> = self
+            .entries
+            .iter()
+            .map(|(vector, chunk)| (cosine_similarity(vector, query), chunk))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(top_k);
+        scored
+    }
+}
+
+// ================================================================================================
+// 流水线
+// ================================================================================================
+
+/// 回答所依据的片段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    /// 上下文中的编号，从 1 开始
+    pub number: usize,
+    /// 与问题的相似度
+    pub score: f32,
+    /// 回答中是否以 `[编号]` 引用了该片段
+    pub cited: bool,
+    /// 片段内容
+    pub chunk: Chunk,
+}
+
+/// [`RagPipeline::ask`] 的结果
+#[derive(Debug, Clone)]
+pub struct RagAnswer {
+    /// 模型的回答
+    pub answer: String,
+    /// 填入上下文的全部片段，按编号排列
+    pub sources: Vec<Source>,
+    /// 生成请求的统计信息
+    pub stats: RequestStats,
+}
+
+impl RagAnswer {
+    /// 回答中实际引用的片段
+    pub fn cited(&self) -> impl Iterator<Item = &Source> {
+        self.sources.iter().filter(|source| source.cited)
+    }
+}
+
+/// 检索增强生成流水线
+///
+/// ```rust,no_run
+/// use nanoai::client::LLMClient;
+/// use nanoai::config::Config;
+/// use nanoai::rag::{Document, RagPipeline};
+///
+/// # async fn run() -> nanoai::error::Result<()> {
+/// let client = LLMClient::new(Config::from_env()?);
+/// let mut pipeline = RagPipeline::new(client).with_top_k(3);
+/// pipeline.index(&[Document::new("faq.md", "Refunds are processed within 5 days.")]).await?;
+/// let answer = pipeline.ask("How long do refunds take?").await?;
+/// for source in answer.cited() {
+///     println!("[{}] {}", source.number, source.chunk.document_id);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RagPipeline {
+    client: LLMClient,
+    splitter: TextSplitter,
+    index: VectorIndex,
+    top_k: usize,
+    template: PromptTemplate,
+}
+
+impl RagPipeline {
+    /// 使用给定的客户端计算向量和生成回答，默认每次召回 4 个片段
+    pub fn new(client: LLMClient) -> Self {
+        Self {
+            client,
+            splitter: TextSplitter::default(),
+            index: VectorIndex::new(),
+            top_k: 4,
+            template: PromptTemplate::parse(DEFAULT_TEMPLATE).expect("valid default template"),
+        }
+    }
+
+    /// 设置文本切分器，只影响之后索引的文档
+    pub fn with_splitter(mut self, splitter: TextSplitter) -> Self {
+        self.splitter = splitter;
+        self
+    }
+
+    /// 设置每次召回的片段数
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+
+    /// 设置问答模板，模板必须包含 `{context}` 和 `{question}` 两个变量
+    pub fn with_template(mut self, template: PromptTemplate) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// 当前的向量索引
+    pub fn vector_index(&self) -> &VectorIndex {
+        &self.index
+    }
+
+    /// 切分并索引文档，返回新增的片段数
+    pub async fn index(&mut self, documents: &[Document]) -> Result<usize> {
+        let chunks: Vec<Chunk> = documents
+            .iter()
+            .flat_map(|document| {
+                self.splitter
+                    .split(&document.text)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(position, text)| Chunk {
+                        document_id: document.id.clone(),
+                        position,
+                        text,
+                    })
+            })
+            .collect();
+        for batch in chunks.chunks(EMBED_BATCH) {
+            let inputs: Vec<&str> = batch.iter().map(|chunk| chunk.text.as_str()).collect();
+            let vectors = self.client.embed(&inputs).await?;
+            for (vector, chunk) in vectors.into_iter().zip(batch) {
+                self.index.add(vector, chunk.clone());
+            }
+        }
+        Ok(chunks.len())
+    }
+
+    /// 召回与问题最相关的片段，填入上下文后生成回答
+    pub async fn ask(&self, question: &str) -> Result<RagAnswer> {
+        let query = self
+            .client
+            .embed(&[question])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        let retrieved = self.index.search(&query, self.top_k);
+        let context = retrieved
+            .iter()
+            .enumerate()
+            .map(|(i, (_, chunk))| format!("[{}] ({})\n{}", i + 1, chunk.document_id, chunk.text))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let prompt = self
+            .template
+            .render(&[("context", &context), ("question", question)])?;
+
+        let messages = [message(Role::User, &prompt)];
+        let response = self
+            .client
+            .generate_internal(None, &messages, &RequestOptions::default())
+            .await?;
+        let sources = retrieved
+            .into_iter()
+            .enumerate()
+            .map(|(i, (score, chunk))| Source {
+                number: i + 1,
+                score,
+                cited: response.content.contains(&format!("[{}]", i + 1)),
+                chunk: chunk.clone(),
+            })
+            .collect();
+        Ok(RagAnswer {
+            answer: response.content,
+            sources,
+            stats: response.stats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[test]
+    fn test_splitter() {
+        let splitter = TextSplitter::new(20, 6);
+        let chunks = splitter.split("First sentence. Second one here.\n\nThird!");
+        assert_eq!(chunks, ["First sentence.", "Second one here.", "Third!"]);
+
+        // 相邻片段重叠完整的句子
+        let chunks = TextSplitter::new(12, 6).split("One. Two. Three.");
+        assert_eq!(chunks, ["One. Two.", "Two. Three."]);
+
+        // 超长的句子在句中截断
+        let chunks = TextSplitter::new(4, 0).split("abcdefghij");
+        assert_eq!(chunks, ["abcd", "efgh", "ij"]);
+        assert!(splitter.split("  \n ").is_empty());
+    }
+
+    #[test]
+    fn test_vector_index() {
+        let mut index = VectorIndex::new();
+        for (i, vector) in [[1.0, 0.0], [0.0, 1.0], [0.6, 0.8]].into_iter().enumerate() {
+            index.add(
+                vector.to_vec(),
+                Chunk {
+                    position: i,
+                    ..Chunk::default()
+                },
+            );
+        }
+        let found: Vec<usize> = index
+            .search(&[0.0, 1.0], 2)
+            .into_iter()
+            .map(|(_, chunk)| chunk.position)
+            .collect();
+        assert_eq!(found, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_ask_cites_sources() {
+        let embeddings = |vectors: &[[f32; 2]]| {
+            let data: Vec<_> = vectors
+                .iter()
+                .enumerate()
+                .map(|(index, v)| serde_json::json!({"index": index, "embedding": v}))
+                .collect();
+            MockResponse::json(serde_json::json!({ "data": data }))
+        };
+        let server = MockServer::start(vec![
+            embeddings(&[[1.0, 0.0], [0.0, 1.0]]),
+            embeddings(&[[0.1, 1.0]]),
+            MockResponse::json(completion_body("Five days [1].")),
+        ])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let mut pipeline = RagPipeline::new(client).with_top_k(2);
+        let documents = [
+            Document::new("shipping.md", "Orders ship in two days."),
+            Document::new("refunds.md", "Refunds take five days."),
+        ];
+        assert_eq!(pipeline.index(&documents).await.unwrap(), 2);
+
+        let answer = pipeline.ask("How long do refunds take?").await.unwrap();
+        assert_eq!(answer.answer, "Five days [1].");
+        assert_eq!(answer.sources.len(), 2);
+        assert_eq!(answer.sources[0].chunk.document_id, "refunds.md");
+        let cited: Vec<usize> = answer.cited().map(|source| source.number).collect();
+        assert_eq!(cited, [1]);
+
+        let requests = server.requests();
+        let prompt: serde_json::Value = serde_json::from_str(&requests[2].body).unwrap();
+        let content = prompt["messages"].as_array().unwrap().last().unwrap()["content"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(content.contains("[1] (refunds.md)\nRefunds take five days."));
+        assert!(content.ends_with("Question: How long do refunds take?"));
+    }
+}
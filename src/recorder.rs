@@ -0,0 +1,156 @@
+//! 飞行记录器模块
+//!
+//! 通过 `Config::with_flight_recorder` 启用后，客户端在环形缓冲区中保留最近若干次
+//! 请求与响应，便于排查线上的提示词问题。记录只包含请求体和响应体，不包含认证标头。
+//! 流式请求不记录。
+use crate::client::LLMClient;
+use crate::error::NanoError;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// 一次请求与响应的记录
+#[derive(Debug, Clone, Serialize)]
+pub struct Exchange {
+    /// 请求开始的时间
+    pub timestamp: SystemTime,
+    /// 请求的端点
+    pub endpoint: String,
+    /// 请求体
+    pub request: Value,
+    /// 响应体，非 JSON 响应记录为字符串，未收到响应时为空
+    pub response: Option<Value>,
+    /// 请求失败时的错误信息
+    pub error: Option<String>,
+    /// 从发送到读完响应体的耗时，包含重试
+    pub duration: Duration,
+}
+
+/// 保留最近 `capacity` 条记录的环形缓冲区，由同一客户端的所有句柄共享
+#[derive(Debug)]
+pub(crate) struct FlightRecorder {
+    capacity: usize,
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl FlightRecorder {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            exchanges: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Exchange>> {
+        self.exchanges.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, exchange: Exchange) {
+        let mut exchanges = self.lock();
+        if exchanges.len() == self.capacity {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(exchange);
+    }
+}
+
+/// 将响应体解析为 JSON，失败时保留原始文本
+fn body_value(body: &[u8]) -> Value {
+    serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+}
+
+impl LLMClient {
+    /// 最近的请求与响应，按时间从旧到新排列
+    ///
+    /// 未通过 `Config::with_flight_recorder` 启用时返回空列表。
+    pub fn recent_exchanges(&self) -> Vec<Exchange> {
+        self.recorder
+            .as_ref()
+            .map(|recorder| recorder.lock().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 清空飞行记录器
+    pub fn clear_exchanges(&self) {
+        if let Some(recorder) = &self.recorder {
+            recorder.lock().clear();
+        }
+    }
+
+    /// 记录一次请求，`outcome` 为响应体或请求失败的错误
+    pub(crate) fn record_exchange(
+        &self,
+        endpoint: &str,
+        request: &Value,
+        started: Instant,
+        outcome: Result<&[u8], &NanoError>,
+    ) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+        let (response, error) = match outcome {
+            Ok(body) => (Some(body_value(body)), None),
+            Err(e) => {
+                let body = match e {
+                    NanoError::Api { raw_body, .. } => Some(body_value(raw_body.as_bytes())),
+                    _ => None,
+                };
+                (body, Some(e.to_string()))
+            }
+        };
+        recorder.push(Exchange {
+            timestamp: SystemTime::now() - started.elapsed(),
+            endpoint: endpoint.to_string(),
+            request: request.clone(),
+            response,
+            error,
+            duration: started.elapsed(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_keeps_last_exchanges() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("one")),
+            MockResponse::json(completion_body("two")),
+            MockResponse::new(400, "bad request"),
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_flight_recorder(2),
+        );
+        client.generate("first").await.unwrap();
+        client.generate("second").await.unwrap();
+        client.generate("third").await.unwrap_err();
+
+        let exchanges = client.recent_exchanges();
+        assert_eq!(exchanges.len(), 2);
+        let messages = exchanges[0].request["messages"].as_array().unwrap();
+        assert_eq!(messages.last().unwrap()["content"], "second");
+        assert_eq!(
+            exchanges[0].response.as_ref().unwrap()["choices"][0]["message"]["content"],
+            "two"
+        );
+        assert_eq!(exchanges[1].response, Some(Value::from("bad request")));
+        assert!(exchanges[1].error.is_some());
+        assert!(exchanges[1].endpoint.ends_with("/chat/completions"));
+
+        client.clear_exchanges();
+        assert!(client.recent_exchanges().is_empty());
+        assert!(LLMClient::new(Config::default())
+            .recent_exchanges()
+            .is_empty());
+    }
+}
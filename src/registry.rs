@@ -0,0 +1,173 @@
+//! 客户端注册表模块
+//!
+//! 应用通常同时使用多个模型：响应快的小模型、能力强的大模型、专门的嵌入模型等。
+//! [`ClientRegistry`] 按用途为它们命名（如 `fast`、`smart`、`embedder`），
+//! 并通过 [`ClientRegistry::route`] 按任务类型选择客户端，把模型选择策略集中在一处。
+use crate::{
+    client::LLMClient,
+    config::Config,
+    error::{NanoError, Result},
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+type Router = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// 按用途命名的客户端集合
+///
+/// ```rust
+/// use nanoai::config::Config;
+/// use nanoai::registry::ClientRegistry;
+///
+/// let registry = ClientRegistry::new()
+///     .with_config("fast", Config::default().with_model("gpt-4o-mini"))
+///     .with_config("smart", Config::default().with_model("o3"))
+///     .with_route("reasoning", "smart")
+///     .with_default("fast");
+///
+/// assert!(registry.route("reasoning").is_ok());
+/// assert!(registry.route("chitchat").is_ok());
+/// ```
+#[derive(Clone, Default)]
+pub struct ClientRegistry {
+    clients: BTreeMap<String, LLMClient>,
+    routes: HashMap<String, String>,
+    router: Option<Router>,
+    default: Option<String>,
+}
+
+impl fmt::Debug for ClientRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientRegistry")
+            .field("clients", &self.clients.keys().collect::<Vec<_>>())
+            .field("routes", &self.routes)
+            .field("router", &self.router.is_some())
+            .field("default", &self.default)
+            .finish()
+    }
+}
+
+impl ClientRegistry {
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册客户端，同名客户端会被替换
+    pub fn with_client(mut self, name: impl Into<String>, client: LLMClient) -> Self {
+        self.register(name, client);
+        self
+    }
+
+    /// 按配置创建并注册客户端
+    pub fn with_config(self, name: impl Into<String>, config: Config) -> Self {
+        self.with_client(name, LLMClient::new(config))
+    }
+
+    /// 将任务类型映射到客户端
+    pub fn with_route(mut self, task_kind: impl Into<String>, client: impl Into<String>) -> Self {
+        self.routes.insert(task_kind.into(), client.into());
+        self
+    }
+
+    /// 设置路由回调，返回任务类型应使用的客户端名称
+    ///
+    /// 回调优先于 [`Self::with_route`] 的映射，返回 `None` 时继续按映射和默认客户端选择。
+    pub fn with_router(
+        mut self,
+        router: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.router = Some(Arc::new(router));
+        self
+    }
+
+    /// 设置没有匹配路由时使用的客户端
+    pub fn with_default(mut self, client: impl Into<String>) -> Self {
+        self.default = Some(client.into());
+        self
+    }
+
+    /// 注册客户端，同名客户端会被替换
+    pub fn register(&mut self, name: impl Into<String>, client: LLMClient) {
+        self.clients.insert(name.into(), client);
+    }
+
+    /// 已注册的客户端名称，按字母顺序排列
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    /// 按名称获取客户端
+    pub fn get(&self, name: &str) -> Option<&LLMClient> {
+        self.clients.get(name)
+    }
+
+    /// 按名称获取客户端，不存在时返回 [`NanoError::Config`]
+    pub fn client(&self, name: &str) -> Result<&LLMClient> {
+        self.get(name)
+            .ok_or_else(|| NanoError::Config(format!("Unknown client '{}'", name)))
+    }
+
+    /// 按任务类型选择客户端
+    ///
+    /// 依次尝试路由回调、任务类型映射和默认客户端，都没有结果时返回 [`NanoError::Config`]。
+    pub fn route(&self, task_kind: &str) -> Result<&LLMClient> {
+        let name = self
+            .router
+            .as_ref()
+            .and_then(|router| router(task_kind))
+            .or_else(|| self.routes.get(task_kind).cloned())
+            .or_else(|| self.default.clone())
+            .ok_or_else(|| {
+                NanoError::Config(format!("No client routed for task '{}'", task_kind))
+            })?;
+        self.client(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ClientRegistry {
+        ClientRegistry::new()
+            .with_config("fast", Config::default().with_model("fast-model"))
+            .with_config("smart", Config::default().with_model("smart-model"))
+            .with_config("embedder", Config::default().with_model("embed-model"))
+            .with_route("reasoning", "smart")
+            .with_route("embed", "embedder")
+    }
+
+    fn model(client: Result<&LLMClient>) -> String {
+        client.unwrap().config.model.clone()
+    }
+
+    #[test]
+    fn test_route() {
+        let registry = registry();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["embedder", "fast", "smart"]
+        );
+        assert_eq!(model(registry.route("reasoning")), "smart-model");
+        assert_eq!(model(registry.route("embed")), "embed-model");
+        assert!(registry.route("chat").is_err());
+
+        let registry = registry
+            .with_default("fast")
+            .with_route("broken", "missing");
+        assert_eq!(model(registry.route("chat")), "fast-model");
+        assert!(registry.route("broken").is_err());
+    }
+
+    #[test]
+    fn test_router_hook() {
+        let registry = registry()
+            .with_default("fast")
+            .with_router(|task| task.starts_with("code").then(|| "smart".to_string()));
+        assert_eq!(model(registry.route("code-review")), "smart-model");
+        assert_eq!(model(registry.route("embed")), "embed-model");
+        assert_eq!(model(registry.route("chat")), "fast-model");
+    }
+}
@@ -0,0 +1,411 @@
+//! 终端渲染模块
+//!
+//! 将流式返回的 Markdown 文本增量渲染为带 ANSI 样式的终端输出，
+//! 命令行聊天程序无需各自实现粗体、代码块、列表等格式的处理。
+use crate::error::Result;
+use futures::{Stream, StreamExt};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const ITALIC: &str = "3";
+const UNDERLINE: &str = "4";
+const STRIKE: &str = "9";
+const CODE: &str = "36";
+const HEADING: &str = "35";
+
+/// 行首可能构成块级标记的字符
+const MARKER_CHARS: &str = "#>-*+_`.0123456789";
+
+/// 流式 Markdown 渲染器
+///
+/// 逐个接收内容片段，立即输出已能确定样式的部分。行首的块级标记（标题、列表、
+/// 引用、代码围栏）和行内可能成对的标记（`**`、`~~`）在确定含义前会被暂存，
+/// 其余文本不必等到整行到达即可输出。
+///
+/// 支持的格式：
+///
+/// - `#` 标题、`-`/`*`/`+` 无序列表、`1.` 有序列表、`>` 引用和分隔线
+/// - ```` ``` ```` 代码块（首行的语言名作为标签显示）和 `` ` `` 行内代码
+/// - `**粗体**`、`*斜体*`、`~~删除线~~` 和 `\` 转义
+///
+/// 为避免误伤 `snake_case` 之类的标识符，`_` 不作为强调标记。
+///
+/// ```rust
+/// use nanoai::render::MarkdownStream;
+///
+/// let mut renderer = MarkdownStream::new().with_ansi(false);
+/// let mut out = renderer.push("# Title\n- **it");
+/// out += &renderer.push("em**\n");
+/// out += &renderer.finish();
+/// assert_eq!(out, "Title\n  • item\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct MarkdownStream {
+    ansi: bool,
+    pending: String,
+    line_start: bool,
+    code_block: bool,
+    heading: bool,
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+}
+
+impl Default for MarkdownStream {
+    fn default() -> Self {
+        Self {
+            ansi: true,
+            pending: String::new(),
+            line_start: true,
+            code_block: false,
+            heading: false,
+            bold: false,
+            italic: false,
+            strike: false,
+            code: false,
+        }
+    }
+}
+
+impl MarkdownStream {
+    /// 创建输出 ANSI 样式的渲染器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 是否输出 ANSI 转义序列，关闭后只去除 Markdown 标记（适用于非终端输出）
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// 追加一个内容片段，返回可以立即输出的部分
+    pub fn push(&mut self, delta: &str) -> String {
+        self.pending.push_str(delta);
+        self.drain(false)
+    }
+
+    /// 输出暂存的剩余内容，并关闭所有未闭合的样式
+    pub fn finish(&mut self) -> String {
+        let mut out = self.drain(true);
+        let styled = self.code_block || self.heading || self.bold || self.italic;
+        if self.ansi && (styled || self.strike || self.code) {
+            out.push_str(RESET);
+        }
+        *self = Self::new().with_ansi(self.ansi);
+        out
+    }
+
+    /// 渲染文本片段流，错误原样传递
+    pub fn render<S>(mut self, stream: S) -> impl Stream<Item = Result<String>>
+    where
+        S: Stream<Item = Result<String>>,
+    {
+        async_stream::stream! {
+            futures::pin_mut!(stream);
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(text) => {
+                        let out = self.push(&text);
+                        if !out.is_empty() {
+                            yield Ok(out);
+                        }
+                    }
+                    Err(e) => yield Err(e),
+                }
+            }
+            let out = self.finish();
+            if !out.is_empty() {
+                yield Ok(out);
+            }
+        }
+    }
+
+    /// 处理暂存的文本，`last` 为真时不再等待后续片段
+    fn drain(&mut self, last: bool) -> String {
+        let mut out = String::new();
+        let mut pos = 0;
+        while pos < self.pending.len() {
+            let consumed = if self.line_start {
+                self.block(pos, last, &mut out)
+            } else if self.code_block {
+                self.code_line(pos, &mut out)
+            } else {
+                self.inline(pos, last, &mut out)
+            };
+            match consumed {
+                Some(len) => pos += len,
+                None => break,
+            }
+        }
+        self.pending.drain(..pos);
+        out
+    }
+
+    /// 识别行首的块级标记，返回消耗的字节数，需要等待更多内容时返回 `None`
+    fn block(&mut self, pos: usize, last: bool, out: &mut String) -> Option<usize> {
+        let rest = &self.pending[pos..];
+        let line_end = rest.find('\n');
+        let line = &rest[..line_end.unwrap_or(rest.len())];
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        let marker_len = trimmed
+            .find(|c| !MARKER_CHARS.contains(c))
+            .unwrap_or(trimmed.len());
+        let marker = &trimmed[..marker_len];
+        // 标记之后还没有其他字符时无法确定含义
+        if line_end.is_none() && !last && marker_len == trimmed.len() {
+            return None;
+        }
+        let whole_line = line_end.map_or(rest.len(), |end| end + 1);
+
+        if marker.starts_with("```") {
+            if line_end.is_none() && !last {
+                return None;
+            }
+            if self.code_block {
+                self.code_block = false;
+            } else {
+                let lang = trimmed.trim_start_matches('`').trim();
+                if !lang.is_empty() {
+                    out.push_str(&self.paint(&[DIM], lang));
+                    out.push('\n');
+                }
+                self.code_block = true;
+            }
+            return Some(whole_line);
+        }
+        if self.code_block {
+            self.line_start = false;
+            out.push_str("    ");
+            return Some(0);
+        }
+        if is_rule(trimmed) {
+            if line_end.is_none() && !last {
+                return None;
+            }
+            out.push_str(&self.paint(&[DIM], &"─".repeat(40)));
+            out.push('\n');
+            return Some(whole_line);
+        }
+
+        self.line_start = false;
+        let after = &trimmed[marker_len..];
+        let spaced = after.starts_with(' ');
+        let consumed = line.len() - after.len();
+        if !marker.is_empty() && marker.len() <= 6 && marker.bytes().all(|b| b == b'#') && spaced {
+            self.heading = true;
+            out.push_str(&self.style());
+            return Some(consumed + 1);
+        }
+        if matches!(marker, "-" | "*" | "+") && spaced {
+            out.push_str(indent);
+            out.push_str("  • ");
+            return Some(consumed + 1);
+        }
+        let ordered = marker
+            .strip_suffix('.')
+            .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if ordered.is_some() && spaced {
+            out.push_str(indent);
+            out.push_str("  ");
+            out.push_str(marker);
+            out.push(' ');
+            return Some(consumed + 1);
+        }
+        if let Some(quoted) = trimmed.strip_prefix('>') {
+            out.push_str(indent);
+            out.push_str(&self.paint(&[DIM], "│ "));
+            let quoted = quoted.strip_prefix(' ').unwrap_or(quoted);
+            return Some(line.len() - quoted.len());
+        }
+        Some(0)
+    }
+
+    /// 输出代码块中的一行（或其中已到达的部分）
+    fn code_line(&mut self, pos: usize, out: &mut String) -> Option<usize> {
+        let rest = &self.pending[pos..];
+        let end = rest.find('\n').map_or(rest.len(), |end| end + 1);
+        let text = &rest[..end];
+        let (body, newline) = match text.strip_suffix('\n') {
+            Some(body) => (body, true),
+            None => (text, false),
+        };
+        let painted = self.paint(&[CODE], body);
+        out.push_str(&painted);
+        if newline {
+            out.push('\n');
+            self.line_start = true;
+        }
+        Some(end)
+    }
+
+    /// 处理行内文本，返回消耗的字节数，需要等待更多内容时返回 `None`
+    fn inline(&mut self, pos: usize, last: bool, out: &mut String) -> Option<usize> {
+        let rest = &self.pending[pos..];
+        let mut chars = rest.chars();
+        let c = chars.next()?;
+        let next = chars.next();
+        if next.is_none() && !last && matches!(c, '*' | '~' | '\\') && !self.code {
+            return None;
+        }
+        match c {
+            '\n' => {
+                if self.heading {
+                    self.heading = false;
+                    out.push_str(&self.style());
+                }
+                out.push('\n');
+                self.line_start = true;
+            }
+            '`' => {
+                self.code = !self.code;
+                out.push_str(&self.style());
+            }
+            _ if self.code => out.push(c),
+            '\\' => match next {
+                Some(escaped) if escaped.is_ascii_punctuation() => {
+                    out.push(escaped);
+                    return Some(2);
+                }
+                _ => out.push(c),
+            },
+            '*' if next == Some('*') => {
+                self.bold = !self.bold;
+                out.push_str(&self.style());
+                return Some(2);
+            }
+            '*' if self.italic || next.is_some_and(|n| !n.is_whitespace()) => {
+                self.italic = !self.italic;
+                out.push_str(&self.style());
+            }
+            '~' if next == Some('~') => {
+                self.strike = !self.strike;
+                out.push_str(&self.style());
+                return Some(2);
+            }
+            _ => out.push(c),
+        }
+        Some(c.len_utf8())
+    }
+
+    /// 当前行内状态对应的完整样式序列
+    fn style(&self) -> String {
+        let mut codes = Vec::new();
+        if self.heading {
+            codes.extend([BOLD, UNDERLINE, HEADING]);
+        }
+        if self.bold {
+            codes.push(BOLD);
+        }
+        if self.italic {
+            codes.push(ITALIC);
+        }
+        if self.strike {
+            codes.push(STRIKE);
+        }
+        if self.code {
+            codes.push(CODE);
+        }
+        self.style_with(&codes)
+    }
+
+    fn style_with(&self, codes: &[&str]) -> String {
+        match (self.ansi, codes.is_empty()) {
+            (false, _) => String::new(),
+            (true, true) => RESET.to_string(),
+            (true, false) => format!("{}\x1b[{}m", RESET, codes.join(";")),
+        }
+    }
+
+    /// 以指定样式输出一段文本，之后恢复当前行内样式
+    fn paint(&self, codes: &[&str], text: &str) -> String {
+        if !self.ansi || text.is_empty() {
+            return text.to_string();
+        }
+        format!("{}{}{}", self.style_with(codes), text, self.style())
+    }
+}
+
+/// 是否为分隔线（`---`、`***` 或 `___`，可以夹杂空格）
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&marker| compact.chars().all(|c| c == marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    /// 逐字符输入，确保结果与片段边界无关
+    fn render_chars(text: &str, ansi: bool) -> String {
+        let mut renderer = MarkdownStream::new().with_ansi(ansi);
+        let mut out: String = text
+            .chars()
+            .map(|c| renderer.push(&c.to_string()))
+            .collect();
+        out += &renderer.finish();
+        out
+    }
+
+    #[test]
+    fn test_plain_rendering() {
+        let text = "# Title\nSome **bold** and *italic* with `code_x` and snake_case.\n\
+                    - one\n  * nested\n3. third\n> quoted\n---\n```rust\nlet a = *b;\n```\ndone";
+        let expected = "Title\nSome bold and italic with code_x and snake_case.\n\
+                        \x20 • one\n    • nested\n  3. third\n│ quoted\n"
+            .to_string()
+            + &"─".repeat(40)
+            + "\nrust\n    let a = *b;\ndone";
+        assert_eq!(render_chars(text, false), expected);
+
+        let mut renderer = MarkdownStream::new().with_ansi(false);
+        assert_eq!(
+            renderer.push("2 * 3 = 6, \\*not italic\\* *"),
+            "2 * 3 = 6, *not italic* "
+        );
+        assert_eq!(renderer.finish(), "*");
+    }
+
+    #[test]
+    fn test_ansi_rendering() {
+        let out = render_chars("a **b** `c`", true);
+        assert_eq!(out, "a \x1b[0m\x1b[1mb\x1b[0m \x1b[0m\x1b[36mc\x1b[0m");
+
+        // 未闭合的样式在结束时重置
+        let out = render_chars("## Head\n**open", true);
+        assert!(out.starts_with("\x1b[0m\x1b[1;4;35mHead\x1b[0m\n"));
+        assert!(out.ends_with("open\x1b[0m"));
+    }
+
+    #[test]
+    fn test_incremental_output() {
+        let mut renderer = MarkdownStream::new().with_ansi(false);
+        // 普通文本不等待换行
+        assert_eq!(renderer.push("Hello wor"), "Hello wor");
+        // 可能是 `**` 的一部分时暂存
+        assert_eq!(renderer.push("ld *"), "ld ");
+        assert_eq!(renderer.push("*x**\n"), "x\n");
+        // 行首标记在确定含义前暂存
+        assert_eq!(renderer.push("-"), "");
+        assert_eq!(renderer.push(" item"), "  • item");
+    }
+
+    #[tokio::test]
+    async fn test_render_stream() {
+        let chunks = vec![Ok("**a".to_string()), Ok("**\n- b".to_string())];
+        let out: Vec<String> = MarkdownStream::new()
+            .with_ansi(false)
+            .render(stream::iter(chunks))
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(out.concat(), "a\n  • b");
+    }
+}
@@ -0,0 +1,192 @@
+//! 用量报告模块
+//!
+//! 按模型汇总请求数、token、费用和错误率，可导出为 CSV 或 JSON，用于简单的月度花费核算。
+//! 费用按 `Budget::pricing` 计算，未配置单价时为 0。
+use crate::budget::Pricing;
+use crate::client::LLMClient;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard};
+
+/// 单个模型的用量汇总
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ModelUsage {
+    /// 请求次数，包含失败的请求
+    pub requests: u64,
+    /// 失败的请求数
+    pub errors: u64,
+    /// 累计输入 token
+    pub prompt_tokens: u64,
+    /// 累计输出 token
+    pub completion_tokens: u64,
+    /// 累计费用（美元）
+    pub cost_usd: f64,
+}
+
+impl ModelUsage {
+    /// 累计 token 总数
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// 失败请求的比例，没有请求时为 0
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+/// 按模型汇总的用量报告
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// 各模型的用量，按模型名称排序
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl UsageReport {
+    /// 所有模型的合计
+    pub fn total(&self) -> ModelUsage {
+        self.models
+            .values()
+            .fold(ModelUsage::default(), |acc, m| ModelUsage {
+                requests: acc.requests + m.requests,
+                errors: acc.errors + m.errors,
+                prompt_tokens: acc.prompt_tokens + m.prompt_tokens,
+                completion_tokens: acc.completion_tokens + m.completion_tokens,
+                cost_usd: acc.cost_usd + m.cost_usd,
+            })
+    }
+
+    /// 导出为 CSV，每个模型一行
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "model,requests,errors,error_rate,prompt_tokens,completion_tokens,total_tokens,cost_usd\n",
+        );
+        for (model, usage) in &self.models {
+            let model = if model.contains([',', '"', '\n']) {
+                format!("\"{}\"", model.replace('"', "\"\""))
+            } else {
+                model.clone()
+            };
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.4},{},{},{},{:.6}",
+                model,
+                usage.requests,
+                usage.errors,
+                usage.error_rate(),
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens(),
+                usage.cost_usd
+            );
+        }
+        csv
+    }
+
+    /// 导出为 JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// 按模型累计用量，由同一客户端的所有句柄共享
+#[derive(Debug, Default)]
+pub(crate) struct ReportTracker {
+    models: Mutex<BTreeMap<String, ModelUsage>>,
+}
+
+impl ReportTracker {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, ModelUsage>> {
+        self.models.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录一次成功的请求
+    pub(crate) fn record(
+        &self,
+        model: &str,
+        prompt_tokens: u64,
+        completion_tokens: u64,
+        pricing: Option<&Pricing>,
+    ) {
+        let mut models = self.lock();
+        let usage = models.entry(model.to_string()).or_default();
+        usage.requests += 1;
+        usage.prompt_tokens += prompt_tokens;
+        usage.completion_tokens += completion_tokens;
+        if let Some(pricing) = pricing {
+            usage.cost_usd += pricing.cost(prompt_tokens, completion_tokens);
+        }
+    }
+
+    /// 记录一次失败的请求
+    pub(crate) fn record_error(&self, model: &str) {
+        let mut models = self.lock();
+        let usage = models.entry(model.to_string()).or_default();
+        usage.requests += 1;
+        usage.errors += 1;
+    }
+}
+
+impl LLMClient {
+    /// 客户端创建以来按模型汇总的用量报告
+    pub fn usage_report(&self) -> UsageReport {
+        UsageReport {
+            models: self.report.lock().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::Budget;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_usage_report() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("ok")),
+            MockResponse::new(400, "bad request"),
+            MockResponse::json(completion_body("ok")),
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_model("a")
+                .with_budget(Budget {
+                    pricing: Some(Pricing {
+                        prompt_per_million: 1_000_000.0,
+                        completion_per_million: 0.0,
+                    }),
+                    ..Budget::default()
+                }),
+        );
+        client.generate("hi").await.unwrap();
+        client.generate("hi").await.unwrap_err();
+        client.with_model("b,c").generate("hi").await.unwrap();
+
+        let report = client.usage_report();
+        let a = report.models["a"];
+        assert_eq!((a.requests, a.errors, a.total_tokens()), (2, 1, 5));
+        assert_eq!(a.error_rate(), 0.5);
+        assert_eq!(a.cost_usd, 3.0);
+        assert_eq!(report.total().requests, 3);
+
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "a,2,1,0.5000,3,2,5,3.000000");
+        assert!(lines[2].starts_with("\"b,c\",1,0,"));
+
+        let parsed: UsageReport = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
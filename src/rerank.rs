@@ -0,0 +1,98 @@
+//! 重排序模块
+//!
+//! 调用 Cohere / Jina 兼容的 `/rerank` 端点，按与查询的相关度为候选文档打分，
+//! 通常用于对向量检索召回的结果做精排。
+use crate::{client::LLMClient, error::Result};
+use serde::{Deserialize, Serialize};
+
+/// 单个文档的重排序结果
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+pub struct RerankResult {
+    /// 文档在输入中的下标
+    #[serde(default)]
+    pub index: usize,
+    /// 相关度分数，越大越相关
+    #[serde(default, alias = "score")]
+    pub relevance_score: f32,
+}
+
+/// `/rerank` 响应体
+#[derive(Debug, Deserialize, Default)]
+struct RerankList {
+    #[serde(default)]
+    results: Vec<RerankResult>,
+}
+
+impl LLMClient {
+    /// 按与 `query` 的相关度为文档打分，结果按分数从高到低排列
+    ///
+    /// 使用的模型由 `Config::with_rerank_model` 配置。
+    pub async fn rerank(&self, query: &str, documents: &[&str]) -> Result<Vec<RerankResult>> {
+        self.rerank_top_n(query, documents, documents.len()).await
+    }
+
+    /// 同 [`Self::rerank`]，只返回最相关的 `top_n` 个文档
+    pub async fn rerank_top_n(
+        &self,
+        query: &str,
+        documents: &[&str],
+        top_n: usize,
+    ) -> Result<Vec<RerankResult>> {
+        if documents.is_empty() || top_n == 0 {
+            return Ok(Vec::new());
+        }
+        let endpoint = format!("{}/rerank", self.config.api_base);
+        let headers = self.build_headers().await?;
+        let params = serde_json::json!({
+            "model": &self.config.rerank_model,
+            "query": query,
+            "documents": documents,
+            "top_n": top_n.min(documents.len()),
+        });
+        let request_builder = self.client.post(&endpoint).headers(headers).json(&params);
+        let response = self.call_api_with_retry(request_builder).await?;
+
+        let mut results = response.json::<RerankList>().await?.results;
+        results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        results.truncate(top_n);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_rerank() {
+        let body = serde_json::json!({
+            "results": [
+                {"index": 2, "relevance_score": 0.1},
+                {"index": 0, "relevance_score": 0.9, "document": {"text": "a"}},
+                {"index": 1, "score": 0.5},
+            ]
+        });
+        let server = MockServer::start(vec![MockResponse::json(body)]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_rerank_model("jina-reranker-v2"),
+        );
+
+        let results = client.rerank_top_n("q", &["a", "b", "c"], 2).await.unwrap();
+        let indices: Vec<usize> = results.iter().map(|r| r.index).collect();
+        assert_eq!(indices, [0, 1]);
+        assert_eq!(results[1].relevance_score, 0.5);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/rerank");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["model"], "jina-reranker-v2");
+        assert_eq!(body["documents"], serde_json::json!(["a", "b", "c"]));
+        assert_eq!(body["top_n"], 2);
+
+        assert!(client.rerank("q", &[]).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+//! OpenAI Responses API 模块
+//!
+//! 当 `Config` 选择 [`ApiBackend::Responses`] 时，客户端改为调用 `/responses` 端点，
+//! 使用输入项 (input items)、内置工具和按事件类型区分的流式事件。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+    options::RequestOptions,
+    stream::{decode_event, StreamHandle, StreamMetadata},
+    types::{
+        Citation, CompletionTokensDetails, FinishReason, Message, PromptTokensDetails,
+        RequestStats, ResponseWithStats,
+    },
+    utils::parse_response,
+};
+use futures::{future, stream::BoxStream, StreamExt};
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Instant;
+
+// ================================================================================================
+// 后端选择
+// ================================================================================================
+
+/// 生成请求使用的 API 后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiBackend {
+    /// `/chat/completions` 端点
+    #[default]
+    ChatCompletions,
+    /// `/responses` 端点
+    Responses,
+}
+
+/// Responses API 内置工具
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseTool {
+    /// 网页搜索
+    WebSearchPreview,
+    /// 在向量库中检索文件
+    FileSearch {
+        /// 向量库 ID 列表
+        vector_store_ids: Vec<String>,
+    },
+    /// 代码解释器
+    CodeInterpreter {
+        /// 运行容器配置
+        container: Value,
+    },
+}
+
+// ================================================================================================
+// 响应结构
+// ================================================================================================
+
+/// 输出项中的内容片段
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponseContent {
+    /// 片段类型，如 `output_text`、`refusal`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// 文本内容
+    #[serde(default)]
+    pub text: String,
+    /// 拒绝说明，仅 `refusal` 类型包含
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    /// 文本中的标注，`url_citation` 类型包含引用来源
+    #[serde(default)]
+    pub annotations: Vec<ResponseAnnotation>,
+}
+
+/// 输出文本中的标注
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponseAnnotation {
+    /// 标注类型，如 `url_citation`、`file_citation`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// 引用来源
+    #[serde(flatten)]
+    pub citation: Citation,
+}
+
+/// 输出项
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponseOutputItem {
+    /// 输出项类型，如 `message`、`web_search_call`、`function_call`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// 输出项 ID
+    #[serde(default)]
+    pub id: String,
+    /// 消息内容，仅 `message` 类型包含
+    #[serde(default)]
+    pub content: Vec<ResponseContent>,
+}
+
+/// Responses API token 使用情况
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponsesUsage {
+    /// 输入 token 数量
+    #[serde(default)]
+    pub input_tokens: u32,
+    /// 输出 token 数量
+    #[serde(default)]
+    pub output_tokens: u32,
+    /// 总 token 数量
+    #[serde(default)]
+    pub total_tokens: u32,
+    /// 输入 token 明细
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens_details: Option<PromptTokensDetails>,
+    /// 输出 token 明细
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens_details: Option<CompletionTokensDetails>,
+}
+
+/// Responses API 响应体
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponsesResponse {
+    /// 响应 ID
+    #[serde(default)]
+    pub id: String,
+    /// 使用模型
+    #[serde(default)]
+    pub model: String,
+    /// 响应状态
+    #[serde(default)]
+    pub status: String,
+    /// 输出项列表
+    #[serde(default)]
+    pub output: Vec<ResponseOutputItem>,
+    /// token 使用情况
+    #[serde(default)]
+    pub usage: ResponsesUsage,
+    /// 失败原因，仅 `status` 为 `failed` 时包含
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+    /// 未完成的原因，仅 `status` 为 `incomplete` 时包含
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incomplete_details: Option<IncompleteDetails>,
+}
+
+/// 响应失败的原因
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponseError {
+    /// 错误码，如 `server_error`
+    #[serde(default)]
+    pub code: Option<String>,
+    /// 错误信息
+    #[serde(default)]
+    pub message: String,
+}
+
+/// 响应未完成的原因
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct IncompleteDetails {
+    /// 原因，如 `max_output_tokens`、`content_filter`
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl ResponsesResponse {
+    /// 拼接所有 `message` 输出项中的文本
+    pub fn output_text(&self) -> String {
+        self.output
+            .iter()
+            .filter(|item| item.kind == "message")
+            .flat_map(|item| &item.content)
+            .filter(|c| c.kind == "output_text")
+            .map(|c| c.text.as_str())
+            .collect()
+    }
+
+    /// 模型的拒绝说明
+    pub fn refusal(&self) -> Option<String> {
+        self.output
+            .iter()
+            .flat_map(|item| &item.content)
+            .find(|c| c.kind == "refusal")
+            .map(|c| c.refusal.clone().unwrap_or_default())
+    }
+
+    /// 失败或被内容过滤时对应的错误
+    ///
+    /// `failed` 转换为 [`NanoError::Api`]，因 `content_filter` 未完成转换为
+    /// [`NanoError::ContentFiltered`]；因 `max_output_tokens` 未完成与 Chat Completions 的
+    /// `length` 一样不视为错误。
+    pub(crate) fn error(&self) -> Option<NanoError> {
+        match self.status.as_str() {
+            "failed" => {
+                let error = self.error.clone().unwrap_or_default();
+                Some(NanoError::Api {
+                    status: 200,
+                    code: error.code,
+                    message: Some(error.message)
+                        .filter(|m| !m.is_empty())
+                        .unwrap_or_else(|| "Response failed".to_string()),
+                    request_id: Some(self.id.clone()).filter(|id| !id.is_empty()),
+                    raw_body: serde_json::to_string(self).unwrap_or_default(),
+                })
+            }
+            "incomplete" => self
+                .incomplete_details
+                .as_ref()
+                .filter(|d| d.reason == "content_filter")
+                .map(|_| NanoError::ContentFiltered {
+                    categories: Vec::new(),
+                    refusal: None,
+                }),
+            _ => None,
+        }
+    }
+
+    /// 将响应 ID、模型和状态写入流元数据
+    pub(crate) fn record(&self, meta: &mut StreamMetadata) {
+        if !self.id.is_empty() {
+            meta.id = Some(self.id.clone());
+        }
+        if !self.model.is_empty() {
+            meta.model = Some(self.model.clone());
+        }
+        meta.finish_reason = match self.status.as_str() {
+            "completed" => Some(FinishReason::Stop),
+            "incomplete" => Some(FinishReason::Length),
+            "" | "in_progress" | "queued" => None,
+            other => Some(FinishReason::Other(other.to_string())),
+        };
+    }
+
+    /// 所有输出文本中的网页引用
+    pub fn citations(&self) -> Vec<Citation> {
+        self.output
+            .iter()
+            .filter(|item| item.kind == "message")
+            .flat_map(|item| &item.content)
+            .flat_map(|c| &c.annotations)
+            .filter(|a| a.kind == "url_citation")
+            .map(|a| a.citation.clone())
+            .collect()
+    }
+}
+
+/// Responses API 流式事件
+///
+/// 每个 SSE 事件都带有 `type` 字段，例如 `response.output_text.delta`、
+/// `response.completed`。
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ResponseStreamEvent {
+    /// 事件类型
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// 文本增量，仅 `response.output_text.delta` 事件包含
+    pub delta: Option<String>,
+    /// 完整响应，仅 `response.created`/`response.completed` 等事件包含
+    pub response: Option<ResponsesResponse>,
+}
+
+// ================================================================================================
+// 请求实现
+// ================================================================================================
+
+impl LLMClient {
+    /// 构建 `/responses` 请求体
+    ///
+    /// Responses API 不支持 `seed` 和停止序列，`RequestOptions` 中仅采样参数生效。
+    fn responses_params(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+        stream: bool,
+    ) -> Value {
+        let input: Vec<Value> = messages
+            .iter()
+            .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+            .collect();
+
+        let mut params = serde_json::json!({
+            "model": &self.config.model,
+            "input": input,
+            "temperature": options.temperature.unwrap_or(self.config.temperature),
+            "top_p": options.top_p.unwrap_or(self.config.top_p),
+            "max_output_tokens": options.max_tokens.unwrap_or(self.config.max_tokens),
+            "stream": stream,
+        });
+        if !system_message.is_empty() {
+            params["instructions"] = Value::from(system_message);
+        }
+        if !self.config.response_tools.is_empty() {
+            params["tools"] = serde_json::to_value(&self.config.response_tools).unwrap_or_default();
+        }
+        if let Some(choice) = &options.tool_choice {
+            params["tool_choice"] = choice.responses_value();
+        }
+        if let Some(parallel) = options.parallel_tool_calls {
+            params["parallel_tool_calls"] = Value::from(parallel);
+        }
+        if let Some(search) = &options.web_search {
+            search.apply(&mut params);
+        }
+        if let Some(key) = &options.prompt_cache_key {
+            params["prompt_cache_key"] = Value::from(key.as_str());
+        }
+        if let Some(user) = &options.user {
+            params["user"] = Value::from(user.as_str());
+        }
+        params
+    }
+
+    /// 调用 `/responses` 并返回带统计信息的完整响应
+    pub(crate) async fn call_responses_with_stats(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
+        let endpoint = format!("{}/responses", self.config.api_base);
+        let headers = self.request_headers(options).await?;
+        let params = self.responses_params(system_message, messages, options, false);
+        let request_builder = self.client.post(&endpoint).headers(headers).json(&params);
+
+        let started = Instant::now();
+        let (response, attempts) = match self.call_api_with_attempts(request_builder).await {
+            Ok(result) => result,
+            Err(e) => {
+                self.record_exchange(&endpoint, &params, started, Err(&e));
+                return Err(e);
+            }
+        };
+        let mut stats = RequestStats::default();
+        attempts.record(&mut stats, &response);
+        let bytes = response.bytes().await?;
+        self.record_exchange(&endpoint, &params, started, Ok(&bytes));
+        let body =
+            parse_response::<ResponsesResponse>(&bytes, self.config.strict_parsing, &["/output"])?;
+        if let Some(error) = body.error() {
+            return Err(error);
+        }
+        if let Some(refusal) = body.refusal() {
+            return Err(NanoError::ContentFiltered {
+                categories: Vec::new(),
+                refusal: Some(refusal),
+            });
+        }
+
+        stats.prompt_tokens = Some(body.usage.input_tokens);
+        stats.completion_tokens = Some(body.usage.output_tokens);
+        stats.total_tokens = Some(body.usage.total_tokens);
+        stats.cached_tokens = body.usage.input_tokens_details.map(|d| d.cached_tokens);
+        stats.reasoning_tokens = body.usage.output_tokens_details.map(|d| d.reasoning_tokens);
+        stats.model = self.config.model.clone();
+        stats.served_model = Some(body.model.clone()).filter(|m| !m.is_empty());
+        stats.timestamp = Some(std::time::SystemTime::now());
+        Ok(ResponseWithStats {
+            content: body.output_text(),
+            stats,
+            citations: body.citations(),
+        })
+    }
+
+    /// 调用 `/responses` 并返回文本增量流
+    pub(crate) async fn stream_responses(
+        &self,
+        system_message: &str,
+        messages: &[Message],
+        options: &RequestOptions,
+        handle: &StreamHandle,
+    ) -> Result<BoxStream<'static, Result<String>>> {
+        let endpoint = format!("{}/responses", self.config.api_base);
+        let mut headers = self.request_headers(options).await?;
+        headers.insert("Accept", HeaderValue::from_static("text/event-stream"));
+        let params = self.responses_params(system_message, messages, options, true);
+        let request_builder = self.client.post(&endpoint).headers(headers).json(&params);
+
+        let (response, permit) = self.call_api_for_stream(request_builder).await?;
+        let handle = handle.clone();
+        let heartbeat = handle.clone();
+        let strict = self.config.strict_parsing;
+        let events = self
+            .stream_handler
+            .stream_with(response.bytes_stream(), move |event| {
+                if let Some(comment) = &event.comment {
+                    heartbeat.record_heartbeat(comment.clone());
+                }
+                decode_event(event, strict)
+            });
+        Ok(events
+            .filter_map(move |res: Result<ResponseStreamEvent>| {
+                let _permit = &permit;
+                if let Ok(ResponseStreamEvent {
+                    response: Some(body),
+                    ..
+                }) = &res
+                {
+                    handle.update(|meta| body.record(meta));
+                }
+                future::ready(match res {
+                    Ok(event) if event.kind == "response.output_text.delta" => event.delta.map(Ok),
+                    // `response.failed` 和 `response.incomplete` 携带失败原因
+                    Ok(event) => event
+                        .response
+                        .as_ref()
+                        .and_then(ResponsesResponse::error)
+                        .map(Err),
+                    Err(e) => Some(Err(e)),
+                })
+            })
+            .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{MockResponse, MockServer};
+
+    fn sse(events: &[Value]) -> MockResponse {
+        let body: String = events
+            .iter()
+            .map(|e| format!("event: {}\ndata: {}\n\n", e["type"].as_str().unwrap(), e))
+            .collect();
+        MockResponse::new(200, &body).with_header("Content-Type", "text/event-stream")
+    }
+
+    fn delta(text: &str) -> Value {
+        serde_json::json!({"type": "response.output_text.delta", "delta": text})
+    }
+
+    #[tokio::test]
+    async fn test_responses_backend() {
+        let completed = serde_json::json!({
+            "id": "resp_1", "model": "gpt-4o", "status": "completed",
+            "output": [{"type": "message", "content": [{"type": "output_text", "text": "Hi!"}]}],
+            "usage": {"input_tokens": 3, "output_tokens": 2, "total_tokens": 5}
+        });
+        let failed = serde_json::json!({
+            "type": "response.failed",
+            "response": {"id": "resp_2", "status": "failed",
+                         "error": {"code": "server_error", "message": "The model crashed"}}
+        });
+        let filtered = serde_json::json!({
+            "type": "response.incomplete",
+            "response": {"id": "resp_3", "status": "incomplete",
+                         "incomplete_details": {"reason": "content_filter"}}
+        });
+        let done = serde_json::json!({"type": "response.completed", "response": completed});
+        let server = MockServer::start(vec![
+            MockResponse::json(completed.clone()),
+            sse(&[delta("Hel"), delta("lo"), done]),
+            sse(&[delta("Par"), failed]),
+            sse(&[delta("Par"), filtered]),
+        ])
+        .await;
+        let config = Config::default()
+            .with_api_base(&server.base)
+            .with_api_backend(ApiBackend::Responses);
+        let client = LLMClient::new(config);
+
+        let response = client.generate_with_stats("hi").await.unwrap();
+        assert_eq!(response.content, "Hi!");
+        assert_eq!(response.stats.prompt_tokens, Some(3));
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/responses");
+        let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["instructions"], "You are a helpful AI assistant.");
+        assert_eq!(body["input"][0]["content"], "hi");
+
+        let (stream, handle) = client
+            .stream_generate_with_handle("hi", &RequestOptions::default())
+            .await
+            .unwrap();
+        let chunks: Vec<String> = stream.map(|c| c.unwrap()).collect().await;
+        assert_eq!(chunks.concat(), "Hello");
+        assert_eq!(handle.finish_reason(), Some(FinishReason::Stop));
+
+        let items: Vec<_> = client.stream_generate("hi").await.unwrap().collect().await;
+        assert_eq!(items[0].as_deref().ok(), Some("Par"));
+        match &items[1] {
+            Err(NanoError::Api { code, message, .. }) => {
+                assert_eq!(code.as_deref(), Some("server_error"));
+                assert_eq!(message, "The model crashed");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let items: Vec<_> = client.stream_generate("hi").await.unwrap().collect().await;
+        assert!(matches!(
+            items.last(),
+            Some(Err(NanoError::ContentFiltered { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_output_text_joins_message_items() {
+        let body: ResponsesResponse = serde_json::from_value(serde_json::json!({
+            "id": "resp_1",
+            "output": [
+                {"type": "web_search_call", "id": "ws_1"},
+                {"type": "message", "id": "msg_1", "content": [
+                    {"type": "output_text", "text": "Hello, "},
+                    {"type": "output_text", "text": "world", "annotations": [
+                        {"type": "url_citation", "url": "https://example.com", "title": "Example",
+                         "start_index": 7, "end_index": 12}
+                    ]}
+                ]}
+            ],
+            "usage": {"input_tokens": 3, "output_tokens": 2, "total_tokens": 5}
+        }))
+        .unwrap();
+        assert_eq!(body.output_text(), "Hello, world");
+        assert_eq!(body.usage.total_tokens, 5);
+        let citations = body.citations();
+        assert_eq!(citations[0].url, "https://example.com");
+        assert_eq!(citations[0].title.as_deref(), Some("Example"));
+    }
+
+    #[test]
+    fn test_response_tool_serialization() {
+        let tool = serde_json::to_value(ResponseTool::WebSearchPreview).unwrap();
+        assert_eq!(tool, serde_json::json!({"type": "web_search_preview"}));
+    }
+}
@@ -0,0 +1,463 @@
+//! 模型路由模块
+//!
+//! [`ModelRouter`] 为每个请求从候选模型中选择一个：先排除能力不足（工具调用、图像输入、
+//! JSON 输出）或上下文窗口放不下的模型，再由 [`RoutingPolicy`] 按预估费用和延迟挑选。
+//! 延迟优先使用客户端实际观测到的中位数，没有观测数据时使用 [`ModelProfile`] 中的预估值。
+//! 配置 [`ComplexityClassifier`] 后，还会按提示词的复杂度排除能力不够的模型。
+use crate::{
+    budget::Pricing,
+    client::LLMClient,
+    complexity::{Complexity, ComplexityClassifier},
+    error::{NanoError, Result},
+    tokenizer,
+    types::{Message, ResponseWithStats, Role},
+    utils::message,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+// ================================================================================================
+// 模型与请求描述
+// ================================================================================================
+
+/// 模型能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// 工具调用
+    Tools,
+    /// 图像输入
+    Vision,
+    /// JSON 输出模式
+    Json,
+}
+
+/// 候选模型的描述
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelProfile {
+    pub(crate) model: String,
+    pub(crate) pricing: Pricing,
+    pub(crate) context_window: Option<u32>,
+    pub(crate) capabilities: Vec<Capability>,
+    pub(crate) latency: Option<Duration>,
+    pub(crate) max_complexity: Complexity,
+}
+
+impl ModelProfile {
+    /// 描述名为 `model` 的模型，默认免费、没有额外能力、上下文窗口不限
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            pricing: Pricing::default(),
+            context_window: None,
+            capabilities: Vec::new(),
+            latency: None,
+            max_complexity: Complexity::Complex,
+        }
+    }
+
+    /// 设置单价（美元 / 百万 token）
+    pub fn with_pricing(mut self, prompt_per_million: f64, completion_per_million: f64) -> Self {
+        self.pricing = Pricing {
+            prompt_per_million,
+            completion_per_million,
+        };
+        self
+    }
+
+    /// 设置上下文窗口长度
+    pub fn with_context_window(mut self, tokens: u32) -> Self {
+        self.context_window = Some(tokens);
+        self
+    }
+
+    /// 添加模型支持的能力
+    pub fn with_capability(mut self, capability: Capability) -> Self {
+        self.capabilities.push(capability);
+        self
+    }
+
+    /// 设置预估延迟，客户端观测到该模型的请求后以观测值为准
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// 设置模型能胜任的最高复杂度，默认为 [`Complexity::Complex`]
+    ///
+    /// 例如免费模型设为 [`Complexity::Simple`]，只接收简单的请求。
+    pub fn with_max_complexity(mut self, complexity: Complexity) -> Self {
+        self.max_complexity = complexity;
+        self
+    }
+
+    /// 模型名称
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+/// 一次请求对模型的要求
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteRequest {
+    /// 提示词 token 数
+    pub prompt_tokens: u64,
+    /// 预计输出 token 数，用于估算费用和检查上下文窗口
+    pub output_tokens: u64,
+    /// 必须具备的能力
+    pub capabilities: Vec<Capability>,
+    /// 延迟目标
+    pub latency_target: Option<Duration>,
+    /// 提示词复杂度，未知时不按复杂度筛选
+    pub complexity: Option<Complexity>,
+}
+
+impl RouteRequest {
+    /// 按消息列表估算 token 数，预计输出 256 个 token
+    pub fn for_messages(messages: &[Message]) -> Self {
+        Self {
+            prompt_tokens: tokenizer::count_message_tokens("", messages) as u64,
+            output_tokens: 256,
+            capabilities: Vec::new(),
+            latency_target: None,
+            complexity: None,
+        }
+    }
+
+    /// 按单条提示词估算 token 数
+    pub fn for_prompt(prompt: &str) -> Self {
+        Self::for_messages(&[message(Role::User, prompt)])
+    }
+
+    /// 设置预计输出 token 数
+    pub fn with_output_tokens(mut self, tokens: u64) -> Self {
+        self.output_tokens = tokens;
+        self
+    }
+
+    /// 要求模型具备某项能力
+    pub fn with_capability(mut self, capability: Capability) -> Self {
+        self.capabilities.push(capability);
+        self
+    }
+
+    /// 设置延迟目标
+    pub fn with_latency_target(mut self, target: Duration) -> Self {
+        self.latency_target = Some(target);
+        self
+    }
+
+    /// 设置提示词复杂度
+    pub fn with_complexity(mut self, complexity: Complexity) -> Self {
+        self.complexity = Some(complexity);
+        self
+    }
+}
+
+// ================================================================================================
+// 路由策略
+// ================================================================================================
+
+/// 满足请求硬性要求的候选模型
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    /// 模型描述
+    pub profile: &'a ModelProfile,
+    /// 按单价预估的本次请求费用（美元）
+    pub estimated_cost: f64,
+    /// 观测到的延迟中位数，没有观测数据时为预估延迟
+    pub latency: Option<Duration>,
+}
+
+/// 路由策略
+pub trait RoutingPolicy: fmt::Debug + Send + Sync {
+    /// 从候选模型中选择一个，返回其在 `candidates` 中的下标；`candidates` 不为空
+    fn choose(&self, request: &RouteRequest, candidates: &[Candidate<'_>]) -> usize;
+}
+
+/// 选择满足延迟目标的最便宜模型
+///
+/// 没有设置延迟目标时选择最便宜的模型；延迟未知的模型视为满足目标。
+/// 没有模型满足目标时选择最快的模型。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheapestPolicy;
+
+impl RoutingPolicy for CheapestPolicy {
+    fn choose(&self, request: &RouteRequest, candidates: &[Candidate<'_>]) -> usize {
+        let meets_target = |c: &Candidate<'_>| match (request.latency_target, c.latency) {
+            (Some(target), Some(latency)) => latency <= target,
+            _ => true,
+        };
+        candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| meets_target(c))
+            .min_by(|(_, a), (_, b)| a.estimated_cost.total_cmp(&b.estimated_cost))
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| FastestPolicy.choose(request, candidates))
+    }
+}
+
+/// 选择延迟最低的模型，延迟未知的模型排在最后，延迟相同时选择更便宜的模型
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FastestPolicy;
+
+impl RoutingPolicy for FastestPolicy {
+    fn choose(&self, _request: &RouteRequest, candidates: &[Candidate<'_>]) -> usize {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                let latency = |c: &Candidate<'_>| c.latency.unwrap_or(Duration::MAX);
+                latency(a)
+                    .cmp(&latency(b))
+                    .then(a.estimated_cost.total_cmp(&b.estimated_cost))
+            })
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+// ================================================================================================
+// 路由器
+// ================================================================================================
+
+/// 按请求自动选择模型的路由器
+///
+/// ```rust
+/// use nanoai::client::LLMClient;
+/// use nanoai::config::Config;
+/// use nanoai::router::{Capability, ModelProfile, ModelRouter, RouteRequest};
+///
+/// let router = ModelRouter::new(LLMClient::new(Config::default()))
+///     .with_model(ModelProfile::new("small").with_pricing(0.1, 0.4).with_context_window(16_000))
+///     .with_model(
+///         ModelProfile::new("large")
+///             .with_pricing(2.5, 10.0)
+///             .with_capability(Capability::Vision),
+///     );
+///
+/// assert_eq!(router.select(&RouteRequest::for_prompt("hi")).unwrap().model(), "small");
+/// let vision = RouteRequest::for_prompt("describe").with_capability(Capability::Vision);
+/// assert_eq!(router.select(&vision).unwrap().model(), "large");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelRouter {
+    client: LLMClient,
+    profiles: Vec<ModelProfile>,
+    policy: Arc<dyn RoutingPolicy>,
+    classifier: Option<ComplexityClassifier>,
+}
+
+impl ModelRouter {
+    /// 创建使用 `client` 发送请求的路由器，默认策略为 [`CheapestPolicy`]
+    pub fn new(client: LLMClient) -> Self {
+        Self {
+            client,
+            profiles: Vec::new(),
+            policy: Arc::new(CheapestPolicy),
+            classifier: None,
+        }
+    }
+
+    /// 添加候选模型
+    pub fn with_model(mut self, profile: ModelProfile) -> Self {
+        self.profiles.push(profile);
+        self
+    }
+
+    /// 设置路由策略
+    pub fn with_policy(mut self, policy: impl RoutingPolicy + 'static) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// 设置复杂度分类器，[`Self::generate`] 用它判断提示词的复杂度
+    pub fn with_classifier(mut self, classifier: ComplexityClassifier) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// 为请求选择模型
+    ///
+    /// 没有模型满足能力和上下文窗口要求时返回 [`NanoError::InvalidRequest`]。
+    pub fn select(&self, request: &RouteRequest) -> Result<&ModelProfile> {
+        let candidates: Vec<Candidate<'_>> = self
+            .profiles
+            .iter()
+            .filter(|p| {
+                request
+                    .capabilities
+                    .iter()
+                    .all(|c| p.capabilities.contains(c))
+            })
+            .filter(|p| request.complexity.is_none_or(|c| c <= p.max_complexity))
+            .filter(|p| {
+                p.context_window.is_none_or(|window| {
+                    request.prompt_tokens + request.output_tokens <= window as u64
+                })
+            })
+            .map(|profile| Candidate {
+                profile,
+                estimated_cost: profile
+                    .pricing
+                    .cost(request.prompt_tokens, request.output_tokens),
+                latency: self
+                    .client
+                    .latency(&profile.model)
+                    .filter(|stats| stats.latency.count() > 0)
+                    .map(|stats| stats.latency.p50())
+                    .or(profile.latency),
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Err(NanoError::InvalidRequest(format!(
+                "No model satisfies the request ({} prompt tokens, capabilities {:?}, {:?})",
+                request.prompt_tokens, request.capabilities, request.complexity
+            )));
+        }
+        let index = self.policy.choose(request, &candidates);
+        candidates
+            .get(index)
+            .map(|c| c.profile)
+            .ok_or_else(|| NanoError::Config(format!("Routing policy chose candidate {}", index)))
+    }
+
+    /// 返回使用所选模型的客户端句柄
+    pub fn client_for(&self, request: &RouteRequest) -> Result<LLMClient> {
+        Ok(self.client.with_model(&self.select(request)?.model))
+    }
+
+    /// 按提示词长度和复杂度选择模型并生成响应
+    pub async fn generate(&self, prompt: &str) -> Result<ResponseWithStats> {
+        let mut request = RouteRequest::for_prompt(prompt);
+        if let Some(classifier) = &self.classifier {
+            request.complexity = Some(classifier.classify_with_model(prompt).await?);
+        }
+        self.generate_with(prompt, &request).await
+    }
+
+    /// 按给定的要求选择模型并生成响应
+    pub async fn generate_with(
+        &self,
+        prompt: &str,
+        request: &RouteRequest,
+    ) -> Result<ResponseWithStats> {
+        self.client_for(request)?.generate_with_stats(prompt).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    fn router(client: LLMClient) -> ModelRouter {
+        ModelRouter::new(client)
+            .with_model(
+                ModelProfile::new("cheap")
+                    .with_pricing(0.1, 0.4)
+                    .with_context_window(1_000)
+                    .with_latency(Duration::from_millis(900)),
+            )
+            .with_model(
+                ModelProfile::new("fast")
+                    .with_pricing(1.0, 4.0)
+                    .with_capability(Capability::Json)
+                    .with_latency(Duration::from_millis(200)),
+            )
+            .with_model(
+                ModelProfile::new("frontier")
+                    .with_pricing(5.0, 20.0)
+                    .with_capability(Capability::Json)
+                    .with_capability(Capability::Tools)
+                    .with_latency(Duration::from_secs(2)),
+            )
+    }
+
+    #[test]
+    fn test_select() {
+        let router = router(LLMClient::new(Config::default()));
+        let select = |request: RouteRequest| router.select(&request).unwrap().model().to_string();
+
+        let short = RouteRequest::for_prompt("hello");
+        assert_eq!(select(short.clone()), "cheap");
+        assert_eq!(select(short.clone().with_output_tokens(2_000)), "fast");
+        assert_eq!(
+            select(short.clone().with_capability(Capability::Tools)),
+            "frontier"
+        );
+        assert_eq!(
+            select(
+                short
+                    .clone()
+                    .with_latency_target(Duration::from_millis(500))
+            ),
+            "fast"
+        );
+        // 延迟目标无法满足时选择最快的模型
+        assert_eq!(
+            select(short.clone().with_latency_target(Duration::from_millis(1))),
+            "fast"
+        );
+
+        let router = router.with_policy(FastestPolicy);
+        assert_eq!(router.select(&short).unwrap().model(), "fast");
+
+        let vision = short.with_capability(Capability::Vision);
+        assert!(matches!(
+            router.select(&vision),
+            Err(NanoError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_route_by_complexity() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("Paris")),
+            MockResponse::json(completion_body("proof")),
+        ])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let router = ModelRouter::new(client)
+            .with_model(ModelProfile::new("free").with_max_complexity(Complexity::Simple))
+            .with_model(
+                ModelProfile::new("mid")
+                    .with_pricing(0.5, 1.0)
+                    .with_max_complexity(Complexity::Moderate),
+            )
+            .with_model(ModelProfile::new("frontier").with_pricing(5.0, 20.0))
+            .with_classifier(ComplexityClassifier::new());
+
+        router
+            .generate("What is the capital of France?")
+            .await
+            .unwrap();
+        router
+            .generate("Prove that sqrt(2) is irrational, step by step.")
+            .await
+            .unwrap();
+        let models: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .map(|r| serde_json::from_str::<serde_json::Value>(&r.body).unwrap()["model"].clone())
+            .collect();
+        assert_eq!(models, ["free", "frontier"]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_uses_selected_model() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let router = router(client.clone());
+
+        let request = RouteRequest::for_prompt("hi").with_capability(Capability::Json);
+        let response = router.generate_with("hi", &request).await.unwrap();
+        assert_eq!(response.content, "ok");
+        let body: serde_json::Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        assert_eq!(body["model"], "fast");
+        assert!(client.latency("fast").is_some());
+    }
+}
@@ -0,0 +1,71 @@
+//! 运行时抽象模块
+//!
+//! 库内部的计时（重试退避、调用截止时间、传输超时、限速输出）和后台任务
+//! （统计汇出、webhook、任务队列、压测）都通过本模块使用 tokio，其他模块不直接调用
+//! `tokio::time` 或 `tokio::spawn`。
+//!
+//! 本库依赖 tokio 运行时：HTTP 请求由 reqwest 发送，需要 tokio 的 IO 驱动。在 async-std、
+//! smol 等执行器上使用时，需要借助 `async-compat` 等兼容层提供 tokio 运行时。
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 等待超时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Elapsed;
+
+/// 等待指定时长
+pub(crate) async fn sleep(duration: Duration) {
+    sleep_until(Instant::now() + duration).await
+}
+
+/// 等待到指定时刻
+pub(crate) async fn sleep_until(deadline: Instant) {
+    tokio::time::sleep_until(deadline.into()).await
+}
+
+/// 限制 `future` 的执行时长
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    timeout_at(Instant::now() + duration, future).await
+}
+
+/// 在指定时刻之前完成 `future`，否则返回 [`Elapsed`]
+pub(crate) async fn timeout_at<F: Future>(
+    deadline: Instant,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    tokio::time::timeout_at(deadline.into(), future)
+        .await
+        .map_err(|_| Elapsed)
+}
+
+/// 在后台执行任务
+pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timers() {
+        let started = Instant::now();
+        sleep(Duration::from_millis(20)).await;
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        assert_eq!(timeout(Duration::from_secs(1), async { 7 }).await, Ok(7));
+        let pending = futures::future::pending::<()>();
+        assert_eq!(
+            timeout(Duration::from_millis(10), pending).await,
+            Err(Elapsed)
+        );
+    }
+}
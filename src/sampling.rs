@@ -0,0 +1,236 @@
+//! 多次采样模块
+//!
+//! 对同一提示以不同的随机种子并发采样多次，再通过多数投票或评审模型选出最终答案
+//! （self-consistency / best-of-N），用于提高推理类任务的可靠性；
+//! 也支持以相同种子在多个模型上并发执行同一提示进行对比。
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+    options::RequestOptions,
+    types::ResponseWithStats,
+};
+use futures::future::{join_all, try_join_all};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// ================================================================================================
+// Best-of-N
+// ================================================================================================
+
+/// 从多个候选答案中选出最终答案的方式
+#[derive(Debug, Clone)]
+pub enum BestOfSelector {
+    /// 多数投票：规范化（去除首尾空白、合并空白、忽略大小写）后出现次数最多的答案，
+    /// 票数相同时取最先生成的答案
+    MajorityVote,
+    /// 由评审模型从候选答案中选出最佳答案，评审失败时退回多数投票
+    Judge(LLMClient),
+}
+
+/// 多次采样的结果
+#[derive(Debug, Clone)]
+pub struct BestOf {
+    /// 最终选出的答案
+    pub content: String,
+    /// 被选中的候选答案在 `candidates` 中的下标
+    pub selected: usize,
+    /// 所有成功生成的候选答案
+    pub candidates: Vec<ResponseWithStats>,
+}
+
+impl LLMClient {
+    /// 以不同的随机种子并发采样 `n` 次，并按 `selector` 选出最终答案
+    ///
+    /// 第 `i` 次采样使用 `seed + i` 作为种子，`seed` 取自 `Config` 中的 `random_seed`，
+    /// 未配置时随机生成。部分采样失败时只在成功的候选中选择，全部失败时返回第一个错误。
+    pub async fn generate_best_of(
+        &self,
+        prompt: &str,
+        n: usize,
+        selector: BestOfSelector,
+    ) -> Result<BestOf> {
+        if n == 0 {
+            return Err(NanoError::InvalidRequest("n must be at least 1".into()));
+        }
+        let base_seed = self.config.random_seed.unwrap_or_else(|| fastrand::u64(..));
+        let samples = join_all((0..n as u64).map(|i| {
+            let options = RequestOptions::new().with_seed(base_seed.wrapping_add(i));
+            async move { self.generate_with_options(prompt, &options).await }
+        }))
+        .await;
+
+        let mut candidates = Vec::with_capacity(n);
+        let mut first_error = None;
+        for sample in samples {
+            match sample {
+                Ok(response) => candidates.push(response),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return Err(first_error.unwrap_or(NanoError::NoContent));
+        }
+
+        let answers: Vec<&str> = candidates.iter().map(|c| c.content.as_str()).collect();
+        let selected = match selector {
+            BestOfSelector::MajorityVote => majority_vote(&answers),
+            BestOfSelector::Judge(judge) => match judge_select(&judge, prompt, &answers).await {
+                Some(index) => index,
+                None => majority_vote(&answers),
+            },
+        };
+
+        Ok(BestOf {
+            content: candidates[selected].content.clone(),
+            selected,
+            candidates,
+        })
+    }
+}
+
+// ================================================================================================
+// 模型对比
+// ================================================================================================
+
+impl LLMClient {
+    /// 以相同的随机种子在多个模型上并发执行同一提示
+    ///
+    /// 返回 `(模型名称, 响应)` 列表，顺序与 `models` 一致，统计信息中包含各模型的耗时和
+    /// token 用量。种子取自 `Config` 中的 `random_seed`，未配置时随机生成。
+    /// 对比请求不使用 `Config::with_fallback_models` 中的降级模型，任一模型请求失败时
+    /// 返回该错误。
+    pub async fn compare_models(
+        &self,
+        prompt: &str,
+        models: &[&str],
+    ) -> Result<Vec<(String, ResponseWithStats)>> {
+        let seed = self.config.random_seed.unwrap_or_else(|| fastrand::u64(..));
+        let options = RequestOptions::new().with_seed(seed);
+        try_join_all(models.iter().map(|model| {
+            let mut client = self.with_model(model);
+            // 降级到其他模型会让对比结果张冠李戴
+            Arc::make_mut(&mut client.config).fallback_models.clear();
+            let options = &options;
+            async move {
+                let response = client.generate_with_options(prompt, options).await?;
+                Ok::<_, NanoError>((model.to_string(), response))
+            }
+        }))
+        .await
+    }
+}
+
+// ================================================================================================
+// 辅助函数
+// ================================================================================================
+
+fn normalize(answer: &str) -> String {
+    answer
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// 返回得票最多的答案下标，票数相同时取下标最小者
+fn majority_vote(answers: &[&str]) -> usize {
+    let normalized: Vec<String> = answers.iter().map(|a| normalize(a)).collect();
+    let mut votes: HashMap<&str, usize> = HashMap::new();
+    for answer in &normalized {
+        *votes.entry(answer.as_str()).or_default() += 1;
+    }
+    let mut best = 0;
+    for (i, answer) in normalized.iter().enumerate() {
+        if votes[answer.as_str()] > votes[normalized[best].as_str()] {
+            best = i;
+        }
+    }
+    best
+}
+
+const JUDGE_PROMPT: &str = "You are given a question and several candidate answers. Pick the \
+most accurate and complete answer. Reply with only the number of the best candidate.";
+
+/// 由评审模型选出最佳答案，返回 `None` 表示评审失败或回复无法解析
+async fn judge_select(judge: &LLMClient, prompt: &str, answers: &[&str]) -> Option<usize> {
+    let mut request = format!("{}\n\nQuestion:\n{}\n", JUDGE_PROMPT, prompt);
+    for (i, answer) in answers.iter().enumerate() {
+        request.push_str(&format!("\nCandidate {}:\n{}\n", i + 1, answer));
+    }
+    let verdict = judge.generate(&request).await.ok()?;
+    parse_choice(&verdict, answers.len())
+}
+
+/// 解析评审回复中的第一个数字（从 1 开始编号）
+fn parse_choice(verdict: &str, count: usize) -> Option<usize> {
+    let digits: String = verdict
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(char::is_ascii_digit)
+        .collect();
+    let choice: usize = digits.parse().ok()?;
+    (1..=count).contains(&choice).then(|| choice - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_majority_vote() {
+        assert_eq!(majority_vote(&["42", "41", " 42\n", "41"]), 0);
+        assert_eq!(majority_vote(&["a", "B", "b"]), 1);
+        assert_eq!(majority_vote(&["only"]), 0);
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("2", 3), Some(1));
+        assert_eq!(parse_choice("Candidate 3 is best.", 3), Some(2));
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("none", 3), None);
+    }
+
+    #[tokio::test]
+    async fn test_compare_models() {
+        use crate::config::Config;
+        use crate::test_util::{completion_body, MockResponse, MockServer};
+        use std::time::Duration;
+
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let results = client
+            .compare_models("hi", &["m1", "m2", "m3"])
+            .await
+            .unwrap();
+        let models: Vec<_> = results.iter().map(|(model, _)| model.as_str()).collect();
+        assert_eq!(models, ["m1", "m2", "m3"]);
+
+        let bodies: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .map(|r| serde_json::from_str(&r.body).unwrap())
+            .collect();
+        let mut requested: Vec<_> = bodies
+            .iter()
+            .map(|b| b["model"].as_str().unwrap())
+            .collect();
+        requested.sort();
+        assert_eq!(requested, ["m1", "m2", "m3"]);
+        assert!(bodies[0]["seed"].is_u64());
+        assert!(bodies.iter().all(|b| b["seed"] == bodies[0]["seed"]));
+
+        // 失败的模型不会被降级模型顶替
+        let server = MockServer::start(vec![MockResponse::new(503, "busy")]).await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_retry_backoff(Duration::from_millis(1))
+                .with_fallback_models(vec!["backup".into()]),
+        );
+        assert!(client.compare_models("hi", &["m1"]).await.is_err());
+        assert!(server.requests().iter().all(|r| !r.body.contains("backup")));
+    }
+}
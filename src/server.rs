@@ -0,0 +1,513 @@
+//! OpenAI 兼容代理服务模块（需要启用 `server` feature）
+//!
+//! 以 axum 暴露 `/v1/chat/completions`（支持 SSE 流式响应），请求经由 [`LLMClient`]
+//! 转发到上游，因此会应用客户端的并发与优先级控制、重试与降级、预算、上下文策略和
+//! 护栏等逻辑，可作为本地网关使用。[`router_with_cache`] 还会用 [`CachedClient`]
+//! 缓存非流式响应。
+//!
+//! 模块同时提供 [`into_sse`] 和 [`into_body`]，用于在自己的 axum 服务中转发流式结果。
+use crate::{
+    chat_model::{CachedClient, ChatModel},
+    client::LLMClient,
+    error::{NanoError, Result},
+    options::RequestOptions,
+    stream::encode_sse,
+    types::{FinishReason, Message, Role},
+    utils::message,
+};
+use axum::{
+    body::Body,
+    extract::State,
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ================================================================================================
+// 请求结构
+// ================================================================================================
+
+/// 停止序列，兼容字符串和字符串数组两种写法
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Stop {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// `/v1/chat/completions` 请求体
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    /// 模型名称，为空时使用客户端配置的模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 消息列表，开头的系统消息会作为本次请求的系统提示
+    pub messages: Vec<Message>,
+    /// 是否以 SSE 流式返回
+    #[serde(default)]
+    pub stream: bool,
+    /// 温度参数
+    pub temperature: Option<f32>,
+    /// top_p 参数
+    pub top_p: Option<f32>,
+    /// 最大生成 token 数
+    pub max_tokens: Option<u32>,
+    /// 随机种子
+    pub seed: Option<u64>,
+    /// 停止序列
+    stop: Option<Stop>,
+    /// presence penalty
+    pub presence_penalty: Option<f32>,
+    /// frequency penalty
+    pub frequency_penalty: Option<f32>,
+}
+
+impl ChatCompletionRequest {
+    fn options(&self) -> RequestOptions {
+        let mut options = RequestOptions::new();
+        options.seed = self.seed;
+        options.temperature = self.temperature;
+        options.top_p = self.top_p;
+        options.max_tokens = self.max_tokens;
+        options.presence_penalty = self.presence_penalty;
+        options.frequency_penalty = self.frequency_penalty;
+        options.stop = self.stop.clone().map(|stop| match stop {
+            Stop::One(s) => vec![s],
+            Stop::Many(v) => v,
+        });
+        options
+    }
+
+    /// 拆分开头的系统消息和其余对话
+    fn split_system(&self) -> (Option<String>, Vec<Message>) {
+        let count = self
+            .messages
+            .iter()
+            .take_while(|m| m.role == Role::System)
+            .count();
+        let system = (count > 0).then(|| {
+            self.messages[..count]
+                .iter()
+                .map(|m| m.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        });
+        (system, self.messages[count..].to_vec())
+    }
+}
+
+// ================================================================================================
+// 服务
+// ================================================================================================
+
+/// 代理服务的共享状态
+#[derive(Debug, Clone)]
+struct Gateway {
+    client: LLMClient,
+    cache: Option<Arc<CachedClient<LLMClient>>>,
+}
+
+/// 创建代理服务的路由
+pub fn router(client: LLMClient) -> Router {
+    gateway_router(Gateway {
+        client,
+        cache: None,
+    })
+}
+
+/// 创建缓存非流式响应的代理服务路由
+///
+/// `cache` 包装的客户端即为上游客户端。请求指定了其他模型或使用流式响应时不经过缓存。
+///
+/// ```rust,no_run
+/// use nanoai::chat_model::CachedClient;
+/// use nanoai::client::LLMClient;
+/// use nanoai::config::Config;
+/// use nanoai::server::router_with_cache;
+/// use std::time::Duration;
+///
+/// let client = LLMClient::new(Config::default());
+/// let app = router_with_cache(CachedClient::new(client).with_ttl(Duration::from_secs(600)));
+/// ```
+pub fn router_with_cache(cache: CachedClient<LLMClient>) -> Router {
+    gateway_router(Gateway {
+        client: cache.inner().clone(),
+        cache: Some(Arc::new(cache)),
+    })
+}
+
+fn gateway_router(gateway: Gateway) -> Router {
+    Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .with_state(gateway)
+}
+
+/// 在 `addr` 上启动代理服务，直到进程退出或发生 IO 错误
+pub async fn serve(client: LLMClient, addr: SocketAddr) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(client)).await?;
+    Ok(())
+}
+
+async fn chat_completions(
+    State(gateway): State<Gateway>,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Response {
+    let Gateway { client, cache } = gateway;
+    let (client, cache) = match &request.model {
+        Some(model) if model != &client.config.model => (client.with_model(model), None),
+        _ => (client, cache),
+    };
+    let (system, messages) = request.split_system();
+    let id = format!("chatcmpl-{:016x}", fastrand::u64(..));
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let model = client.config.model.clone();
+
+    let options = request.options();
+    if !request.stream {
+        let result = match &cache {
+            Some(cache) => {
+                let messages: Vec<Message> = system
+                    .as_deref()
+                    .map(|system| message(Role::System, system))
+                    .into_iter()
+                    .chain(messages)
+                    .collect();
+                cache.chat(&messages, &options).await
+            }
+            None => {
+                client
+                    .generate_internal(system.as_deref(), &messages, &options)
+                    .await
+            }
+        };
+        return match result {
+            Ok(response) => Json(json!({
+                "id": id,
+                "object": "chat.completion",
+                "created": created,
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": response.content},
+                    "finish_reason": response
+                        .stats
+                        .finish_reason
+                        .as_ref()
+                        .map_or("stop", FinishReason::as_str),
+                }],
+                "usage": {
+                    "prompt_tokens": response.stats.prompt_tokens.unwrap_or_default(),
+                    "completion_tokens": response.stats.completion_tokens.unwrap_or_default(),
+                    "total_tokens": response.stats.total_tokens.unwrap_or_default(),
+                },
+            }))
+            .into_response(),
+            Err(e) => error_response(&e),
+        };
+    }
+
+    let (chunks, handle) = match client
+        .stream_internal_with_handle(system.as_deref(), messages, &options)
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => return error_response(&e),
+    };
+    let chunk = move |delta: Value, finish_reason: Option<&str>| {
+        json!({
+            "id": &id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": &model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        })
+    };
+    let first = chunk(json!({"role": "assistant"}), None);
+    let last_chunk = chunk.clone();
+    // 结束原因在上游流读完后才能确定
+    let last = stream::once(async move {
+        let reason = handle.finish_reason();
+        Ok(last_chunk(
+            json!({}),
+            Some(reason.as_ref().map_or("stop", FinishReason::as_str)),
+        ))
+    });
+    let body = chunks.map(move |text| text.map(|text| chunk(json!({"content": text}), None)));
+    let events = stream::once(async { Ok(first) }).chain(body).chain(last);
+    into_sse(events).into_response()
+}
+
+// ================================================================================================
+// 流式响应适配
+// ================================================================================================
+
+/// 将结果流转换为 axum 的 SSE 响应
+///
+/// 编码规则与 [`encode_sse`] 相同：每个元素序列化为一个 `data` 事件，正常结束时追加
+/// `data: [DONE]`，遇到错误时发送 `error` 事件并结束。连接空闲时自动发送保活注释。
+pub fn into_sse<S, T>(
+    stream: S,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>> + Send + 'static>
+where
+    S: Stream<Item = Result<T>> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    let events = async_stream::stream! {
+        futures::pin_mut!(stream);
+        while let Some(item) = stream.next().await {
+            let event = item.and_then(|item| Ok(Event::default().data(serde_json::to_string(&item)?)));
+            match event {
+                Ok(event) => yield Ok(event),
+                Err(e) => {
+                    let body = json!({ "error": { "message": e.to_string() } });
+                    yield Ok(Event::default().event("error").data(body.to_string()));
+                    return;
+                }
+            }
+        }
+        yield Ok(Event::default().data("[DONE]"));
+    };
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// 将结果流转换为 SSE 编码的 HTTP 响应体，适合需要自行设置响应头的场景
+pub fn into_body<S, T>(stream: S) -> Body
+where
+    S: Stream<Item = Result<T>> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    Body::from_stream(encode_sse(stream))
+}
+
+// ================================================================================================
+// 错误处理
+// ================================================================================================
+
+/// 错误对应的 HTTP 状态码
+fn status_code(e: &NanoError) -> StatusCode {
+    match e {
+        NanoError::InvalidRequest(_) | NanoError::ContextOverflow { .. } => StatusCode::BAD_REQUEST,
+        NanoError::Auth(_) => StatusCode::UNAUTHORIZED,
+        NanoError::ModelNotFound(_) => StatusCode::NOT_FOUND,
+        NanoError::RateLimit { .. } | NanoError::BudgetExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        NanoError::GuardrailViolation { .. } | NanoError::ContentFiltered { .. } => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        NanoError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        NanoError::Http(_)
+        | NanoError::Connect(_)
+        | NanoError::Api { .. }
+        | NanoError::NoContent => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// OpenAI 风格的错误响应体
+fn error_body(e: &NanoError) -> Value {
+    json!({
+        "error": {
+            "message": e.to_string(),
+            "type": status_code(e).canonical_reason().unwrap_or("error"),
+            "code": status_code(e).as_u16(),
+        }
+    })
+}
+
+fn error_response(e: &NanoError) -> Response {
+    (status_code(e), Json(error_body(e))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+
+    /// 在本地端口上运行 `app`，返回其地址
+    async fn spawn(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}/v1/chat/completions", addr)
+    }
+
+    fn upstream(base: &str) -> LLMClient {
+        LLMClient::new(Config::default().with_api_base(base))
+    }
+
+    #[test]
+    fn test_request_parsing() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Hi"}
+            ],
+            "stream": true,
+            "stop": "\n",
+            "seed": 7
+        }))
+        .unwrap();
+        assert!(request.stream);
+
+        let (system, messages) = request.split_system();
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        assert_eq!(messages.len(), 1);
+
+        let options = request.options();
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.stop, Some(vec!["\n".to_string()]));
+    }
+
+    #[test]
+    fn test_error_status() {
+        let e = NanoError::BudgetExceeded("tokens".into());
+        assert_eq!(status_code(&e), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error_body(&e)["error"]["code"], 429);
+    }
+
+    #[tokio::test]
+    async fn test_router_reports_finish_reason() {
+        let mut body = completion_body("truncated");
+        body["choices"][0]["finish_reason"] = json!("length");
+        let server = MockServer::start(vec![MockResponse::json(body)]).await;
+        let url = spawn(router(upstream(&server.base))).await;
+
+        let response: Value = reqwest::Client::new()
+            .post(&url)
+            .json(&json!({"messages": [{"role": "user", "content": "Hi"}]}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["choices"][0]["message"]["content"], "truncated");
+        assert_eq!(response["choices"][0]["finish_reason"], "length");
+        assert_eq!(response["usage"]["total_tokens"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_router_accepts_content_parts() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let url = spawn(router(upstream(&server.base))).await;
+
+        let response = reqwest::Client::new()
+            .post(&url)
+            .json(&json!({"messages": [
+                {"role": "system", "content": [{"type": "text", "text": "Be brief."}]},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is"},
+                    {"type": "text", "text": "2 + 2?"}
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "add", "arguments": "{}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "4"}
+            ]}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let sent: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        let messages = sent["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["content"], "Be brief.");
+        assert_eq!(messages[1]["content"], "What is\n2 + 2?");
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+    }
+
+    #[tokio::test]
+    async fn test_router_streams_finish_reason() {
+        let events = [("Hel", Value::Null), ("lo", json!("length"))].map(|(text, reason)| {
+            json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": "mock",
+                "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": reason}],
+            })
+        });
+        let mut sse: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+        sse.push_str("data: [DONE]\n\n");
+        let server = MockServer::start(vec![
+            MockResponse::new(200, &sse).with_header("Content-Type", "text/event-stream")
+        ])
+        .await;
+        let url = spawn(router(upstream(&server.base))).await;
+
+        let body = reqwest::Client::new()
+            .post(&url)
+            .json(&json!({"messages": [{"role": "user", "content": "Hi"}], "stream": true}))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let chunks: Vec<Value> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        let content: String = chunks
+            .iter()
+            .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        assert_eq!(content, "Hello");
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(
+            chunks.last().unwrap()["choices"][0]["finish_reason"],
+            "length"
+        );
+        assert!(body.trim_end().ends_with("data: [DONE]"));
+    }
+
+    #[tokio::test]
+    async fn test_router_with_cache() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("first")),
+            MockResponse::json(completion_body("second")),
+        ])
+        .await;
+        let url = spawn(router_with_cache(CachedClient::new(upstream(&server.base)))).await;
+        let request = json!({"messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "Hi"}
+        ]});
+
+        let http = reqwest::Client::new();
+        for _ in 0..2 {
+            let response: Value = http
+                .post(&url)
+                .json(&request)
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            assert_eq!(response["choices"][0]["message"]["content"], "first");
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let sent: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(sent["messages"][0]["content"], "Be brief.");
+    }
+}
@@ -0,0 +1,669 @@
+//! 多轮对话会话模块
+use crate::{
+    client::LLMClient,
+    error::{NanoError, Result},
+    memory::{summarize, Memory, SUMMARY_HEADER},
+    options::RequestOptions,
+    store::{SessionData, SessionStore},
+    template,
+    tokenizer::count_message_tokens,
+    types::{Message, ResponseWithStats, Role},
+    utils::message,
+};
+use futures::StreamExt;
+use log::warn;
+use std::sync::Arc;
+
+// ================================================================================================
+// 对话会话
+// ================================================================================================
+
+/// 会话历史的压缩策略
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CompactionPolicy {
+    /// 不压缩
+    #[default]
+    Disabled,
+    /// 历史超过 `threshold_tokens` 时，将较早的对话压缩为摘要，附加在请求的系统消息之后，
+    /// 最近 `keep_recent` 条消息保留原文
+    ///
+    /// 摘要由 `model` 生成（通常是更便宜的模型），为 `None` 时使用会话的模型。
+    /// 再次压缩时，已有的摘要会与新压缩的对话合并。
+    Summarize {
+        /// 触发压缩的历史 token 数
+        threshold_tokens: usize,
+        /// 保留原文的最近消息数
+        keep_recent: usize,
+        /// 生成摘要的模型
+        model: Option<String>,
+    },
+}
+
+/// 多轮对话会话
+///
+/// 维护对话历史，每次发送时将完整历史作为上下文，并把用户消息和模型回复追加到历史中。
+/// 配置 [`Memory`] 后，上下文改由记忆构建，完整历史仍保留用于导出。
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    client: LLMClient,
+    system_message: Option<String>,
+    history: Vec<Message>,
+    /// 压缩掉的较早对话的摘要
+    summary: Option<String>,
+    memory: Option<Arc<dyn Memory>>,
+    user: Option<String>,
+    compaction: CompactionPolicy,
+}
+
+impl ChatSession {
+    /// 使用给定的客户端创建一个空会话
+    pub fn new(client: LLMClient) -> Self {
+        Self {
+            client,
+            system_message: None,
+            history: Vec::new(),
+            summary: None,
+            memory: None,
+            user: None,
+            compaction: CompactionPolicy::Disabled,
+        }
+    }
+
+    /// 设置会话记忆
+    ///
+    /// 克隆出的会话与原会话共享同一份记忆。
+    pub fn with_memory(mut self, memory: impl Memory + 'static) -> Self {
+        self.memory = Some(Arc::new(memory));
+        self
+    }
+
+    /// 设置会话专属的系统消息，覆盖客户端配置中的系统消息
+    pub fn with_system_message(mut self, system_message: &str) -> Self {
+        self.system_message = Some(system_message.to_string());
+        self
+    }
+
+    /// 设置会话的终端用户标识，随每个请求作为 `user` 字段发送
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// 设置历史压缩策略
+    ///
+    /// 每次发送前检查历史长度，压缩掉的消息从 [`Self::history`] 中移除，其摘要见
+    /// [`Self::summary`]。配置记忆时上下文由记忆构建，不执行压缩。
+    pub fn with_compaction(mut self, policy: CompactionPolicy) -> Self {
+        self.compaction = policy;
+        self
+    }
+
+    /// 会话使用的系统消息
+    pub fn system_message(&self) -> &str {
+        self.system_message
+            .as_deref()
+            .unwrap_or(&self.client.config.system_message)
+    }
+
+    /// 对话历史（不含系统消息和已压缩的对话）
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+
+    /// 已压缩对话的摘要
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// 清空对话历史、摘要和记忆
+    pub async fn clear(&mut self) {
+        self.history.clear();
+        self.summary = None;
+        if let Some(memory) = &self.memory {
+            memory.clear().await;
+        }
+    }
+
+    /// 发送一条用户消息并返回模型回复
+    pub async fn send(&mut self, prompt: &str) -> Result<String> {
+        self.send_with_stats(prompt).await.map(|res| res.content)
+    }
+
+    /// 发送一条用户消息并返回模型回复，包括性能统计信息
+    ///
+    /// 请求失败时历史保持不变。
+    pub async fn send_with_stats(&mut self, prompt: &str) -> Result<ResponseWithStats> {
+        self.compact().await?;
+        let (user, messages, options) = self.turn_context(prompt).await?;
+        let system = self.request_system_message()?;
+        let response = self
+            .client
+            .generate_internal(system.as_deref(), &messages, &options)
+            .await?;
+        self.record_turn(user, &response.content).await;
+        Ok(response)
+    }
+
+    /// 发送一条用户消息，以流式方式接收模型回复
+    ///
+    /// 每收到一个文本片段调用一次 `on_delta`，流结束后将完整回复追加到历史并返回。
+    /// 请求或流中途失败时历史保持不变。
+    pub async fn send_streaming(
+        &mut self,
+        prompt: &str,
+        mut on_delta: impl FnMut(&str),
+    ) -> Result<String> {
+        self.compact().await?;
+        let (user, messages, options) = self.turn_context(prompt).await?;
+        let system = self.request_system_message()?;
+        let mut stream = self
+            .client
+            .stream_internal(system.as_deref(), messages, &options)
+            .await?;
+        let mut content = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            on_delta(&chunk);
+            content.push_str(&chunk);
+        }
+        self.record_turn(user, &content).await;
+        Ok(content)
+    }
+
+    /// 重新生成最后一条回复
+    pub async fn regenerate_last(&mut self) -> Result<String> {
+        self.regenerate_last_with(&RequestOptions::default())
+            .await
+            .map(|res| res.content)
+    }
+
+    /// 重新生成最后一条回复，并在本次请求中覆盖采样参数（如更高的温度或另一个种子）
+    ///
+    /// 历史以模型回复结尾时替换该回复；以用户消息结尾时（例如用 [`Self::edit_message`]
+    /// 修改了最后一条用户消息）为其生成回复。请求失败时历史保持不变。
+    pub async fn regenerate_last_with(
+        &mut self,
+        options: &RequestOptions,
+    ) -> Result<ResponseWithStats> {
+        let end = match self.history.last() {
+            Some(last) if last.role == Role::Assistant => self.history.len() - 1,
+            _ => self.history.len(),
+        };
+        let user = match end.checked_sub(1).map(|i| &self.history[i]) {
+            Some(user) if user.role == Role::User => user.clone(),
+            _ => {
+                return Err(NanoError::InvalidRequest(
+                    "No user message to regenerate a reply for".into(),
+                ))
+            }
+        };
+        let prior = end - 1;
+        self.rebuild_memory(prior).await?;
+        let messages = self.context(&self.history[..prior], &user).await?;
+        let system = self.request_system_message()?;
+        let response = match self
+            .client
+            .generate_internal(
+                system.as_deref(),
+                &messages,
+                &self.request_options(options.clone()),
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                self.rebuild_memory(self.history.len()).await?;
+                return Err(e);
+            }
+        };
+        self.history.truncate(prior);
+        self.record_turn(user, &response.content).await;
+        Ok(response)
+    }
+
+    /// 修改第 `index` 条历史消息的内容，并丢弃其后的所有消息
+    ///
+    /// 之后的回复基于旧内容生成，已不再成立。修改用户消息后可以调用
+    /// [`Self::regenerate_last`] 为其生成新的回复。
+    ///
+    /// 重新生成和修改消息都会用剩余的历史重建记忆，摘要记忆和向量记忆因此会重新调用模型。
+    pub async fn edit_message(&mut self, index: usize, content: &str) -> Result<()> {
+        let len = self.history.len();
+        let Some(target) = self.history.get_mut(index) else {
+            return Err(NanoError::InvalidRequest(format!(
+                "Message index {} out of range ({} messages)",
+                index, len
+            )));
+        };
+        target.content = content.to_string();
+        self.history.truncate(index + 1);
+        self.rebuild_memory(self.history.len()).await
+    }
+
+    /// 按压缩策略将较早的对话压缩为摘要
+    ///
+    /// 摘要请求失败时返回错误，历史保持不变。
+    async fn compact(&mut self) -> Result<()> {
+        let CompactionPolicy::Summarize {
+            threshold_tokens,
+            keep_recent,
+            model,
+        } = &self.compaction
+        else {
+            return Ok(());
+        };
+        if self.memory.is_some()
+            || count_message_tokens(&self.client.config.model, &self.history) <= *threshold_tokens
+        {
+            return Ok(());
+        }
+        // 保留的部分从用户消息开始，不拆开一轮问答
+        let mut split = self.history.len().saturating_sub(*keep_recent);
+        while split > 0 && split < self.history.len() && self.history[split].role != Role::User {
+            split += 1;
+        }
+        if split == 0 {
+            return Ok(());
+        }
+        let client = match model {
+            Some(model) => self.client.with_model(model),
+            None => self.client.clone(),
+        };
+        let existing = self.summary.as_deref().unwrap_or_default();
+        let summary = summarize(&client, existing, &self.history[..split]).await?;
+        self.history.drain(..split);
+        self.summary = Some(summary);
+        Ok(())
+    }
+
+    /// 本轮请求的系统消息，有压缩摘要时附加在会话系统消息之后
+    ///
+    /// 没有摘要时返回会话专属的系统消息（`None` 表示使用客户端配置）。
+    fn request_system_message(&self) -> Result<Option<String>> {
+        let Some(summary) = &self.summary else {
+            return Ok(self.system_message.clone());
+        };
+        let system = match &self.system_message {
+            Some(system) => system.clone(),
+            None => template::system_message(&self.client.config)?.into_owned(),
+        };
+        Ok(Some(join_summary(&system, summary)))
+    }
+
+    /// 构建本轮请求的用户消息、上下文和请求选项
+    async fn turn_context(&self, prompt: &str) -> Result<(Message, Vec<Message>, RequestOptions)> {
+        let user = message(Role::User, prompt);
+        let messages = self.context(&self.history, &user).await?;
+        Ok((
+            user,
+            messages,
+            self.request_options(RequestOptions::default()),
+        ))
+    }
+
+    /// 在 `history`（或记忆）之后追加用户消息，构成请求上下文
+    async fn context(&self, history: &[Message], user: &Message) -> Result<Vec<Message>> {
+        let mut messages = match &self.memory {
+            Some(memory) => memory.context(&user.content).await?,
+            None => history.to_vec(),
+        };
+        messages.push(user.clone());
+        Ok(messages)
+    }
+
+    /// 为请求选项补上会话的终端用户标识
+    fn request_options(&self, mut options: RequestOptions) -> RequestOptions {
+        if options.user.is_none() {
+            options.user = self.user.clone();
+        }
+        options
+    }
+
+    /// 用历史的前 `len` 条消息重建记忆，未配置记忆时不做任何事
+    async fn rebuild_memory(&self, len: usize) -> Result<()> {
+        if let Some(memory) = &self.memory {
+            memory.clear().await;
+            memory.save(&self.history[..len]).await?;
+        }
+        Ok(())
+    }
+
+    /// 将一轮问答写入历史和记忆
+    ///
+    /// 回复已经生成，写入记忆失败时只记录警告，不丢弃这一轮。
+    async fn record_turn(&mut self, user: Message, reply: &str) {
+        self.history.extend([user, message(Role::Assistant, reply)]);
+        if let Some(memory) = &self.memory {
+            let turn = &self.history[self.history.len() - 2..];
+            if let Err(e) = memory.save(turn).await {
+                warn!("Failed to save the turn to memory: {}", e);
+            }
+        }
+    }
+
+    /// 导出可持久化的会话状态
+    pub fn snapshot(&self) -> SessionData {
+        SessionData {
+            system_message: self.system_message.clone(),
+            history: self.history.clone(),
+            summary: self.summary.clone(),
+        }
+    }
+
+    /// 从持久化的状态恢复会话
+    pub fn restore(client: LLMClient, data: SessionData) -> Self {
+        Self {
+            system_message: data.system_message,
+            history: data.history,
+            summary: data.summary,
+            ..Self::new(client)
+        }
+    }
+
+    /// 将会话保存到存储
+    pub async fn save_to(&self, store: &dyn SessionStore, id: &str) -> Result<()> {
+        store.save(id, &self.snapshot()).await
+    }
+
+    /// 从存储加载会话，不存在时创建新会话
+    pub async fn load_from(client: LLMClient, store: &dyn SessionStore, id: &str) -> Result<Self> {
+        Ok(match store.load(id).await? {
+            Some(data) => Self::restore(client, data),
+            None => Self::new(client),
+        })
+    }
+
+    /// 包含系统消息（及压缩摘要）在内的完整对话记录
+    fn transcript(&self) -> Vec<Message> {
+        let system = match &self.summary {
+            Some(summary) => join_summary(self.system_message(), summary),
+            None => self.system_message().to_string(),
+        };
+        let system_iter = (!system.is_empty()).then(|| message(Role::System, &system));
+        system_iter
+            .into_iter()
+            .chain(self.history.iter().cloned())
+            .collect()
+    }
+}
+
+/// 将压缩摘要附加到系统消息之后
+fn join_summary(system: &str, summary: &str) -> String {
+    if system.is_empty() {
+        format!("{}{}", SUMMARY_HEADER, summary)
+    } else {
+        format!("{}\n\n{}{}", system, SUMMARY_HEADER, summary)
+    }
+}
+
+// ================================================================================================
+// 对话导出
+// ================================================================================================
+
+/// 对话导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Markdown 对话记录，便于人工阅读
+    Markdown,
+    /// OpenAI 微调格式的 JSONL，整个会话为一行 `{"messages": [...]}`
+    Jsonl,
+}
+
+impl ChatSession {
+    /// 将会话导出为指定格式
+    pub fn export(&self, format: ExportFormat) -> Result<String> {
+        let transcript = self.transcript();
+        match format {
+            ExportFormat::Markdown => Ok(export_markdown(&transcript)),
+            ExportFormat::Jsonl => {
+                let line = serde_json::to_string(&serde_json::json!({ "messages": transcript }))?;
+                Ok(line + "\n")
+            }
+        }
+    }
+}
+
+fn role_title(role: Role) -> &'static str {
+    match role {
+        Role::System => "System",
+        Role::Developer => "Developer",
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+        Role::Tool => "Tool",
+        Role::Function => "Function",
+        Role::Unknown => "Unknown",
+    }
+}
+
+fn export_markdown(transcript: &[Message]) -> String {
+    transcript
+        .iter()
+        .map(|m| match &m.tool_call_id {
+            Some(id) => format!("## {} ({})\n\n{}\n", role_title(m.role), id, m.content),
+            None => format!("## {}\n\n{}\n", role_title(m.role), m.content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn session() -> ChatSession {
+        let mut session =
+            ChatSession::new(LLMClient::new(Config::default())).with_system_message("Be brief.");
+        session.history = vec![
+            message(Role::User, "Hi"),
+            message(Role::Assistant, "Hello!"),
+        ];
+        session
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        let markdown = session().export(ExportFormat::Markdown).unwrap();
+        assert_eq!(
+            markdown,
+            "## System\n\nBe brief.\n\n## User\n\nHi\n\n## Assistant\n\nHello!\n"
+        );
+    }
+
+    #[test]
+    fn test_export_jsonl() {
+        let jsonl = session().export(ExportFormat::Jsonl).unwrap();
+        assert_eq!(jsonl.lines().count(), 1);
+        let value: serde_json::Value = serde_json::from_str(jsonl.trim()).unwrap();
+        assert_eq!(value["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(value["messages"][0]["role"], "system");
+        assert_eq!(value["messages"][2]["content"], "Hello!");
+    }
+
+    #[tokio::test]
+    async fn test_session_user() {
+        use crate::test_util::{completion_body, MockResponse, MockServer};
+
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let mut session = ChatSession::new(client).with_user("user-42");
+        session.send("hi").await.unwrap();
+
+        let body: serde_json::Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+        assert_eq!(body["user"], "user-42");
+    }
+
+    #[tokio::test]
+    async fn test_memory_error_keeps_reply() {
+        use crate::memory::BufferMemory;
+        use crate::test_util::{completion_body, MockResponse, MockServer};
+        use futures::future::BoxFuture;
+
+        /// 上下文正常、保存总是失败的记忆
+        #[derive(Debug, Default)]
+        struct FailingMemory(BufferMemory);
+
+        impl Memory for FailingMemory {
+            fn save<'a>(&'a self, _messages: &'a [Message]) -> BoxFuture<'a, Result<()>> {
+                Box::pin(async { Err(NanoError::RequestError("memory unavailable".into())) })
+            }
+
+            fn context<'a>(&'a self, input: &'a str) -> BoxFuture<'a, Result<Vec<Message>>> {
+                self.0.context(input)
+            }
+
+            fn clear(&self) -> BoxFuture<'_, ()> {
+                self.0.clear()
+            }
+        }
+
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let mut session = ChatSession::new(client).with_memory(FailingMemory::default());
+        assert_eq!(session.send("hi").await.unwrap(), "ok");
+        let contents: Vec<_> = session
+            .history()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, ["hi", "ok"]);
+    }
+
+    #[tokio::test]
+    async fn test_send_streaming() {
+        use crate::test_util::{sse_response, MockServer};
+
+        let server = MockServer::start(vec![sse_response(&["Hel", "lo"])]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let mut session = ChatSession::new(client);
+        let mut deltas = Vec::new();
+        let reply = session
+            .send_streaming("hi", |delta| deltas.push(delta.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(reply, "Hello");
+        assert_eq!(deltas, ["Hel", "lo"]);
+        assert_eq!(session.history().len(), 2);
+        assert_eq!(session.history()[1].content, "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_and_edit() {
+        use crate::test_util::{completion_body, MockResponse, MockServer};
+
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("a")),
+            MockResponse::json(completion_body("b")),
+            MockResponse::json(completion_body("c")),
+        ])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let mut session = ChatSession::new(client);
+        assert!(session.regenerate_last().await.is_err());
+
+        session.send("hi").await.unwrap();
+        let options = RequestOptions::new().with_temperature(1.2);
+        let response = session.regenerate_last_with(&options).await.unwrap();
+        assert_eq!(response.content, "b");
+        let contents: Vec<_> = session
+            .history()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, ["hi", "b"]);
+
+        assert!(session.edit_message(5, "x").await.is_err());
+        session.edit_message(0, "hello").await.unwrap();
+        assert_eq!(session.history().len(), 1);
+        assert_eq!(session.regenerate_last().await.unwrap(), "c");
+        let contents: Vec<_> = session
+            .history()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, ["hello", "c"]);
+
+        let requests = server.requests();
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert!((body["temperature"].as_f64().unwrap() - 1.2).abs() < 1e-6);
+        // 重新生成时不包含被替换的旧回复
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.last().unwrap()["content"], "hi");
+        assert!(messages.iter().all(|m| m["content"] != "a"));
+        let body: serde_json::Value = serde_json::from_str(&requests[2].body).unwrap();
+        assert_eq!(
+            body["messages"].as_array().unwrap().last().unwrap()["content"],
+            "hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summarize_compaction() {
+        use crate::test_util::{completion_body, MockResponse, MockServer};
+
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("S1")),
+            MockResponse::json(completion_body("ok")),
+            MockResponse::json(completion_body("S2")),
+            MockResponse::json(completion_body("ok")),
+        ])
+        .await;
+        let client = LLMClient::new(Config::default().with_api_base(&server.base));
+        let mut session = ChatSession::new(client).with_compaction(CompactionPolicy::Summarize {
+            threshold_tokens: 20,
+            keep_recent: 3,
+            model: Some("cheap".into()),
+        });
+        session.history = (0..6)
+            .map(|i| {
+                let role = if i % 2 == 0 {
+                    Role::User
+                } else {
+                    Role::Assistant
+                };
+                message(role, &format!("turn {} with a little padding", i))
+            })
+            .collect();
+
+        session.send("next").await.unwrap();
+        // 保留最近的完整一轮（从用户消息开始），之前的对话压缩为摘要
+        let contents: Vec<_> = session
+            .history()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                "turn 4 with a little padding",
+                "turn 5 with a little padding",
+                "next",
+                "ok",
+            ]
+        );
+        assert_eq!(session.summary(), Some("S1"));
+        let requests = server.requests();
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["model"], "cheap");
+        assert!(requests[0].body.contains("turn 3"));
+        assert!(!requests[0].body.contains("turn 4"));
+
+        // 摘要合并进唯一的系统消息，历史中不出现额外的系统消息
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        let system = Config::default().system_message;
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(
+            messages[0]["content"],
+            format!("{}\n\nSummary of the earlier conversation:\nS1", system)
+        );
+        assert!(messages[1..].iter().all(|m| m["role"] != "system"));
+
+        // 再次压缩时合并已有摘要
+        session.send("again").await.unwrap();
+        assert!(server.requests()[2].body.contains("Existing summary:\\nS1"));
+        assert_eq!(session.summary(), Some("S2"));
+        assert!(session.history().iter().all(|m| m.role != Role::System));
+        assert_eq!(session.snapshot().summary.as_deref(), Some("S2"));
+    }
+}
@@ -0,0 +1,63 @@
+//! 统计信息接收器模块
+//!
+//! 通过 `Config::with_stats_sink` 注册 [`StatsSink`]，每次请求完成后收到该请求的
+//! [`RequestStats`]，便于导出到 Datadog、ClickHouse 或内部计费系统，而无需本库引入这些依赖。
+//! 流式请求在流正常结束时上报，token 数为客户端估算值。
+use crate::client::LLMClient;
+use crate::runtime;
+use crate::types::RequestStats;
+use futures::future::BoxFuture;
+use std::fmt::Debug;
+
+/// 统计信息接收器
+///
+/// 在后台任务中调用，耗时的导出不会阻塞请求返回。接收器自行处理导出失败。
+pub trait StatsSink: Debug + Send + Sync {
+    /// 记录一次请求的统计信息
+    fn record(&self, stats: RequestStats) -> BoxFuture<'_, ()>;
+}
+
+impl LLMClient {
+    /// 将统计信息分发给所有接收器
+    pub(crate) fn emit_stats(&self, stats: &RequestStats) {
+        for sink in &self.config.stats_sinks {
+            let sink = sink.clone();
+            let stats = stats.clone();
+            runtime::spawn(async move { sink.record(stats).await });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+    use tokio::sync::mpsc;
+
+    #[derive(Debug)]
+    struct ChannelSink(mpsc::UnboundedSender<RequestStats>);
+
+    impl StatsSink for ChannelSink {
+        fn record(&self, stats: RequestStats) -> BoxFuture<'_, ()> {
+            let _ = self.0.send(stats);
+            Box::pin(async {})
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sink_receives_stats() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_stats_sink(ChannelSink(tx)),
+        );
+        client.generate("hi").await.unwrap();
+
+        let stats = rx.recv().await.unwrap();
+        assert_eq!(stats.total_tokens, Some(5));
+        assert_eq!(stats.attempts, 1);
+    }
+}
@@ -0,0 +1,247 @@
+//! 增量 SSE / NDJSON 解析模块
+//!
+//! [`Parser`] 与传输层无关：向其写入任意切分的字节，再迭代取出完整的事件。
+//! 客户端内部用它解析流式响应，自建传输（WebSocket 隧道、代理等）时也可以直接复用。
+//!
+//! ```
+//! use nanoai::sse::Parser;
+//!
+//! let mut parser = Parser::new();
+//! parser.feed(b"event: ping\ndata: {\"a\"");
+//! assert!(parser.next().is_none());
+//! parser.feed(b": 1}\n\n");
+//! let event = parser.next().unwrap().unwrap();
+//! assert_eq!(event.event.as_deref(), Some("ping"));
+//! assert_eq!(event.data, "{\"a\": 1}");
+//! ```
+use crate::error::Result;
+use bytes::{Buf, BytesMut};
+
+/// 一个完整的事件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    /// 事件类型（`event:` 字段），未指定时为空
+    pub event: Option<String>,
+    /// 事件数据，多行 `data:` 以换行连接；NDJSON 模式下为整行内容
+    pub data: String,
+    /// 事件 ID（`id:` 字段）
+    pub id: Option<String>,
+    /// 建议的重连间隔（`retry:` 字段，毫秒）
+    pub retry: Option<u64>,
+    /// 注释行（`: ...`）的内容，仅在启用 [`Parser::with_comments`] 时出现，此时其他字段为空
+    pub comment: Option<String>,
+}
+
+/// 输入格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Server-Sent Events
+    #[default]
+    Sse,
+    /// 每行一个 JSON 值（Newline-Delimited JSON）
+    Ndjson,
+}
+
+/// 增量事件解析器
+///
+/// 行结束符支持 `\n`、`\r\n` 和 `\r`。只在完整的行上解码 UTF-8，
+/// 多字节字符被切分到两次写入中不会损坏。
+#[derive(Debug, Default)]
+pub struct Parser {
+    format: Format,
+    buffer: BytesMut,
+    /// 正在构建的 SSE 事件
+    pending: Event,
+    has_data: bool,
+    comments: bool,
+}
+
+impl Parser {
+    /// 创建 SSE 解析器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建 NDJSON 解析器
+    pub fn ndjson() -> Self {
+        Self::with_format(Format::Ndjson)
+    }
+
+    /// 创建指定格式的解析器
+    pub fn with_format(format: Format) -> Self {
+        Self {
+            format,
+            ..Self::default()
+        }
+    }
+
+    /// 是否将注释行作为单独的事件返回
+    ///
+    /// 服务端常用注释行作为保活心跳（如 `: OPENROUTER PROCESSING`），默认忽略。
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// 写入一段字节
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// 是否还有未解析完的字节
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty() && !self.has_data
+    }
+
+    /// 取出下一个完整的事件，数据不足时返回 `None`
+    pub fn next_event(&mut self) -> Result<Option<Event>> {
+        while let Some(line) = self.next_line(false)? {
+            if let Some(event) = self.process_line(line) {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 输入结束，处理缓冲区中最后一行
+    ///
+    /// NDJSON 模式下返回没有以换行结尾的最后一行；SSE 模式下按规范丢弃
+    /// 没有以空行结束的事件。
+    pub fn finish(&mut self) -> Result<Option<Event>> {
+        if let Some(event) = self.next_event()? {
+            return Ok(Some(event));
+        }
+        let event = match self.next_line(true)? {
+            Some(line) => self.process_line(line),
+            None => None,
+        };
+        self.pending = Event::default();
+        self.has_data = false;
+        Ok(event)
+    }
+
+    /// 从缓冲区取出一行（不含行结束符）
+    ///
+    /// 缓冲区以 `\r` 结尾时可能是被切分的 `\r\n`，除非输入已结束，否则等待更多数据。
+    fn next_line(&mut self, eof: bool) -> Result<Option<String>> {
+        let line = match self.buffer.iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(pos) if self.buffer[pos] == b'\r' && pos + 1 == self.buffer.len() && !eof => {
+                return Ok(None)
+            }
+            Some(pos) => {
+                let line = self.buffer.split_to(pos);
+                let crlf = self.buffer.starts_with(b"\r\n");
+                self.buffer.advance(if crlf { 2 } else { 1 });
+                line
+            }
+            None if eof && !self.buffer.is_empty() => self.buffer.split(),
+            None => return Ok(None),
+        };
+        Ok(Some(String::from_utf8(line.to_vec())?))
+    }
+
+    /// 处理一行，完成一个事件时返回该事件
+    fn process_line(&mut self, line: String) -> Option<Event> {
+        if self.format == Format::Ndjson {
+            return (!line.trim().is_empty()).then(|| Event {
+                data: line,
+                ..Event::default()
+            });
+        }
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.pending);
+            return std::mem::take(&mut self.has_data).then_some(event);
+        }
+        if let Some(comment) = line.strip_prefix(':') {
+            return self.comments.then(|| Event {
+                comment: Some(comment.strip_prefix(' ').unwrap_or(comment).to_string()),
+                ..Event::default()
+            });
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "data" => {
+                if self.has_data {
+                    self.pending.data.push('\n');
+                }
+                self.pending.data.push_str(value);
+                self.has_data = true;
+            }
+            "event" => self.pending.event = Some(value.to_string()),
+            "id" => self.pending.id = Some(value.to_string()),
+            "retry" => self.pending.retry = value.parse().ok(),
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Iterator for Parser {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_fields_and_line_endings() {
+        let mut parser = Parser::new();
+        parser.feed(b": comment\r\nevent: delta\r\nid: 7\r\nretry: 1000\r\ndata:a\r\ndata: b\r");
+        assert!(parser.next().is_none());
+        parser.feed(b"\n\r\ndata: second\n\n\n");
+
+        let events: Vec<Event> = parser.by_ref().map(|e| e.unwrap()).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event {
+                    event: Some("delta".into()),
+                    data: "a\nb".into(),
+                    id: Some("7".into()),
+                    retry: Some(1000),
+                    ..Event::default()
+                },
+                Event {
+                    data: "second".into(),
+                    ..Event::default()
+                },
+            ]
+        );
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut parser = Parser::new().with_comments(true);
+        parser.feed(b": OPENROUTER PROCESSING\n\ndata: x\n\n");
+        let events: Vec<Event> = parser.map(|e| e.unwrap()).collect();
+        assert_eq!(events[0].comment.as_deref(), Some("OPENROUTER PROCESSING"));
+        assert_eq!(events[1].data, "x");
+    }
+
+    #[test]
+    fn test_sse_discards_unterminated_event() {
+        let mut parser = Parser::new();
+        parser.feed(b"data: partial");
+        assert_eq!(parser.finish().unwrap(), None);
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let mut parser = Parser::ndjson();
+        parser.feed("{\"a\":1}\n\n{\"b\":\"你".as_bytes());
+        assert_eq!(parser.next().unwrap().unwrap().data, "{\"a\":1}");
+        assert!(parser.next().is_none());
+        parser.feed("好\"}".as_bytes());
+        assert_eq!(parser.finish().unwrap().unwrap().data, "{\"b\":\"你好\"}");
+    }
+}
@@ -0,0 +1,480 @@
+//! 存储模块
+//!
+//! 将 [`ChatSession`](crate::session::ChatSession) 的状态持久化到外部存储，
+//! 使多个服务实例可以共享同一会话。启用 `redis` feature 后提供基于 Redis 的实现。
+//!
+//! 后台任务的输入、状态和输出可以记录到 [`JobStore`]，使排队中的任务在重启后恢复执行。
+//! 启用 `sqlite` feature 后提供基于 SQLite 的实现。
+use crate::{error::Result, jobs::JobStatus, options::RequestOptions, types::Message};
+use futures::future::{self, BoxFuture};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+// ================================================================================================
+// 会话存储
+// ================================================================================================
+
+/// 可持久化的会话状态
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SessionData {
+    /// 会话专属的系统消息
+    #[serde(default)]
+    pub system_message: Option<String>,
+    /// 对话历史
+    #[serde(default)]
+    pub history: Vec<Message>,
+    /// 已压缩对话的摘要
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// 会话存储
+pub trait SessionStore: Debug + Send + Sync {
+    /// 读取会话，不存在时返回 `None`
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SessionData>>>;
+
+    /// 保存会话
+    fn save<'a>(&'a self, id: &'a str, data: &'a SessionData) -> BoxFuture<'a, Result<()>>;
+
+    /// 删除会话
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// 进程内的会话存储，适合测试和单实例部署
+#[derive(Debug, Default)]
+pub struct InMemorySessionStore {
+    sessions: Mutex<HashMap<String, SessionData>>,
+}
+
+impl InMemorySessionStore {
+    /// 创建空的存储
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, SessionData>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SessionData>>> {
+        Box::pin(future::ready(Ok(self.sessions().get(id).cloned())))
+    }
+
+    fn save<'a>(&'a self, id: &'a str, data: &'a SessionData) -> BoxFuture<'a, Result<()>> {
+        self.sessions().insert(id.to_string(), data.clone());
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.sessions().remove(id);
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use self::redis_store::RedisSessionStore;
+
+#[cfg(feature = "redis")]
+mod redis_store {
+    use super::{SessionData, SessionStore};
+    use crate::error::{NanoError, Result};
+    use futures::future::BoxFuture;
+    use redis::{aio::ConnectionManager, AsyncCommands};
+    use std::time::Duration;
+
+    impl From<redis::RedisError> for NanoError {
+        fn from(e: redis::RedisError) -> Self {
+            NanoError::Storage(e.to_string())
+        }
+    }
+
+    /// 基于 Redis 的会话存储
+    ///
+    /// 每个会话以 JSON 字符串保存在 `{prefix}{id}` 键下，可选设置过期时间，
+    /// 每次保存都会刷新过期时间。
+    #[derive(Clone)]
+    pub struct RedisSessionStore {
+        conn: ConnectionManager,
+        prefix: String,
+        ttl: Option<Duration>,
+    }
+
+    impl std::fmt::Debug for RedisSessionStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedisSessionStore")
+                .field("prefix", &self.prefix)
+                .field("ttl", &self.ttl)
+                .finish()
+        }
+    }
+
+    impl RedisSessionStore {
+        /// 连接到 Redis，例如 `redis://127.0.0.1/`
+        pub async fn connect(url: &str) -> Result<Self> {
+            let client = redis::Client::open(url)?;
+            let conn = ConnectionManager::new(client).await?;
+            Ok(Self {
+                conn,
+                prefix: "nanoai:session:".into(),
+                ttl: None,
+            })
+        }
+
+        /// 设置键前缀，默认为 `nanoai:session:`
+        pub fn with_prefix(mut self, prefix: &str) -> Self {
+            self.prefix = prefix.to_string();
+            self
+        }
+
+        /// 设置会话过期时间
+        pub fn with_ttl(mut self, ttl: Duration) -> Self {
+            self.ttl = Some(ttl);
+            self
+        }
+
+        fn key(&self, id: &str) -> String {
+            format!("{}{}", self.prefix, id)
+        }
+    }
+
+    impl SessionStore for RedisSessionStore {
+        fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SessionData>>> {
+            Box::pin(async move {
+                let mut conn = self.conn.clone();
+                let raw: Option<String> = conn.get(self.key(id)).await?;
+                raw.map(|raw| serde_json::from_str(&raw).map_err(NanoError::from))
+                    .transpose()
+            })
+        }
+
+        fn save<'a>(&'a self, id: &'a str, data: &'a SessionData) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                let mut conn = self.conn.clone();
+                let raw = serde_json::to_string(data)?;
+                match self.ttl {
+                    Some(ttl) => {
+                        conn.set_ex::<_, _, ()>(self.key(id), raw, ttl.as_secs().max(1))
+                            .await?
+                    }
+                    None => conn.set::<_, _, ()>(self.key(id), raw).await?,
+                }
+                Ok(())
+            })
+        }
+
+        fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                let mut conn = self.conn.clone();
+                conn.del::<_, ()>(self.key(id)).await?;
+                Ok(())
+            })
+        }
+    }
+}
+
+// ================================================================================================
+// 任务存储
+// ================================================================================================
+
+/// 后台任务记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobRecord {
+    /// 任务 ID
+    pub id: String,
+    /// 执行任务的模型
+    pub model: String,
+    /// 提示
+    pub prompt: String,
+    /// 请求参数覆盖
+    #[serde(default)]
+    pub options: RequestOptions,
+    /// 任务状态
+    pub status: JobStatus,
+    /// 生成结果，仅成功的任务包含
+    #[serde(default)]
+    pub output: Option<String>,
+    /// 错误信息，仅失败的任务包含
+    #[serde(default)]
+    pub error: Option<String>,
+    /// 提交时间（Unix 秒）
+    pub created_at: u64,
+    /// 最近一次状态变化的时间（Unix 秒）
+    pub updated_at: u64,
+}
+
+/// 任务存储
+pub trait JobStore: Debug + Send + Sync {
+    /// 保存任务记录，ID 相同时覆盖
+    fn save<'a>(&'a self, record: &'a JobRecord) -> BoxFuture<'a, Result<()>>;
+
+    /// 读取任务记录，不存在时返回 `None`
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<JobRecord>>>;
+
+    /// 按提交时间顺序列出任务，`status` 为空时列出全部任务
+    fn list(&self, status: Option<JobStatus>) -> BoxFuture<'_, Result<Vec<JobRecord>>>;
+}
+
+/// 共享的任务存储，便于在配置客户端后继续查询任务记录
+impl<T: JobStore + ?Sized> JobStore for std::sync::Arc<T> {
+    fn save<'a>(&'a self, record: &'a JobRecord) -> BoxFuture<'a, Result<()>> {
+        (**self).save(record)
+    }
+
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<JobRecord>>> {
+        (**self).load(id)
+    }
+
+    fn list(&self, status: Option<JobStatus>) -> BoxFuture<'_, Result<Vec<JobRecord>>> {
+        (**self).list(status)
+    }
+}
+
+/// 进程内的任务存储，适合测试和查看近期任务
+#[derive(Debug, Default)]
+pub struct InMemoryJobStore {
+    jobs: Mutex<HashMap<String, JobRecord>>,
+}
+
+impl InMemoryJobStore {
+    /// 创建空的存储
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, HashMap<String, JobRecord>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl JobStore for InMemoryJobStore {
+    fn save<'a>(&'a self, record: &'a JobRecord) -> BoxFuture<'a, Result<()>> {
+        self.jobs().insert(record.id.clone(), record.clone());
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<JobRecord>>> {
+        Box::pin(future::ready(Ok(self.jobs().get(id).cloned())))
+    }
+
+    fn list(&self, status: Option<JobStatus>) -> BoxFuture<'_, Result<Vec<JobRecord>>> {
+        let mut records: Vec<JobRecord> = self
+            .jobs()
+            .values()
+            .filter(|r| status.is_none_or(|s| r.status == s))
+            .cloned()
+            .collect();
+        records.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Box::pin(future::ready(Ok(records)))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_store::SqliteJobStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite_store {
+    use super::{JobRecord, JobStore};
+    use crate::{
+        error::{NanoError, Result},
+        jobs::JobStatus,
+    };
+    use futures::future::BoxFuture;
+    use rusqlite::{params, Connection, OptionalExtension};
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    impl From<rusqlite::Error> for NanoError {
+        fn from(e: rusqlite::Error) -> Self {
+            NanoError::Storage(e.to_string())
+        }
+    }
+
+    /// 基于 SQLite 的任务存储
+    ///
+    /// 每个任务以 JSON 保存在 `jobs` 表中，状态和时间单独成列以便查询。
+    /// 数据库操作在阻塞线程池中执行。
+    #[derive(Debug, Clone)]
+    pub struct SqliteJobStore {
+        conn: Arc<Mutex<Connection>>,
+    }
+
+    impl SqliteJobStore {
+        /// 打开（或创建）数据库文件
+        pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+            Self::init(Connection::open(path)?)
+        }
+
+        /// 创建内存数据库，适合测试
+        pub fn open_in_memory() -> Result<Self> {
+            Self::init(Connection::open_in_memory()?)
+        }
+
+        fn init(conn: Connection) -> Result<Self> {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS jobs (
+                    id TEXT PRIMARY KEY,
+                    status TEXT NOT NULL,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    data TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS jobs_status ON jobs (status, created_at);",
+            )?;
+            Ok(Self {
+                conn: Arc::new(Mutex::new(conn)),
+            })
+        }
+
+        async fn with_conn<T, F>(&self, f: F) -> Result<T>
+        where
+            T: Send + 'static,
+            F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        {
+            let conn = self.conn.clone();
+            tokio::task::spawn_blocking(move || f(&conn.lock().unwrap_or_else(|e| e.into_inner())))
+                .await
+                .map_err(|e| NanoError::Storage(e.to_string()))?
+        }
+    }
+
+    fn status_name(status: JobStatus) -> Result<String> {
+        match serde_json::to_value(status)? {
+            serde_json::Value::String(name) => Ok(name),
+            other => Ok(other.to_string()),
+        }
+    }
+
+    impl JobStore for SqliteJobStore {
+        fn save<'a>(&'a self, record: &'a JobRecord) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                let data = serde_json::to_string(record)?;
+                let status = status_name(record.status)?;
+                let (id, created_at, updated_at) =
+                    (record.id.clone(), record.created_at, record.updated_at);
+                self.with_conn(move |conn| {
+                    conn.execute(
+                        "INSERT OR REPLACE INTO jobs (id, status, created_at, updated_at, data)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![id, status, created_at as i64, updated_at as i64, data],
+                    )?;
+                    Ok(())
+                })
+                .await
+            })
+        }
+
+        fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<JobRecord>>> {
+            let id = id.to_string();
+            Box::pin(self.with_conn(move |conn| {
+                let data: Option<String> = conn
+                    .query_row("SELECT data FROM jobs WHERE id = ?1", [id], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                data.map(|data| serde_json::from_str(&data).map_err(NanoError::from))
+                    .transpose()
+            }))
+        }
+
+        fn list(&self, status: Option<JobStatus>) -> BoxFuture<'_, Result<Vec<JobRecord>>> {
+            Box::pin(async move {
+                let status = status.map(status_name).transpose()?;
+                self.with_conn(move |conn| {
+                    let mut stmt = conn.prepare(
+                        "SELECT data FROM jobs WHERE ?1 IS NULL OR status = ?1
+                         ORDER BY created_at, id",
+                    )?;
+                    let rows = stmt.query_map([status], |row| row.get::<_, String>(0))?;
+                    rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+                })
+                .await
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Role;
+    use crate::utils::message;
+
+    #[tokio::test]
+    async fn test_in_memory_store_roundtrip() {
+        let store = InMemorySessionStore::new();
+        assert!(store.load("s1").await.unwrap().is_none());
+
+        let data = SessionData {
+            system_message: Some("Be brief.".into()),
+            history: vec![message(Role::User, "Hi")],
+            summary: None,
+        };
+        store.save("s1", &data).await.unwrap();
+        assert_eq!(store.load("s1").await.unwrap(), Some(data));
+
+        store.delete("s1").await.unwrap();
+        assert!(store.load("s1").await.unwrap().is_none());
+    }
+
+    fn record(id: &str, status: JobStatus, created_at: u64) -> JobRecord {
+        JobRecord {
+            id: id.into(),
+            model: "m".into(),
+            prompt: "p".into(),
+            options: RequestOptions::default(),
+            status,
+            output: None,
+            error: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    async fn check_job_store(store: &dyn JobStore) {
+        store
+            .save(&record("b", JobStatus::Queued, 2))
+            .await
+            .unwrap();
+        store
+            .save(&record("a", JobStatus::Completed, 1))
+            .await
+            .unwrap();
+        store
+            .save(&record("c", JobStatus::Queued, 3))
+            .await
+            .unwrap();
+
+        let mut done = record("c", JobStatus::Completed, 3);
+        done.output = Some("ok".into());
+        store.save(&done).await.unwrap();
+        assert_eq!(store.load("c").await.unwrap(), Some(done));
+        assert!(store.load("missing").await.unwrap().is_none());
+
+        let all: Vec<String> = store
+            .list(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(all, ["a", "b", "c"]);
+        let queued = store.list(Some(JobStatus::Queued)).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].id, "b");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_job_store() {
+        check_job_store(&InMemoryJobStore::new()).await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_job_store() {
+        check_job_store(&SqliteJobStore::open_in_memory().unwrap()).await;
+    }
+}
@@ -1,16 +1,26 @@
 //! 流式响应处理模块
 use crate::{
+    client::RequestPermit,
     error::{NanoError, Result},
-    types::StreamCompletionResponse,
+    runtime,
+    sse::{Event, Parser},
+    types::{FinishReason, RequestStats, StreamCompletionResponse},
+    utils::{parse_response, repair_json},
 };
 use async_stream::try_stream;
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
 use futures::{Stream, StreamExt};
-use log::debug;
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
 use std::{
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::watch;
 
 // ================================================================================================
 // 流式响应包装器
@@ -20,64 +30,86 @@ const DONE_CHUNK: &str = "[DONE]";
 
 /// 一个无状态的流处理器，用于解析 SSE (Server-Sent Events) 数据流
 #[derive(Debug, Clone, Default)]
-pub struct StreamWrapper;
+pub struct StreamWrapper {
+    heartbeats: bool,
+}
 
 impl StreamWrapper {
     /// 创建一个新的 `StreamWrapper` 实例
     ///
     /// 这是一个无状态的结构体，所以 `new` 只是 `default` 的别名
     pub fn new() -> Self {
-        StreamWrapper
+        Self::default()
+    }
+
+    /// 是否在事件流中保留注释行，用作心跳（见 [`Event::comment`]）
+    pub fn with_heartbeats(mut self, heartbeats: bool) -> Self {
+        self.heartbeats = heartbeats;
+        self
     }
 
     /// 将一个 `BytesStream` 转换为一个解析 `StreamCompletionResponse` 的流
-    pub fn stream<S>(
+    pub fn stream<S>(&self, bytes_stream: S) -> impl Stream<Item = Result<StreamCompletionResponse>>
+    where
+        S: Stream<Item = std::result::Result<Bytes, reqwest::Error>> + Send + 'static + Unpin,
+    {
+        self.stream_as(bytes_stream)
+    }
+
+    /// 将一个 `BytesStream` 转换为一个逐事件解析为 `T` 的流
+    ///
+    /// 例如使用 Responses API 时 `T` 为 `ResponseStreamEvent`。
+    /// 按 [`parse_event`] 的规则分派事件类型。
+    pub fn stream_as<T, S>(&self, bytes_stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = std::result::Result<Bytes, reqwest::Error>> + Send + 'static + Unpin,
+        T: DeserializeOwned,
+    {
+        self.stream_with(bytes_stream, parse_event::<T>)
+    }
+
+    /// 将一个 `BytesStream` 转换为流，由 `dispatch` 按事件类型决定如何处理每个事件
+    ///
+    /// `dispatch` 返回 `Ok(None)` 时跳过该事件，返回错误时流以该错误结束。
+    /// 适用于每种 `event:` 类型对应不同数据结构的服务端（如 Anthropic）。
+    pub fn stream_with<S, T, F>(
         &self,
-        mut bytes_stream: S,
-    ) -> impl Stream<Item = Result<StreamCompletionResponse>>
+        bytes_stream: S,
+        mut dispatch: F,
+    ) -> impl Stream<Item = Result<T>>
     where
         S: Stream<Item = std::result::Result<Bytes, reqwest::Error>> + Send + 'static + Unpin,
+        F: FnMut(Event) -> Result<Option<T>>,
     {
+        let events = self.events(bytes_stream);
+        try_stream! {
+            futures::pin_mut!(events);
+            while let Some(event) = events.next().await {
+                if let Some(item) = dispatch(event?)? {
+                    yield item;
+                }
+            }
+        }
+    }
+
+    /// 将一个 `BytesStream` 转换为原始 SSE 事件流
+    pub fn events<S>(&self, mut bytes_stream: S) -> impl Stream<Item = Result<Event>>
+    where
+        S: Stream<Item = std::result::Result<Bytes, reqwest::Error>> + Send + 'static + Unpin,
+    {
+        let mut parser = Parser::new().with_comments(self.heartbeats);
         try_stream! {
-            let mut buffer = BytesMut::new();
             while let Some(bytes_res) = bytes_stream.next().await {
                 let bytes = bytes_res.map_err(NanoError::from)?;
-                buffer.extend_from_slice(&bytes);
-
-                loop {
-                    if let Some(pos) = buffer.windows(2).position(|w| w == [b'\n', b'\n']) {
-                        let event_bytes = buffer.split_to(pos + 2);
-
-                        let event_str = String::from_utf8_lossy(&event_bytes).to_string();
-
-                        let mut data = String::new();
-                        for line in event_str.lines() {
-                            let trimmed = line.trim();
-                            if trimmed.starts_with(':') {
-                                continue;
-                            }
-                            if let Some(content) = trimmed.strip_prefix("data: ") {
-                                if !data.is_empty() {
-                                    data.push('\n');
-                                }
-                                data.push_str(content);
-                            }
-                        }
-
-                        if !data.is_empty() && data != DONE_CHUNK {
-                            match serde_json::from_str(&data) {
-                                Ok(resp) => yield resp,
-                                Err(e) => Err(NanoError::Json(format!("Failed to parse event: '{}', error: {}", data, e)))?,
-                            }
-                        }
-                    } else {
-                        break;
-                    }
+                parser.feed(&bytes);
+
+                while let Some(event) = parser.next_event()? {
+                    yield event;
                 }
             }
 
-            if !buffer.is_empty() {
-                debug!("Leftover buffer: {:?}", String::from_utf8_lossy(&buffer));
+            if !parser.is_empty() {
+                debug!("Leftover buffer in SSE parser");
             }
         }
     }
@@ -85,16 +117,95 @@ impl StreamWrapper {
     // process_chunk 已弃用，使用状态流处理
 }
 
+/// 默认的事件分派规则
+///
+/// - `[DONE]` 终止标记、`ping` 事件和心跳注释被跳过
+/// - `error` 事件和顶层带 `error` 字段的数据（部分服务端在流中途这样报告错误）转换为
+///   [`NanoError::Api`]
+/// - 其他事件的数据按 JSON 解析为 `T`，事件类型由 `T` 自身区分（如带 `type` 标签的枚举）
+pub fn parse_event<T: DeserializeOwned>(event: Event) -> Result<Option<T>> {
+    decode_event(event, false)
+}
+
+/// 同 [`parse_event`]，`strict` 为真时按 [`parse_response`] 的严格模式解析数据
+pub(crate) fn decode_event<T: DeserializeOwned>(event: Event, strict: bool) -> Result<Option<T>> {
+    if event.comment.is_some() {
+        return Ok(None);
+    }
+    match event.event.as_deref() {
+        Some("ping") => return Ok(None),
+        Some("error") => return Err(NanoError::from_error_body(200, event.data, event.id)),
+        _ => {}
+    }
+    if event.data == DONE_CHUNK {
+        return Ok(None);
+    }
+    if is_error_payload(&event.data) {
+        return Err(NanoError::from_error_body(200, event.data, event.id));
+    }
+    if strict {
+        return parse_response(event.data.as_bytes(), true, &[]).map(Some);
+    }
+    serde_json::from_str(&event.data).map(Some).map_err(|e| {
+        NanoError::Json(format!(
+            "Failed to parse event: '{}', error: {}",
+            event.data, e
+        ))
+    })
+}
+
+/// 数据是否为 `{"error": ...}` 形式的错误
+fn is_error_payload(data: &str) -> bool {
+    data.contains("\"error\"")
+        && serde_json::from_str::<Value>(data)
+            .is_ok_and(|value| value.get("error").is_some_and(|error| !error.is_null()))
+}
+
+/// Chat Completions 流的事件分派规则
+///
+/// 只解析未指定类型或类型为 `message` 的事件，其他类型的事件（如服务端扩展的进度事件）
+/// 被忽略，而不是因缺少 `choices` 等字段解析失败。
+pub(crate) fn parse_chunk_event(
+    event: Event,
+    strict: bool,
+) -> Result<Option<StreamCompletionResponse>> {
+    match event.event.as_deref() {
+        None | Some("message") | Some("error") => decode_event(event, strict),
+        Some(other) => {
+            debug!("Skipping SSE event of type '{}'", other);
+            Ok(None)
+        }
+    }
+}
+
 /// `Stream<Item = Result<StreamCompletionResponse>>` 的简单包装
+///
+/// 由客户端创建时持有本次请求的并发配额。流结束时归还配额；提前释放时先中止
+/// 底层 HTTP 请求，再归还配额。
 pub struct CompletionStream {
     inner: Pin<Box<dyn Stream<Item = Result<StreamCompletionResponse>> + Send>>,
+    permit: Option<RequestPermit>,
 }
 
 impl CompletionStream {
     /// 创建一个新的 `CompletionStream`
-    pub fn new(stream: impl Stream<Item = Result<StreamCompletionResponse>> + Send + 'static) -> Self {
+    pub fn new(
+        stream: impl Stream<Item = Result<StreamCompletionResponse>> + Send + 'static,
+    ) -> Self {
         Self {
             inner: Box::pin(stream),
+            permit: None,
+        }
+    }
+
+    /// 创建一个在读取期间持有并发配额的 `CompletionStream`
+    pub(crate) fn with_permit(
+        stream: impl Stream<Item = Result<StreamCompletionResponse>> + Send + 'static,
+        permit: Option<RequestPermit>,
+    ) -> Self {
+        Self {
+            inner: Box::pin(stream),
+            permit,
         }
     }
 }
@@ -103,6 +214,611 @@ impl Stream for CompletionStream {
     type Item = Result<StreamCompletionResponse>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.inner.as_mut().poll_next(cx)
+        let poll = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(None) = poll {
+            self.permit = None;
+        }
+        poll
+    }
+}
+
+impl Drop for CompletionStream {
+    fn drop(&mut self) {
+        // 释放响应体即关闭连接，中止仍在进行的请求
+        self.inner = Box::pin(futures::stream::empty());
+        self.permit = None;
+    }
+}
+
+// ================================================================================================
+// 流元数据
+// ================================================================================================
+
+/// 流式响应的元数据
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamMetadata {
+    /// 响应 ID
+    pub id: Option<String>,
+    /// 服务端报告的模型
+    pub model: Option<String>,
+    /// 生成结束的原因，流结束前为空
+    pub finish_reason: Option<FinishReason>,
+}
+
+/// 与文本流一同返回的句柄，用于在流结束后读取响应 ID、模型、结束原因和统计信息
+///
+/// 元数据随流的推进逐步填充，读取时返回当前快照。
+#[derive(Debug, Clone, Default)]
+pub struct StreamHandle {
+    metadata: Arc<Mutex<StreamMetadata>>,
+    stats: Arc<Mutex<Option<RequestStats>>>,
+    heartbeat: Arc<watch::Sender<Option<String>>>,
+}
+
+impl StreamHandle {
+    /// 当前元数据的快照
+    pub fn metadata(&self) -> StreamMetadata {
+        self.metadata
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 响应 ID
+    pub fn id(&self) -> Option<String> {
+        self.metadata().id
+    }
+
+    /// 服务端报告的模型
+    pub fn model(&self) -> Option<String> {
+        self.metadata().model
+    }
+
+    /// 生成结束的原因
+    pub fn finish_reason(&self) -> Option<FinishReason> {
+        self.metadata().finish_reason
+    }
+
+    /// 流结束后的统计信息，流尚未读完时为 `None`
+    pub fn stats(&self) -> Option<RequestStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 订阅心跳
+    ///
+    /// 启用 `Config::with_stream_heartbeats` 后，服务端每发送一条 SSE 注释行
+    /// （如长时间预填充期间的 `: OPENROUTER PROCESSING`）都会更新为该注释的内容，
+    /// 可用于重置自身的空闲计时器或显示"模型思考中"状态。只有在读取文本流时才会收到心跳。
+    pub fn heartbeats(&self) -> watch::Receiver<Option<String>> {
+        self.heartbeat.subscribe()
+    }
+
+    pub(crate) fn record_heartbeat(&self, comment: String) {
+        self.heartbeat.send_replace(Some(comment));
+    }
+
+    pub(crate) fn record_stats(&self, stats: RequestStats) {
+        *self.stats.lock().unwrap_or_else(|e| e.into_inner()) = Some(stats);
+    }
+
+    pub(crate) fn update(&self, f: impl FnOnce(&mut StreamMetadata)) {
+        f(&mut self.metadata.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// 记录一个 `/chat/completions` 流式数据块中的元数据
+    pub(crate) fn record_chunk(&self, chunk: &StreamCompletionResponse) {
+        self.update(|meta| {
+            if meta.id.is_none() && !chunk.id.is_empty() {
+                meta.id = Some(chunk.id.clone());
+            }
+            if meta.model.is_none() && !chunk.model.is_empty() {
+                meta.model = Some(chunk.model.clone());
+            }
+            if let Some(reason) = chunk.choices.first().and_then(|c| c.finish_reason.clone()) {
+                meta.finish_reason = Some(FinishReason::from(reason));
+            }
+        });
+    }
+}
+
+// ================================================================================================
+// 客户端停止序列
+// ================================================================================================
+
+/// 在客户端按停止序列截断输出，用于忽略 `stop` 参数的服务端
+///
+/// 流式处理时，末尾可能是停止序列开头的部分会被暂存，直到确认不构成停止序列后再输出。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StopFilter {
+    stops: Vec<String>,
+    pending: String,
+    stopped: bool,
+}
+
+impl StopFilter {
+    pub(crate) fn new(stops: &[String]) -> Self {
+        Self {
+            stops: stops.iter().filter(|s| !s.is_empty()).cloned().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// 截断到第一个停止序列之前，返回截断后的文本和是否遇到停止序列
+    pub(crate) fn truncate<'a>(&self, text: &'a str) -> (&'a str, bool) {
+        match self
+            .stops
+            .iter()
+            .filter_map(|stop| text.find(stop.as_str()))
+            .min()
+        {
+            Some(pos) => (&text[..pos], true),
+            None => (text, false),
+        }
+    }
+
+    /// 是否已遇到停止序列
+    pub(crate) fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// 追加一个文本片段，返回可以安全输出的部分
+    pub(crate) fn push(&mut self, chunk: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.pending.push_str(chunk);
+        let (head, stopped) = self.truncate(&self.pending);
+        if stopped {
+            let head = head.to_string();
+            self.stopped = true;
+            self.pending.clear();
+            return head;
+        }
+        // 暂存可能构成停止序列开头的最长后缀
+        let hold = self
+            .stops
+            .iter()
+            .flat_map(|stop| {
+                stop.char_indices()
+                    .skip(1)
+                    .map(|(i, _)| &stop[..i])
+                    .filter(|prefix| self.pending.ends_with(prefix))
+                    .map(str::len)
+            })
+            .max()
+            .unwrap_or(0);
+        let emit = self.pending.len() - hold;
+        let rest = self.pending.split_off(emit);
+        std::mem::replace(&mut self.pending, rest)
+    }
+
+    /// 流结束时输出暂存的文本
+    pub(crate) fn finish(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+// ================================================================================================
+// 通道事件
+// ================================================================================================
+
+/// [`LLMClient::stream_to_channel`](crate::client::LLMClient::stream_to_channel) 发送的事件
+///
+/// 序列化为 `{"type": "delta", "data": "..."}` 形式，便于转发给其他进程。错误只保留消息文本，
+/// 反序列化后为 [`NanoError::StreamError`]。
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// 文本增量
+    Delta(String),
+    /// 服务端心跳（SSE 注释行），需启用 `Config::with_stream_heartbeats`
+    Heartbeat(String),
+    /// 请求或流处理出错，之后不会再有事件
+    #[serde(with = "error_message")]
+    Error(NanoError),
+    /// 流正常结束
+    Done,
+}
+
+/// [`StreamEvent::Error`] 的错误与消息文本之间的转换
+mod error_message {
+    use crate::error::NanoError;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        error: &NanoError,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(error)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<NanoError, D::Error> {
+        String::deserialize(deserializer).map(NanoError::StreamError)
+    }
+}
+
+// ================================================================================================
+// 增量 JSON 解析
+// ================================================================================================
+
+/// 增量 JSON 解析器
+///
+/// 累积流式返回的文本片段，并在任意时刻将已到达的部分解析为 JSON 值。
+/// 未闭合的字符串、对象和数组会借助 [`repair_json`] 自动补全，
+/// 因此可以在生成结束前渲染部分结构化结果。
+#[derive(Debug, Clone, Default)]
+pub struct PartialJson {
+    buffer: String,
+}
+
+impl PartialJson {
+    /// 创建一个空的 `PartialJson`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一个文本片段
+    pub fn push(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+    }
+
+    /// 已累积的原始文本
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
+    /// 将当前已到达的文本解析为 JSON 值，无法解析时返回 `None`
+    pub fn value(&self) -> Option<Value> {
+        if self.buffer.trim().is_empty() {
+            return None;
+        }
+        serde_json::from_str(&repair_json(&self.buffer)).ok()
+    }
+
+    /// 返回 `pointer` 指向的数组中已经完整到达的元素
+    ///
+    /// 数组的最后一个元素可能仍在生成中，只有在其后出现新元素
+    /// 或 `finished` 为 `true` 时才视为完整。`pointer` 使用 JSON Pointer
+    /// 语法，空字符串表示顶层数组。
+    pub fn complete_items(&self, pointer: &str, finished: bool) -> Vec<Value> {
+        let Some(Value::Array(mut items)) = self.value().and_then(|v| v.pointer(pointer).cloned())
+        else {
+            return Vec::new();
+        };
+        if !finished {
+            items.pop();
+        }
+        items
+    }
+}
+
+// ================================================================================================
+// 文本流适配器
+// ================================================================================================
+
+/// 文本片段流的扩展方法
+pub trait TextStreamExt: Stream<Item = Result<String>> + Sized {
+    /// 将每个文本片段同时写入 `writer`（文件、套接字等），并原样传给消费者
+    ///
+    /// 流结束时刷新 `writer`。写入失败时记录警告并停止镜像，不影响消费者读取。
+    fn tee_to<W>(self, mut writer: W) -> impl Stream<Item = Result<String>>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        async_stream::stream! {
+            let stream = self;
+            futures::pin_mut!(stream);
+            let mut mirroring = true;
+            while let Some(chunk) = stream.next().await {
+                if let (true, Ok(text)) = (mirroring, &chunk) {
+                    if let Err(e) = writer.write_all(text.as_bytes()).await {
+                        warn!("Failed to mirror stream chunk: {}", e);
+                        mirroring = false;
+                    }
+                }
+                yield chunk;
+            }
+            if mirroring {
+                if let Err(e) = writer.flush().await {
+                    warn!("Failed to flush stream mirror: {}", e);
+                }
+            }
+        }
+    }
+
+    /// 按目标速度（字符/秒）逐字输出，模拟打字效果
+    ///
+    /// 上游数据块会被拆分为更小的片段；上游慢于目标速度时不会在之后突发补齐。
+    /// `chars_per_second` 不为正数时不做限速。
+    fn paced(self, chars_per_second: f64) -> impl Stream<Item = Result<String>> {
+        let interval =
+            (chars_per_second > 0.0).then(|| Duration::from_secs_f64(1.0 / chars_per_second));
+        async_stream::stream! {
+            let stream = self;
+            futures::pin_mut!(stream);
+            let mut next = Instant::now();
+            while let Some(chunk) = stream.next().await {
+                let (Some(interval), Ok(text)) = (interval, &chunk) else {
+                    yield chunk;
+                    continue;
+                };
+                next = next.max(Instant::now());
+                let mut pending = String::new();
+                for c in text.chars() {
+                    runtime::sleep_until(next).await;
+                    pending.push(c);
+                    next += interval;
+                    // 需要等待下一个字符时先输出已积累的片段
+                    if next > Instant::now() {
+                        yield Ok(std::mem::take(&mut pending));
+                    }
+                }
+                if !pending.is_empty() {
+                    yield Ok(pending);
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
+
+impl<S: Stream<Item = Result<String>>> TextStreamExt for S {}
+
+// ================================================================================================
+// SSE 编码
+// ================================================================================================
+
+/// 将单个值编码为 SSE `data` 事件
+pub fn sse_data<T: Serialize>(item: &T) -> Result<Bytes> {
+    Ok(Bytes::from(format!(
+        "data: {}\n\n",
+        serde_json::to_string(item)?
+    )))
+}
+
+/// 将错误编码为 `error` 类型的 SSE 事件，数据为 `{"error": {"message": ...}}`
+pub fn sse_error(e: &NanoError) -> Bytes {
+    let body = serde_json::json!({ "error": { "message": e.to_string() } });
+    Bytes::from(format!("event: error\ndata: {}\n\n", body))
+}
+
+/// 将结果流重新编码为 SSE 字节流，便于直接转发给浏览器
+///
+/// 每个元素序列化为 JSON 后作为一个 `data` 事件发送（文本片段会编码为 JSON 字符串），
+/// 流正常结束时追加 `data: [DONE]`。遇到错误时发送一个 [`sse_error`] 事件并结束，
+/// 因此返回的流本身不会产出 `Err`。
+pub fn encode_sse<S, T>(stream: S) -> impl Stream<Item = Result<Bytes>>
+where
+    S: Stream<Item = Result<T>>,
+    T: Serialize,
+{
+    async_stream::stream! {
+        futures::pin_mut!(stream);
+        while let Some(item) = stream.next().await {
+            match item.and_then(|item| sse_data(&item)) {
+                Ok(event) => yield Ok(event),
+                Err(e) => {
+                    yield Ok(sse_error(&e));
+                    return;
+                }
+            }
+        }
+        yield Ok(Bytes::from(format!("data: {}\n\n", DONE_CHUNK)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_event_serde() {
+        let events = [
+            StreamEvent::Delta("Hi".into()),
+            StreamEvent::Error(NanoError::Timeout),
+            StreamEvent::Done,
+        ];
+        assert_eq!(
+            serde_json::to_value(&events).unwrap(),
+            serde_json::json!([
+                {"type": "delta", "data": "Hi"},
+                {"type": "error", "data": NanoError::Timeout.to_string()},
+                {"type": "done"},
+            ])
+        );
+        let event: StreamEvent =
+            serde_json::from_value(serde_json::json!({"type": "error", "data": "boom"})).unwrap();
+        assert!(matches!(event, StreamEvent::Error(NanoError::StreamError(m)) if m == "boom"));
+    }
+
+    #[test]
+    fn test_partial_json_value_progresses() {
+        let mut partial = PartialJson::new();
+        assert!(partial.value().is_none());
+
+        partial.push(r#"{"title": "Ru"#);
+        assert_eq!(partial.value(), Some(serde_json::json!({"title": "Ru"})));
+
+        partial.push(r#"st", "tags": ["a""#);
+        assert_eq!(
+            partial.value(),
+            Some(serde_json::json!({"title": "Rust", "tags": ["a"]}))
+        );
+    }
+
+    #[test]
+    fn test_partial_json_complete_items() {
+        let mut partial = PartialJson::new();
+        partial.push(r#"{"items": [{"n": 1}, {"n": 2}, {"n""#);
+        assert_eq!(
+            partial.complete_items("/items", false),
+            vec![serde_json::json!({"n": 1}), serde_json::json!({"n": 2})]
+        );
+
+        partial.push(": 3}]}");
+        assert_eq!(partial.complete_items("/items", true).len(), 3);
+        assert!(partial.complete_items("/missing", true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_encode_sse() {
+        let items = futures::stream::iter(vec![Ok("Hel".to_string()), Ok("lo".to_string())]);
+        let events: Vec<Bytes> = encode_sse(items).map(|e| e.unwrap()).collect().await;
+        assert_eq!(
+            events,
+            vec![
+                Bytes::from("data: \"Hel\"\n\n"),
+                Bytes::from("data: \"lo\"\n\n"),
+                Bytes::from("data: [DONE]\n\n"),
+            ]
+        );
+
+        let items = futures::stream::iter(vec![
+            Ok(serde_json::json!({"n": 1})),
+            Err(NanoError::StreamError("boom".into())),
+            Ok(serde_json::json!({"n": 2})),
+        ]);
+        let events: Vec<Bytes> = encode_sse(items).map(|e| e.unwrap()).collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(events[1].starts_with(b"event: error\n"));
+    }
+
+    #[test]
+    fn test_stop_filter_across_chunks() {
+        let mut filter = StopFilter::new(&["END".to_string()]);
+        let mut out = String::new();
+        for chunk in ["Hello E", "N", "Xtra E", "ND tail"] {
+            out.push_str(&filter.push(chunk));
+        }
+        assert!(filter.stopped());
+        assert_eq!(out, "Hello ENXtra ");
+
+        let mut filter = StopFilter::new(&["\n\n".to_string()]);
+        assert_eq!(filter.push("a\n"), "a");
+        assert_eq!(filter.finish(), "\n");
+        assert_eq!(filter.truncate("x\n\ny"), ("x", true));
+    }
+
+    #[tokio::test]
+    async fn test_tee_to() {
+        let chunks = futures::stream::iter(vec![
+            Ok("Hel".to_string()),
+            Ok("lo".to_string()),
+            Err(NanoError::StreamError("boom".into())),
+        ]);
+        let mut transcript = Vec::new();
+        let items: Vec<_> = chunks.tee_to(&mut transcript).collect().await;
+        assert_eq!(items.len(), 3);
+        assert!(items[2].is_err());
+        assert_eq!(transcript, b"Hello");
+    }
+
+    #[tokio::test]
+    async fn test_paced() {
+        let chunks = futures::stream::iter(vec![Ok("abc".to_string()), Ok("de".to_string())]);
+        let started = Instant::now();
+        let pieces: Vec<String> = chunks.paced(200.0).map(|c| c.unwrap()).collect().await;
+        assert_eq!(pieces.concat(), "abcde");
+        assert!(pieces.len() > 2);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        let chunks = futures::stream::iter(vec![Ok("abc".to_string())]);
+        let pieces: Vec<_> = chunks.paced(0.0).collect().await;
+        assert_eq!(pieces.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_multibyte_split_across_chunks() {
+        let body = ["你好", "，世界🌏"]
+            .iter()
+            .map(|text| {
+                let event = serde_json::json!({
+                    "id": "1", "object": "chunk", "created": 0, "model": "m",
+                    "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": null}]
+                });
+                format!("data: {}\n\n", event)
+            })
+            .collect::<String>()
+            .into_bytes();
+
+        for split in 0..=body.len() {
+            let chunks = vec![
+                Ok(Bytes::copy_from_slice(&body[..split])),
+                Ok(Bytes::copy_from_slice(&body[split..])),
+            ];
+            let text: String = StreamWrapper::new()
+                .stream(futures::stream::iter(chunks))
+                .map(|chunk| chunk.unwrap().choices[0].delta.content.clone().unwrap())
+                .collect()
+                .await;
+            assert_eq!(text, "你好，世界🌏", "split at byte {}", split);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_type_dispatch() {
+        let chunk = serde_json::json!({
+            "id": "1", "object": "chunk", "created": 0, "model": "m",
+            "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": null}]
+        });
+        let body = format!(
+            "event: ping\ndata: {{}}\n\nevent: progress\ndata: {{\"step\": 1}}\n\n\
+             data: {chunk}\n\nevent: message\ndata: {chunk}\n\n\
+             event: error\ndata: {{\"error\": {{\"type\": \"overloaded_error\", \"message\": \"Overloaded\"}}}}\n\n"
+        );
+        let bytes = || futures::stream::iter(vec![Ok(Bytes::from(body.clone()))]);
+
+        let items: Vec<_> = StreamWrapper::new()
+            .stream_with(bytes(), |event| parse_chunk_event(event, false))
+            .collect()
+            .await;
+        assert_eq!(items.len(), 3);
+        assert!(items[..2].iter().all(|item| item.is_ok()));
+        match &items[2] {
+            Err(NanoError::Api { code, message, .. }) => {
+                assert_eq!(code.as_deref(), Some("overloaded_error"));
+                assert_eq!(message, "Overloaded");
+            }
+            other => panic!("unexpected {:?}", other.as_ref().map(|_| ())),
+        }
+
+        // 自定义分派：按事件类型取出不同结构的数据
+        let types: Vec<String> = StreamWrapper::new()
+            .stream_with(bytes(), |event| Ok(event.event))
+            .map(|item| item.unwrap())
+            .collect()
+            .await;
+        assert_eq!(types, ["ping", "progress", "message", "error"]);
+    }
+
+    #[tokio::test]
+    async fn test_error_payload_mid_stream() {
+        let chunk = serde_json::json!({
+            "id": "1", "object": "chunk", "created": 0, "model": "m",
+            "choices": [{"index": 0, "delta": {"content": "hi"}, "finish_reason": null}]
+        });
+        let body = format!(
+            "data: {chunk}\n\n\
+             data: {{\"error\": {{\"message\": \"upstream timed out\", \"code\": 504}}}}\n\n"
+        );
+        let items: Vec<_> = StreamWrapper::new()
+            .stream(futures::stream::iter(vec![Ok(Bytes::from(body))]))
+            .collect()
+            .await;
+        assert!(items[0].is_ok());
+        match &items[1] {
+            Err(NanoError::Api {
+                code,
+                message,
+                raw_body,
+                ..
+            }) => {
+                assert_eq!(code.as_deref(), Some("504"));
+                assert_eq!(message, "upstream timed out");
+                assert!(raw_body.contains("upstream timed out"));
+            }
+            other => panic!("unexpected {:?}", other.as_ref().map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,476 @@
+//! 提示词模板模块
+//!
+//! 模板中的 `{name}` 在渲染时替换为同名变量，`{{` 和 `}}` 输出字面的花括号。
+//! 模板在创建时解析，占位符写错（如缺少右花括号）会立即报错，而不是在发出请求时才发现。
+//!
+//! `{> name}` 引入同一个 [`TemplateLibrary`] 中的片段，可以把人设、任务说明和输出格式
+//! 等公共部分拆成片段复用，而不是在每个提示词中复制一遍。
+//!
+//! `Config::with_system_template` 设置的系统消息模板在每次请求时渲染，变量来自
+//! [`VariableProvider`] 和内置的日期时间变量，因此动态的系统消息不需要重新创建客户端。
+use crate::config::Config;
+use crate::error::{NanoError, Result};
+use crate::types::rfc3339;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 模板的组成部分
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Var(String),
+    Include(String),
+}
+
+/// 带 `{name}` 占位符的提示词模板
+///
+/// ```rust
+/// use nanoai::template::PromptTemplate;
+///
+/// let template = PromptTemplate::parse("Translate to {lang}: {text}").unwrap();
+/// let prompt = template.render(&[("lang", "French"), ("text", "hello")]).unwrap();
+/// assert_eq!(prompt, "Translate to French: hello");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    segments: Vec<Segment>,
+}
+
+impl PromptTemplate {
+    /// 解析模板
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.char_indices().peekable();
+        while let Some((pos, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+                '{' => {
+                    let rest = &source[pos + 1..];
+                    let end = rest.find('}').ok_or_else(|| {
+                        NanoError::InvalidRequest(format!("Unclosed placeholder at byte {}", pos))
+                    })?;
+                    let inner = rest[..end].trim();
+                    let (name, include) = match inner.strip_prefix('>') {
+                        Some(name) => (name.trim(), true),
+                        None => (inner, false),
+                    };
+                    if !is_identifier(name) {
+                        return Err(NanoError::InvalidRequest(format!(
+                            "Invalid placeholder '{{{}}}' at byte {}",
+                            &rest[..end],
+                            pos
+                        )));
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(if include {
+                        Segment::Include(name.to_string())
+                    } else {
+                        Segment::Var(name.to_string())
+                    });
+                    while chars.next_if(|&(i, _)| i <= pos + 1 + end).is_some() {}
+                }
+                '}' => {
+                    return Err(NanoError::InvalidRequest(format!(
+                        "Unmatched '}}' at byte {}",
+                        pos
+                    )))
+                }
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+
+    /// 模板中直接出现的变量名，按首次出现的顺序排列，不包括片段中的变量
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Var(name) = segment {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// 模板中引入的片段名，按首次出现的顺序排列
+    pub fn partials(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Include(name) = segment {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// 用给定的变量渲染模板，缺少变量时返回 [`NanoError::InvalidRequest`]
+    pub fn render(&self, vars: &[(&str, &str)]) -> Result<String> {
+        self.render_with(|name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    /// 用 `lookup` 查找变量值渲染模板，`lookup` 返回 `None` 视为缺少变量
+    ///
+    /// 引入片段的模板需要通过 [`TemplateLibrary::render`] 渲染。
+    pub fn render_with(&self, mut lookup: impl FnMut(&str) -> Option<String>) -> Result<String> {
+        let mut out = String::new();
+        self.expand(None, &mut lookup, &mut Vec::new(), &mut out)?;
+        Ok(out)
+    }
+
+    /// 渲染到 `out`，`stack` 为正在展开的片段，用于发现循环引用
+    fn expand(
+        &self,
+        library: Option<&TemplateLibrary>,
+        lookup: &mut dyn FnMut(&str) -> Option<String>,
+        stack: &mut Vec<String>,
+        out: &mut String,
+    ) -> Result<()> {
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Var(name) => {
+                    let value = lookup(name).ok_or_else(|| {
+                        NanoError::InvalidRequest(format!("Missing template variable '{}'", name))
+                    })?;
+                    out.push_str(&value);
+                }
+                Segment::Include(name) => {
+                    if stack.contains(name) {
+                        return Err(NanoError::InvalidRequest(format!(
+                            "Partial '{}' includes itself via {}",
+                            name,
+                            stack.join(" > ")
+                        )));
+                    }
+                    let partial = library
+                        .and_then(|library| library.templates.get(name))
+                        .ok_or_else(|| {
+                            NanoError::InvalidRequest(format!("Unknown partial '{}'", name))
+                        })?;
+                    stack.push(name.clone());
+                    partial.expand(library, lookup, stack, out)?;
+                    stack.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 可以互相引入的一组命名模板，以及它们共享的变量
+///
+/// 渲染时变量按以下顺序查找：调用时传入的变量、库的共享变量。片段与引入它的模板
+/// 使用同一个作用域，因此片段中的 `{lang}` 也可以由调用方提供。
+///
+/// ```rust
+/// use nanoai::template::TemplateLibrary;
+///
+/// let mut library = TemplateLibrary::new().with_var("persona", "a terse assistant");
+/// library.add("system", "You are {persona}.").unwrap();
+/// library.add("json", "Reply with JSON only.").unwrap();
+/// library.add("extract", "{> system}\nExtract {field} from the text.\n{> json}").unwrap();
+///
+/// let prompt = library.render("extract", &[("field", "dates")]).unwrap();
+/// assert_eq!(
+///     prompt,
+///     "You are a terse assistant.\nExtract dates from the text.\nReply with JSON only."
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TemplateLibrary {
+    templates: HashMap<String, PromptTemplate>,
+    shared: HashMap<String, String>,
+}
+
+impl TemplateLibrary {
+    /// 创建空的模板库
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加共享变量
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_var(name, value);
+        self
+    }
+
+    /// 设置共享变量，已有的同名变量会被替换
+    pub fn set_var(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.shared.insert(name.into(), value.into());
+    }
+
+    /// 解析并添加模板，同名模板会被替换
+    ///
+    /// 引入的片段在渲染时才查找，因此添加的顺序无关紧要。
+    pub fn add(&mut self, name: &str, source: &str) -> Result<()> {
+        self.insert(name, PromptTemplate::parse(source)?);
+        Ok(())
+    }
+
+    /// 添加已解析的模板，同名模板会被替换
+    pub fn insert(&mut self, name: &str, template: PromptTemplate) {
+        self.templates.insert(name.to_string(), template);
+    }
+
+    /// 获取模板
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    /// 渲染名为 `name` 的模板，`vars` 覆盖同名的共享变量
+    pub fn render(&self, name: &str, vars: &[(&str, &str)]) -> Result<String> {
+        let template = self
+            .get(name)
+            .ok_or_else(|| NanoError::InvalidRequest(format!("Unknown template '{}'", name)))?;
+        self.expand(template, vars, vec![name.to_string()])
+    }
+
+    /// 用库中的片段和共享变量渲染不在库中的模板
+    pub fn render_template(
+        &self,
+        template: &PromptTemplate,
+        vars: &[(&str, &str)],
+    ) -> Result<String> {
+        self.expand(template, vars, Vec::new())
+    }
+
+    fn expand(
+        &self,
+        template: &PromptTemplate,
+        vars: &[(&str, &str)],
+        mut stack: Vec<String>,
+    ) -> Result<String> {
+        let mut lookup = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+                .or_else(|| self.shared.get(name).cloned())
+        };
+        let mut out = String::new();
+        template.expand(Some(self), &mut lookup, &mut stack, &mut out)?;
+        Ok(out)
+    }
+}
+
+// ================================================================================================
+// 运行时变量
+// ================================================================================================
+
+/// 在请求时提供模板变量的值
+///
+/// ```rust
+/// use nanoai::config::Config;
+/// use nanoai::template::VariableProvider;
+///
+/// #[derive(Debug)]
+/// struct CurrentUser;
+///
+/// impl VariableProvider for CurrentUser {
+///     fn get(&self, name: &str) -> Option<String> {
+///         (name == "user").then(|| "Ada".to_string())
+///     }
+/// }
+///
+/// let config = Config::default()
+///     .with_system_template("You are {persona}, talking to {user}. Today is {date}.")
+///     .with_template_var("persona", "a patient tutor")
+///     .with_variable_provider(CurrentUser);
+/// ```
+pub trait VariableProvider: fmt::Debug + Send + Sync {
+    /// 返回变量的当前值，不认识的变量返回 `None`
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+impl VariableProvider for HashMap<String, String> {
+    fn get(&self, name: &str) -> Option<String> {
+        HashMap::get(self, name).cloned()
+    }
+}
+
+/// 内置变量，均为 UTC 时间
+///
+/// | 变量 | 示例 |
+/// | --- | --- |
+/// | `date` | `2024-05-01` |
+/// | `time` | `08:30` |
+/// | `datetime` | `2024-05-01T08:30:00.000Z` |
+/// | `weekday` | `Wednesday` |
+/// | `unix` | `1714552200` |
+pub fn builtin_variable(name: &str) -> Option<String> {
+    const WEEKDAYS: [&str; 7] = [
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+    ];
+    let now = SystemTime::now();
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let datetime = rfc3339::format(now);
+    Some(match name {
+        "date" => datetime[..10].to_string(),
+        "time" => datetime[11..16].to_string(),
+        "datetime" => datetime,
+        // 1970-01-01 是星期四
+        "weekday" => WEEKDAYS[(secs / 86_400 % 7) as usize].to_string(),
+        "unix" => secs.to_string(),
+        _ => return None,
+    })
+}
+
+/// 请求使用的系统消息：设置了系统消息模板时按当前变量渲染，否则为固定的系统消息
+///
+/// 变量依次从 `Config::with_template_var` 设置的静态变量、变量提供者（按添加顺序）
+/// 和内置变量中查找，模板无法解析或缺少变量时返回 [`NanoError::InvalidRequest`]。
+pub(crate) fn system_message(config: &Config) -> Result<Cow<'_, str>> {
+    let Some(source) = &config.system_template else {
+        return Ok(Cow::Borrowed(&config.system_message));
+    };
+    PromptTemplate::parse(source)?
+        .render_with(|name| {
+            config
+                .template_vars
+                .get(name)
+                .cloned()
+                .or_else(|| config.variable_providers.iter().find_map(|p| p.get(name)))
+                .or_else(|| builtin_variable(name))
+        })
+        .map(Cow::Owned)
+}
+
+/// 变量名只允许字母、数字、下划线、`-` 和 `.`
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::LLMClient;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_render() {
+        let template = PromptTemplate::parse("{greeting}, { name }! {{literal}} {name}").unwrap();
+        assert_eq!(template.variables(), ["greeting", "name"]);
+        let out = template
+            .render(&[("greeting", "Hi"), ("name", "Ada")])
+            .unwrap();
+        assert_eq!(out, "Hi, Ada! {literal} Ada");
+
+        let err = template.render(&[("greeting", "Hi")]).unwrap_err();
+        assert!(err.to_string().contains("'name'"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(PromptTemplate::parse("{open").is_err());
+        assert!(PromptTemplate::parse("close}").is_err());
+        assert!(PromptTemplate::parse("{two words}").is_err());
+        assert!(PromptTemplate::parse("中文 {变量}").is_ok());
+        assert!(PromptTemplate::parse("{>}").is_err());
+    }
+
+    #[test]
+    fn test_library_partials_and_scopes() {
+        let mut library = TemplateLibrary::new().with_var("lang", "English");
+        library.add("format", "Answer in {lang}.").unwrap();
+        library
+            .add("task", "{> persona} Summarize {text}. {>format}")
+            .unwrap();
+        library.add("persona", "You are {name}.").unwrap();
+
+        let template = library.get("task").unwrap();
+        assert_eq!(template.variables(), ["text"]);
+        assert_eq!(template.partials(), ["persona", "format"]);
+
+        let out = library
+            .render("task", &[("name", "Ada"), ("text", "this")])
+            .unwrap();
+        assert_eq!(out, "You are Ada. Summarize this. Answer in English.");
+        let out = library
+            .render(
+                "task",
+                &[("name", "Ada"), ("text", "this"), ("lang", "French")],
+            )
+            .unwrap();
+        assert!(out.ends_with("Answer in French."));
+
+        assert!(template.render(&[("text", "x")]).is_err());
+        assert!(library.render("missing", &[]).is_err());
+        library.add("persona", "{> task}").unwrap();
+        let err = library.render("task", &[("text", "x")]).unwrap_err();
+        assert!(err.to_string().contains("includes itself"), "{}", err);
+    }
+
+    #[derive(Debug, Default)]
+    struct Counter(AtomicUsize);
+
+    impl VariableProvider for Counter {
+        fn get(&self, name: &str) -> Option<String> {
+            (name == "turn").then(|| (self.0.fetch_add(1, Ordering::SeqCst) + 1).to_string())
+        }
+    }
+
+    #[test]
+    fn test_builtin_variables() {
+        let date = builtin_variable("date").unwrap();
+        assert_eq!(date.len(), 10);
+        assert!(builtin_variable("datetime").unwrap().starts_with(&date));
+        assert_eq!(builtin_variable("time").unwrap().len(), 5);
+        assert!(builtin_variable("weekday").unwrap().ends_with("day"));
+        assert!(builtin_variable("persona").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_system_template() {
+        let server = MockServer::start(vec![
+            MockResponse::json(completion_body("one")),
+            MockResponse::json(completion_body("two")),
+        ])
+        .await;
+        let config = Config::default()
+            .with_api_base(&server.base)
+            .with_system_template("You are {persona}. Turn {turn}, {date}.")
+            .with_template_var("persona", "a pirate")
+            .with_variable_provider(Counter::default());
+        let client = LLMClient::new(config);
+        client.generate("hi").await.unwrap();
+        client.generate("hi").await.unwrap();
+
+        let date = builtin_variable("date").unwrap();
+        let requests = server.requests();
+        for (i, request) in requests.iter().enumerate() {
+            let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            let expected = format!("You are a pirate. Turn {}, {}.", i + 1, date);
+            assert_eq!(body["messages"][0]["content"], expected.as_str());
+        }
+
+        let client = LLMClient::new(Config::default().with_system_template("Hi {nobody}"));
+        let err = client.generate("hi").await.unwrap_err();
+        assert!(matches!(err, NanoError::InvalidRequest(_)));
+    }
+}
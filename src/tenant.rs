@@ -0,0 +1,190 @@
+//! 多租户调度模块
+//!
+//! 通过 [`LLMClient::for_tenant`] 为每个租户创建子句柄。子句柄与父客户端共享连接池和
+//! 全局并发上限，同时拥有独立的并发配额、请求速率限制和用量统计，避免单个租户占满所有
+//! 请求槽位或上游的速率额度。
+use crate::{
+    budget::{UsageSnapshot, UsageTracker},
+    client::LLMClient,
+    runtime,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// 按固定间隔放行请求的限速器
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// 每分钟放行 `per_minute` 个请求
+    pub(crate) fn per_minute(per_minute: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / per_minute.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 等待下一个放行时刻
+    pub(crate) async fn acquire(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        runtime::sleep_until(slot).await;
+    }
+}
+
+/// 单个租户的调度状态
+#[derive(Debug)]
+pub(crate) struct Tenant {
+    /// 租户名称
+    pub(crate) name: String,
+    /// 租户并发配额
+    pub(crate) semaphore: Semaphore,
+    /// 租户请求速率限制
+    pub(crate) rate: Option<RateLimiter>,
+    /// 租户累计用量
+    pub(crate) usage: UsageTracker,
+}
+
+/// 租户注册表，由父客户端及其所有子句柄共享
+pub(crate) type TenantRegistry = Arc<Mutex<HashMap<String, Arc<Tenant>>>>;
+
+impl LLMClient {
+    /// 获取指定租户的子句柄
+    ///
+    /// 同名租户共享同一份并发配额、速率限制和用量统计。每个租户的并发上限由
+    /// `Config::with_tenant_max_concurrent_requests` 配置，默认与全局上限相同；
+    /// 每分钟请求数由 `Config::with_tenant_rate_limit` 配置，默认不限速。
+    pub fn for_tenant(&self, name: &str) -> LLMClient {
+        let limit = self
+            .config
+            .tenant_max_concurrent_requests
+            .or(self.config.max_concurrent_requests)
+            .unwrap_or(64);
+        let tenant = self
+            .tenants
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                Arc::new(Tenant {
+                    name: name.to_string(),
+                    semaphore: Semaphore::new(limit),
+                    rate: self.config.tenant_rate_limit.map(RateLimiter::per_minute),
+                    usage: UsageTracker::default(),
+                })
+            })
+            .clone();
+
+        LLMClient {
+            tenant: Some(tenant),
+            ..self.clone()
+        }
+    }
+
+    /// 当前句柄所属的租户名称
+    pub fn tenant_name(&self) -> Option<&str> {
+        self.tenant.as_ref().map(|t| t.name.as_str())
+    }
+
+    /// 各租户的累计用量
+    pub fn tenant_usage(&self) -> HashMap<String, UsageSnapshot> {
+        self.tenants
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(name, tenant)| (name.clone(), tenant.usage.snapshot()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{completion_body, sse_response, MockResponse, MockServer};
+    use crate::{client::LLMClient, config::Config};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_for_tenant_shares_state_by_name() {
+        let client = LLMClient::new(Config::default().with_tenant_max_concurrent_requests(2));
+        let a1 = client.for_tenant("team-a");
+        let a2 = client.for_tenant("team-a");
+        let b = a1.for_tenant("team-b");
+
+        assert_eq!(a1.tenant_name(), Some("team-a"));
+        assert_eq!(b.tenant_name(), Some("team-b"));
+        assert!(client.tenant_name().is_none());
+
+        a1.record_usage(10, 5);
+        a2.record_usage(1, 1);
+        let usage = client.tenant_usage();
+        assert_eq!(usage["team-a"].requests, 2);
+        assert_eq!(usage["team-a"].total_tokens(), 17);
+        assert_eq!(usage["team-b"].requests, 0);
+        assert_eq!(client.usage().requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_concurrency_limit() {
+        let server = MockServer::start(vec![
+            sse_response(&["a"]),
+            MockResponse::json(completion_body("b")),
+            MockResponse::json(completion_body("a")),
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_tenant_max_concurrent_requests(1),
+        );
+        let a = client.for_tenant("team-a");
+
+        // 未读完的流占用 team-a 唯一的配额
+        let stream = a.stream_generate("hi").await.unwrap();
+        let blocked = tokio::time::timeout(Duration::from_millis(100), a.generate("hi")).await;
+        assert!(blocked.is_err());
+        assert_eq!(
+            client.for_tenant("team-b").generate("hi").await.unwrap(),
+            "b"
+        );
+
+        drop(stream);
+        assert_eq!(a.generate("hi").await.unwrap(), "a");
+        let usage = client.tenant_usage();
+        assert_eq!(usage["team-a"].requests, 2);
+        assert_eq!(usage["team-b"].requests, 1);
+        assert_eq!(usage["team-b"].total_tokens(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_rate_limit() {
+        let server = MockServer::start(vec![MockResponse::json(completion_body("ok"))]).await;
+        // 每 50ms 放行一个请求
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&server.base)
+                .with_tenant_rate_limit(1200),
+        );
+        let a = client.for_tenant("team-a");
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            a.generate("hi").await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        // 其他租户和未指定租户的请求不受 team-a 限速影响
+        let started = Instant::now();
+        client.for_tenant("team-b").generate("hi").await.unwrap();
+        client.generate("hi").await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+}
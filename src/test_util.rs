@@ -0,0 +1,187 @@
+//! 测试辅助工具：按顺序返回预设响应并记录收到的请求的本地 HTTP 服务
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// 预设响应
+#[derive(Debug, Clone)]
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn json(body: serde_json::Value) -> Self {
+        Self::new(200, &body.to_string()).with_header("Content-Type", "application/json")
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// 收到的请求
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedRequest {
+    pub path: String,
+    /// 标头名称统一为小写
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+/// 本地模拟服务，依次返回预设响应，用完后重复最后一个
+#[derive(Debug)]
+pub(crate) struct MockServer {
+    pub base: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((socket, _)) = listener.accept().await {
+                respond(socket, &responses, &mut served, &recorded).await;
+            }
+        });
+        Self { base, requests }
+    }
+
+    /// 在 Unix 域套接字 `path` 上启动服务，`base` 为 `unix://{path}`
+    #[cfg(unix)]
+    pub async fn start_unix(path: &std::path::Path, responses: Vec<MockResponse>) -> Self {
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+        let base = format!("unix://{}", path.display());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((socket, _)) = listener.accept().await {
+                respond(socket, &responses, &mut served, &recorded).await;
+            }
+        });
+        Self { base, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn respond<S: AsyncRead + AsyncWrite + Unpin>(
+    mut socket: S,
+    responses: &[MockResponse],
+    served: &mut usize,
+    recorded: &Mutex<Vec<RecordedRequest>>,
+) {
+    let Some(request) = read_request(&mut socket).await else {
+        return;
+    };
+    recorded.lock().unwrap().push(request);
+    let response = &responses[(*served).min(responses.len() - 1)];
+    *served += 1;
+
+    let mut head = format!(
+        "HTTP/1.1 {} MOCK\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let _ = socket.write_all(head.as_bytes()).await;
+    let _ = socket.write_all(response.body.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
+
+async fn read_request<S: AsyncRead + Unpin>(socket: &mut S) -> Option<RecordedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let path = lines.next()?.split_whitespace().nth(1)?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let body = String::from_utf8_lossy(&buf[header_end..]).to_string();
+    Some(RecordedRequest {
+        path,
+        headers,
+        body,
+    })
+}
+
+/// 最小的 `/chat/completions` 响应体
+pub(crate) fn completion_body(content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 0,
+        "model": "mock",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+    })
+}
+
+/// 由文本片段组成的 `/chat/completions` SSE 响应
+pub(crate) fn sse_response(chunks: &[&str]) -> MockResponse {
+    let mut body = String::new();
+    for chunk in chunks {
+        let event = serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "mock",
+            "choices": [{"index": 0, "delta": {"content": chunk}, "finish_reason": null}]
+        });
+        body.push_str(&format!("data: {}\n\n", event));
+    }
+    body.push_str("data: [DONE]\n\n");
+    MockResponse::new(200, &body).with_header("Content-Type", "text/event-stream")
+}
@@ -0,0 +1,95 @@
+//! Token 计数模块
+//!
+//! 启用 `tokenizer` feature 时使用 tiktoken 对文本进行精确计数；
+//! 未启用时退化为基于字符数的估算（CJK 字符按 1 个 token，其它字符按 4 个字符 1 个 token）。
+use crate::types::Message;
+
+/// 每条消息的固定开销（角色、分隔符等）
+const TOKENS_PER_MESSAGE: usize = 3;
+/// 每次请求为助手回复预留的开销
+const TOKENS_PER_REPLY: usize = 3;
+
+/// 计算文本在指定模型下的 token 数量
+///
+/// `model` 可以带有 OpenRouter 风格的供应商前缀（如 `openai/gpt-4o`），
+/// 无法识别的模型按 `cl100k_base` 编码计数。
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    imp::count(model, text)
+}
+
+/// 计算消息列表在指定模型下的 token 数量，包括每条消息的格式开销
+pub fn count_message_tokens(model: &str, messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .map(|m| TOKENS_PER_MESSAGE + count_tokens(model, &m.content))
+        .sum::<usize>()
+        + TOKENS_PER_REPLY
+}
+
+#[cfg(feature = "tokenizer")]
+mod imp {
+    use tiktoken_rs::{
+        cl100k_base_singleton, o200k_base_singleton, tokenizer::get_tokenizer,
+        tokenizer::Tokenizer, CoreBPE,
+    };
+
+    fn bpe_for(model: &str) -> &'static CoreBPE {
+        let name = model.rsplit('/').next().unwrap_or(model);
+        match get_tokenizer(name) {
+            Some(Tokenizer::O200kBase) => o200k_base_singleton(),
+            _ => cl100k_base_singleton(),
+        }
+    }
+
+    pub(super) fn count(model: &str, text: &str) -> usize {
+        bpe_for(model).encode_with_special_tokens(text).len()
+    }
+}
+
+#[cfg(not(feature = "tokenizer"))]
+mod imp {
+    fn is_cjk(c: char) -> bool {
+        matches!(c,
+            '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{ac00}'..='\u{d7af}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{ff00}'..='\u{ffef}')
+    }
+
+    pub(super) fn count(_model: &str, text: &str) -> usize {
+        let (cjk, other) = text.chars().fold((0usize, 0usize), |(cjk, other), c| {
+            if is_cjk(c) {
+                (cjk + 1, other)
+            } else {
+                (cjk, other + 1)
+            }
+        });
+        cjk + other.div_ceil(4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Role;
+    use crate::utils::message;
+
+    #[test]
+    fn test_count_tokens_non_empty() {
+        assert_eq!(count_tokens("gpt-4o", ""), 0);
+        assert!(count_tokens("gpt-4o", "Hello, world!") > 0);
+        assert!(count_tokens("openai/gpt-4o", "你好，世界") >= 2);
+    }
+
+    #[test]
+    fn test_count_message_tokens_includes_overhead() {
+        let messages = vec![message(Role::User, "Hello")];
+        let content = count_tokens("gpt-4o", "Hello");
+        assert_eq!(
+            count_message_tokens("gpt-4o", &messages),
+            content + TOKENS_PER_MESSAGE + TOKENS_PER_REPLY
+        );
+    }
+}
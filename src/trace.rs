@@ -0,0 +1,90 @@
+//! 链路追踪模块
+//!
+//! 为发出的请求添加关联标头：可配置的请求 ID 标头，以及在启用 `otel` feature 时，
+//! 从当前 `tracing` span 提取的 W3C Trace Context（`traceparent`/`tracestate`），
+//! 使 LLM 调用可以与分布式链路中的其他调用关联。
+use crate::{
+    config::Config,
+    error::{NanoError, Result},
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// 为一次逻辑请求添加追踪标头，内部重试复用同一组标头
+pub(crate) fn inject(config: &Config, headers: &mut HeaderMap) -> Result<()> {
+    if let Some(name) = &config.request_id_header {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| NanoError::Config(format!("Invalid request id header: {}", e)))?;
+        if !headers.contains_key(&name) {
+            let id = format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..));
+            let value = HeaderValue::from_str(&id)
+                .map_err(|e| NanoError::Config(format!("Invalid request id: {}", e)))?;
+            headers.insert(name, value);
+        }
+    }
+
+    #[cfg(feature = "otel")]
+    if config.trace_propagation {
+        otel::inject_current_span(headers);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::{SpanContext, TraceContextExt};
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    /// 从当前 span 注入 `traceparent` 和 `tracestate`，当前 span 无有效上下文时不做任何修改
+    pub(super) fn inject_current_span(headers: &mut HeaderMap) {
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        let Some(traceparent) = traceparent(span_context) else {
+            return;
+        };
+        if let Ok(value) = HeaderValue::from_str(&traceparent) {
+            headers.insert("traceparent", value);
+        }
+        let tracestate = span_context.trace_state().header();
+        if !tracestate.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&tracestate) {
+                headers.insert("tracestate", value);
+            }
+        }
+    }
+
+    /// 按 W3C Trace Context 格式生成 `traceparent`
+    pub(super) fn traceparent(span_context: &SpanContext) -> Option<String> {
+        span_context.is_valid().then(|| {
+            format!(
+                "00-{}-{}-{:02x}",
+                span_context.trace_id(),
+                span_context.span_id(),
+                span_context.trace_flags().to_u8()
+            )
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use opentelemetry::trace::{SpanId, TraceFlags, TraceId, TraceState};
+
+        #[test]
+        fn test_traceparent_format() {
+            let span_context = SpanContext::new(
+                TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+                SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+                TraceFlags::SAMPLED,
+                true,
+                TraceState::default(),
+            );
+            assert_eq!(
+                traceparent(&span_context).as_deref(),
+                Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            );
+            assert!(traceparent(&SpanContext::empty_context()).is_none());
+        }
+    }
+}
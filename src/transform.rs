@@ -0,0 +1,129 @@
+//! 内容转换模块
+//!
+//! 在客户端统一改写发出的提示词和收到的输出，例如去除邮箱、身份证号等敏感信息，
+//! 而不必在每个调用点单独处理。通过 `Config::with_prompt_transform` 和
+//! `Config::with_response_transform` 配置，按添加顺序依次执行。
+use crate::error::{NanoError, Result};
+use crate::types::Message;
+use regex::Regex;
+use std::fmt;
+use std::sync::Arc;
+
+// ================================================================================================
+// 转换接口
+// ================================================================================================
+
+/// 内容转换器
+pub trait ContentTransform: fmt::Debug + Send + Sync {
+    /// 返回转换后的文本
+    fn transform(&self, text: &str) -> String;
+}
+
+/// 依次执行所有转换器
+pub(crate) fn apply(transforms: &[Arc<dyn ContentTransform>], text: &str) -> String {
+    transforms
+        .iter()
+        .fold(text.to_string(), |text, t| t.transform(&text))
+}
+
+/// 对系统消息和所有消息内容执行转换，未配置转换器时返回 `None`
+pub(crate) fn apply_messages(
+    transforms: &[Arc<dyn ContentTransform>],
+    system_message: &str,
+    messages: &[Message],
+) -> Option<(String, Vec<Message>)> {
+    if transforms.is_empty() {
+        return None;
+    }
+    let messages = messages
+        .iter()
+        .map(|m| Message {
+            content: apply(transforms, &m.content),
+            ..m.clone()
+        })
+        .collect();
+    Some((apply(transforms, system_message), messages))
+}
+
+// ================================================================================================
+// 内置转换器
+// ================================================================================================
+
+/// 基于正则表达式的替换
+#[derive(Debug, Clone)]
+pub struct RegexReplace {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl RegexReplace {
+    /// 将匹配 `pattern` 的内容替换为 `replacement`，支持 `$1` 形式的捕获组引用
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| NanoError::Config(format!("Invalid transform pattern: {}", e)))?;
+        Ok(Self {
+            pattern,
+            replacement: replacement.to_string(),
+        })
+    }
+
+    /// 将邮箱地址替换为 `[EMAIL]`
+    pub fn emails() -> Self {
+        Self::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[EMAIL]")
+            .expect("valid email pattern")
+    }
+
+    /// 将美国社会安全号（`123-45-6789`）替换为 `[SSN]`
+    pub fn ssns() -> Self {
+        Self::new(r"\b\d{3}-\d{2}-\d{4}\b", "[SSN]").expect("valid SSN pattern")
+    }
+}
+
+impl ContentTransform for RegexReplace {
+    fn transform(&self, text: &str) -> String {
+        self.pattern
+            .replace_all(text, self.replacement.as_str())
+            .into_owned()
+    }
+}
+
+/// 使用闭包的自定义转换器
+#[derive(Clone)]
+pub struct FnTransform(Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl FnTransform {
+    /// 使用 `f` 转换文本
+    pub fn new(f: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for FnTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FnTransform")
+    }
+}
+
+impl ContentTransform for FnTransform {
+    fn transform(&self, text: &str) -> String {
+        (self.0)(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_in_order() {
+        let transforms: Vec<Arc<dyn ContentTransform>> = vec![
+            Arc::new(RegexReplace::emails()),
+            Arc::new(RegexReplace::ssns()),
+            Arc::new(FnTransform::new(|s| s.trim().to_string())),
+        ];
+        let text = apply(&transforms, "  mail a.b@example.com, ssn 123-45-6789 ");
+        assert_eq!(text, "mail [EMAIL], ssn [SSN]");
+        assert!(apply_messages(&[], "sys", &[]).is_none());
+        assert!(RegexReplace::new("(", "").is_err());
+    }
+}
@@ -0,0 +1,825 @@
+//! 传输层模块
+//!
+//! 默认通过 HTTP 发送请求。其他传输方式将请求转换后发送，再将结果转换为等价的 HTTP
+//! 响应，因此重试、错误处理和流式解析无需区分传输方式。
+//!
+//! 启用 `websocket` 特性后可改用 WebSocket：请求和响应的 JSON 结构与 HTTP 完全相同，
+//! 只是承载在 WebSocket 帧中，适用于提供 WebSocket 聊天端点的网关。只有
+//! `/chat/completions` 请求经由 WebSocket 发送，其他端点（向量、音频、模型列表等）
+//! 返回 `NanoError::InvalidRequest`。协议约定：
+//!
+//! - 每个请求建立一个连接，连接地址为 HTTP 端点对应的 `ws://` 或 `wss://` 地址，
+//!   认证等标头在握手时发送
+//! - 客户端发送一个文本帧，内容为请求体
+//! - 非流式请求：服务端回复一个帧，内容为响应体
+//! - 流式请求：服务端每个数据块回复一个帧，以 `[DONE]` 帧或关闭连接结束
+//! - 服务端以 `{"error": {...}}` 帧报告错误，可附带 `status` 字段指明对应的 HTTP 状态码，
+//!   缺省为 500
+use serde::{Deserialize, Serialize};
+
+/// 请求使用的传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    /// HTTP
+    #[default]
+    Http,
+    /// WebSocket，需启用 `websocket` 特性
+    #[cfg(feature = "websocket")]
+    WebSocket,
+    /// gRPC，需启用 `grpc` 特性，见 [`grpc`]
+    #[cfg(feature = "grpc")]
+    Grpc,
+}
+
+impl Transport {
+    /// 传输方式能否承载发往 `path` 的请求，WebSocket 和 gRPC 只承载 `/chat/completions`
+    pub(crate) fn supports(self, path: &str) -> bool {
+        self == Transport::Http || path.ends_with("/chat/completions")
+    }
+}
+
+#[cfg(feature = "websocket")]
+pub(crate) use websocket::execute as execute_websocket;
+
+/// 构造等价的 HTTP 响应
+#[cfg(any(feature = "websocket", feature = "grpc"))]
+fn response(
+    status: reqwest::StatusCode,
+    content_type: &'static str,
+    body: reqwest::Body,
+) -> reqwest::Response {
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        reqwest::header::CONTENT_TYPE,
+        reqwest::header::HeaderValue::from_static(content_type),
+    );
+    reqwest::Response::from(response)
+}
+
+/// 构造 OpenAI 风格的错误响应
+#[cfg(any(feature = "websocket", feature = "grpc"))]
+fn error_response(
+    status: reqwest::StatusCode,
+    message: &str,
+    code: Option<&str>,
+) -> reqwest::Response {
+    let body = serde_json::json!({"error": {"message": message, "code": code}}).to_string();
+    response(status, "application/json", reqwest::Body::from(body))
+}
+
+#[cfg(feature = "websocket")]
+mod websocket {
+    use super::{error_response, response};
+    use bytes::Bytes;
+    use futures::{stream, SinkExt, StreamExt};
+    use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use reqwest::{Body, Request, Response, StatusCode};
+    use serde_json::Value;
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::{Error, Message};
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+    type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// 通过 WebSocket 发送请求，并将结果转换为等价的 HTTP 响应
+    ///
+    /// 连接失败时返回 502 响应，`timeout` 内未收到首个响应帧时返回 504 响应，
+    /// 与 HTTP 网关的行为一致，因此重试和错误处理无需区分传输方式。
+    pub(crate) async fn execute(request: Request, timeout: Duration) -> Response {
+        // 握手的 future 嵌套很深，装箱后调用方的 future 不会超出编译器的递归深度限制
+        match crate::runtime::timeout(timeout, Box::pin(send(request))).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => error_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("WebSocket request failed: {}", e),
+                None,
+            ),
+            Err(_) => error_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                "WebSocket request timed out",
+                None,
+            ),
+        }
+    }
+
+    async fn send(request: Request) -> Result<Response, Error> {
+        let mut url = request.url().clone();
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        // http(s) 与 ws(s) 之间的转换总是成功
+        let _ = url.set_scheme(scheme);
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default()
+            .to_vec();
+        let streaming = serde_json::from_slice::<Value>(&body)
+            .ok()
+            .and_then(|body| body.get("stream")?.as_bool())
+            .unwrap_or(false);
+
+        let mut handshake = url.as_str().into_client_request()?;
+        for (name, value) in request.headers() {
+            if name != CONTENT_TYPE && name != CONTENT_LENGTH {
+                handshake.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+        let (mut socket, _) = tokio_tungstenite::connect_async(handshake).await?;
+        socket
+            .send(Message::text(String::from_utf8_lossy(&body).into_owned()))
+            .await?;
+
+        let first = next_frame(&mut socket)
+            .await?
+            .ok_or(Error::ConnectionClosed)?;
+        if let Some(status) = error_status(&first) {
+            let _ = socket.close(None).await;
+            return Ok(response(status, "application/json", Body::from(first)));
+        }
+        if !streaming {
+            let _ = socket.close(None).await;
+            return Ok(response(
+                StatusCode::OK,
+                "application/json",
+                Body::from(first),
+            ));
+        }
+
+        // 将帧转换为 SSE 事件，复用 HTTP 流式响应的解析逻辑
+        let frames = stream::unfold((Some(first), Some(socket)), |(first, socket)| async move {
+            let mut socket = socket?;
+            let frame = match first {
+                Some(frame) => Ok(Some(frame)),
+                None => next_frame(&mut socket).await,
+            };
+            match frame {
+                Ok(Some(frame)) if frame.as_ref() != b"[DONE]" => {
+                    let event = [b"data: ".as_slice(), &frame, b"\n\n"].concat();
+                    Some((Ok(Bytes::from(event)), (None, Some(socket))))
+                }
+                Ok(_) => {
+                    let _ = socket.close(None).await;
+                    None
+                }
+                Err(e) => Some((Err(e), (None, None))),
+            }
+        });
+        Ok(response(
+            StatusCode::OK,
+            "text/event-stream",
+            Body::wrap_stream(frames),
+        ))
+    }
+
+    /// 读取下一个数据帧，连接关闭时返回 `None`
+    async fn next_frame(socket: &mut Socket) -> Result<Option<Bytes>, Error> {
+        while let Some(message) = socket.next().await {
+            match message? {
+                Message::Text(text) => return Ok(Some(text.into())),
+                Message::Binary(data) => return Ok(Some(data)),
+                Message::Close(_) => return Ok(None),
+                // Ping 由 tungstenite 自动回复
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// 帧为 `{"error": ...}` 时返回对应的状态码
+    fn error_status(frame: &[u8]) -> Option<StatusCode> {
+        let value = serde_json::from_slice::<Value>(frame).ok()?;
+        value.get("error").filter(|error| !error.is_null())?;
+        let status = value
+            .get("status")
+            .and_then(Value::as_u64)
+            .and_then(|status| StatusCode::from_u16(status as u16).ok())
+            .filter(|status| !status.is_success());
+        Some(status.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+    }
+}
+
+/// gRPC 传输
+///
+/// 将 `/chat/completions` 请求映射为 `proto/nanoai.proto` 中定义的 `nanoai.v1.Generation`
+/// 服务调用，适用于内部统一使用 gRPC 的自托管推理服务。消息类型公开，便于用 Rust
+/// 实现该服务。其他端点（向量、音频、模型列表、Responses API 等）没有对应的服务方法，
+/// 请求时返回 `NanoError::InvalidRequest`。
+///
+/// 每个服务地址复用同一个 HTTP/2 连接。目前只支持明文连接（h2c）。gRPC 状态码按
+/// 语义转换为 HTTP 状态码，例如 `RESOURCE_EXHAUSTED` 转换为 429、`UNAVAILABLE` 转换为
+/// 503，因此同样会触发重试。
+#[cfg(feature = "grpc")]
+pub mod grpc {
+    use super::{error_response, response};
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use reqwest::header::{HeaderMap, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, HOST, USER_AGENT};
+    use reqwest::{Body, StatusCode};
+    use serde_json::{json, Map, Value};
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::{LazyLock, Mutex};
+    use std::time::Duration;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::metadata::MetadataMap;
+    use tonic::transport::{Channel, Endpoint};
+    use tonic::{Code, Status};
+    use tonic_prost::ProstCodec;
+
+    /// `Generate` 方法路径
+    pub const GENERATE_PATH: &str = "/nanoai.v1.Generation/Generate";
+    /// `GenerateStream` 方法路径
+    pub const GENERATE_STREAM_PATH: &str = "/nanoai.v1.Generation/GenerateStream";
+
+    /// 对话消息
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ChatMessage {
+        /// 角色
+        #[prost(string, tag = "1")]
+        pub role: String,
+        /// 文本内容
+        #[prost(string, tag = "2")]
+        pub content: String,
+    }
+
+    /// 生成请求
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerateRequest {
+        /// 模型名称
+        #[prost(string, tag = "1")]
+        pub model: String,
+        /// 对话消息
+        #[prost(message, repeated, tag = "2")]
+        pub messages: Vec<ChatMessage>,
+        /// 采样温度
+        #[prost(float, optional, tag = "3")]
+        pub temperature: Option<f32>,
+        /// 核采样概率
+        #[prost(float, optional, tag = "4")]
+        pub top_p: Option<f32>,
+        /// 最大生成 token 数
+        #[prost(uint32, optional, tag = "5")]
+        pub max_tokens: Option<u32>,
+        /// 停止序列
+        #[prost(string, repeated, tag = "6")]
+        pub stop: Vec<String>,
+        /// 其余请求参数，JSON 对象，为空时没有其他参数
+        #[prost(string, tag = "7")]
+        pub parameters: String,
+    }
+
+    /// token 用量
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Usage {
+        /// 提示 token 数
+        #[prost(uint32, tag = "1")]
+        pub prompt_tokens: u32,
+        /// 生成 token 数
+        #[prost(uint32, tag = "2")]
+        pub completion_tokens: u32,
+    }
+
+    /// 完整响应
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerateResponse {
+        /// 响应 ID
+        #[prost(string, tag = "1")]
+        pub id: String,
+        /// 实际使用的模型
+        #[prost(string, tag = "2")]
+        pub model: String,
+        /// 生成的文本
+        #[prost(string, tag = "3")]
+        pub content: String,
+        /// 结束原因，取值与 OpenAI 的 `finish_reason` 相同
+        #[prost(string, tag = "4")]
+        pub finish_reason: String,
+        /// token 用量
+        #[prost(message, optional, tag = "5")]
+        pub usage: Option<Usage>,
+    }
+
+    /// 流式数据块
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerateChunk {
+        /// 响应 ID
+        #[prost(string, tag = "1")]
+        pub id: String,
+        /// 实际使用的模型
+        #[prost(string, tag = "2")]
+        pub model: String,
+        /// 文本增量
+        #[prost(string, tag = "3")]
+        pub delta: String,
+        /// 结束原因，在最后一个数据块中给出
+        #[prost(string, tag = "4")]
+        pub finish_reason: String,
+        /// token 用量
+        #[prost(message, optional, tag = "5")]
+        pub usage: Option<Usage>,
+    }
+
+    /// 按服务地址缓存的连接
+    static CHANNELS: LazyLock<Mutex<HashMap<String, Channel>>> = LazyLock::new(Default::default);
+
+    /// 通过 gRPC 发送请求，并将结果转换为等价的 HTTP 响应
+    pub(crate) async fn execute(request: reqwest::Request, timeout: Duration) -> reqwest::Response {
+        let body: Value = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|body| serde_json::from_slice(body).ok())
+            .unwrap_or_default();
+        let result = crate::runtime::timeout(timeout, call(&request, &body, timeout))
+            .await
+            .unwrap_or_else(|_| Err(Status::deadline_exceeded("gRPC request timed out")));
+        result.unwrap_or_else(|status| {
+            error_response(
+                http_status(status.code()),
+                status.message(),
+                Some(&format!("{:?}", status.code())),
+            )
+        })
+    }
+
+    async fn call(
+        request: &reqwest::Request,
+        body: &Value,
+        timeout: Duration,
+    ) -> Result<reqwest::Response, Status> {
+        let mut grpc = tonic::client::Grpc::new(channel(request.url())?);
+        grpc.ready()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        let mut call = tonic::Request::new(generate_request(body));
+        *call.metadata_mut() = MetadataMap::from_headers(forwarded_headers(request.headers()));
+        call.set_timeout(timeout);
+
+        if body["stream"] != true {
+            let path = PathAndQuery::from_static(GENERATE_PATH);
+            let codec = ProstCodec::<GenerateRequest, GenerateResponse>::default();
+            let response = grpc.unary(call, path, codec).await?.into_inner();
+            let body = completion_json(response).to_string();
+            return Ok(response_ok("application/json", Body::from(body)));
+        }
+
+        let path = PathAndQuery::from_static(GENERATE_STREAM_PATH);
+        let codec = ProstCodec::<GenerateRequest, GenerateChunk>::default();
+        let chunks = grpc.server_streaming(call, path, codec).await?.into_inner();
+        // 将数据块转换为 SSE 事件，复用 HTTP 流式响应的解析逻辑；流中途的错误转换为错误事件
+        let events = chunks.map(|chunk| {
+            let data = match chunk {
+                Ok(chunk) => chunk_json(chunk),
+                Err(status) => json!({
+                    "error": {"message": status.message(), "code": format!("{:?}", status.code())}
+                }),
+            };
+            Ok::<_, Infallible>(Bytes::from(format!("data: {}\n\n", data)))
+        });
+        Ok(response_ok("text/event-stream", Body::wrap_stream(events)))
+    }
+
+    fn response_ok(content_type: &'static str, body: Body) -> reqwest::Response {
+        response(StatusCode::OK, content_type, body)
+    }
+
+    /// 返回服务地址对应的连接，首次使用时建立
+    fn channel(url: &reqwest::Url) -> Result<Channel, Status> {
+        let origin = url.origin().ascii_serialization();
+        let mut channels = CHANNELS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(channel) = channels.get(&origin) {
+            return Ok(channel.clone());
+        }
+        let channel = Endpoint::from_shared(origin.clone())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?
+            .connect_lazy();
+        channels.insert(origin, channel.clone());
+        Ok(channel)
+    }
+
+    /// 作为 gRPC 元数据转发的标头，不包括由 HTTP/2 和 gRPC 自身管理的标头
+    fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+        let mut forwarded = headers.clone();
+        for name in [CONTENT_TYPE, CONTENT_LENGTH, ACCEPT, HOST, USER_AGENT] {
+            forwarded.remove(name);
+        }
+        forwarded
+    }
+
+    /// 将 `/chat/completions` 请求体转换为 [`GenerateRequest`]
+    fn generate_request(body: &Value) -> GenerateRequest {
+        let mut params = body.as_object().cloned().unwrap_or_default();
+        let mut take = |name: &str| params.remove(name).unwrap_or(Value::Null);
+        let model = take("model").as_str().unwrap_or_default().to_string();
+        let messages = take("messages")
+            .as_array()
+            .map(|messages| {
+                messages
+                    .iter()
+                    .map(|message| ChatMessage {
+                        role: message["role"].as_str().unwrap_or_default().to_string(),
+                        content: text_content(&message["content"]),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let temperature = take("temperature").as_f64().map(|v| v as f32);
+        let top_p = take("top_p").as_f64().map(|v| v as f32);
+        let max_tokens = take("max_tokens").as_u64().map(|v| v as u32);
+        let stop = match take("stop") {
+            Value::String(stop) => vec![stop],
+            Value::Array(stops) => stops
+                .into_iter()
+                .filter_map(|stop| stop.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        };
+        take("stream");
+        take("stream_options");
+        let parameters = if params.is_empty() {
+            String::new()
+        } else {
+            Value::Object(params).to_string()
+        };
+        GenerateRequest {
+            model,
+            messages,
+            temperature,
+            top_p,
+            max_tokens,
+            stop,
+            parameters,
+        }
+    }
+
+    /// 消息内容中的文本，多模态内容只保留文本部分
+    fn text_content(content: &Value) -> String {
+        match content {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        }
+    }
+
+    fn finish_reason(reason: String) -> Value {
+        if reason.is_empty() {
+            Value::Null
+        } else {
+            Value::String(reason)
+        }
+    }
+
+    fn usage_json(usage: Option<Usage>) -> Value {
+        usage.map_or(Value::Null, |usage| {
+            json!({
+                "prompt_tokens": usage.prompt_tokens,
+                "completion_tokens": usage.completion_tokens,
+                "total_tokens": usage.prompt_tokens + usage.completion_tokens,
+            })
+        })
+    }
+
+    /// 将 [`GenerateResponse`] 转换为 `/chat/completions` 响应体
+    fn completion_json(response: GenerateResponse) -> Value {
+        json!({
+            "id": response.id,
+            "object": "chat.completion",
+            "created": 0,
+            "model": response.model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": response.content},
+                "finish_reason": finish_reason(response.finish_reason),
+            }],
+            "usage": usage_json(response.usage),
+        })
+    }
+
+    /// 将 [`GenerateChunk`] 转换为 `/chat/completions` 流式数据块
+    fn chunk_json(chunk: GenerateChunk) -> Value {
+        let mut value = Map::new();
+        value.insert("id".into(), chunk.id.into());
+        value.insert("object".into(), "chat.completion.chunk".into());
+        value.insert("created".into(), 0.into());
+        value.insert("model".into(), chunk.model.into());
+        value.insert(
+            "choices".into(),
+            json!([{
+                "index": 0,
+                "delta": {"content": chunk.delta},
+                "finish_reason": finish_reason(chunk.finish_reason),
+            }]),
+        );
+        if chunk.usage.is_some() {
+            value.insert("usage".into(), usage_json(chunk.usage));
+        }
+        Value::Object(value)
+    }
+
+    /// gRPC 状态码对应的 HTTP 状态码
+    fn http_status(code: Code) -> StatusCode {
+        match code {
+            Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+                StatusCode::BAD_REQUEST
+            }
+            Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Code::PermissionDenied => StatusCode::FORBIDDEN,
+            Code::NotFound => StatusCode::NOT_FOUND,
+            Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+            Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+            Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+            Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "websocket"))]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::error::NanoError;
+    use crate::test_util::completion_body;
+    use crate::LLMClient;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::Request;
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// 依次处理每个连接：检查握手请求，按请求类型回复
+    #[allow(clippy::result_large_err)] // 握手回调的错误类型由 tungstenite 决定
+    async fn serve(replies: Vec<Option<serde_json::Value>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for reply in replies {
+                let (stream, _) = listener.accept().await.unwrap();
+                let callback = |request: &Request, response| {
+                    assert_eq!(request.uri().path(), "/v1/chat/completions");
+                    assert!(request.headers().contains_key("authorization"));
+                    Ok(response)
+                };
+                let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback)
+                    .await
+                    .unwrap();
+                let Some(Ok(Message::Text(request))) = socket.next().await else {
+                    panic!("expected request frame");
+                };
+                let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+                let frames = match reply {
+                    Some(reply) => vec![reply.to_string()],
+                    None if request["stream"] == true => ["Hel", "lo"]
+                        .iter()
+                        .map(|text| {
+                            serde_json::json!({
+                                "id": "1", "object": "chat.completion.chunk", "created": 0,
+                                "model": "mock",
+                                "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": null}]
+                            })
+                            .to_string()
+                        })
+                        .chain(["[DONE]".to_string()])
+                        .collect(),
+                    None => vec![completion_body("pong").to_string()],
+                };
+                for frame in frames {
+                    socket.send(Message::text(frame)).await.unwrap();
+                }
+                let _ = socket.close(None).await;
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn test_websocket_transport() {
+        let base = serve(vec![
+            None,
+            None,
+            Some(serde_json::json!({"status": 400, "error": {"message": "bad model", "code": "invalid_model"}})),
+        ])
+        .await;
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&base)
+                .with_api_key("k")
+                .with_transport(Transport::WebSocket),
+        );
+
+        assert_eq!(client.generate("ping").await.unwrap(), "pong");
+
+        let text: Vec<String> = client
+            .stream_generate("hi")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text.concat(), "Hello");
+
+        match client.generate("ping").await {
+            Err(NanoError::Api { status, code, .. }) => {
+                assert_eq!(status, 400);
+                assert_eq!(code.as_deref(), Some("invalid_model"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // 其他端点不建立连接，服务端只处理了上面三个请求
+        assert!(matches!(
+            client.list_models().await,
+            Err(NanoError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            client.embed(&["x"]).await,
+            Err(NanoError::InvalidRequest(_))
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "grpc"))]
+mod grpc_tests {
+    use super::grpc::*;
+    use super::*;
+    use crate::config::Config;
+    use crate::error::NanoError;
+    use crate::LLMClient;
+    use futures::future::BoxFuture;
+    use futures::stream::{self, BoxStream};
+    use futures::StreamExt;
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use tonic::body::Body;
+    use tonic::codegen::{http, Service};
+    use tonic::server::{NamedService, ServerStreamingService, UnaryService};
+    use tonic::{Request, Response, Status};
+    use tonic_prost::ProstCodec;
+
+    /// 收到的认证标头和请求
+    type Recorded = (Option<String>, GenerateRequest);
+
+    /// 回显最后一条消息的测试服务，记录收到的请求
+    #[derive(Clone, Default)]
+    struct EchoService {
+        requests: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    impl EchoService {
+        fn record(&self, request: Request<GenerateRequest>) -> Result<GenerateRequest, Status> {
+            let auth = request
+                .metadata()
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let request = request.into_inner();
+            self.requests.lock().unwrap().push((auth, request.clone()));
+            if request.model == "missing" {
+                return Err(Status::not_found("model not found"));
+            }
+            Ok(request)
+        }
+    }
+
+    impl UnaryService<GenerateRequest> for EchoService {
+        type Response = GenerateResponse;
+        type Future = BoxFuture<'static, Result<Response<GenerateResponse>, Status>>;
+
+        fn call(&mut self, request: Request<GenerateRequest>) -> Self::Future {
+            let result = self.record(request).map(|request| {
+                Response::new(GenerateResponse {
+                    id: "grpc-1".into(),
+                    model: request.model,
+                    content: format!("echo: {}", request.messages.last().unwrap().content),
+                    finish_reason: "stop".into(),
+                    usage: Some(Usage {
+                        prompt_tokens: 3,
+                        completion_tokens: 2,
+                    }),
+                })
+            });
+            Box::pin(async move { result })
+        }
+    }
+
+    impl ServerStreamingService<GenerateRequest> for EchoService {
+        type Response = GenerateChunk;
+        type ResponseStream = BoxStream<'static, Result<GenerateChunk, Status>>;
+        type Future = BoxFuture<'static, Result<Response<Self::ResponseStream>, Status>>;
+
+        fn call(&mut self, request: Request<GenerateRequest>) -> Self::Future {
+            let result = self.record(request).map(|request| {
+                let chunks = ["Hel", "lo", ""].into_iter().map(move |delta| {
+                    Ok(GenerateChunk {
+                        id: "grpc-1".into(),
+                        model: request.model.clone(),
+                        delta: delta.into(),
+                        finish_reason: if delta.is_empty() {
+                            "stop".into()
+                        } else {
+                            String::new()
+                        },
+                        usage: None,
+                    })
+                });
+                Response::new(stream::iter(chunks).boxed())
+            });
+            Box::pin(async move { result })
+        }
+    }
+
+    impl Service<http::Request<Body>> for EchoService {
+        type Response = http::Response<Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<'static, Result<http::Response<Body>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            let service = self.clone();
+            Box::pin(async move {
+                let codec = ProstCodec::<GenerateResponse, GenerateRequest>::default();
+                let mut unary = tonic::server::Grpc::new(codec);
+                let codec = ProstCodec::<GenerateChunk, GenerateRequest>::default();
+                let mut streaming = tonic::server::Grpc::new(codec);
+                Ok(match request.uri().path() {
+                    GENERATE_PATH => unary.unary(service, request).await,
+                    GENERATE_STREAM_PATH => streaming.server_streaming(service, request).await,
+                    _ => Status::unimplemented("").into_http(),
+                })
+            })
+        }
+    }
+
+    impl NamedService for EchoService {
+        const NAME: &'static str = "nanoai.v1.Generation";
+    }
+
+    #[tokio::test]
+    async fn test_grpc_transport() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        let service = EchoService::default();
+        let server = tonic::transport::Server::builder()
+            .add_service(service.clone())
+            .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener));
+        tokio::spawn(server);
+
+        let client = LLMClient::new(
+            Config::default()
+                .with_api_base(&base)
+                .with_api_key("k")
+                .with_temperature(0.5)
+                .with_transport(Transport::Grpc),
+        );
+
+        let response = client.generate_with_stats("ping").await.unwrap();
+        assert_eq!(response.content, "echo: ping");
+        assert_eq!(response.stats.total_tokens, Some(5));
+
+        let text: Vec<String> = client
+            .stream_generate("hi")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(text.concat(), "Hello");
+
+        match client.with_model("missing").generate("ping").await {
+            Err(NanoError::Api { status, code, .. }) => {
+                assert_eq!(status, 404);
+                assert_eq!(code.as_deref(), Some("NotFound"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // 其他端点不会被当作 `Generate` 调用
+        assert!(matches!(
+            client.list_models().await,
+            Err(NanoError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            client.embed(&["x"]).await,
+            Err(NanoError::InvalidRequest(_))
+        ));
+
+        let requests = service.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let (auth, request) = &requests[0];
+        assert_eq!(auth.as_deref(), Some("Bearer k"));
+        assert_eq!(request.temperature, Some(0.5));
+        assert_eq!(request.messages.last().unwrap().role, "user");
+    }
+}
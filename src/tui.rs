@@ -0,0 +1,476 @@
+//! 终端聊天界面
+//!
+//! 基于 ratatui 的交互式聊天程序（`nanoai chat --tui`），支持滚动查看历史、流式输出、
+//! 切换模型，并在底部显示累计 token 用量和费用。消息通过
+//! [`ChatSession::send_streaming`] 发送，因此界面本身也是流式响应和会话功能的端到端检查。
+//!
+//! 快捷键：`Enter` 发送，`PgUp`/`PgDn` 滚动，`Ctrl+P` 切换模型，`Ctrl+L` 清空对话，
+//! `Ctrl+C` 退出。
+use crate::{
+    budget::UsageSnapshot, client::LLMClient, error::Result, render::MarkdownStream, runtime,
+    session::ChatSession, types::Role,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use tokio::sync::mpsc;
+
+/// 每次翻页滚动的行数
+const PAGE: u16 = 10;
+
+// ================================================================================================
+// 入口
+// ================================================================================================
+
+/// 运行终端聊天界面，直到用户退出
+///
+/// `models` 是模型切换器中的候选模型；为空时只使用客户端配置的模型。
+pub async fn run(client: LLMClient, models: Vec<String>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, client, models).await;
+    ratatui::restore();
+    result
+}
+
+/// 后台发回界面的事件
+enum Update {
+    Key(KeyEvent),
+    Resize,
+    Delta(String),
+    Done(Box<ChatSession>, Result<String>),
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: LLMClient,
+    models: Vec<String>,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    // crossterm 的读取是阻塞的，放在独立线程中，退出时不必等待它结束
+    let keys = tx.clone();
+    std::thread::spawn(move || loop {
+        let update = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => Update::Key(key),
+            Ok(Event::Resize(..)) => Update::Resize,
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+        if keys.send(update).is_err() {
+            break;
+        }
+    });
+
+    let mut app = App::new(&client.config.model, models);
+    let mut session = Some(ChatSession::new(client.clone()));
+    loop {
+        app.usage = client.usage();
+        terminal.draw(|frame| app.draw(frame))?;
+        let Some(update) = rx.recv().await else {
+            return Ok(());
+        };
+        match update {
+            Update::Key(key) => match app.handle_key(key) {
+                Action::None => {}
+                Action::Quit => return Ok(()),
+                Action::Submit(prompt) => {
+                    let Some(mut active) = session.take() else {
+                        continue;
+                    };
+                    app.begin(&prompt);
+                    let tx = tx.clone();
+                    runtime::spawn(async move {
+                        let deltas = tx.clone();
+                        let result = active
+                            .send_streaming(&prompt, |delta| {
+                                let _ = deltas.send(Update::Delta(delta.to_string()));
+                            })
+                            .await;
+                        let _ = tx.send(Update::Done(Box::new(active), result));
+                    });
+                }
+                Action::SwitchModel(model) => {
+                    if let Some(active) = session.take() {
+                        let handle = client.with_model(&model);
+                        session = Some(ChatSession::restore(handle, active.snapshot()));
+                    }
+                    app.model = model;
+                }
+                Action::Clear => {
+                    if let Some(active) = session.as_mut() {
+                        active.clear().await;
+                    }
+                    app.entries.clear();
+                }
+            },
+            Update::Resize => {}
+            Update::Delta(delta) => app.delta(&delta),
+            Update::Done(active, result) => {
+                session = Some(*active);
+                app.finish(result);
+            }
+        }
+    }
+}
+
+// ================================================================================================
+// 界面状态
+// ================================================================================================
+
+/// 按键对应的操作
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Submit(String),
+    SwitchModel(String),
+    Clear,
+    Quit,
+}
+
+/// 滚动区中的一条消息
+#[derive(Debug)]
+struct Entry {
+    role: Role,
+    text: String,
+}
+
+#[derive(Debug)]
+struct App {
+    model: String,
+    models: Vec<String>,
+    entries: Vec<Entry>,
+    input: String,
+    /// 从底部向上滚动的行数，为 0 时跟随最新输出
+    scroll: u16,
+    /// 模型切换器中选中的下标，`None` 表示切换器未打开
+    picker: Option<usize>,
+    streaming: bool,
+    renderer: MarkdownStream,
+    error: Option<String>,
+    usage: UsageSnapshot,
+}
+
+impl App {
+    fn new(model: &str, mut models: Vec<String>) -> Self {
+        if !models.iter().any(|m| m == model) {
+            models.insert(0, model.to_string());
+        }
+        Self {
+            model: model.to_string(),
+            models,
+            entries: Vec::new(),
+            input: String::new(),
+            scroll: 0,
+            picker: None,
+            streaming: false,
+            renderer: MarkdownStream::new().with_ansi(false),
+            error: None,
+            usage: UsageSnapshot::default(),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if let Some(selected) = self.picker {
+            match key.code {
+                KeyCode::Up => self.picker = Some(selected.saturating_sub(1)),
+                KeyCode::Down => self.picker = Some((selected + 1).min(self.models.len() - 1)),
+                KeyCode::Enter => {
+                    self.picker = None;
+                    return Action::SwitchModel(self.models[selected].clone());
+                }
+                KeyCode::Esc => self.picker = None,
+                KeyCode::Char('c') if ctrl => return Action::Quit,
+                _ => {}
+            }
+            return Action::None;
+        }
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Action::Quit,
+            KeyCode::Char('p') if ctrl && !self.streaming => {
+                let current = self.models.iter().position(|m| *m == self.model);
+                self.picker = Some(current.unwrap_or(0));
+            }
+            KeyCode::Char('l') if ctrl && !self.streaming => return Action::Clear,
+            KeyCode::Char(c) if !ctrl => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_add(PAGE),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE),
+            KeyCode::Enter if !self.streaming && !self.input.trim().is_empty() => {
+                return Action::Submit(std::mem::take(&mut self.input));
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// 开始一轮对话：添加用户消息和等待填充的模型回复
+    fn begin(&mut self, prompt: &str) {
+        self.entries.push(Entry {
+            role: Role::User,
+            text: prompt.to_string(),
+        });
+        self.entries.push(Entry {
+            role: Role::Assistant,
+            text: String::new(),
+        });
+        self.streaming = true;
+        self.scroll = 0;
+        self.error = None;
+    }
+
+    fn delta(&mut self, delta: &str) {
+        let rendered = self.renderer.push(delta);
+        if let Some(entry) = self.entries.last_mut() {
+            entry.text.push_str(&rendered);
+        }
+    }
+
+    fn finish(&mut self, result: Result<String>) {
+        let rest = self.renderer.finish();
+        if let Some(entry) = self.entries.last_mut() {
+            entry.text.push_str(&rest);
+        }
+        self.streaming = false;
+        if let Err(e) = result {
+            // 失败的一轮不会写入会话历史，界面上也只保留已收到的部分
+            if self
+                .entries
+                .last()
+                .is_some_and(|entry| entry.text.is_empty())
+            {
+                self.entries.pop();
+            }
+            self.error = Some(e.to_string());
+        }
+    }
+
+    // ============================================================================================
+    // 绘制
+    // ============================================================================================
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [history, input, footer] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let lines = self.lines();
+        let height = history.height.saturating_sub(2);
+        let total = wrapped_height(&lines, history.width.saturating_sub(2));
+        let max_scroll = total.saturating_sub(height);
+        self.scroll = self.scroll.min(max_scroll);
+        let title = format!(" {} ", self.model);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(title.bold()))
+                .wrap(Wrap { trim: false })
+                .scroll((max_scroll - self.scroll, 0)),
+            history,
+        );
+
+        let title = if self.streaming {
+            " Waiting for response… "
+        } else {
+            " Message "
+        };
+        frame.render_widget(
+            Paragraph::new(self.input.as_str()).block(Block::bordered().title(title)),
+            input,
+        );
+        if self.picker.is_none() {
+            let width = Line::raw(self.input.as_str()).width() as u16;
+            let x = (input.x + 1 + width).min(input.right().saturating_sub(2));
+            frame.set_cursor_position((x, input.y + 1));
+        }
+
+        frame.render_widget(Paragraph::new(self.footer()), footer);
+
+        if let Some(selected) = self.picker {
+            self.draw_picker(frame, selected);
+        }
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (label, color) = match entry.role {
+                Role::User => ("You".to_string(), Color::Cyan),
+                _ => (self.model.clone(), Color::Green),
+            };
+            lines.push(Line::from(Span::styled(
+                label,
+                Style::new().fg(color).add_modifier(Modifier::BOLD),
+            )));
+            let pending = self.streaming && index + 1 == self.entries.len();
+            if pending && entry.text.is_empty() {
+                lines.push(Line::from("…".dark_gray()));
+            }
+            lines.extend(entry.text.lines().map(|line| Line::raw(line.to_string())));
+            lines.push(Line::default());
+        }
+        lines
+    }
+
+    fn footer(&self) -> Line<'static> {
+        let usage = &self.usage;
+        let mut spans = vec![Span::raw(format!(
+            " {} requests │ {} tokens (in {} / out {}) │ ${:.4} │ ",
+            usage.requests,
+            usage.total_tokens(),
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.cost_usd
+        ))];
+        spans.push(match &self.error {
+            Some(error) => Span::styled(error.clone(), Style::new().fg(Color::Red)),
+            None => "Ctrl+P model · PgUp/PgDn scroll · Ctrl+L clear · Ctrl+C quit".dark_gray(),
+        });
+        Line::from(spans)
+    }
+
+    fn draw_picker(&self, frame: &mut Frame, selected: usize) {
+        let width = self.models.iter().map(|m| m.len()).max().unwrap_or(0) as u16 + 6;
+        let area = centered(frame.area(), width.max(24), self.models.len() as u16 + 2);
+        let list = List::new(self.models.iter().map(String::as_str))
+            .block(Block::bordered().title(" Switch model "))
+            .highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(selected));
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+}
+
+/// 按 `width` 换行后的大致行数
+fn wrapped_height(lines: &[Line], width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    let rows: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(width).max(1))
+        .sum();
+    rows.min(u16::MAX as usize) as u16
+}
+
+/// 在 `area` 中居中的矩形
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::NanoError;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::from(code))
+    }
+
+    fn ctrl(app: &mut App, c: char) -> Action {
+        app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+    }
+
+    fn screen(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for row in buffer.content().chunks(buffer.area.width as usize) {
+            // 宽字符之后的单元格只是占位
+            let mut skip = 0;
+            for cell in row {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                skip = Span::raw(cell.symbol()).width().saturating_sub(1);
+                text.push_str(cell.symbol());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn test_input_and_streaming() {
+        let mut app = App::new("model-a", Vec::new());
+        for c in "hi!".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(press(&mut app, KeyCode::Enter), Action::Submit("hi".into()));
+        assert!(app.input.is_empty());
+
+        app.begin("hi");
+        // 回复完成前不能再次发送
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(press(&mut app, KeyCode::Enter), Action::None);
+        app.delta("**Hel");
+        app.delta("lo**\n- item");
+        app.finish(Ok("Hello".into()));
+        assert!(!app.streaming);
+        assert_eq!(app.entries[1].text, "Hello\n  • item");
+
+        app.usage.requests = 1;
+        app.usage.prompt_tokens = 12;
+        let screen = screen(&mut app);
+        assert!(screen.contains("model-a"));
+        assert!(screen.contains("  • item"));
+        assert!(screen.contains("1 requests │ 12 tokens"));
+    }
+
+    #[test]
+    fn test_failed_turn() {
+        let mut app = App::new("model-a", Vec::new());
+        app.begin("hi");
+        app.finish(Err(NanoError::Timeout));
+        assert_eq!(app.entries.len(), 1);
+        assert!(screen(&mut app).contains(&NanoError::Timeout.to_string()));
+    }
+
+    #[test]
+    fn test_model_picker() {
+        let mut app = App::new("model-b", vec!["model-a".into(), "model-b".into()]);
+        ctrl(&mut app, 'p');
+        assert_eq!(app.picker, Some(1));
+        assert!(screen(&mut app).contains("Switch model"));
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Action::SwitchModel("model-a".into())
+        );
+        assert_eq!(app.picker, None);
+        assert_eq!(ctrl(&mut app, 'c'), Action::Quit);
+    }
+
+    #[test]
+    fn test_scrollback() {
+        let mut app = App::new("model-a", Vec::new());
+        for i in 0..10 {
+            app.begin(&format!("question {}", i));
+            app.finish(Ok(String::new()));
+        }
+        assert!(screen(&mut app).contains("question 9"));
+        press(&mut app, KeyCode::PageUp);
+        press(&mut app, KeyCode::PageUp);
+        let screen = screen(&mut app);
+        assert!(!screen.contains("question 9"));
+        assert!(screen.contains("question 5"));
+    }
+}
@@ -1,18 +1,221 @@
 //! API 数据结构模块
 
-use serde::{Deserialize, Serialize};
+use crate::error::NanoError;
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
 
 // ================================================================================================
 // API 请求结构
 // ================================================================================================
 
 /// 对话消息
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+///
+/// 设置了 `cache_control` 的消息会以内容片段数组的形式发送，以便服务端缓存到该消息为止的前缀。
+/// 反序列化时同时接受字符串和内容片段数组，数组中的文本片段按换行拼接。
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Message {
     /// 角色
     pub role: Role,
-    /// 内容
+    /// 内容，服务端返回 `null` 时为空字符串
     pub content: String,
+    /// 模型拒绝回答时给出的说明
+    pub refusal: Option<String>,
+    /// 工具调用 ID，仅 `Role::Tool` 消息使用，对应助手消息中的工具调用
+    pub tool_call_id: Option<String>,
+    /// 参与者名称，`Role::Function` 消息中为函数名
+    pub name: Option<String>,
+    /// 助手消息中的标注，例如联网搜索返回的引用来源
+    pub annotations: Vec<Annotation>,
+    /// 提示缓存断点（Anthropic 等服务端），缓存到该消息为止的前缀
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Part<'a> {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            text: &'a str,
+            cache_control: &'a CacheControl,
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Content<'a> {
+            Text(&'a str),
+            Parts([Part<'a>; 1]),
+        }
+
+        #[derive(Serialize)]
+        struct Wire<'a> {
+            role: Role,
+            content: Content<'a>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            refusal: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            tool_call_id: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            name: Option<&'a str>,
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            annotations: &'a [Annotation],
+        }
+
+        let content = match &self.cache_control {
+            Some(cache_control) => Content::Parts([Part {
+                kind: "text",
+                text: &self.content,
+                cache_control,
+            }]),
+            None => Content::Text(&self.content),
+        };
+        Wire {
+            role: self.role,
+            content,
+            refusal: self.refusal.as_deref(),
+            tool_call_id: self.tool_call_id.as_deref(),
+            name: self.name.as_deref(),
+            annotations: &self.annotations,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Part {
+            #[serde(default)]
+            text: Option<String>,
+            #[serde(default)]
+            cache_control: Option<CacheControl>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Content {
+            Text(String),
+            Parts(Vec<Part>),
+        }
+
+        #[derive(Deserialize)]
+        struct Wire {
+            role: Role,
+            #[serde(default)]
+            content: Option<Content>,
+            #[serde(default)]
+            refusal: Option<String>,
+            #[serde(default)]
+            tool_call_id: Option<String>,
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default)]
+            annotations: Vec<Annotation>,
+            #[serde(default)]
+            cache_control: Option<CacheControl>,
+        }
+
+        let wire = Wire::deserialize(deserializer)?;
+        let mut cache_control = wire.cache_control;
+        let content = match wire.content {
+            Some(Content::Text(text)) => text,
+            Some(Content::Parts(parts)) => {
+                let mut texts = Vec::new();
+                for part in parts {
+                    texts.extend(part.text);
+                    cache_control = part.cache_control.or(cache_control);
+                }
+                texts.join("\n")
+            }
+            None => String::new(),
+        };
+        Ok(Message {
+            role: wire.role,
+            content,
+            refusal: wire.refusal,
+            tool_call_id: wire.tool_call_id,
+            name: wire.name,
+            annotations: wire.annotations,
+            cache_control,
+        })
+    }
+}
+
+impl Message {
+    /// 在该消息处设置提示缓存断点
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+}
+
+/// 提示缓存控制
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CacheControl {
+    /// 缓存类型，目前为 `ephemeral`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// 缓存有效期，例如 `5m`、`1h`，为空时使用服务端默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+}
+
+impl CacheControl {
+    /// 临时缓存，使用服务端默认有效期
+    pub fn ephemeral() -> Self {
+        Self {
+            kind: "ephemeral".into(),
+            ttl: None,
+        }
+    }
+
+    /// 设置缓存有效期
+    pub fn with_ttl(mut self, ttl: impl Into<String>) -> Self {
+        self.ttl = Some(ttl.into());
+        self
+    }
+}
+
+/// 消息标注
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Annotation {
+    /// 标注类型，如 `url_citation`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// 网页引用，仅 `url_citation` 类型包含
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_citation: Option<Citation>,
+}
+
+/// 引用来源
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Citation {
+    /// 来源 URL
+    #[serde(default)]
+    pub url: String,
+    /// 来源标题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 来源摘录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// 引用在输出文本中的起始位置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<u32>,
+    /// 引用在输出文本中的结束位置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_index: Option<u32>,
+}
+
+impl Message {
+    /// 消息中的网页引用
+    pub fn citations(&self) -> Vec<Citation> {
+        self.annotations
+            .iter()
+            .filter_map(|a| a.url_citation.clone())
+            .collect()
+    }
 }
 
 /// 角色枚举
@@ -21,11 +224,67 @@ pub struct Message {
 pub enum Role {
     /// 系统
     System,
+    /// 开发者指令，o 系列模型中取代系统消息
+    Developer,
     /// 用户
     #[default]
     User,
     /// 机器人
     Assistant,
+    /// 工具调用结果
+    Tool,
+    /// 函数调用结果（旧版 function calling）
+    Function,
+    /// 无法识别的角色，反序列化时兜底，避免整个响应解析失败
+    #[serde(other)]
+    Unknown,
+}
+
+impl Role {
+    /// API 中使用的角色名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::Developer => "developer",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+            Role::Function => "function",
+            Role::Unknown => "unknown",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = NanoError;
+
+    /// 严格解析角色名称，不区分大小写，无法识别时返回 [`NanoError::InvalidRequest`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Role::System,
+            Role::Developer,
+            Role::User,
+            Role::Assistant,
+            Role::Tool,
+            Role::Function,
+        ]
+        .into_iter()
+        .find(|role| role.as_str().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| NanoError::InvalidRequest(format!("Unknown role '{}'", s)))
+    }
+}
+
+impl From<&str> for Role {
+    /// 宽松转换，供 `message("user", ..)` 这类写法使用
+    ///
+    /// 无法识别的名称转换为 [`Role::Unknown`] 并记录警告，包含该角色的请求在发送前以
+    /// `NanoError::InvalidRequest` 失败；需要在构造时拒绝非法角色时使用 `str::parse::<Role>()`。
+    fn from(s: &str) -> Self {
+        s.parse().unwrap_or_else(|e| {
+            warn!("{}, using Role::Unknown", e);
+            Role::Unknown
+        })
+    }
 }
 
 // ================================================================================================
@@ -69,6 +328,21 @@ pub struct Choice {
     /// 消息内容
     #[serde(default)]
     pub message: Message,
+    /// 内容过滤结果（Azure OpenAI 等），键为类别名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_filter_results: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl Choice {
+    /// 被内容过滤拦截的类别
+    pub fn filtered_categories(&self) -> Vec<String> {
+        self.content_filter_results
+            .iter()
+            .flatten()
+            .filter(|(_, result)| result["filtered"] == true)
+            .map(|(category, _)| category.clone())
+            .collect()
+    }
 }
 
 /// token 使用情况
@@ -83,6 +357,36 @@ pub struct Usage {
     /// 总 token 数量
     #[serde(default)]
     pub total_tokens: u32,
+    /// 提示 token 明细
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    /// 完成 token 明细
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+/// 提示 token 明细
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq)]
+pub struct PromptTokensDetails {
+    /// 命中提示缓存的 token 数量
+    #[serde(default)]
+    pub cached_tokens: u32,
+    /// 音频输入 token 数量
+    #[serde(default)]
+    pub audio_tokens: u32,
+}
+
+/// 完成 token 明细
+///
+/// 各项均已计入 `completion_tokens`
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq)]
+pub struct CompletionTokensDetails {
+    /// 推理模型内部推理消耗的 token 数量
+    #[serde(default)]
+    pub reasoning_tokens: u32,
+    /// 音频输出 token 数量
+    #[serde(default)]
+    pub audio_tokens: u32,
 }
 
 // ================================================================================================
@@ -96,6 +400,9 @@ pub struct Delta {
     pub role: Option<Role>,
     /// 内容
     pub content: Option<String>,
+    /// 本次增量中新增的标注
+    #[serde(default)]
+    pub annotations: Option<Vec<Annotation>>,
 }
 
 /// 流式 API 响应体
@@ -130,10 +437,59 @@ pub struct StreamChoice {
 // 应用内部数据模型
 // ================================================================================================
 
+/// 生成结束的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum FinishReason {
+    /// 自然结束或遇到停止序列
+    Stop,
+    /// 达到 `max_tokens` 上限
+    Length,
+    /// 模型请求调用工具
+    ToolCalls,
+    /// 内容被服务端过滤
+    ContentFilter,
+    /// 其他服务端返回的原因
+    Other(String),
+}
+
+impl FinishReason {
+    /// 服务端使用的原因字符串
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl From<String> for FinishReason {
+    fn from(reason: String) -> Self {
+        match reason.as_str() {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "length" | "max_tokens" => FinishReason::Length,
+            "tool_calls" | "function_call" | "tool_use" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            _ => FinishReason::Other(reason),
+        }
+    }
+}
+
+impl From<FinishReason> for String {
+    fn from(reason: FinishReason) -> Self {
+        reason.as_str().to_string()
+    }
+}
+
 /// 请求统计信息
 ///
-/// 记录 API 请求的详细统计数据，用于性能监控和分析
-#[derive(Debug, Clone, Default)]
+/// 记录 API 请求的详细统计数据，用于性能监控和分析。可以序列化后写入日志或跨进程传递，
+/// `timestamp` 序列化为 RFC 3339 格式的 UTC 时间（如 `2024-05-01T08:30:00.250Z`）。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RequestStats {
     /// 请求耗时（毫秒）
     pub duration_ms: u64,
@@ -143,19 +499,380 @@ pub struct RequestStats {
     pub completion_tokens: Option<u32>,
     /// 总 token 数量
     pub total_tokens: Option<u32>,
+    /// 命中提示缓存的输入 token 数量
+    pub cached_tokens: Option<u32>,
+    /// 输出 token 中用于内部推理的数量
+    pub reasoning_tokens: Option<u32>,
+    /// 音频输入 token 数量
+    pub prompt_audio_tokens: Option<u32>,
+    /// 音频输出 token 数量
+    pub completion_audio_tokens: Option<u32>,
     /// 使用的模型名称
     pub model: String,
     /// 请求时间戳
+    #[serde(with = "rfc3339")]
     pub timestamp: Option<std::time::SystemTime>,
+    /// 实际发送的次数，包括重试
+    pub attempts: u32,
+    /// 重试前退避等待的总时长（毫秒）
+    pub backoff_ms: u64,
+    /// 最终返回响应的端点
+    pub endpoint: Option<String>,
+    /// 服务端报告的实际处理请求的模型，可能与请求的模型不同（例如经过路由）
+    pub served_model: Option<String>,
+    /// 生成结束的原因
+    pub finish_reason: Option<FinishReason>,
+    /// 生成该响应所用的提示词版本（`name@version`），见 [`crate::prompts::PromptRegistry`]
+    pub prompt_version: Option<String>,
 }
 
 /// 带统计信息的响应结果
 ///
 /// 包含生成的内容和详细的请求统计信息
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseWithStats {
     /// 生成的文本内容
     pub content: String,
     /// 请求统计信息
     pub stats: RequestStats,
-}
\ No newline at end of file
+    /// 输出引用的来源，例如联网搜索结果
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+}
+
+/// 共享前缀批量生成的结果
+///
+/// 由 [`crate::generate_many_with_prefix`] 返回
+#[derive(Debug)]
+pub struct PrefixBatch {
+    /// 每个后缀的生成结果，顺序与输入一致
+    pub results: Vec<crate::error::Result<ResponseWithStats>>,
+    /// 成功请求的输入 token 总数
+    pub prompt_tokens: u64,
+    /// 成功请求中命中提示缓存的输入 token 总数
+    pub cached_tokens: u64,
+}
+
+impl PrefixBatch {
+    /// 输入 token 的缓存命中率
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.prompt_tokens == 0 {
+            return 0.0;
+        }
+        self.cached_tokens as f64 / self.prompt_tokens as f64
+    }
+}
+
+/// `Option<SystemTime>` 与 RFC 3339 字符串之间的转换
+pub(crate) mod rfc3339 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub(super) fn serialize<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.serialize_str(&format(*time)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| {
+                parse(&s).ok_or_else(|| D::Error::custom(format!("invalid RFC 3339 time '{}'", s)))
+            })
+            .transpose()
+    }
+
+    /// 格式化为精确到毫秒的 UTC 时间，早于 1970 年的时间按 1970 年处理
+    pub(crate) fn format(time: SystemTime) -> String {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let rem = secs % 86_400;
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60,
+            since_epoch.subsec_millis()
+        )
+    }
+
+    /// 解析 `YYYY-MM-DDTHH:MM:SS[.fff][Z|±HH:MM]`
+    pub(super) fn parse(s: &str) -> Option<SystemTime> {
+        let num = |range: std::ops::Range<usize>| -> Option<i64> {
+            let part = s.get(range)?;
+            part.bytes()
+                .all(|b| b.is_ascii_digit())
+                .then(|| part.parse().ok())?
+        };
+        let bytes = s.as_bytes();
+        if bytes.len() < 20
+            || bytes[4] != b'-'
+            || bytes[7] != b'-'
+            || !matches!(bytes[10], b'T' | b't' | b' ')
+            || bytes[13] != b':'
+            || bytes[16] != b':'
+        {
+            return None;
+        }
+        let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+        let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+            return None;
+        }
+        // 闰秒按下一秒的开始处理
+        if second > 60 {
+            return None;
+        }
+
+        let mut rest = &s[19..];
+        let mut nanos = 0u32;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return None;
+            }
+            for (i, b) in fraction.bytes().take(digits.min(9)).enumerate() {
+                nanos += (b - b'0') as u32 * 10u32.pow(8 - i as u32);
+            }
+            rest = &fraction[digits..];
+        }
+        let offset = match rest {
+            "Z" | "z" => 0,
+            _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+                let hours: i64 = rest.get(1..3)?.parse().ok()?;
+                let minutes: i64 = rest.get(4..6)?.parse().ok()?;
+                let sign = match rest.as_bytes()[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return None,
+                };
+                sign * (hours * 3600 + minutes * 60)
+            }
+            _ => return None,
+        };
+
+        let days = days_from_civil(year, month, day);
+        let secs = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+        let secs = u64::try_from(secs).ok()?;
+        Some(UNIX_EPOCH + Duration::new(secs, nanos))
+    }
+
+    /// 1970-01-01 起的天数转换为公历日期
+    fn civil_from_days(days: i64) -> (i64, i64, i64) {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+
+    /// 公历日期转换为 1970-01-01 起的天数
+    fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::message;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_stats_serde() {
+        let response = ResponseWithStats {
+            content: "hi".into(),
+            stats: RequestStats {
+                duration_ms: 120,
+                prompt_tokens: Some(10),
+                model: "deepseek-chat".into(),
+                timestamp: Some(UNIX_EPOCH + Duration::from_millis(1_714_552_200_250)),
+                finish_reason: Some(FinishReason::Stop),
+                ..RequestStats::default()
+            },
+            citations: Vec::new(),
+        };
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["stats"]["timestamp"], "2024-05-01T08:30:00.250Z");
+        assert_eq!(value["stats"]["finish_reason"], "stop");
+
+        let parsed: ResponseWithStats = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.stats.timestamp, response.stats.timestamp);
+        assert_eq!(parsed.stats.prompt_tokens, Some(10));
+
+        // 缺失的字段取默认值，时区偏移换算为 UTC
+        let stats: RequestStats = serde_json::from_value(serde_json::json!({
+            "model": "m", "timestamp": "2024-05-01T16:30:00.25+08:00"
+        }))
+        .unwrap();
+        assert_eq!(stats.timestamp, response.stats.timestamp);
+        assert_eq!(stats.attempts, 0);
+        assert!(
+            serde_json::from_value::<RequestStats>(serde_json::json!({"timestamp": null}))
+                .unwrap()
+                .timestamp
+                .is_none()
+        );
+
+        for invalid in ["2024-13-01T00:00:00Z", "2024-05-01 08:30", "yesterday"] {
+            assert!(rfc3339::parse(invalid).is_none(), "{}", invalid);
+        }
+        assert_eq!(rfc3339::format(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            rfc3339::format(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn test_role_serde() {
+        assert_eq!(serde_json::to_value(Role::Developer).unwrap(), "developer");
+        assert_eq!(serde_json::to_value(Role::Tool).unwrap(), "tool");
+        let role: Role = serde_json::from_value(serde_json::json!("critic")).unwrap();
+        assert_eq!(role, Role::Unknown);
+    }
+
+    #[test]
+    fn test_role_from_str() {
+        assert_eq!("Assistant".parse::<Role>().unwrap(), Role::Assistant);
+        assert_eq!(Role::from(" tool "), Role::Tool);
+        assert!(matches!(
+            "usr".parse::<Role>(),
+            Err(NanoError::InvalidRequest(_))
+        ));
+        assert!("unknown".parse::<Role>().is_err());
+        assert_eq!(Role::from("usr"), Role::Unknown);
+        for role in [Role::System, Role::Developer, Role::Function] {
+            assert_eq!(role.as_str().parse::<Role>().unwrap(), role);
+            assert_eq!(serde_json::to_value(role).unwrap(), role.as_str());
+        }
+    }
+
+    #[test]
+    fn test_tool_message_serialization() {
+        let msg = Message {
+            role: Role::Tool,
+            content: "42".into(),
+            tool_call_id: Some("call_1".into()),
+            ..Message::default()
+        };
+        let value = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"role": "tool", "content": "42", "tool_call_id": "call_1"})
+        );
+
+        let plain = serde_json::to_value(Message::default()).unwrap();
+        assert_eq!(plain, serde_json::json!({"role": "user", "content": ""}));
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let messages = [
+            message(Role::System, "Be brief."),
+            message(Role::User, "long context")
+                .with_cache_control(CacheControl::ephemeral().with_ttl("1h")),
+            Message {
+                role: Role::Assistant,
+                refusal: Some("I can't help with that.".into()),
+                ..Message::default()
+            },
+            Message {
+                role: Role::Tool,
+                content: "42".into(),
+                tool_call_id: Some("call_1".into()),
+                ..Message::default()
+            },
+        ];
+        let json = serde_json::to_string(&messages).unwrap();
+        let parsed: Vec<Message> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, messages);
+
+        // 多模态内容只保留文本片段，`null` 内容为空字符串
+        let parsed: Vec<Message> = serde_json::from_value(serde_json::json!([
+            {"role": "user", "content": [
+                {"type": "text", "text": "What is in"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}},
+                {"type": "text", "text": "this image?"}
+            ]},
+            {"role": "assistant", "content": null, "tool_calls": [{"id": "call_1"}]}
+        ]))
+        .unwrap();
+        assert_eq!(parsed[0].content, "What is in\nthis image?");
+        assert_eq!(parsed[1].content, "");
+    }
+
+    #[test]
+    fn test_cache_control_serialization() {
+        let msg = Message {
+            role: Role::User,
+            content: "long context".into(),
+            ..Message::default()
+        }
+        .with_cache_control(CacheControl::ephemeral());
+        let value = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"role": "user", "content": [
+                {"type": "text", "text": "long context", "cache_control": {"type": "ephemeral"}}
+            ]})
+        );
+        assert_eq!(serde_json::from_value::<Message>(value).unwrap(), msg);
+
+        let usage: Usage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 2000, "completion_tokens": 10, "total_tokens": 2010,
+            "prompt_tokens_details": {"cached_tokens": 1920},
+            "completion_tokens_details": {"reasoning_tokens": 8}
+        }))
+        .unwrap();
+        assert_eq!(usage.prompt_tokens_details.unwrap().cached_tokens, 1920);
+        assert_eq!(usage.completion_tokens_details.unwrap().reasoning_tokens, 8);
+    }
+
+    #[test]
+    fn test_annotations_parsing() {
+        let choice: Choice = serde_json::from_value(serde_json::json!({
+            "finish_reason": "stop",
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "Rust 1.0 shipped in 2015.",
+                "annotations": [{
+                    "type": "url_citation",
+                    "url_citation": {
+                        "url": "https://blog.rust-lang.org",
+                        "title": "Rust Blog",
+                        "start_index": 0,
+                        "end_index": 8
+                    }
+                }]
+            }
+        }))
+        .unwrap();
+        let citations = choice.message.citations();
+        assert_eq!(citations.len(), 1);
+        assert_eq!(citations[0].url, "https://blog.rust-lang.org");
+        assert_eq!(citations[0].end_index, Some(8));
+    }
+}
@@ -1,20 +1,145 @@
 //! 工具函数模块
+use crate::error::{NanoError, Result};
 use crate::types::{Message, Role};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
 
 /// 创建消息的便捷函数
 ///
 /// # 参数
 ///
-/// * `role` - 消息角色
+/// * `role` - 消息角色，可以是 [`Role`] 或角色名称（如 `"user"`）
 /// * `content` - 消息内容
 ///
 /// # 返回
 ///
-/// 新创建的消息实例
-pub fn message(role: Role, content: &str) -> Message {
+/// 新创建的消息实例。无法识别的角色名称会转换为 [`Role::Unknown`]，
+/// 需要拒绝非法角色时先用 `str::parse::<Role>()` 校验。
+///
+/// ```rust
+/// use nanoai::types::Role;
+/// use nanoai::utils::message;
+///
+/// assert_eq!(message("user", "Hi"), message(Role::User, "Hi"));
+/// ```
+pub fn message(role: impl Into<Role>, content: &str) -> Message {
+    Message {
+        role: role.into(),
+        content: content.to_string(),
+        ..Message::default()
+    }
+}
+
+/// 创建工具调用结果消息
+///
+/// # 参数
+///
+/// * `tool_call_id` - 助手消息中对应的工具调用 ID
+/// * `content` - 工具执行结果
+///
+/// # 返回
+///
+/// 角色为 `Role::Tool` 的消息
+pub fn tool_message(tool_call_id: &str, content: &str) -> Message {
     Message {
-        role,
+        role: Role::Tool,
         content: content.to_string(),
+        tool_call_id: Some(tool_call_id.to_string()),
+        ..Message::default()
+    }
+}
+
+/// 对话消息列表的构建器
+///
+/// 按顺序追加各角色的消息，避免手工拼装 `Vec<Message>` 时写错角色或遗漏工具调用 ID。
+///
+/// ```rust
+/// use nanoai::types::Role;
+/// use nanoai::utils::ConversationBuilder;
+///
+/// let messages = ConversationBuilder::new()
+///     .system("You are a helpful assistant.")
+///     .user("What's the weather in Paris?")
+///     .assistant("Let me check.")
+///     .tool_result("call_1", r#"{"temp": 18}"#)
+///     .build();
+///
+/// assert_eq!(messages.len(), 4);
+/// assert_eq!(messages[3].role, Role::Tool);
+/// assert_eq!(messages[3].tool_call_id.as_deref(), Some("call_1"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConversationBuilder {
+    messages: Vec<Message>,
+}
+
+impl ConversationBuilder {
+    /// 创建空的构建器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加系统消息
+    pub fn system(self, content: impl Into<String>) -> Self {
+        self.push(Role::System, content)
+    }
+
+    /// 追加开发者指令，o 系列模型中取代系统消息
+    pub fn developer(self, content: impl Into<String>) -> Self {
+        self.push(Role::Developer, content)
+    }
+
+    /// 追加用户消息
+    pub fn user(self, content: impl Into<String>) -> Self {
+        self.push(Role::User, content)
+    }
+
+    /// 追加助手消息
+    pub fn assistant(self, content: impl Into<String>) -> Self {
+        self.push(Role::Assistant, content)
+    }
+
+    /// 追加工具调用结果，`tool_call_id` 对应助手消息中的工具调用
+    pub fn tool_result(
+        mut self,
+        tool_call_id: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        self.messages.push(Message {
+            role: Role::Tool,
+            content: content.into(),
+            tool_call_id: Some(tool_call_id.into()),
+            ..Message::default()
+        });
+        self
+    }
+
+    /// 追加任意消息，例如设置了缓存断点的消息
+    pub fn message(mut self, message: Message) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// 追加多条消息，例如已有的对话历史
+    pub fn messages(mut self, messages: impl IntoIterator<Item = Message>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
+    /// 生成消息列表
+    pub fn build(self) -> Vec<Message> {
+        self.messages
+    }
+
+    fn push(mut self, role: Role, content: impl Into<String>) -> Self {
+        self.messages.push(Message {
+            role,
+            content: content.into(),
+            ..Message::default()
+        });
+        self
     }
 }
 
@@ -22,7 +147,7 @@ pub fn message(role: Role, content: &str) -> Message {
 ///
 /// 如果系统消息不为空，则将其作为第一条消息。
 /// 准备发送到 API 的消息列表
-/// 
+///
 /// 如果系统消息不为空，则将其作为第一条消息。
 pub(crate) fn prepare_messages(system_message: &str, messages: &[Message]) -> Vec<Message> {
     let system_iter = if !system_message.is_empty() {
@@ -30,7 +155,417 @@ pub(crate) fn prepare_messages(system_message: &str, messages: &[Message]) -> Ve
     } else {
         vec![].into_iter()
     };
-    system_iter.chain(messages.iter().cloned()).collect()
+    // 标注只出现在响应中，不随历史消息发回
+    let history = messages.iter().map(|m| Message {
+        annotations: Vec::new(),
+        ..m.clone()
+    });
+    system_iter.chain(history).collect()
+}
+
+/// 检查消息角色，拒绝 `Role::Unknown`
+///
+/// 未知角色通常来自拼写错误的角色名（如 `message("usr", ..)`），发出后只会被服务端以
+/// 400 拒绝，因此在发送前返回 `NanoError::InvalidRequest`。
+pub(crate) fn validate_roles(messages: &[Message]) -> Result<()> {
+    match messages.iter().position(|m| m.role == Role::Unknown) {
+        Some(index) => Err(NanoError::InvalidRequest(format!(
+            "Message {} has an unknown role",
+            index
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// 修复模型输出中常见的“接近合法”的 JSON
+///
+/// 依次处理以下问题：
+///
+/// * 去除 Markdown 代码块围栏（如 ```` ```json ````）以及 JSON 前后的说明文字
+/// * 将单引号字符串转换为双引号字符串
+/// * 删除对象和数组中的尾随逗号
+/// * 补全未闭合的字符串、对象和数组
+///
+/// 该函数只做尽力修复，不保证返回值一定是合法 JSON。
+///
+/// # 参数
+///
+/// * `input` - 模型输出的原始文本
+///
+/// # 返回
+///
+/// 修复后的 JSON 文本
+pub fn repair_json(input: &str) -> String {
+    let text = strip_code_fence(input.trim());
+    let text = match text.find(['{', '[']) {
+        Some(start) => &text[start..],
+        None => text,
+    };
+
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut closers: Vec<char> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    // 当前对象中尚未跟随 `:` 的键在 `out` 中的起始位置
+    let mut pending_key: Option<usize> = None;
+
+    for c in text.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                // JSON 不支持 `\'` 转义，直接保留单引号
+                if c == '\'' {
+                    out.pop();
+                }
+                out.push(c);
+                escaped = false;
+                continue;
+            }
+            match c {
+                '\\' => {
+                    out.push(c);
+                    escaped = true;
+                }
+                c if c == q => {
+                    out.push('"');
+                    quote = None;
+                }
+                '"' => out.push_str("\\\""),
+                '\n' => out.push_str("\\n"),
+                _ => out.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => {
+                if closers.last() == Some(&'}') && out.trim_end().ends_with(['{', ',']) {
+                    pending_key = Some(out.len());
+                }
+                out.push('"');
+                quote = Some(c);
+            }
+            ':' => {
+                pending_key = None;
+                out.push(c);
+            }
+            '{' => {
+                closers.push('}');
+                out.push(c);
+            }
+            '[' => {
+                closers.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                pending_key = None;
+                strip_trailing_comma(&mut out);
+                if closers.last() == Some(&c) {
+                    closers.pop();
+                }
+                out.push(c);
+                // 顶层值已经完整，忽略其后的说明文字
+                if closers.is_empty() {
+                    return out;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if escaped {
+        out.pop();
+    }
+    if quote.is_some() {
+        out.push('"');
+    }
+    // 只有键没有值的成员无法补全，直接丢弃
+    if let Some(pos) = pending_key {
+        out.truncate(pos);
+    }
+    strip_trailing_comma(&mut out);
+    if out.ends_with(':') {
+        out.push_str("null");
+    }
+    while let Some(closer) = closers.pop() {
+        strip_trailing_comma(&mut out);
+        out.push(closer);
+    }
+    out
+}
+
+/// 将模型输出解析为指定类型，解析失败时先尝试 [`repair_json`] 修复
+pub(crate) fn parse_json_lenient<T: DeserializeOwned>(content: &str) -> Result<T> {
+    serde_json::from_str(content).or_else(|e| {
+        serde_json::from_str(&repair_json(content))
+            .map_err(|_| NanoError::Json(format!("Failed to parse model output: {}", e)))
+    })
+}
+
+/// 解析服务端响应体
+///
+/// 宽松模式下未知字段被忽略、缺失字段取默认值。严格模式下字段类型不符或 `required`
+/// 中的字段缺失时返回 [`NanoError::Json`]，错误信息包含出错位置的 JSON 指针
+/// （如 `/choices/0/message/content`）；未知字段仍被接受，但会记录警告。
+///
+/// `required` 中的指针可以用 `*` 匹配数组的每个元素，如 `/choices/*/message`。
+pub(crate) fn parse_response<T: DeserializeOwned>(
+    body: &[u8],
+    strict: bool,
+    required: &[&str],
+) -> Result<T> {
+    if !strict {
+        return Ok(serde_json::from_slice(body)?);
+    }
+    let value: Value = serde_json::from_slice(body)?;
+    for pattern in required {
+        let segments: Vec<&str> = pattern.split('/').skip(1).collect();
+        if let Some(pointer) = find_missing(&value, &segments, String::new()) {
+            return Err(NanoError::Json(format!(
+                "Unexpected response shape at {}: missing field",
+                pointer
+            )));
+        }
+    }
+    let mut unknown = Vec::new();
+    let mut record = |path: serde_ignored::Path| unknown.push(ignored_pointer(&path));
+    let deserializer = serde_ignored::Deserializer::new(&value, &mut record);
+    let parsed = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let pointer: String = e.path().iter().map(segment_pointer).collect();
+        NanoError::Json(format!(
+            "Unexpected response shape at {}: {}",
+            if pointer.is_empty() { "/" } else { &pointer },
+            e.inner()
+        ))
+    })?;
+    for pointer in unknown {
+        warn!("Unknown field in response at {}", pointer);
+    }
+    Ok(parsed)
+}
+
+/// 查找 `segments` 描述的路径上第一个缺失的字段，返回其 JSON 指针
+///
+/// 中间值类型不符（如期望数组却是对象）时不视为缺失，交给反序列化报告。
+fn find_missing(value: &Value, segments: &[&str], pointer: String) -> Option<String> {
+    let (first, rest) = segments.split_first()?;
+    if *first == "*" {
+        return value
+            .as_array()?
+            .iter()
+            .enumerate()
+            .find_map(|(index, item)| find_missing(item, rest, format!("{}/{}", pointer, index)));
+    }
+    let object = value.as_object()?;
+    let pointer = format!("{}/{}", pointer, escape_pointer(first));
+    match object.get(*first) {
+        Some(child) => find_missing(child, rest, pointer),
+        None => Some(pointer),
+    }
+}
+
+/// 按 RFC 6901 转义 JSON 指针中的一段
+fn escape_pointer(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn segment_pointer(segment: &serde_path_to_error::Segment) -> String {
+    use serde_path_to_error::Segment;
+    match segment {
+        Segment::Seq { index } => format!("/{}", index),
+        Segment::Map { key } => format!("/{}", escape_pointer(key)),
+        Segment::Enum { variant } => format!("/{}", escape_pointer(variant)),
+        Segment::Unknown => "/?".to_string(),
+    }
+}
+
+fn ignored_pointer(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}/{}", ignored_pointer(parent), index),
+        Path::Map { parent, key } => {
+            format!("{}/{}", ignored_pointer(parent), escape_pointer(key))
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => ignored_pointer(parent),
+    }
+}
+
+/// 词级差异中的一段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", content = "text", rename_all = "lowercase")]
+pub enum DiffOp {
+    /// 两段文本共有的内容
+    Equal(String),
+    /// 只出现在第二段文本中的内容
+    Insert(String),
+    /// 只出现在第一段文本中的内容
+    Delete(String),
+}
+
+/// 两段回复的比较结果，由 [`diff_responses`] 返回
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResponseDiff {
+    /// 按顺序排列的差异，相邻的同类片段已合并
+    pub ops: Vec<DiffOp>,
+    /// 词级相似度：`2 × 共有词数 / 总词数`，范围 0 到 1，两段都为空时为 1
+    pub similarity: f64,
+    /// 两段文本向量的余弦相似度，由 `LLMClient::diff_responses` 计算
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantic_similarity: Option<f32>,
+}
+
+impl ResponseDiff {
+    /// 两段文本的词是否完全相同（忽略空白差异）
+    pub fn is_identical(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, DiffOp::Equal(_)))
+    }
+
+    /// 以 `[-删除-]{+插入+}` 标记差异的文本
+    pub fn render(&self) -> String {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DiffOp::Equal(text) => text.clone(),
+                DiffOp::Insert(text) => format!("{{+{}+}}", text),
+                DiffOp::Delete(text) => format!("[-{}-]", text),
+            })
+            .collect()
+    }
+}
+
+/// 逐词比较两段回复，用于影子流量和 A/B 实验中对比不同模型或提示词的输出
+///
+/// 英文等按空白和标点分词，中日韩文字每个字符作为一个词；比较时忽略空白差异，
+/// 差异片段保留原文中的空白。
+///
+/// ```rust
+/// use nanoai::utils::{diff_responses, DiffOp};
+///
+/// let diff = diff_responses("The cat sat.", "The dog sat.");
+/// assert_eq!(diff.render(), "The [-cat -]{+dog +}sat.");
+/// assert!((diff.similarity - 0.75).abs() < 1e-9);
+/// ```
+pub fn diff_responses(a: &str, b: &str) -> ResponseDiff {
+    let a = diff_tokens(a);
+    let b = diff_tokens(b);
+    let key = |token: &str| token.trim_end().to_string();
+    let (a_keys, b_keys): (Vec<String>, Vec<String>) = (
+        a.iter().map(|t| key(t)).collect(),
+        b.iter().map(|t| key(t)).collect(),
+    );
+
+    // 最长公共子序列，lcs[i][j] 为 a[i..] 与 b[j..] 的结果
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a_keys[i] == b_keys[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<DiffOp> = Vec::new();
+    let mut push = |op: DiffOp| match (ops.last_mut(), op) {
+        (Some(DiffOp::Equal(last)), DiffOp::Equal(text))
+        | (Some(DiffOp::Insert(last)), DiffOp::Insert(text))
+        | (Some(DiffOp::Delete(last)), DiffOp::Delete(text)) => last.push_str(&text),
+        (_, op) => ops.push(op),
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a_keys[i] == b_keys[j] {
+            push(DiffOp::Equal(b[j].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < a.len()
+            && (j == b.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            push(DiffOp::Delete(a[i].to_string()));
+            i += 1;
+        } else {
+            push(DiffOp::Insert(b[j].to_string()));
+            j += 1;
+        }
+    }
+
+    let total = a.len() + b.len();
+    ResponseDiff {
+        ops,
+        similarity: if total == 0 {
+            1.0
+        } else {
+            2.0 * lcs[0] as f64 / total as f64
+        },
+        semantic_similarity: None,
+    }
+}
+
+/// 按词切分文本，每个词带上其后的空白；开头的空白单独成词
+fn diff_tokens(text: &str) -> Vec<&str> {
+    let is_cjk = |c: char| {
+        matches!(c, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}'
+            | '\u{ac00}'..='\u{d7af}' | '\u{3000}'..='\u{303f}' | '\u{ff00}'..='\u{ffef}')
+    };
+    let is_word = |c: char| c.is_alphanumeric() && !is_cjk(c) || c == '_' || c == '\'';
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        if c.is_whitespace() && pos == 0 {
+            while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+        } else {
+            if is_word(c) {
+                while chars.next_if(|&(_, c)| is_word(c)).is_some() {}
+            }
+            while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+        }
+        let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        tokens.push(&text[start..end]);
+        start = end;
+    }
+    tokens
+}
+
+/// 提取 Markdown 代码块中的内容，没有代码块时原样返回
+///
+/// 只识别位于行首的围栏，并且要求开头的围栏出现在 JSON 之前，
+/// 因此 JSON 字符串值中的 ```` ``` ```` 不会被当作围栏。
+fn strip_code_fence(text: &str) -> &str {
+    let line_start = |pos: usize| pos == 0 || text.as_bytes()[pos - 1] == b'\n';
+    let Some(start) = text
+        .match_indices("```")
+        .map(|(pos, _)| pos)
+        .find(|&pos| line_start(pos))
+    else {
+        return text;
+    };
+    if text[..start].contains(['{', '[']) {
+        return text;
+    }
+    // 跳过语言标识所在的行
+    let body_start = text[start..]
+        .find('\n')
+        .map_or(text.len(), |pos| start + pos + 1);
+    let end = text[body_start..]
+        .match_indices("```")
+        .map(|(pos, _)| body_start + pos)
+        .find(|&pos| line_start(pos))
+        .unwrap_or(text.len());
+    &text[body_start..end]
+}
+
+/// 删除末尾的空白和逗号
+fn strip_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    if out.ends_with(',') {
+        out.pop();
+    }
 }
 
 #[cfg(test)]
@@ -64,4 +599,141 @@ mod tests {
         assert_eq!(prepared.len(), 1);
         assert_eq!(prepared[0].role, Role::User);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_repair_json_strips_code_fence() {
+        let raw = "Here you go:\n```json\n{\"a\": 1}\n```\nHope this helps!";
+        assert_eq!(repair_json(raw), r#"{"a": 1}"#);
+
+        // JSON 字符串中的反引号不是围栏
+        let raw = r#"{"code": "```rust\nfn main() {}\n```"}"#;
+        assert_eq!(repair_json(raw), raw);
+        let raw = "Sure:\n{\"fence\": \"```\"}";
+        assert_eq!(repair_json(raw), "{\"fence\": \"```\"}");
+    }
+
+    #[test]
+    fn test_repair_json_trailing_commas() {
+        let repaired = repair_json(r#"{"a": [1, 2, ], "b": 3, }"#);
+        let value: serde_json::Value = serde_json::from_str(&repaired).unwrap();
+        assert_eq!(value, serde_json::json!({"a": [1, 2], "b": 3}));
+    }
+
+    #[test]
+    fn test_repair_json_single_quotes() {
+        let repaired = repair_json(r#"{'name': 'it\'s "ok"'}"#);
+        let value: serde_json::Value = serde_json::from_str(&repaired).unwrap();
+        assert_eq!(value, serde_json::json!({"name": "it's \"ok\""}));
+    }
+
+    #[test]
+    fn test_repair_json_unterminated() {
+        let repaired = repair_json(r#"{"items": [{"name": "a"}, {"name": "b"#);
+        let value: serde_json::Value = serde_json::from_str(&repaired).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"items": [{"name": "a"}, {"name": "b"}]})
+        );
+
+        let repaired = repair_json(r#"{"a": 1, "b"#);
+        let value: serde_json::Value = serde_json::from_str(&repaired).unwrap();
+        assert_eq!(value, serde_json::json!({"a": 1}));
+    }
+
+    #[test]
+    fn test_parse_json_lenient() {
+        #[derive(serde::Deserialize)]
+        struct Answer {
+            score: u32,
+        }
+        let answer: Answer = parse_json_lenient("```json\n{'score': 7,}\n```").unwrap();
+        assert_eq!(answer.score, 7);
+        assert!(parse_json_lenient::<Answer>("no json here").is_err());
+    }
+
+    #[test]
+    fn test_parse_response_strict() {
+        use crate::types::CompletionResponse;
+        let required = ["/choices", "/choices/*/message"];
+
+        // 未知字段在两种模式下都被接受
+        let body =
+            br#"{"choices": [{"message": {"role": "assistant", "content": "hi"}}], "extra": 1}"#;
+        let parsed: CompletionResponse = parse_response(body, true, &required).unwrap();
+        assert_eq!(parsed.choices[0].message.content, "hi");
+
+        // 缺失字段：宽松模式取默认值，严格模式报告指针
+        let body = br#"{"choices": [{"index": 0}]}"#;
+        let parsed: CompletionResponse = parse_response(body, false, &required).unwrap();
+        assert_eq!(parsed.choices[0].message.content, "");
+        let err = parse_response::<CompletionResponse>(body, true, &required).unwrap_err();
+        assert!(err.to_string().contains("/choices/0/message"), "{}", err);
+
+        // 类型不符时报告出错字段的指针
+        let body = br#"{"choices": [{"message": {"role": "assistant", "content": 42}}]}"#;
+        let err = parse_response::<CompletionResponse>(body, true, &required).unwrap_err();
+        assert!(
+            err.to_string().contains("/choices/0/message/content"),
+            "{}",
+            err
+        );
+        assert!(matches!(err, NanoError::Json(_)));
+    }
+
+    #[test]
+    fn test_conversation_builder() {
+        let history = [
+            message(Role::User, "earlier"),
+            message(Role::Assistant, "reply"),
+        ];
+        let messages = ConversationBuilder::new()
+            .developer("Be terse.")
+            .messages(history.clone())
+            .user(String::from("Call the tool"))
+            .tool_result("call_1", "42")
+            .build();
+        let roles: Vec<Role> = messages.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            [
+                Role::Developer,
+                Role::User,
+                Role::Assistant,
+                Role::User,
+                Role::Tool
+            ]
+        );
+        assert_eq!(messages[1..3], history);
+        assert_eq!(messages[4], tool_message("call_1", "42"));
+        assert!(ConversationBuilder::new().build().is_empty());
+    }
+
+    #[test]
+    fn test_diff_responses() {
+        let diff = diff_responses(
+            "Hello  world, it is sunny.",
+            "Hello world, it was sunny today.",
+        );
+        assert_eq!(
+            diff.render(),
+            "Hello world, it [-is -]{+was +}sunny {+today+}."
+        );
+        assert!(!diff.is_identical());
+        assert!(diff.similarity > 0.7 && diff.similarity < 1.0);
+
+        let diff = diff_responses("今天天气很好", "今天天气不好");
+        assert_eq!(
+            diff.ops,
+            [
+                DiffOp::Equal("今天天气".into()),
+                DiffOp::Delete("很".into()),
+                DiffOp::Insert("不".into()),
+                DiffOp::Equal("好".into()),
+            ]
+        );
+
+        assert!(diff_responses("a  b\n", "a b").is_identical());
+        assert_eq!(diff_responses("", "").similarity, 1.0);
+        assert_eq!(diff_responses("x", "").similarity, 0.0);
+    }
+}
@@ -0,0 +1,211 @@
+//! Webhook 回调模块
+//!
+//! 在后台执行生成请求，完成后将结果 POST 到回调地址，适用于"发出即忘"的集成场景。
+use crate::{
+    client::{is_retryable_status, LLMClient},
+    error::{NanoError, Result},
+    options::RequestOptions,
+    runtime,
+    types::ResponseWithStats,
+};
+use log::warn;
+use reqwest::Client;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Webhook 投递选项
+#[derive(Debug, Clone)]
+pub struct WebhookOptions {
+    /// 投递遇到连接失败、超时、429 或 5xx 状态时的最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间，之后每次翻倍
+    pub initial_backoff: Duration,
+    /// 附加到回调请求的标头，例如签名或鉴权信息
+    pub headers: Vec<(String, String)>,
+    /// 生成请求的参数覆盖
+    pub request: RequestOptions,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            headers: Vec::new(),
+            request: RequestOptions::default(),
+        }
+    }
+}
+
+/// 回调请求体
+///
+/// 成功时为 `{"status": "completed", "response": {...}}`，
+/// 生成失败时为 `{"status": "failed", "error": "..."}`。
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum WebhookPayload {
+    Completed { response: Box<ResponseWithStats> },
+    Failed { error: String },
+}
+
+impl LLMClient {
+    /// 在后台生成响应，并将结果投递到 `url`
+    ///
+    /// 生成失败时同样会投递失败通知。回调连接失败、超时或返回 429、5xx 状态时按指数退避
+    /// 重试；其他非 2xx 状态（如 400、404）重试也不会成功，直接失败。重试耗尽或不可重试时
+    /// 返回的任务以错误结束。
+    pub fn generate_to_webhook(
+        &self,
+        prompt: &str,
+        url: &str,
+        options: WebhookOptions,
+    ) -> JoinHandle<Result<()>> {
+        let client = self.clone();
+        let prompt = prompt.to_string();
+        let url = url.to_string();
+        runtime::spawn(async move {
+            let payload = match client
+                .generate_with_options(&prompt, &options.request)
+                .await
+            {
+                Ok(response) => WebhookPayload::Completed {
+                    response: Box::new(response),
+                },
+                Err(e) => WebhookPayload::Failed {
+                    error: e.to_string(),
+                },
+            };
+            client.deliver_webhook(&url, &payload, &options).await
+        })
+    }
+
+    async fn deliver_webhook(
+        &self,
+        url: &str,
+        payload: &WebhookPayload,
+        options: &WebhookOptions,
+    ) -> Result<()> {
+        // 回调地址与上游无关，不能沿用 Unix 套接字、HTTP/2 先验等面向上游的连接设置
+        let http = Client::builder()
+            .timeout(self.config.timeout)
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        let mut backoff = options.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let mut request = http.post(url).json(payload);
+            for (name, value) in &options.headers {
+                request = request.header(name, value);
+            }
+            let (error, retryable) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let retryable = is_retryable_status(response.status());
+                    (NanoError::from_response(response).await, retryable)
+                }
+                Err(e) => {
+                    let retryable = e.is_connect() || e.is_timeout();
+                    (NanoError::from(e), retryable)
+                }
+            };
+            if !retryable || attempt >= options.max_retries {
+                return Err(error);
+            }
+            attempt += 1;
+            warn!("{} (attempt {}), retrying in {:?}", error, attempt, backoff);
+            runtime::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_util::{completion_body, MockResponse, MockServer};
+    use crate::types::RequestStats;
+
+    #[test]
+    fn test_payload_serialization() {
+        let payload = WebhookPayload::Completed {
+            response: Box::new(ResponseWithStats {
+                content: "hi".into(),
+                stats: RequestStats::default(),
+                citations: Vec::new(),
+            }),
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["status"], "completed");
+        assert_eq!(value["response"]["content"], "hi");
+
+        let value = serde_json::to_value(WebhookPayload::Failed {
+            error: "boom".into(),
+        })
+        .unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"status": "failed", "error": "boom"})
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_webhook_bypasses_upstream_transport() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("llm.sock");
+        let llm =
+            MockServer::start_unix(&path, vec![MockResponse::json(completion_body("done"))]).await;
+        let hook = MockServer::start(vec![MockResponse::new(204, "")]).await;
+
+        let client = LLMClient::new(Config::default().with_api_base(&llm.base));
+        client
+            .generate_to_webhook(
+                "hi",
+                &format!("{}/hook", hook.base),
+                WebhookOptions::default(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(llm.requests().len(), 1);
+        let requests = hook.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/hook");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["status"], "completed");
+        assert_eq!(body["response"]["content"], "done");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_retries_transient_errors_only() {
+        let llm = MockServer::start(vec![MockResponse::json(completion_body("done"))]).await;
+        let client = LLMClient::new(Config::default().with_api_base(&llm.base));
+        let options = WebhookOptions {
+            initial_backoff: Duration::from_millis(1),
+            ..WebhookOptions::default()
+        };
+
+        let hook = MockServer::start(vec![
+            MockResponse::new(503, "busy"),
+            MockResponse::new(204, ""),
+        ])
+        .await;
+        client
+            .generate_to_webhook("hi", &hook.base, options.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hook.requests().len(), 2);
+
+        let hook = MockServer::start(vec![MockResponse::new(400, "bad payload")]).await;
+        let result = client
+            .generate_to_webhook("hi", &hook.base, options)
+            .await
+            .unwrap();
+        assert!(matches!(result, Err(NanoError::Api { status: 400, .. })));
+        assert_eq!(hook.requests().len(), 1);
+    }
+}